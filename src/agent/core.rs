@@ -113,6 +113,8 @@ mod tests {
             model: Some("claude-3-opus".to_string()),
             small_fast_model: None,
             max_thinking_tokens: Some(4096),
+            permission_mode_state_file: None,
+            permission_state_dir: None,
         };
 
         let agent = ClaudeAcpAgent::with_config(config);
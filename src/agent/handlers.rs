@@ -14,11 +14,11 @@ use futures::{Stream, StreamExt};
 use sacp::JrConnectionCx;
 use sacp::link::AgentToClient;
 use sacp::schema::{
-    AgentCapabilities, ContentBlock, CurrentModeUpdate, Implementation, InitializeRequest,
-    InitializeResponse, LoadSessionRequest, LoadSessionResponse, NewSessionRequest,
-    NewSessionResponse, PromptCapabilities, PromptRequest, PromptResponse, SessionId, SessionMode,
-    SessionModeId, SessionModeState, SessionNotification, SessionUpdate, SetSessionModeRequest,
-    SetSessionModeResponse, StopReason,
+    AgentCapabilities, ContentBlock, ContentChunk, CurrentModeUpdate, Implementation,
+    InitializeRequest, InitializeResponse, LoadSessionRequest, LoadSessionResponse,
+    NewSessionRequest, NewSessionResponse, PromptCapabilities, PromptRequest, PromptResponse,
+    SessionId, SessionMode, SessionModeId, SessionModeState, SessionNotification, SessionUpdate,
+    SetSessionModeRequest, SetSessionModeResponse, StopReason, TextContent,
 };
 use tokio_util::sync::CancellationToken;
 
@@ -28,8 +28,11 @@ use tokio::sync::broadcast;
 use tracing::instrument;
 
 use crate::agent::flush;
-use crate::agent::slash_commands::{get_predefined_commands, transform_mcp_command_input};
-use crate::session::{PermissionMode, SessionManager};
+use crate::agent::slash_commands::{
+    CompactCommand, ReviewCommand, available_commands_for_mode, available_commands_notification,
+    is_clear_command, transform_mcp_command_input,
+};
+use crate::session::{PermissionMode, Session, SessionManager};
 use crate::terminal::TerminalClient;
 use crate::types::{AgentConfig, AgentError, NewSessionMeta};
 
@@ -141,9 +144,12 @@ pub async fn handle_new_session(
         session.set_external_mcp_servers(request.mcp_servers);
     }
 
-    // Build available modes
-    let available_modes = build_available_modes();
-    let mode_state = SessionModeState::new("default", available_modes);
+    // Build available modes, reporting the mode the session actually started in
+    let available_modes = build_available_modes(&session.custom_mode_names().await);
+    let mode_state = SessionModeState::new(
+        SessionModeId::new(session.permission_mode_id().await),
+        available_modes,
+    );
 
     // Build available models
     let model_state = build_available_models(config);
@@ -162,8 +168,9 @@ pub async fn handle_new_session(
     #[cfg(not(test))] // Only in production, skip in tests
     {
         let session_id_clone = session_id.clone();
+        let mode = session.permission_mode().await;
         tokio::spawn(async move {
-            if let Err(e) = send_available_commands_update(&session_id_clone, connection_cx) {
+            if let Err(e) = send_available_commands_update(&session_id_clone, mode, connection_cx) {
                 tracing::warn!(
                     session_id = %session_id_clone,
                     "Failed to send available commands update: {}",
@@ -245,9 +252,17 @@ pub fn handle_load_session(
         );
     }
 
-    // Build available modes (same as new session)
-    let available_modes = build_available_modes();
-    let mode_state = SessionModeState::new("default", available_modes);
+    // Build available modes (same as new session), falling back to the
+    // crate default like a session that never chose a mode
+    let (current_mode, custom_modes) = match sessions.get_session(&session_id) {
+        Some(session) => (
+            session.permission_mode_id().await,
+            session.custom_mode_names().await,
+        ),
+        None => (PermissionMode::default().as_str().to_string(), Vec::new()),
+    };
+    let available_modes = build_available_modes(&custom_modes);
+    let mode_state = SessionModeState::new(SessionModeId::new(current_mode), available_modes);
 
     // Build available models
     let model_state = build_available_models(config);
@@ -259,9 +274,10 @@ pub fn handle_load_session(
 
 /// Build available permission modes
 ///
-/// Returns the list of permission modes available in the agent.
-fn build_available_modes() -> Vec<SessionMode> {
-    vec![
+/// Returns the built-in permission modes followed by the modes registered
+/// with [`Session::register_permission_strategy`] under `custom_modes`.
+fn build_available_modes(custom_modes: &[String]) -> Vec<SessionMode> {
+    let mut modes = vec![
         SessionMode::new("default", "Default")
             .description("Standard behavior, prompts for dangerous operations"),
         SessionMode::new("acceptEdits", "Accept Edits")
@@ -270,9 +286,15 @@ fn build_available_modes() -> Vec<SessionMode> {
             .description("Planning mode, no actual tool execution"),
         SessionMode::new("dontAsk", "Don't Ask")
             .description("Don't prompt for permissions, deny if not pre-approved"),
+        SessionMode::new("interactive", "Interactive")
+            .description("Prompt for every tool call, including reads"),
         SessionMode::new("bypassPermissions", "Bypass Permissions")
             .description("Bypass all permission checks"),
-    ]
+    ];
+    modes.extend(custom_modes.iter().map(|name| {
+        SessionMode::new(name.as_str(), name.as_str()).description("Registered permission strategy")
+    }));
+    modes
 }
 
 /// Build available models for session
@@ -304,24 +326,20 @@ fn build_available_models(config: &AgentConfig) -> SessionModelState {
 
 /// Send available commands update to client
 ///
-/// Sends the list of available slash commands to the client via ACP notification.
+/// Sends the slash commands available in `mode` to the client via ACP notification.
 #[allow(dead_code)]
 #[allow(unused_variables)]
 #[allow(clippy::unnecessary_wraps)]
 fn send_available_commands_update(
     session_id: &str,
+    mode: PermissionMode,
     connection_cx: JrConnectionCx<AgentToClient>,
 ) -> Result<(), AgentError> {
-    let commands = get_predefined_commands();
-    let command_count = commands.len();
+    let command_count = available_commands_for_mode(mode).len();
 
     #[cfg(not(test))]
     {
-        use sacp::schema::AvailableCommandsUpdate;
-        let notification = SessionNotification::new(
-            SessionId::new(session_id.to_string()),
-            SessionUpdate::AvailableCommandsUpdate(AvailableCommandsUpdate::new(commands)),
-        );
+        let notification = available_commands_notification(session_id, mode);
 
         connection_cx
             .send_notification(notification)
@@ -386,6 +404,12 @@ pub async fn handle_prompt(
     // Reset cancelled flag at the start of each prompt
     // This ensures that cancelled state from previous prompt is cleared
     session.reset_cancelled();
+    // A turn cut short never got to drop its one-turn grants
+    session.permission().await.end_turn();
+    // A prompt is activity, so idle "Always Allow" rules expire here at the latest
+    if session.permission().await.record_activity().await {
+        session.save_permission_state().await;
+    }
 
     // Set the request_id on the session's converter
     // This will attach the request_id to all SessionNotification instances
@@ -459,6 +483,46 @@ pub async fn handle_prompt(
         "Sending query to Claude CLI"
     );
 
+    // `/compact [instructions]` is forwarded as typed to the SDK's own compaction
+    let compact = CompactCommand::parse(&query_text);
+
+    // `/review [scope or file]` becomes a review request scoped to that path or diff
+    let review = ReviewCommand::parse(&query_text);
+    if let Some(review) = review {
+        let validation = review.validate(&session.cwd);
+        let message = match &validation {
+            Ok(()) => review.progress_message(),
+            Err(message) => message.clone(),
+        };
+        let notification = SessionNotification::new(
+            SessionId::new(session_id.to_string()),
+            SessionUpdate::AgentMessageChunk(ContentChunk::new(ContentBlock::Text(
+                TextContent::new(message),
+            ))),
+        );
+        if let Err(e) = send_notification(&connection_cx, notification) {
+            tracing::warn!(
+                session_id = %session_id,
+                error = %e,
+                "Failed to send review notification"
+            );
+        }
+        if validation.is_err() {
+            tracing::info!(
+                session_id = %session_id,
+                query = %query_text,
+                "Review scope not found, nothing sent to Claude CLI"
+            );
+            flush::ensure_notifications_flushed(&connection_cx, 1).await;
+            return Ok(PromptResponse::new(StopReason::EndTurn));
+        }
+    }
+
+    // `/clear` is also handled by the SDK; drop this side's conversation state
+    if is_clear_command(&query_text) {
+        session.reset_conversation().await;
+    }
+
     // Get mutable client access and send the query
     let query_start = Instant::now();
 
@@ -468,7 +532,10 @@ pub async fn handle_prompt(
         // Send the query
         if !query_text.is_empty() {
             // Transform MCP command format: /mcp:server:cmd -> /server:cmd (MCP)
-            let transformed_query = transform_mcp_command_input(&query_text);
+            let transformed_query = match review {
+                Some(review) => review.to_query(),
+                None => transform_mcp_command_input(&query_text),
+            };
             client
                 .query(&transformed_query)
                 .await
@@ -675,6 +742,27 @@ pub async fn handle_prompt(
         "Prompt completed"
     );
 
+    // Report a finished compaction (the SDK doesn't announce it as a message)
+    if let Some(compact) = compact
+        && !cancel_token.is_cancelled()
+        && !session.is_user_cancelled()
+    {
+        let notification = SessionNotification::new(
+            SessionId::new(session_id.to_string()),
+            SessionUpdate::AgentMessageChunk(ContentChunk::new(ContentBlock::Text(
+                TextContent::new(compact.completion_message()),
+            ))),
+        );
+        notification_count += 1;
+        if let Err(e) = send_notification(&connection_cx, notification) {
+            tracing::warn!(
+                session_id = %session_id,
+                error = %e,
+                "Failed to send compaction notification"
+            );
+        }
+    }
+
     // ========================================================================
     // CRITICAL: Flush pending notifications before returning EndTurn
     // ========================================================================
@@ -698,6 +786,9 @@ pub async fn handle_prompt(
     //
     flush::ensure_notifications_flushed(&connection_cx, notification_count).await;
 
+    // One-turn grants such as "Allow All Reads This Turn" end here
+    session.permission().await.end_turn();
+
     // Determine stop reason based on cancellation state and ResultMessage
     // Reference: vendors/claude-code-acp/src/acp-agent.ts lines 286-323
     if cancel_token.is_cancelled() {
@@ -828,32 +919,7 @@ pub async fn handle_set_mode(
 
     // Get previous mode for logging
     let previous_mode = session.permission_mode().await;
-
-    // Parse the mode from mode_id
-    let mode = PermissionMode::parse(mode_id_str).ok_or_else(|| {
-        tracing::warn!(
-            session_id = %session_id_str,
-            mode_id = %mode_id_str,
-            "Invalid mode ID"
-        );
-        AgentError::InvalidMode(mode_id_str.to_string())
-    })?;
-
-    // Set the mode in our permission handler
-    session.set_permission_mode(mode).await;
-
-    // Also set the mode in the SDK client
-    // This is important for the SDK to know the current permission mode
-    let sdk_mode = mode.to_sdk_mode();
-    if let Err(e) = session.client().await.set_permission_mode(sdk_mode).await {
-        tracing::warn!(
-            session_id = %session_id_str,
-            mode = %mode_id_str,
-            error = %e,
-            "Failed to set SDK permission mode (continuing anyway)"
-        );
-        // Don't fail - the local mode is still set
-    }
+    let mode = apply_session_mode(&session, mode_id_str).await?;
 
     // Send CurrentModeUpdate notification to inform the client
     let mode_update = CurrentModeUpdate::new(SessionModeId::new(mode_id_str));
@@ -870,6 +936,15 @@ pub async fn handle_set_mode(
         );
     }
 
+    // The slash commands on offer depend on the mode
+    if let Err(e) = send_available_commands_update(session_id_str, mode, connection_cx) {
+        tracing::warn!(
+            session_id = %session_id_str,
+            "Failed to send available commands update: {}",
+            e
+        );
+    }
+
     tracing::info!(
         session_id = %session_id_str,
         previous_mode = ?previous_mode,
@@ -880,6 +955,48 @@ pub async fn handle_set_mode(
     Ok(SetSessionModeResponse::new())
 }
 
+/// Apply a setMode request's mode to a session and its SDK client
+///
+/// The part of [`handle_set_mode`] that changes state, without the
+/// notifications to the client.
+async fn apply_session_mode(
+    session: &Session,
+    mode_id_str: &str,
+) -> Result<PermissionMode, AgentError> {
+    let session_id_str = session.session_id.as_str();
+
+    // Set the mode in our permission handler: a built-in mode, or else a
+    // registered strategy's name
+    let mode = if let Some(mode) = PermissionMode::parse(mode_id_str) {
+        session.set_permission_mode(mode).await;
+        mode
+    } else if session.set_custom_permission_mode(mode_id_str).await {
+        session.permission_mode().await
+    } else {
+        tracing::warn!(
+            session_id = %session_id_str,
+            mode_id = %mode_id_str,
+            "Invalid mode ID"
+        );
+        return Err(AgentError::InvalidMode(mode_id_str.to_string()));
+    };
+
+    // Also set the mode in the SDK client
+    // This is important for the SDK to know the current permission mode
+    let sdk_mode = mode.to_sdk_mode();
+    if let Err(e) = session.client().await.set_permission_mode(sdk_mode).await {
+        tracing::warn!(
+            session_id = %session_id_str,
+            mode = %mode_id_str,
+            error = %e,
+            "Failed to set SDK permission mode (continuing anyway)"
+        );
+        // Don't fail - the local mode is still set
+    }
+
+    Ok(mode)
+}
+
 /// Handle session cancellation
 ///
 /// Called when a cancel notification is received.
@@ -1197,6 +1314,97 @@ mod tests {
         // TODO: Add integration test for session/new with available commands update
     }
 
+    #[tokio::test]
+    async fn test_set_mode_reports_mode_changes() {
+        let sessions = SessionManager::new();
+        let session = sessions
+            .create_session(
+                "test-set-mode-change".to_string(),
+                std::path::PathBuf::from("/tmp"),
+                &AgentConfig::new(),
+                None,
+            )
+            .unwrap();
+        let changes = Arc::new(std::sync::Mutex::new(Vec::new()));
+        {
+            let changes = Arc::clone(&changes);
+            session.on_mode_change(move |old, new| changes.lock().unwrap().push((old, new)));
+        }
+
+        for mode_id in ["acceptEdits", "plan", "plan"] {
+            let mode = apply_session_mode(&session, mode_id).await.unwrap();
+            assert_eq!(mode.as_str(), mode_id);
+        }
+        // An unknown mode is rejected without a change
+        assert!(matches!(
+            apply_session_mode(&session, "yolo").await,
+            Err(AgentError::InvalidMode(_))
+        ));
+
+        // Re-applying the current mode is not a change
+        assert_eq!(
+            *changes.lock().unwrap(),
+            vec![
+                (PermissionMode::Default, PermissionMode::AcceptEdits),
+                (PermissionMode::AcceptEdits, PermissionMode::Plan),
+            ]
+        );
+        assert_eq!(session.permission_mode().await, PermissionMode::Plan);
+    }
+
+    #[tokio::test]
+    async fn test_set_mode_selects_registered_strategy() {
+        use crate::permissions::strategies::DefaultModeStrategy;
+
+        let sessions = SessionManager::new();
+        let session = sessions
+            .create_session(
+                "test-set-custom-mode".to_string(),
+                std::path::PathBuf::from("/tmp"),
+                &AgentConfig::new(),
+                None,
+            )
+            .unwrap();
+        session
+            .register_permission_strategy("review", Arc::new(DefaultModeStrategy::new()))
+            .await;
+        // Overriding a built-in mode adds no mode of its own
+        session
+            .register_permission_strategy("plan", Arc::new(DefaultModeStrategy::new()))
+            .await;
+        assert_eq!(
+            session.custom_mode_names().await,
+            vec!["review".to_string()]
+        );
+        let modes = build_available_modes(&session.custom_mode_names().await);
+        assert_eq!(modes.last().map(|mode| mode.id.0.as_ref()), Some("review"));
+
+        apply_session_mode(&session, "acceptEdits").await.unwrap();
+        let changes = Arc::new(std::sync::Mutex::new(Vec::new()));
+        {
+            let changes = Arc::clone(&changes);
+            session.on_mode_change(move |old, new| changes.lock().unwrap().push((old, new)));
+        }
+
+        // The registered name selects the strategy, running as its own mode
+        let mode = apply_session_mode(&session, "review").await.unwrap();
+        assert_eq!(mode, PermissionMode::Default);
+        assert_eq!(session.permission_mode_id().await, "review");
+        assert!(session.permission().await.uses_registered_strategy());
+        assert_eq!(
+            *changes.lock().unwrap(),
+            vec![(PermissionMode::AcceptEdits, PermissionMode::Default)]
+        );
+
+        // Built-in modes still take precedence and clear the registered one
+        apply_session_mode(&session, "default").await.unwrap();
+        assert_eq!(session.permission_mode_id().await, "default");
+        assert!(matches!(
+            apply_session_mode(&session, "unregistered").await,
+            Err(AgentError::InvalidMode(_))
+        ));
+    }
+
     #[test]
     fn test_extract_text_from_content() {
         let blocks = vec![
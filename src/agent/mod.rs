@@ -13,3 +13,4 @@ mod slash_commands;
 
 pub use core::ClaudeAcpAgent;
 pub use runner::{run_acp, run_acp_with_cli, shutdown_otel};
+pub(crate) use slash_commands::available_commands_notification;
@@ -417,6 +417,22 @@ async fn run_acp_server() -> Result<(), sacp::Error> {
         "Agent created"
     );
 
+    // Drop the saved permission state of sessions not used for a long time
+    if let Some(dir) = config.permission_state_dir.clone() {
+        tokio::task::spawn_blocking(move || {
+            match crate::session::remove_stale_permission_states(
+                &dir,
+                crate::session::PERMISSION_STATE_MAX_AGE,
+            ) {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!(removed, "Removed stale permission states"),
+                Err(e) => {
+                    tracing::warn!("Failed to clean up permission states in {:?}: {}", dir, e)
+                }
+            }
+        });
+    }
+
     // Build the handler chain
     tracing::debug!("Building ACP handler chain");
     AgentToClient::builder()
@@ -1,9 +1,17 @@
 //! Slash command support
 //!
 //! This module provides predefined slash commands that are sent to clients
-//! via the ACP protocol's `available_commands_update` notification.
+//! via the ACP protocol's `available_commands_update` notification. The set
+//! depends on the permission mode, so it is sent again when the mode changes.
 
-use sacp::schema::{AvailableCommand, AvailableCommandInput, UnstructuredCommandInput};
+use std::path::Path;
+
+use sacp::schema::{
+    AvailableCommand, AvailableCommandInput, AvailableCommandsUpdate, SessionId,
+    SessionNotification, SessionUpdate, UnstructuredCommandInput,
+};
+
+use crate::session::PermissionMode;
 
 /// Cached regex for matching MCP command format
 /// Pattern: /mcp:server:name [args]
@@ -32,6 +40,155 @@ pub fn get_predefined_commands() -> Vec<AvailableCommand> {
     ]
 }
 
+/// Slash commands that make sense in a permission mode
+///
+/// Plan mode leaves out `/init`, which writes CLAUDE.md and would be blocked.
+pub fn available_commands_for_mode(mode: PermissionMode) -> Vec<AvailableCommand> {
+    get_predefined_commands()
+        .into_iter()
+        .filter(|command| !(mode == PermissionMode::Plan && command.name == "init"))
+        .collect()
+}
+
+/// Build the `available_commands_update` notification for a session in `mode`
+pub fn available_commands_notification(
+    session_id: &str,
+    mode: PermissionMode,
+) -> SessionNotification {
+    SessionNotification::new(
+        SessionId::new(session_id.to_string()),
+        SessionUpdate::AvailableCommandsUpdate(AvailableCommandsUpdate::new(
+            available_commands_for_mode(mode),
+        )),
+    )
+}
+
+/// A `/compact [instructions]` command from the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactCommand<'a> {
+    /// Focus instructions for the summary (empty for default compaction)
+    pub instructions: &'a str,
+}
+
+impl<'a> CompactCommand<'a> {
+    /// Parse prompt text as a `/compact` command
+    ///
+    /// Returns None for anything else, including commands that merely start
+    /// with the same letters (`/compactify`).
+    pub fn parse(text: &'a str) -> Option<Self> {
+        let rest = text.trim().strip_prefix("/compact")?;
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        Some(Self {
+            instructions: rest.trim(),
+        })
+    }
+
+    /// Message reported to the client once compaction has finished
+    pub fn completion_message(&self) -> String {
+        if self.instructions.is_empty() {
+            "Conversation compacted".to_string()
+        } else {
+            format!("Conversation compacted (focus: {})", self.instructions)
+        }
+    }
+}
+
+/// What a `/review` command asks the agent to review
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReviewScope<'a> {
+    /// Uncommitted changes in the working tree (no scope given)
+    WorkingChanges,
+    /// A file or directory, relative to the session's cwd unless absolute
+    Path(&'a str),
+    /// A git revision range, e.g. `main..HEAD`
+    Diff(&'a str),
+}
+
+/// Instructions shared by every review request
+const REVIEW_INSTRUCTIONS: &str = "Point out bugs, risky changes and missing tests, \
+     citing the file and line for each finding, and say so if nothing needs changing. \
+     Do not modify any files.";
+
+/// A `/review [scope or file]` command from the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReviewCommand<'a> {
+    /// What to review
+    pub scope: ReviewScope<'a>,
+}
+
+impl<'a> ReviewCommand<'a> {
+    /// Parse prompt text as a `/review` command
+    ///
+    /// A scope with `..` between two revisions (`main..HEAD`) is a diff;
+    /// any other scope is a path (`../lib` included).
+    pub fn parse(text: &'a str) -> Option<Self> {
+        let rest = text.trim().strip_prefix("/review")?;
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let scope = match rest.trim() {
+            "" => ReviewScope::WorkingChanges,
+            scope if is_revision_range(scope) => ReviewScope::Diff(scope),
+            scope => ReviewScope::Path(scope),
+        };
+        Some(Self { scope })
+    }
+
+    /// Check that a path scope exists, relative to `cwd`
+    ///
+    /// Returns the message to report to the client otherwise.
+    pub fn validate(&self, cwd: &Path) -> Result<(), String> {
+        match self.scope {
+            ReviewScope::Path(path) if !cwd.join(path).exists() => {
+                Err(format!("Cannot review {}: no such file or directory", path))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Review request sent to the agent in place of the command
+    pub fn to_query(&self) -> String {
+        match self.scope {
+            ReviewScope::WorkingChanges => format!(
+                "Review the uncommitted changes in this repository (staged, unstaged \
+                 and untracked; see `git status` and `git diff HEAD`). {}",
+                REVIEW_INSTRUCTIONS
+            ),
+            ReviewScope::Path(path) => {
+                format!("Review the code in `{}`. {}", path, REVIEW_INSTRUCTIONS)
+            }
+            ReviewScope::Diff(range) => format!(
+                "Review the changes in `git diff {}`. {}",
+                range, REVIEW_INSTRUCTIONS
+            ),
+        }
+    }
+
+    /// Progress message reported to the client when the review starts
+    pub fn progress_message(&self) -> String {
+        match self.scope {
+            ReviewScope::WorkingChanges => "Reviewing working changes".to_string(),
+            ReviewScope::Path(path) => format!("Reviewing {}", path),
+            ReviewScope::Diff(range) => format!("Reviewing changes in {}", range),
+        }
+    }
+}
+
+/// Check if a review scope is a revision range (`a..b` or `a...b`)
+fn is_revision_range(scope: &str) -> bool {
+    scope.split_once("..").is_some_and(|(from, to)| {
+        let to = to.strip_prefix('.').unwrap_or(to);
+        !from.is_empty() && !from.ends_with('/') && !to.is_empty() && !to.starts_with('/')
+    })
+}
+
+/// Check if prompt text is a `/clear` command, which resets the conversation
+pub fn is_clear_command(text: &str) -> bool {
+    text.trim() == "/clear"
+}
+
 /// Transform MCP command input format
 ///
 /// Converts user input from ACP format to SDK format:
@@ -69,6 +226,94 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_is_clear_command() {
+        assert!(is_clear_command("/clear"));
+        assert!(is_clear_command("  /clear\n"));
+        assert!(!is_clear_command("/clearance"));
+        assert!(!is_clear_command("please /clear"));
+    }
+
+    #[test]
+    fn test_compact_command_forwards_instructions() {
+        let compact = CompactCommand::parse("  /compact   keep the API design notes \n").unwrap();
+        assert_eq!(compact.instructions, "keep the API design notes");
+        assert_eq!(
+            compact.completion_message(),
+            "Conversation compacted (focus: keep the API design notes)"
+        );
+
+        assert_eq!(CompactCommand::parse("/compactify"), None);
+        assert_eq!(CompactCommand::parse("please /compact"), None);
+    }
+
+    #[test]
+    fn test_compact_command_without_instructions() {
+        for text in ["/compact", "/compact   "] {
+            let compact = CompactCommand::parse(text).unwrap();
+            assert!(compact.instructions.is_empty());
+            assert_eq!(compact.completion_message(), "Conversation compacted");
+        }
+    }
+
+    #[test]
+    fn test_review_command_scopes() {
+        let review = ReviewCommand::parse("/review src/lib.rs").unwrap();
+        assert_eq!(review.scope, ReviewScope::Path("src/lib.rs"));
+        assert!(
+            review
+                .to_query()
+                .starts_with("Review the code in `src/lib.rs`.")
+        );
+        assert_eq!(review.progress_message(), "Reviewing src/lib.rs");
+
+        let review = ReviewCommand::parse("/review main..HEAD").unwrap();
+        assert_eq!(review.scope, ReviewScope::Diff("main..HEAD"));
+        assert!(review.to_query().contains("`git diff main..HEAD`"));
+
+        assert_eq!(
+            ReviewCommand::parse("/review origin/main...feature")
+                .unwrap()
+                .scope,
+            ReviewScope::Diff("origin/main...feature")
+        );
+        assert_eq!(
+            ReviewCommand::parse("/review ../shared").unwrap().scope,
+            ReviewScope::Path("../shared")
+        );
+        assert_eq!(ReviewCommand::parse("/reviewer"), None);
+    }
+
+    #[test]
+    fn test_review_command_defaults_to_working_changes() {
+        for text in ["/review", "  /review  \n"] {
+            let review = ReviewCommand::parse(text).unwrap();
+            assert_eq!(review.scope, ReviewScope::WorkingChanges);
+            assert!(review.to_query().contains("git diff HEAD"));
+            assert_eq!(review.progress_message(), "Reviewing working changes");
+            assert_eq!(review.validate(Path::new("/nonexistent")), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_review_command_validates_path_scope() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+
+        let review = ReviewCommand::parse("/review main.rs").unwrap();
+        assert_eq!(review.validate(dir.path()), Ok(()));
+
+        let review = ReviewCommand::parse("/review missing.rs").unwrap();
+        assert_eq!(
+            review.validate(dir.path()),
+            Err("Cannot review missing.rs: no such file or directory".to_string())
+        );
+
+        // Revision ranges are left for git to check
+        let review = ReviewCommand::parse("/review v1..v2").unwrap();
+        assert_eq!(review.validate(dir.path()), Ok(()));
+    }
+
     #[test]
     fn test_predefined_commands() {
         let commands = get_predefined_commands();
@@ -78,6 +323,38 @@ mod tests {
         assert!(commands.iter().any(|c| c.name == "review"));
     }
 
+    #[test]
+    fn test_commands_depend_on_mode() {
+        let names = |mode| {
+            available_commands_for_mode(mode)
+                .into_iter()
+                .map(|command| command.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(PermissionMode::Default),
+            ["compact", "init", "review"]
+        );
+        assert_eq!(names(PermissionMode::Plan), ["compact", "review"]);
+        assert_ne!(
+            names(PermissionMode::Plan),
+            names(PermissionMode::AcceptEdits)
+        );
+    }
+
+    #[test]
+    fn test_available_commands_notification() {
+        let notification = available_commands_notification("session-1", PermissionMode::Plan);
+        let json = serde_json::to_value(&notification).unwrap();
+
+        assert_eq!(json["sessionId"], "session-1");
+        assert_eq!(json["update"]["sessionUpdate"], "available_commands_update");
+        let commands = json["update"]["availableCommands"].as_array().unwrap();
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0]["name"], "compact");
+        assert_eq!(commands[1]["name"], "review");
+    }
+
     #[test]
     fn test_command_descriptions() {
         let commands = get_predefined_commands();
@@ -0,0 +1,33 @@
+//! Pluggable safe-command classification
+//!
+//! The built-in safe set only knows command basenames and a few flag
+//! patterns. Integrators that parse commands with a real shell grammar, or
+//! keep their own allow list, can replace the predicate that decides which
+//! Bash commands are auto-approved as safe.
+
+use std::fmt;
+
+use super::is_safe_command::is_known_safe_command;
+
+/// Decides whether a Bash command is safe to auto-approve
+///
+/// Installed with [`PermissionHandler::set_safe_command_classifier`], it
+/// replaces the built-in check (including any `disabledSafeCommands`)
+/// wherever a mode auto-approves known safe commands. Length limits and
+/// permission rules still apply.
+///
+/// [`PermissionHandler::set_safe_command_classifier`]: crate::session::PermissionHandler::set_safe_command_classifier
+pub trait SafeCommandClassifier: Send + Sync + fmt::Debug {
+    /// Check if `command` (a full Bash command line) is safe to run unprompted
+    fn is_safe_command(&self, command: &str) -> bool;
+}
+
+/// The built-in classifier, [`is_known_safe_command`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KnownSafeCommandClassifier;
+
+impl SafeCommandClassifier for KnownSafeCommandClassifier {
+    fn is_safe_command(&self, command: &str) -> bool {
+        is_known_safe_command(command)
+    }
+}
@@ -0,0 +1,85 @@
+//! `command` builtin analysis
+//!
+//! `command -v rm` only prints where `rm` would be found, but `command rm -rf /`
+//! runs `rm` itself, skipping any shell function or alias of that name. The
+//! query forms are safe; otherwise the command is classified by what it runs.
+
+use super::flags::short_flags;
+
+/// What a `command` builtin invocation does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum CommandBuiltin {
+    /// `-v`/`-V`: describes the named commands without running them
+    Query,
+    /// Runs the wrapped command line
+    Runs(String),
+    /// No command given, so nothing happens
+    Empty,
+}
+
+/// Classify a `command ...` invocation (`parts[0]` is `command`)
+///
+/// Only the options before the wrapped command are `command`'s own, so
+/// `command ls -v` runs `ls -v` rather than querying.
+pub(super) fn classify_command_builtin(parts: &[&str]) -> CommandBuiltin {
+    let args = &parts[1..];
+    let options_end = args
+        .iter()
+        .position(|arg| !arg.starts_with('-') || *arg == "-" || *arg == "--")
+        .unwrap_or(args.len());
+    let flags = short_flags(&args[..options_end], &[]);
+    if flags.contains(&'v') || flags.contains(&'V') {
+        return CommandBuiltin::Query;
+    }
+
+    // Skip `-p` (search the default PATH) and an end-of-options `--`
+    let mut inner = &args[options_end..];
+    if inner.first() == Some(&"--") {
+        inner = &inner[1..];
+    }
+
+    if inner.is_empty() {
+        CommandBuiltin::Empty
+    } else {
+        CommandBuiltin::Runs(inner.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_safety::{command_might_be_dangerous, is_known_safe_command};
+
+    fn classify(command: &str) -> CommandBuiltin {
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        classify_command_builtin(&parts)
+    }
+
+    #[test]
+    fn test_classify_command_builtin() {
+        assert_eq!(classify("command -v rm"), CommandBuiltin::Query);
+        assert_eq!(classify("command -pV git"), CommandBuiltin::Query);
+        assert_eq!(
+            classify("command -p -- rm -rf /"),
+            CommandBuiltin::Runs("rm -rf /".to_string())
+        );
+        assert_eq!(
+            classify("command ls -v"),
+            CommandBuiltin::Runs("ls -v".to_string())
+        );
+        assert_eq!(classify("command"), CommandBuiltin::Empty);
+    }
+
+    #[test]
+    fn test_command_builtin_is_classified_by_what_it_runs() {
+        assert!(is_known_safe_command("command -v rm"));
+        assert!(is_known_safe_command("command -V docker"));
+        assert!(is_known_safe_command("command ls -la"));
+
+        // `command rm` runs rm; only the query forms are safe
+        assert!(!is_known_safe_command("command rm -rf /"));
+        assert!(command_might_be_dangerous("command rm -rf /"));
+        assert!(command_might_be_dangerous("command -p sudo ls"));
+        assert!(!command_might_be_dangerous("command -v rm"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Subcommand classification for container tooling (`docker`, `kubectl`)
+//!
+//! Both CLIs mix read-only inspection subcommands (`docker ps`,
+//! `kubectl get`) with destructive ones (`docker rm`, `kubectl delete`)
+//! under a single binary, so safety depends on the subcommand rather than
+//! the command name.
+
+/// docker global options that consume the following token
+const DOCKER_GLOBAL_OPTIONS_WITH_ARG: &[&str] = &[
+    "-H",
+    "--host",
+    "-c",
+    "--context",
+    "--config",
+    "-l",
+    "--log-level",
+];
+
+/// kubectl global options that consume the following token
+const KUBECTL_GLOBAL_OPTIONS_WITH_ARG: &[&str] = &[
+    "-n",
+    "--namespace",
+    "--context",
+    "--cluster",
+    "--kubeconfig",
+    "--user",
+    "-s",
+    "--server",
+];
+
+/// Check if a docker command only reads state
+pub(super) fn is_safe_docker_command(parts: &[&str]) -> bool {
+    let positionals = positional_args(parts, DOCKER_GLOBAL_OPTIONS_WITH_ARG);
+    match positionals.as_slice() {
+        [
+            "ps" | "images" | "inspect" | "logs" | "version" | "info" | "stats" | "top" | "port"
+            | "diff" | "history",
+            ..,
+        ] => true,
+        // Management commands: `docker container ls`, `docker image inspect`, ...
+        [
+            "container",
+            "ls" | "list" | "ps" | "inspect" | "logs" | "top" | "stats" | "port" | "diff",
+            ..,
+        ]
+        | ["image", "ls" | "list" | "inspect" | "history", ..]
+        | [
+            "volume" | "network" | "context",
+            "ls" | "list" | "inspect",
+            ..,
+        ] => true,
+        _ => false,
+    }
+}
+
+/// Check if a docker command removes resources or escalates privileges
+pub(super) fn is_dangerous_docker_command(parts: &[&str]) -> bool {
+    let positionals = positional_args(parts, DOCKER_GLOBAL_OPTIONS_WITH_ARG);
+    match positionals.as_slice() {
+        ["rm" | "rmi" | "kill", ..] => true,
+        [
+            "container" | "image" | "volume" | "network",
+            "rm" | "prune" | "kill",
+            ..,
+        ]
+        | ["system", "prune", ..] => true,
+        // Privileged containers get full access to the host
+        ["run" | "create" | "exec", ..] | ["container", "run" | "create" | "exec", ..] => parts
+            .iter()
+            .any(|arg| *arg == "--privileged" || *arg == "--privileged=true"),
+        _ => false,
+    }
+}
+
+/// Check if a docker command talks to a registry
+pub(super) fn is_network_docker_command(parts: &[&str]) -> bool {
+    let positionals = positional_args(parts, DOCKER_GLOBAL_OPTIONS_WITH_ARG);
+    matches!(
+        positionals.as_slice(),
+        ["pull" | "push" | "login" | "search", ..] | ["image", "pull" | "push", ..]
+    )
+}
+
+/// Check if a kubectl command only reads cluster state
+pub(super) fn is_safe_kubectl_command(parts: &[&str]) -> bool {
+    let positionals = positional_args(parts, KUBECTL_GLOBAL_OPTIONS_WITH_ARG);
+    match positionals.as_slice() {
+        [
+            "get" | "describe" | "logs" | "explain" | "version" | "api-resources" | "api-versions"
+            | "cluster-info" | "top",
+            ..,
+        ] => true,
+        [
+            "config",
+            "view" | "current-context" | "get-contexts" | "get-clusters",
+            ..,
+        ] => true,
+        ["auth", "can-i" | "whoami", ..] => true,
+        _ => false,
+    }
+}
+
+/// Check if a kubectl command deletes resources or disrupts workloads
+pub(super) fn is_dangerous_kubectl_command(parts: &[&str]) -> bool {
+    let positionals = positional_args(parts, KUBECTL_GLOBAL_OPTIONS_WITH_ARG);
+    matches!(
+        positionals.first().copied(),
+        Some("delete" | "drain" | "cordon" | "taint" | "replace" | "scale")
+    )
+}
+
+/// Collect positional (non-option) arguments after the command name
+///
+/// Skips options, including the value of global options listed in
+/// `options_with_arg` (e.g. `kubectl -n prod get pods` → `["get", "pods"]`).
+fn positional_args<'a>(parts: &[&'a str], options_with_arg: &[&str]) -> Vec<&'a str> {
+    let mut positionals = Vec::new();
+    let mut iter = parts.iter().skip(1);
+    while let Some(part) = iter.next() {
+        if part.starts_with('-') {
+            if options_with_arg.contains(part) {
+                iter.next();
+            }
+        } else {
+            positionals.push(*part);
+        }
+    }
+    positionals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn safe_docker(command: &str) -> bool {
+        is_safe_docker_command(&command.split_whitespace().collect::<Vec<_>>())
+    }
+
+    fn dangerous_docker(command: &str) -> bool {
+        is_dangerous_docker_command(&command.split_whitespace().collect::<Vec<_>>())
+    }
+
+    fn safe_kubectl(command: &str) -> bool {
+        is_safe_kubectl_command(&command.split_whitespace().collect::<Vec<_>>())
+    }
+
+    fn dangerous_kubectl(command: &str) -> bool {
+        is_dangerous_kubectl_command(&command.split_whitespace().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_safe_kubectl_commands() {
+        assert!(safe_kubectl("kubectl get pods"));
+        assert!(safe_kubectl("kubectl describe pod web-1"));
+        assert!(safe_kubectl("kubectl -n prod get pods -o wide"));
+        assert!(safe_kubectl("kubectl logs web-1 --tail 50"));
+        assert!(safe_kubectl("kubectl config view"));
+        assert!(!safe_kubectl("kubectl apply -f deploy.yaml"));
+        assert!(!safe_kubectl("kubectl config use-context prod"));
+    }
+
+    #[test]
+    fn test_dangerous_kubectl_commands() {
+        assert!(dangerous_kubectl("kubectl delete pod x"));
+        assert!(dangerous_kubectl("kubectl --context prod delete ns x"));
+        assert!(dangerous_kubectl("kubectl drain node-1"));
+        assert!(!dangerous_kubectl("kubectl get pods"));
+        // The namespace value is not mistaken for the subcommand
+        assert!(!dangerous_kubectl("kubectl -n delete get pods"));
+    }
+
+    #[test]
+    fn test_safe_docker_commands() {
+        assert!(safe_docker("docker ps"));
+        assert!(safe_docker("docker ps -a"));
+        assert!(safe_docker("docker --context remote images"));
+        assert!(safe_docker("docker container ls"));
+        assert!(safe_docker("docker image inspect alpine"));
+        assert!(!safe_docker("docker run alpine"));
+        assert!(!safe_docker("docker container rm web"));
+    }
+
+    #[test]
+    fn test_network_docker_commands() {
+        let network = |command: &str| {
+            is_network_docker_command(&command.split_whitespace().collect::<Vec<_>>())
+        };
+        assert!(network("docker pull alpine"));
+        assert!(network("docker --context remote push org/app:1.0"));
+        assert!(network("docker image pull alpine"));
+        assert!(!network("docker ps"));
+        assert!(!network("docker image ls"));
+    }
+
+    #[test]
+    fn test_dangerous_docker_commands() {
+        assert!(dangerous_docker("docker run --privileged x"));
+        assert!(dangerous_docker("docker exec --privileged web sh"));
+        assert!(dangerous_docker("docker rm -f web"));
+        assert!(dangerous_docker("docker rmi alpine"));
+        assert!(dangerous_docker("docker system prune -a"));
+        assert!(dangerous_docker("docker volume rm data"));
+        assert!(!dangerous_docker("docker run alpine echo hi"));
+        assert!(!dangerous_docker("docker ps"));
+    }
+}
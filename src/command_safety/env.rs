@@ -0,0 +1,174 @@
+//! `env`-wrapped command analysis
+//!
+//! `env FOO=1 cargo check` runs `cargo check` with an extra variable, so it
+//! is classified by the command it wraps. `env -i` is different: the wrapped
+//! command starts with an empty environment (no `PATH`, no `HOME`), which can
+//! change what it does in surprising ways, so it is always worth a prompt.
+
+use super::{extract_command_basename, is_env_assignment};
+
+/// Short env options that consume an argument (e.g. `-u NAME`)
+const ENV_SHORT_OPTIONS_WITH_ARG: &[char] = &['u', 'C', 'S', 'P'];
+
+/// Long env options that consume an argument when not given as `--opt=value`
+const ENV_LONG_OPTIONS_WITH_ARG: &[&str] = &["--unset", "--chdir", "--split-string"];
+
+/// Extract the command wrapped by `env`, skipping env's options and assignments
+///
+/// Returns `None` if the command is not an env invocation or if env is run
+/// without a command (which just prints the environment).
+///
+/// # Examples
+/// ```ignore
+/// assert_eq!(extract_env_inner_command("env FOO=1 cargo check"), Some("cargo check".to_string()));
+/// assert_eq!(extract_env_inner_command("env -u HOME ls"), Some("ls".to_string()));
+/// assert_eq!(extract_env_inner_command("env"), None);
+/// ```
+pub fn extract_env_inner_command(command: &str) -> Option<String> {
+    let parts: Vec<&str> = command.split_whitespace().collect();
+
+    if extract_command_basename(parts.first()?) != "env" {
+        return None;
+    }
+
+    let mut index = 1;
+    while let Some(part) = parts.get(index) {
+        if *part == "--" {
+            // Explicit end of env options
+            index += 1;
+            break;
+        } else if *part == "-" {
+            // Obsolete spelling of -i
+        } else if let Some(long) = part.strip_prefix("--") {
+            // --opt=value consumes nothing extra; --opt value consumes the next token
+            if !long.contains('=') && ENV_LONG_OPTIONS_WITH_ARG.contains(part) {
+                index += 1;
+            }
+        } else if let Some(flags) = part.strip_prefix('-')
+            && !flags.is_empty()
+        {
+            // Short option cluster, e.g. -i, -iu HOME, -uHOME
+            for (pos, flag) in flags.char_indices() {
+                if ENV_SHORT_OPTIONS_WITH_ARG.contains(&flag) {
+                    // Argument is either the rest of the cluster or the next token
+                    if pos + flag.len_utf8() == flags.len() {
+                        index += 1;
+                    }
+                    break;
+                }
+            }
+        } else if !is_env_assignment(part) {
+            break;
+        }
+        index += 1;
+    }
+
+    let inner = parts.get(index..)?;
+    if inner.is_empty() {
+        None
+    } else {
+        Some(inner.join(" "))
+    }
+}
+
+/// Check if an env invocation clears the environment (`-i`, `-`, `--ignore-environment`)
+pub fn env_clears_environment(command: &str) -> bool {
+    let parts: Vec<&str> = command.split_whitespace().collect();
+    if parts
+        .first()
+        .is_none_or(|first| extract_command_basename(first) != "env")
+    {
+        return false;
+    }
+
+    let mut args = parts[1..].iter();
+    while let Some(part) = args.next() {
+        if *part == "-" || *part == "--ignore-environment" {
+            return true;
+        } else if *part == "--" {
+            return false;
+        } else if part.starts_with("--") {
+            if ENV_LONG_OPTIONS_WITH_ARG.contains(part) {
+                args.next();
+            }
+        } else if let Some(flags) = part.strip_prefix('-') {
+            // Flags after an option taking an argument belong to that argument
+            for (pos, flag) in flags.char_indices() {
+                if flag == 'i' {
+                    return true;
+                }
+                if ENV_SHORT_OPTIONS_WITH_ARG.contains(&flag) {
+                    if pos + flag.len_utf8() == flags.len() {
+                        args.next();
+                    }
+                    break;
+                }
+            }
+        } else if !is_env_assignment(part) {
+            // Reached the wrapped command
+            return false;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_safety::{command_might_be_dangerous, is_known_safe_command};
+
+    #[test]
+    fn test_extract_env_inner_command() {
+        assert_eq!(
+            extract_env_inner_command("env FOO=1 cargo check"),
+            Some("cargo check".to_string())
+        );
+        assert_eq!(
+            extract_env_inner_command("env -i bash -c 'rm -rf /'"),
+            Some("bash -c 'rm -rf /'".to_string())
+        );
+        assert_eq!(
+            extract_env_inner_command("/usr/bin/env -u HOME -C /tmp ls -la"),
+            Some("ls -la".to_string())
+        );
+        assert_eq!(
+            extract_env_inner_command("env --unset=HOME --chdir /tmp A=1 B=2 -- ls"),
+            Some("ls".to_string())
+        );
+        assert_eq!(extract_env_inner_command("env"), None);
+        assert_eq!(extract_env_inner_command("env FOO=1"), None);
+        assert_eq!(extract_env_inner_command("ls -la"), None);
+    }
+
+    #[test]
+    fn test_env_clears_environment() {
+        assert!(env_clears_environment("env -i bash -c 'rm -rf /'"));
+        assert!(env_clears_environment("env - PATH=/bin ls"));
+        assert!(env_clears_environment("env --ignore-environment ls"));
+        assert!(env_clears_environment("env -u HOME -i ls"));
+        assert!(!env_clears_environment("env FOO=1 cargo check"));
+        // `-u i` unsets a variable named `i`; `ls -i` is the wrapped command's flag
+        assert!(!env_clears_environment("env -u i ls"));
+        assert!(!env_clears_environment("env ls -i"));
+        assert!(!env_clears_environment("ls -i"));
+    }
+
+    #[test]
+    fn test_env_wrapped_commands_are_classified() {
+        assert!(is_known_safe_command("env FOO=1 cargo check"));
+        assert!(!command_might_be_dangerous("env FOO=1 cargo check"));
+
+        assert!(command_might_be_dangerous(
+            "env RUST_LOG=debug rm -rf target"
+        ));
+        assert!(!is_known_safe_command("env RUST_LOG=debug rm -rf target"));
+
+        // Clearing the environment always prompts
+        assert!(command_might_be_dangerous("env -i bash -c 'rm -rf /'"));
+        assert!(command_might_be_dangerous("env -i ls"));
+        assert!(!is_known_safe_command("env -i ls"));
+
+        // Printing the environment is not auto-approved
+        assert!(!is_known_safe_command("env"));
+    }
+}
@@ -0,0 +1,233 @@
+//! Plain-English explanations of shell commands
+//!
+//! Permission prompts for Bash show a short description of what the
+//! command does ("force-removes files recursively") so the user doesn't
+//! have to decode the flags. Only commands the dangerous-command
+//! classification knows about are explained; anything else gets no
+//! explanation rather than a guess.
+
+use super::command_builtin::{CommandBuiltin, classify_command_builtin};
+use super::container_cli::{is_dangerous_docker_command, is_dangerous_kubectl_command};
+use super::env::{env_clears_environment, extract_env_inner_command};
+use super::flags::short_flags;
+use super::pipeline::{split_command_lines, split_command_segments};
+use super::redirect::system_path_redirect_target;
+use super::remote_exec::find_remote_execution_command;
+use super::{
+    extract_command_basename, git_subcommand_index, option_parts, strip_background_operator,
+};
+
+/// Explain what a command does in a few words
+///
+/// Each simple command of a pipeline, command list or multi-line script is
+/// explained separately and the explanations are joined with `"; "`.
+/// Returns `None` if none of them is a known pattern.
+///
+/// # Examples
+/// ```ignore
+/// assert_eq!(explain_command("git clean -fd").as_deref(), Some("deletes untracked files"));
+/// assert_eq!(explain_command("ls -la"), None);
+/// ```
+pub fn explain_command(command: &str) -> Option<String> {
+    let mut explanations: Vec<&str> = Vec::new();
+    for explanation in split_command_lines(command)
+        .iter()
+        .flat_map(|line| split_command_segments(line))
+        .filter_map(explain_simple_command)
+    {
+        if !explanations.contains(&explanation) {
+            explanations.push(explanation);
+        }
+    }
+
+    if explanations.is_empty() {
+        None
+    } else {
+        Some(explanations.join("; "))
+    }
+}
+
+/// Explain a single command (no pipes or command lists)
+fn explain_simple_command(command: &str) -> Option<&'static str> {
+    let parts: Vec<&str> = strip_background_operator(command)
+        .split_whitespace()
+        .collect();
+    let cmd_name = extract_command_basename(parts.first()?);
+
+    if system_path_redirect_target(command).is_some() {
+        return Some("writes to a system file");
+    }
+
+    match cmd_name {
+        "rm" => Some(explain_rm(&parts)),
+        "git" => explain_git(&parts),
+        "sudo" | "su" | "doas" => Some("runs a command as another user (usually root)"),
+        "env" if env_clears_environment(command) => {
+            Some("runs a command with an empty environment")
+        }
+        "env" => explain_simple_command(&extract_env_inner_command(command)?),
+        "command" => match classify_command_builtin(&parts) {
+            CommandBuiltin::Runs(inner) => explain_simple_command(&inner),
+            _ => None,
+        },
+        _ if find_remote_execution_command(command).is_some() => {
+            Some("downloads a package from the registry and runs it")
+        }
+        "chmod" => Some("changes file permissions"),
+        "chown" | "chgrp" => Some("changes file ownership"),
+        "fdisk" | "parted" => Some("changes disk partitions"),
+        "dd" => Some("copies raw data between files or devices"),
+        "apt" | "apt-get" | "yum" | "dnf" | "pacman" | "brew" => {
+            Some("installs or removes system packages")
+        }
+        "systemctl" | "service" => Some("starts, stops or reconfigures system services"),
+        "kill" | "killall" | "pkill" => Some("terminates running processes"),
+        "source" => Some("runs a script's commands in the current shell"),
+        _ if parts[0] == "." => Some("runs a script's commands in the current shell"),
+        "docker" if is_dangerous_docker_command(&parts) => {
+            Some("removes containers or images, or runs a privileged container")
+        }
+        "kubectl" if is_dangerous_kubectl_command(&parts) => {
+            Some("deletes or disrupts cluster resources")
+        }
+        _ if cmd_name == "mkfs" || cmd_name.starts_with("mkfs.") => {
+            Some("formats a disk, erasing its contents")
+        }
+        _ => None,
+    }
+}
+
+/// Explain an rm command from its flags
+fn explain_rm(parts: &[&str]) -> &'static str {
+    let args = &parts[1..];
+    let flags = short_flags(args, &[]);
+    let long_options = option_parts(args);
+    let force = flags.contains(&'f') || long_options.contains(&"--force");
+    let recursive =
+        flags.contains(&'r') || flags.contains(&'R') || long_options.contains(&"--recursive");
+
+    match (force, recursive) {
+        (true, true) => "force-removes files recursively, without confirmation",
+        (true, false) => "force-removes files without confirmation",
+        (false, true) => "removes directories and their contents recursively",
+        (false, false) => "removes files",
+    }
+}
+
+/// Explain the git subcommands that change or discard work
+fn explain_git(parts: &[&str]) -> Option<&'static str> {
+    let index = git_subcommand_index(parts)?;
+    let args = &parts[index + 1..];
+    let force = args
+        .iter()
+        .any(|arg| *arg == "--force" || *arg == "-f" || arg.starts_with("--force-with-lease"));
+
+    match parts[index] {
+        "reset" if args.contains(&"--hard") => {
+            Some("discards uncommitted changes and moves the current branch")
+        }
+        "reset" => Some("moves the current branch, unstaging changes"),
+        "clean" => Some("deletes untracked files"),
+        "rm" => Some("removes files from the repository and working tree"),
+        "rebase" => Some("rewrites commit history"),
+        "push" if force => Some("force-pushes, overwriting history on the remote"),
+        "push" => Some("pushes commits to a remote"),
+        "checkout" | "restore" if args.contains(&".") => {
+            Some("discards uncommitted changes to files")
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_rm() {
+        let explanation = explain_command("rm -rf x").unwrap();
+        assert!(explanation.contains("force-removes"));
+        assert!(explanation.contains("recursively"));
+
+        assert_eq!(
+            explain_command("rm -r dir").as_deref(),
+            Some("removes directories and their contents recursively")
+        );
+        assert_eq!(
+            explain_command("/bin/rm a.txt").as_deref(),
+            Some("removes files")
+        );
+        assert_eq!(
+            explain_command("rm -v -fR dir").as_deref(),
+            Some("force-removes files recursively, without confirmation")
+        );
+    }
+
+    #[test]
+    fn test_explain_git() {
+        assert!(
+            explain_command("git reset --hard HEAD~1")
+                .unwrap()
+                .contains("discards uncommitted changes")
+        );
+        assert!(
+            explain_command("git -C /repo push --force origin main")
+                .unwrap()
+                .starts_with("force-pushes")
+        );
+        assert_eq!(explain_command("git status"), None);
+    }
+
+    #[test]
+    fn test_explain_compound_command() {
+        assert_eq!(
+            explain_command("rm -f a && rm -f b; sudo systemctl restart nginx").as_deref(),
+            Some(
+                "force-removes files without confirmation; \
+                 runs a command as another user (usually root)"
+            )
+        );
+        assert_eq!(
+            explain_command("ls -la\nkill 1234").as_deref(),
+            Some("terminates running processes")
+        );
+    }
+
+    #[test]
+    fn test_explain_env_command() {
+        assert_eq!(
+            explain_command("env FOO=1 rm -f a").as_deref(),
+            Some("force-removes files without confirmation")
+        );
+        assert_eq!(
+            explain_command("env -i bash -c 'rm -rf /'").as_deref(),
+            Some("runs a command with an empty environment")
+        );
+        assert_eq!(explain_command("env FOO=1 cargo check"), None);
+        assert_eq!(
+            explain_command("command kill 1234").as_deref(),
+            Some("terminates running processes")
+        );
+        assert_eq!(explain_command("command -v kill"), None);
+        assert_eq!(
+            explain_command("echo 127.0.0.1 x >> /etc/hosts").as_deref(),
+            Some("writes to a system file")
+        );
+        assert_eq!(
+            explain_command("npx create-react-app x").as_deref(),
+            Some("downloads a package from the registry and runs it")
+        );
+        assert_eq!(
+            explain_command(". ./build.sh").as_deref(),
+            Some("runs a script's commands in the current shell")
+        );
+    }
+
+    #[test]
+    fn test_unknown_commands_have_no_explanation() {
+        assert_eq!(explain_command("ls -la"), None);
+        assert_eq!(explain_command("cargo build --release"), None);
+        assert_eq!(explain_command("docker ps"), None);
+        assert_eq!(explain_command(""), None);
+    }
+}
@@ -0,0 +1,79 @@
+//! Short flag parsing
+//!
+//! Most commands accept combined short flags, so `rm -rf`, `rm -fr` and
+//! `rm -r -f` all mean the same thing. Checks that compare whole tokens
+//! against `-f` miss the combined forms; splitting option tokens into
+//! single flags first lets every check see them the same way.
+
+/// Split a command's arguments into its individual short flags
+///
+/// `args` excludes the command name. `-rf` yields `r` and `f`. A flag listed
+/// in `with_value` takes the rest of its cluster (`-ofile`) or the next token
+/// (`-o file`) as its value, so the letters of a value aren't mistaken for
+/// flags. Long options (`--force`), operands and a bare `-` are skipped, and
+/// a standalone `--` ends the options.
+///
+/// # Examples
+/// ```ignore
+/// assert_eq!(short_flags(&["-rf", "dir"], &[]), vec!['r', 'f']);
+/// assert_eq!(short_flags(&["-e", "-z", "-n"], &['e']), vec!['e', 'n']);
+/// ```
+pub(super) fn short_flags(args: &[&str], with_value: &[char]) -> Vec<char> {
+    let mut flags = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if *arg == "--" {
+            break;
+        }
+        if arg.starts_with("--") {
+            continue;
+        }
+        let Some(cluster) = arg.strip_prefix('-') else {
+            continue;
+        };
+        for (pos, flag) in cluster.char_indices() {
+            flags.push(flag);
+            if with_value.contains(&flag) {
+                // The value is the rest of the cluster, or else the next token
+                if pos + flag.len_utf8() == cluster.len() {
+                    args.next();
+                }
+                break;
+            }
+        }
+    }
+    flags
+}
+
+/// Check if `flag` is given in a command's arguments, alone or combined
+pub(super) fn has_short_flag(args: &[&str], flag: char, with_value: &[char]) -> bool {
+    short_flags(args, with_value).contains(&flag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_combined_flags_are_split() {
+        assert_eq!(short_flags(&["-rf", "dir"], &[]), ['r', 'f']);
+        assert_eq!(short_flags(&["-f", "-r", "dir"], &[]), ['f', 'r']);
+        assert_eq!(short_flags(&["-Rfv"], &[]), ['R', 'f', 'v']);
+    }
+
+    #[test]
+    fn test_flag_values_are_not_flags() {
+        // `-e` takes the pattern, attached or as the next token
+        assert_eq!(short_flags(&["-ez", "-n"], &['e']), ['e', 'n']);
+        assert_eq!(short_flags(&["-e", "-z", "-n"], &['e']), ['e', 'n']);
+        assert_eq!(short_flags(&["-nofile"], &['o']), ['n', 'o']);
+    }
+
+    #[test]
+    fn test_operands_and_long_options_are_skipped() {
+        assert!(short_flags(&["--force", "-", "file"], &[]).is_empty());
+        assert_eq!(short_flags(&["-r", "--", "-f"], &[]), ['r']);
+        assert!(has_short_flag(&["-vf", "x"], 'f', &[]));
+        assert!(!has_short_flag(&["--", "-f"], 'f', &[]));
+    }
+}
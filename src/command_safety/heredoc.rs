@@ -0,0 +1,168 @@
+//! Here-document write detection
+//!
+//! `cat <<'EOF' > config.yaml` starts with the read-only `cat`, but the
+//! redirect turns it into a file write. Only the command line that opens the
+//! here-document is inspected for writes; the body that follows it is text.
+
+use super::redirect::{output_redirect_targets, read_word};
+
+/// Return the file a here-document command writes to, if any
+///
+/// Detects a `<<` or `<<-` here-document marker (not the `<<<` here-string)
+/// combined with an output redirect (`>`, `>>`, `>|`, `&>`, `N>`) on the
+/// opening line. Redirects to file descriptors (`>&2`) and to `/dev/null`
+/// are not writes. Quoted text is ignored.
+///
+/// # Examples
+/// ```ignore
+/// assert_eq!(heredoc_write_target("cat <<'EOF' > config.yaml\na: 1\nEOF"), Some("config.yaml".to_string()));
+/// assert_eq!(heredoc_write_target("cat <<EOF\nhello\nEOF"), None);
+/// ```
+pub fn heredoc_write_target(command: &str) -> Option<String> {
+    let header = command.lines().next().unwrap_or("");
+    if heredoc_delimiters(header).is_empty() {
+        return None;
+    }
+    output_redirect_targets(header).into_iter().next()
+}
+
+/// A here-document opened on a command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Heredoc {
+    /// The delimiter word with quotes removed
+    pub delimiter: String,
+    /// Whether the `<<-` form was used, which lets the closing line be
+    /// indented with tabs
+    pub strip_tabs: bool,
+    /// Whether the body is expanded; only a quoted delimiter (`<<'EOF'`)
+    /// keeps `$(...)` in the body from running
+    pub expands: bool,
+}
+
+/// Return the here-documents opened on a command line
+///
+/// Here-documents are listed in the order their bodies follow the line.
+pub(super) fn heredoc_delimiters(line: &str) -> Vec<Heredoc> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut delimiters = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            } else if c == '\\' && q == '"' {
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '\'' | '"' => quote = Some(c),
+            '\\' => i += 1,
+            '<' if chars.get(i + 1) == Some(&'<') => {
+                if chars.get(i + 2) == Some(&'<') {
+                    i += 3;
+                    continue;
+                }
+                let mut j = i + 2;
+                let strip_tabs = chars.get(j) == Some(&'-');
+                if strip_tabs {
+                    j += 1;
+                }
+                while chars.get(j).is_some_and(|c| c.is_whitespace()) {
+                    j += 1;
+                }
+                let (word, consumed) = read_word(&chars[j..]);
+                let quoted = chars[j..j + consumed]
+                    .iter()
+                    .any(|c| matches!(c, '\'' | '"' | '\\'));
+                i = j + consumed;
+                if !word.is_empty() {
+                    delimiters.push(Heredoc {
+                        delimiter: word.replace('\\', ""),
+                        strip_tabs,
+                        expands: !quoted,
+                    });
+                }
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    delimiters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heredoc_to_file_is_a_write() {
+        assert_eq!(
+            heredoc_write_target("cat <<'EOF' > config.yaml\nkey: value\nEOF"),
+            Some("config.yaml".to_string())
+        );
+        assert_eq!(
+            heredoc_write_target("cat <<-EOF >> notes.md\n\tline\n\tEOF"),
+            Some("notes.md".to_string())
+        );
+        // Redirect before the marker, and without spaces
+        assert_eq!(
+            heredoc_write_target("cat >\"my file.txt\" <<EOF\nx\nEOF"),
+            Some("my file.txt".to_string())
+        );
+        assert_eq!(
+            heredoc_write_target("cat <<EOF &>out.log\nx\nEOF"),
+            Some("out.log".to_string())
+        );
+    }
+
+    #[test]
+    fn test_heredoc_to_stdout_is_not_a_write() {
+        assert_eq!(
+            heredoc_write_target("cat <<'EOF'\nhello > world\nEOF"),
+            None
+        );
+        assert_eq!(heredoc_write_target("cat <<EOF >&2\nwarning\nEOF"), None);
+        assert_eq!(heredoc_write_target("cat <<EOF 2>/dev/null\nx\nEOF"), None);
+        // A quoted `>` is not a redirect
+        assert_eq!(heredoc_write_target("cat <<EOF | grep '>'\nx\nEOF"), None);
+    }
+
+    #[test]
+    fn test_heredoc_delimiters() {
+        assert_eq!(
+            heredoc_delimiters("cat <<'EOF' > f"),
+            vec![Heredoc {
+                delimiter: "EOF".to_string(),
+                strip_tabs: false,
+                expands: false,
+            }]
+        );
+        assert_eq!(
+            heredoc_delimiters("cat <<-END | grep x"),
+            vec![Heredoc {
+                delimiter: "END".to_string(),
+                strip_tabs: true,
+                expands: true,
+            }]
+        );
+        assert_eq!(heredoc_delimiters("cat <<\\EOF")[0].delimiter, "EOF");
+        assert!(!heredoc_delimiters("cat <<\\EOF")[0].expands);
+        assert!(!heredoc_delimiters("cat <<\"EOF\"")[0].expands);
+        assert!(heredoc_delimiters("cat <<< 'hi'").is_empty());
+        assert!(heredoc_delimiters("echo '<<EOF'").is_empty());
+    }
+
+    #[test]
+    fn test_redirect_without_heredoc_is_ignored() {
+        assert_eq!(heredoc_write_target("echo hi > file.txt"), None);
+        assert_eq!(heredoc_write_target("cat <<< 'hi' > file.txt"), None);
+    }
+}
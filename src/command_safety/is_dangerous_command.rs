@@ -4,27 +4,87 @@
 //!
 //! Reference: vendors/codex/codex-rs/core/src/command_safety/is_dangerous_command.rs
 
-use super::extract_command_basename;
+use super::command_builtin::{CommandBuiltin, classify_command_builtin};
+use super::container_cli::{is_dangerous_docker_command, is_dangerous_kubectl_command};
+use super::env::{env_clears_environment, extract_env_inner_command};
+use super::flags::has_short_flag;
+use super::pipeline::{split_command_lines, split_command_segments};
+use super::redirect::system_path_redirect_target;
+use super::remote_exec::find_remote_execution_command;
+use super::{
+    extract_command_basename, git_subcommand_index, option_parts, strip_background_operator,
+};
 
 /// Check if a command might be dangerous
 ///
 /// Dangerous commands may cause data loss or system damage. They should
 /// always require explicit user confirmation, even if "Always Allow" was
-/// selected for that command type.
+/// selected for that command type. A pipeline, command list or multi-line
+/// script is dangerous if any of its segments is.
 ///
 /// # Examples
 /// ```ignore
 /// assert!(command_might_be_dangerous("rm -rf /"));
 /// assert!(command_might_be_dangerous("git reset --hard"));
+/// assert!(command_might_be_dangerous("ls && rm -rf /"));
+/// assert!(command_might_be_dangerous("ls\nrm -rf /"));
 /// assert!(!command_might_be_dangerous("ls -la"));
 /// ```
 pub fn command_might_be_dangerous(command: &str) -> bool {
-    let parts: Vec<&str> = command.split_whitespace().collect();
+    split_command_lines(command)
+        .iter()
+        .flat_map(|line| split_command_segments(line))
+        .any(is_dangerous_simple_command)
+}
+
+/// Check if a command might be dangerous, honoring user-configured commands
+///
+/// Any segment whose basename appears in `extra` is dangerous in addition to
+/// the built-in list, so bespoke destructive tools (e.g. an internal
+/// `wipe-db`) get the same treatment as `rm -rf`.
+pub fn command_might_be_dangerous_including(command: &str, extra: &[String]) -> bool {
+    find_configured_dangerous_command(command, extra).is_some()
+        || command_might_be_dangerous(command)
+}
+
+/// Find the first configured dangerous command used anywhere in `command`
+///
+/// Returns the matching entry from `extra`, or `None` if no segment runs one.
+pub fn find_configured_dangerous_command<'a>(
+    command: &str,
+    extra: &'a [String],
+) -> Option<&'a str> {
+    split_command_lines(command)
+        .iter()
+        .flat_map(|line| split_command_segments(line))
+        .find_map(|segment| {
+            let cmd_name = extract_command_basename(strip_background_operator(segment));
+            extra.iter().find(|name| *name == cmd_name)
+        })
+        .map(String::as_str)
+}
+
+/// Check if a single command (no pipes or command lists) might be dangerous
+fn is_dangerous_simple_command(command: &str) -> bool {
+    // Backgrounding (`cmd &`) doesn't change what runs
+    let parts: Vec<&str> = strip_background_operator(command)
+        .split_whitespace()
+        .collect();
 
     let Some(first) = parts.first() else {
         return false;
     };
 
+    // Writing into a system directory is dangerous, whatever the command
+    if system_path_redirect_target(command).is_some() {
+        return true;
+    }
+
+    // Package runners execute whatever the registry serves (`npx pkg`, `pnpm dlx pkg`)
+    if find_remote_execution_command(command).is_some() {
+        return true;
+    }
+
     let cmd_name = extract_command_basename(first);
 
     match cmd_name {
@@ -37,6 +97,19 @@ pub fn command_might_be_dangerous(command: &str) -> bool {
         // sudo elevates privileges - always needs explicit confirmation
         "sudo" => true,
 
+        // env runs another command: dangerous if it is, or if it clears the environment
+        "env" => {
+            env_clears_environment(command)
+                || extract_env_inner_command(command)
+                    .is_some_and(|inner| is_dangerous_simple_command(&inner))
+        }
+
+        // `command X` runs X (`command -v X` only looks it up)
+        "command" => matches!(
+            classify_command_builtin(&parts),
+            CommandBuiltin::Runs(inner) if is_dangerous_simple_command(&inner)
+        ),
+
         // Permission changes are dangerous
         "chmod" | "chown" | "chgrp" => true,
 
@@ -52,9 +125,17 @@ pub fn command_might_be_dangerous(command: &str) -> bool {
         // Kill processes
         "kill" | "killall" | "pkill" => true,
 
+        // Container tooling: resource removal and privileged containers
+        "docker" => is_dangerous_docker_command(&parts),
+        "kubectl" => is_dangerous_kubectl_command(&parts),
+
         // Recursive operations with sudo-like effects
         "su" | "doas" => true,
 
+        // Sourcing runs a script's code in the current shell (`.` has no basename)
+        "source" => true,
+        _ if *first == "." => true,
+
         // Handle commands with prefixes (e.g., mkfs.ext4)
         _ => cmd_name.starts_with("mkfs."),
     }
@@ -62,29 +143,28 @@ pub fn command_might_be_dangerous(command: &str) -> bool {
 
 /// Check if rm command is dangerous
 ///
-/// rm is dangerous with:
-/// - `-f` or `-rf` flags (force, no confirmation)
-/// - `-r` with important paths
+/// rm is dangerous with the force flag, alone or combined (`-f`, `-rf`,
+/// `-fr`, `--force`), since it removes files without confirmation.
 fn is_dangerous_rm(parts: &[&str]) -> bool {
-    // Check for force flags
-    for part in parts.iter().skip(1) {
-        // Check for -f, -rf, -fr, etc.
-        if part.starts_with('-') {
-            let flags = part.trim_start_matches('-');
-            if flags.contains('f') {
-                return true;
-            }
-        }
-    }
-    false
+    let args = &parts[1..];
+    has_short_flag(args, 'f', &[]) || option_parts(args).contains(&"--force")
 }
 
 /// Check if git subcommand is dangerous
+///
+/// If the subcommand can't be found, any of the words may be it.
 fn is_dangerous_git_subcommand(parts: &[&str]) -> bool {
-    matches!(
-        parts.get(1).copied(),
-        Some("reset" | "rm" | "clean" | "rebase" | "push" | "force-push")
-    ) || parts.iter().any(|arg| *arg == "--force" || *arg == "-f")
+    let is_dangerous = |word: &str| {
+        matches!(
+            word,
+            "reset" | "rm" | "clean" | "rebase" | "push" | "force-push"
+        )
+    };
+    let subcommand = match git_subcommand_index(parts) {
+        Some(index) => is_dangerous(parts[index]),
+        None => parts.iter().skip(1).any(|word| is_dangerous(word)),
+    };
+    subcommand || parts.iter().any(|arg| *arg == "--force" || *arg == "-f")
 }
 
 #[cfg(test)]
@@ -97,6 +177,9 @@ mod tests {
         assert!(command_might_be_dangerous("rm -f file.txt"));
         assert!(command_might_be_dangerous("rm -rf /tmp/test"));
         assert!(command_might_be_dangerous("rm -fr /tmp/test"));
+        assert!(command_might_be_dangerous("rm -r -f /tmp/test"));
+        assert!(command_might_be_dangerous("rm -Rfv /tmp/test"));
+        assert!(command_might_be_dangerous("rm --force file.txt"));
     }
 
     #[test]
@@ -105,6 +188,9 @@ mod tests {
         assert!(!command_might_be_dangerous("rm file.txt"));
         assert!(!command_might_be_dangerous("rm -r dir"));
         assert!(!command_might_be_dangerous("rm -i file.txt"));
+        // Operands after `--` are file names, even if they look like flags
+        assert!(!command_might_be_dangerous("rm -- -f.txt"));
+        assert!(!command_might_be_dangerous("rm --one-file-system -r dir"));
     }
 
     #[test]
@@ -117,6 +203,22 @@ mod tests {
         assert!(command_might_be_dangerous("git rebase -i HEAD~3"));
     }
 
+    #[test]
+    fn test_dangerous_git_after_global_options() {
+        assert!(command_might_be_dangerous("git -C /repo reset --hard"));
+        assert!(command_might_be_dangerous(
+            "git --no-pager -C /repo clean -xdf"
+        ));
+        assert!(command_might_be_dangerous(
+            "git --work-tree=/src --git-dir=/src/.git rm -r src"
+        ));
+        assert!(!command_might_be_dangerous("git -C /repo status"));
+        // Unknown options don't hide the subcommand
+        assert!(command_might_be_dangerous("git --no-advice reset --hard"));
+        assert!(command_might_be_dangerous("git -x push origin"));
+        assert!(!command_might_be_dangerous("git --no-advice status"));
+    }
+
     #[test]
     fn test_non_dangerous_git_commands() {
         assert!(!command_might_be_dangerous("git status"));
@@ -168,12 +270,42 @@ mod tests {
         assert!(command_might_be_dangerous("pkill -f python"));
     }
 
+    #[test]
+    fn test_remote_package_execution_dangerous() {
+        assert!(command_might_be_dangerous("npx create-react-app x"));
+        assert!(command_might_be_dangerous("bunx cowsay hi"));
+        assert!(command_might_be_dangerous("pnpm dlx create-vite app"));
+        assert!(!command_might_be_dangerous("pnpm install"));
+    }
+
     #[test]
     fn test_privilege_escalation_dangerous() {
         assert!(command_might_be_dangerous("su -"));
         assert!(command_might_be_dangerous("doas ls"));
     }
 
+    #[test]
+    fn test_redirects_to_system_paths_dangerous() {
+        assert!(command_might_be_dangerous("echo x >> /etc/hosts"));
+        assert!(command_might_be_dangerous("echo x > /etc/hosts"));
+        assert!(command_might_be_dangerous(
+            "cat a | tee b >> /usr/local/etc/x.conf"
+        ));
+        // Project files are writes, but not dangerous ones
+        assert!(!command_might_be_dangerous("echo x >> ./notes.txt"));
+        assert!(!command_might_be_dangerous("echo x"));
+        assert!(!command_might_be_dangerous("ls 2>/dev/null"));
+    }
+
+    #[test]
+    fn test_sourced_scripts_dangerous() {
+        assert!(command_might_be_dangerous("source venv/bin/activate"));
+        assert!(command_might_be_dangerous(". ./build.sh"));
+        assert!(command_might_be_dangerous("cd app && . .env"));
+        // Running a script by relative path is not sourcing
+        assert!(!command_might_be_dangerous("./build.sh"));
+    }
+
     #[test]
     fn test_safe_commands_not_dangerous() {
         assert!(!command_might_be_dangerous("ls -la"));
@@ -195,4 +327,84 @@ mod tests {
         assert!(!command_might_be_dangerous(""));
         assert!(!command_might_be_dangerous("   "));
     }
+
+    #[test]
+    fn test_dangerous_container_commands() {
+        assert!(command_might_be_dangerous("kubectl delete pod x"));
+        assert!(command_might_be_dangerous("docker run --privileged x"));
+        assert!(!command_might_be_dangerous("kubectl get pods"));
+        assert!(!command_might_be_dangerous("docker ps"));
+    }
+
+    #[test]
+    fn test_backgrounded_commands() {
+        assert!(command_might_be_dangerous("rm -rf / &"));
+        assert!(command_might_be_dangerous("rm -rf /&"));
+        assert!(command_might_be_dangerous("git push --force &"));
+        assert!(!command_might_be_dangerous("ls &"));
+    }
+
+    #[test]
+    fn test_configured_dangerous_commands() {
+        let extra = vec!["wipe-db".to_string(), "reset-env".to_string()];
+
+        assert!(command_might_be_dangerous_including(
+            "wipe-db --all",
+            &extra
+        ));
+        assert!(command_might_be_dangerous_including(
+            "/opt/tools/wipe-db staging",
+            &extra
+        ));
+        assert!(command_might_be_dangerous_including(
+            "ls && reset-env",
+            &extra
+        ));
+        assert_eq!(
+            find_configured_dangerous_command("echo x | wipe-db", &extra),
+            Some("wipe-db")
+        );
+
+        // Built-in dangerous commands are still flagged
+        assert!(command_might_be_dangerous_including("rm -rf /", &extra));
+        // Unrelated commands are unaffected, including arguments that mention the tool
+        assert!(!command_might_be_dangerous_including("ls -la", &extra));
+        assert!(!command_might_be_dangerous_including(
+            "cat wipe-db.md",
+            &extra
+        ));
+        assert_eq!(find_configured_dangerous_command("rm -rf /", &extra), None);
+        assert!(!command_might_be_dangerous_including("wipe-db", &[]));
+    }
+
+    #[test]
+    fn test_dangerous_segment_in_pipeline() {
+        assert!(command_might_be_dangerous("ls && rm -rf /tmp/x"));
+        assert!(command_might_be_dangerous("cat f | sudo tee /etc/hosts"));
+        assert!(!command_might_be_dangerous("cat f | grep x | sort"));
+        // Operators inside quotes are not commands
+        assert!(!command_might_be_dangerous("echo 'ok; rm -rf /'"));
+    }
+
+    #[test]
+    fn test_dangerous_command_in_subshell() {
+        assert!(command_might_be_dangerous("(rm -rf /)"));
+        assert!(command_might_be_dangerous("(cd /; rm -rf *)"));
+        assert!(command_might_be_dangerous(
+            "ls && (cd x && git reset --hard)"
+        ));
+        assert!(!command_might_be_dangerous("(cd x && ls)"));
+    }
+
+    #[test]
+    fn test_dangerous_line_in_multi_line_script() {
+        assert!(command_might_be_dangerous(
+            "cd build\necho cleaning up\nrm -rf /\nls"
+        ));
+        // A continuation splits the dangerous subcommand from `git`
+        assert!(command_might_be_dangerous("git \\\n  reset --hard HEAD~1"));
+        assert!(!command_might_be_dangerous("cd build\nls -la\npwd"));
+        // Here-document bodies are text, not commands
+        assert!(!command_might_be_dangerous("cat <<'EOF'\nrm -rf /\nEOF"));
+    }
 }
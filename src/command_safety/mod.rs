@@ -5,11 +5,38 @@
 //!
 //! Reference: vendors/codex/codex-rs/core/src/command_safety/
 
+mod classifier;
+mod command_builtin;
+mod container_cli;
+mod env;
+mod explain;
+mod flags;
+mod heredoc;
 mod is_dangerous_command;
 mod is_safe_command;
+mod network;
+mod pipeline;
+mod redirect;
+mod remote_exec;
+mod sudo;
 
-pub use is_dangerous_command::command_might_be_dangerous;
-pub use is_safe_command::is_known_safe_command;
+pub use classifier::{KnownSafeCommandClassifier, SafeCommandClassifier};
+pub use env::{env_clears_environment, extract_env_inner_command};
+pub use explain::explain_command;
+pub use heredoc::heredoc_write_target;
+pub use is_dangerous_command::{
+    command_might_be_dangerous, command_might_be_dangerous_including,
+    find_configured_dangerous_command,
+};
+pub use is_safe_command::{is_known_safe_command, is_known_safe_command_excluding};
+pub use network::bash_command_uses_network;
+pub use pipeline::{split_command_lines, split_command_segments};
+pub use redirect::{output_redirect_targets, system_path_redirect_target};
+pub use remote_exec::find_remote_execution_command;
+pub use sudo::{SudoCommandSafety, classify_sudo_command, extract_sudo_inner_command};
+
+use std::borrow::Cow;
+use std::collections::HashMap;
 
 /// Extract the basename of a command, handling full paths
 ///
@@ -25,6 +52,139 @@ pub fn extract_command_basename(cmd: &str) -> &str {
         .unwrap_or("")
 }
 
+/// Replace an aliased or wrapper command at the start of `command` with its canonical name
+///
+/// The first word is looked up as written, then by basename, so both
+/// `./scripts/gitw` and `gitw` can be configured. Arguments are kept as-is;
+/// commands without a matching alias are returned unchanged.
+///
+/// Examples (with `gitw -> git`):
+/// - `./scripts/gitw status` → `git status`
+/// - `git status` → unchanged
+pub fn resolve_command_alias<'a>(
+    command: &'a str,
+    aliases: &HashMap<String, String>,
+) -> Cow<'a, str> {
+    let trimmed = command.trim_start();
+    let Some(first) = trimmed.split_whitespace().next() else {
+        return Cow::Borrowed(command);
+    };
+
+    match aliases
+        .get(first)
+        .or_else(|| aliases.get(extract_command_basename(first)))
+    {
+        Some(canonical) => Cow::Owned(format!("{}{}", canonical, &trimmed[first.len()..])),
+        None => Cow::Borrowed(command),
+    }
+}
+
+/// Strip a trailing background operator (`&`) from a command
+///
+/// `rm -rf / &` runs the same command as `rm -rf /`, just asynchronously, so
+/// classification must look at the underlying command. `&&` is left alone.
+///
+/// Examples:
+/// - `rm -rf / &` → `rm -rf /`
+/// - `sleep 10&` → `sleep 10`
+/// - `make && make install` → unchanged
+pub fn strip_background_operator(command: &str) -> &str {
+    let trimmed = command.trim_end();
+    match trimmed.strip_suffix('&') {
+        Some(rest) if !rest.ends_with('&') => rest.trim_end(),
+        _ => trimmed,
+    }
+}
+
+/// Check if a token is an environment variable assignment (`NAME=value`)
+fn is_env_assignment(token: &str) -> bool {
+    token.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty()
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// Tokens that may still be options: everything before a standalone `--`
+///
+/// After `--`, tokens are operands (`rg -- --search-zip` searches for the
+/// literal text `--search-zip`). Not used for `find`, whose `--` only ends
+/// the leading `-H`/`-L`/`-P` options; `-exec` and friends after it still run.
+fn option_parts<'a>(parts: &'a [&'a str]) -> &'a [&'a str] {
+    match parts.iter().position(|arg| *arg == "--") {
+        Some(end) => &parts[..end],
+        None => parts,
+    }
+}
+
+/// Check if a command is short enough to be auto-approved
+///
+/// `max_len` is in characters; `None` means no limit. Long commands are hard
+/// to review, so they prompt even when every part of them is known safe.
+pub fn within_auto_approve_len(command: &str, max_len: Option<usize>) -> bool {
+    max_len.is_none_or(|max| command.chars().count() <= max)
+}
+
+/// Git global options that take their value as the next token
+const GIT_OPTIONS_WITH_VALUE: &[&str] = &[
+    "-C",
+    "-c",
+    "--git-dir",
+    "--work-tree",
+    "--namespace",
+    "--config-env",
+    "--attr-source",
+];
+
+/// Git global options that take no value
+const GIT_FLAG_OPTIONS: &[&str] = &[
+    "-p",
+    "--paginate",
+    "-P",
+    "--no-pager",
+    "--bare",
+    "--no-replace-objects",
+    "--literal-pathspecs",
+    "--no-optional-locks",
+];
+
+/// Check if a git global option (without its separate value) is a known one
+///
+/// Unknown options before the subcommand, like `--exec-path=<dir>`, may
+/// change what the subcommand runs.
+fn is_known_git_option(part: &str) -> bool {
+    GIT_FLAG_OPTIONS.contains(&part)
+        || GIT_OPTIONS_WITH_VALUE.contains(&part)
+        || GIT_OPTIONS_WITH_VALUE.iter().any(|opt| {
+            opt.starts_with("--") && part.strip_prefix(opt).is_some_and(|v| v.starts_with('='))
+        })
+}
+
+/// Find the index of git's subcommand in `parts`, skipping global options
+///
+/// `parts[0]` is `git` itself. Global options such as `-C <dir>`,
+/// `-c key=val` and `--git-dir=<dir>` may come before the subcommand, so
+/// `git -C /repo status` has its subcommand at index 3. Unknown long options
+/// are skipped, as git's long options only take a value after `=` unless
+/// listed above. Returns `None` if there is no subcommand or an unknown short
+/// option comes first; callers looking for a risky subcommand should then
+/// fail closed.
+fn git_subcommand_index(parts: &[&str]) -> Option<usize> {
+    let mut i = 1;
+    while let Some(part) = parts.get(i) {
+        if GIT_OPTIONS_WITH_VALUE.contains(part) {
+            i += 2;
+        } else if part.starts_with("--") || is_known_git_option(part) {
+            i += 1;
+        } else if part.starts_with('-') {
+            return None;
+        } else {
+            return Some(i);
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -37,4 +197,64 @@ mod tests {
         assert_eq!(extract_command_basename("ls -la"), "ls");
         assert_eq!(extract_command_basename(""), "");
     }
+
+    #[test]
+    fn test_resolve_command_alias() {
+        let aliases = HashMap::from([
+            ("gitw".to_string(), "git".to_string()),
+            ("./bin/k".to_string(), "kubectl".to_string()),
+        ]);
+
+        assert_eq!(
+            resolve_command_alias("./scripts/gitw status -s", &aliases),
+            "git status -s"
+        );
+        assert_eq!(resolve_command_alias("gitw log", &aliases), "git log");
+        assert_eq!(
+            resolve_command_alias("./bin/k get pods", &aliases),
+            "kubectl get pods"
+        );
+
+        // Unconfigured commands pass through unchanged
+        assert!(matches!(
+            resolve_command_alias("hgw status", &aliases),
+            Cow::Borrowed("hgw status")
+        ));
+        assert_eq!(resolve_command_alias("git status", &aliases), "git status");
+        assert_eq!(resolve_command_alias("", &aliases), "");
+        // Only the command position is resolved
+        assert_eq!(resolve_command_alias("echo gitw", &aliases), "echo gitw");
+    }
+
+    #[test]
+    fn test_git_subcommand_index() {
+        let index = |command: &str| {
+            let parts: Vec<&str> = command.split_whitespace().collect();
+            git_subcommand_index(&parts)
+        };
+        assert_eq!(index("git status"), Some(1));
+        assert_eq!(index("git -C /repo status"), Some(3));
+        assert_eq!(index("git -c color.ui=never --no-pager log"), Some(4));
+        assert_eq!(index("git --git-dir=.git --work-tree=. diff"), Some(3));
+        assert_eq!(index("git -p --work-tree /src show"), Some(4));
+        assert_eq!(index("git"), None);
+        assert_eq!(index("git -C"), None);
+        assert_eq!(index("git --unknown status"), Some(2));
+        assert_eq!(index("git --unknown=value push"), Some(2));
+        assert_eq!(index("git --attr-source HEAD log"), Some(3));
+        assert_eq!(index("git -x status"), None);
+    }
+
+    #[test]
+    fn test_strip_background_operator() {
+        assert_eq!(strip_background_operator("rm -rf / &"), "rm -rf /");
+        assert_eq!(strip_background_operator("sleep 10&"), "sleep 10");
+        assert_eq!(strip_background_operator("npm run dev &  "), "npm run dev");
+        assert_eq!(
+            strip_background_operator("make && make install"),
+            "make && make install"
+        );
+        assert_eq!(strip_background_operator("ls"), "ls");
+        assert_eq!(strip_background_operator(""), "");
+    }
 }
@@ -0,0 +1,212 @@
+//! Network access detection
+//!
+//! A "no-network" policy needs to know when a Bash command talks to another
+//! machine at all, not just when it pipes a download into a shell. Commands
+//! are recognized by name, plus the subcommands of git, docker and package
+//! managers that contact a remote, and `rsync` to or from a remote host.
+
+use super::command_builtin::{CommandBuiltin, classify_command_builtin};
+use super::container_cli::is_network_docker_command;
+use super::env::extract_env_inner_command;
+use super::pipeline::{split_command_lines, split_command_segments};
+use super::sudo::extract_sudo_inner_command;
+use super::{extract_command_basename, git_subcommand_index, strip_background_operator};
+
+/// Commands whose purpose is to reach another host
+const NETWORK_COMMANDS: &[&str] = &[
+    "curl",
+    "wget",
+    "ssh",
+    "scp",
+    "sftp",
+    "ftp",
+    "telnet",
+    "nc",
+    "ncat",
+    "netcat",
+    "ping",
+    "ping6",
+    "traceroute",
+    "dig",
+    "nslookup",
+];
+
+/// Git subcommands that fetch from or push to a remote
+const GIT_NETWORK_SUBCOMMANDS: &[&str] = &["fetch", "pull", "push", "clone", "ls-remote"];
+
+/// Git subcommands that contact a remote through one of their own subcommands
+/// (`git submodule update`, `git remote update`)
+const GIT_NETWORK_NESTED_SUBCOMMANDS: &[(&str, &[&str])] = &[
+    ("submodule", &["update", "add", "sync"]),
+    ("remote", &["update", "prune"]),
+];
+
+/// Package manager subcommands that download from or upload to a registry
+const PACKAGE_MANAGER_NETWORK_SUBCOMMANDS: &[(&str, &[&str])] = &[
+    ("npm", &["install", "i", "ci", "add", "update", "publish"]),
+    ("pnpm", &["install", "i", "add", "update", "publish"]),
+    ("yarn", &["install", "add", "upgrade", "publish"]),
+    ("pip", &["install", "download"]),
+    ("pip3", &["install", "download"]),
+    ("cargo", &["install", "publish", "fetch", "search"]),
+];
+
+/// Check if a Bash command accesses the network
+///
+/// Each simple command of a pipeline, command list or multi-line script is
+/// checked, and commands wrapped by `env`, `sudo` or `command` are checked
+/// by what they run.
+///
+/// # Examples
+/// ```ignore
+/// assert!(bash_command_uses_network("cargo build && git push origin main"));
+/// assert!(!bash_command_uses_network("git status"));
+/// ```
+pub fn bash_command_uses_network(command: &str) -> bool {
+    split_command_lines(command)
+        .iter()
+        .flat_map(|line| split_command_segments(line))
+        .any(simple_command_uses_network)
+}
+
+/// Check a single command (no pipes or command lists)
+fn simple_command_uses_network(command: &str) -> bool {
+    let command = strip_background_operator(command);
+    let parts: Vec<&str> = command.split_whitespace().collect();
+    let Some(first) = parts.first() else {
+        return false;
+    };
+
+    match extract_command_basename(first) {
+        "env" => extract_env_inner_command(command)
+            .is_some_and(|inner| simple_command_uses_network(&inner)),
+        "sudo" => extract_sudo_inner_command(command)
+            .is_some_and(|inner| simple_command_uses_network(&inner)),
+        "command" => match classify_command_builtin(&parts) {
+            CommandBuiltin::Runs(inner) => simple_command_uses_network(&inner),
+            _ => false,
+        },
+        // If the subcommand can't be found, any of the words may be it
+        "git" => match git_subcommand_index(&parts) {
+            Some(index) => is_git_network_subcommand(&parts[index..]),
+            None => (1..parts.len()).any(|index| is_git_network_subcommand(&parts[index..])),
+        },
+        "docker" => is_network_docker_command(&parts),
+        "rsync" => parts[1..].iter().any(|arg| is_rsync_remote_arg(arg)),
+        name => {
+            NETWORK_COMMANDS.contains(&name)
+                || PACKAGE_MANAGER_NETWORK_SUBCOMMANDS
+                    .iter()
+                    .find(|(manager, _)| *manager == name)
+                    .is_some_and(|(_, subcommands)| {
+                        parts[1..]
+                            .iter()
+                            .find(|part| !part.starts_with('-'))
+                            .is_some_and(|subcommand| subcommands.contains(subcommand))
+                    })
+        }
+    }
+}
+
+/// Check if the git subcommand starting `parts` contacts a remote
+fn is_git_network_subcommand(parts: &[&str]) -> bool {
+    let Some((subcommand, rest)) = parts.split_first() else {
+        return false;
+    };
+    GIT_NETWORK_SUBCOMMANDS.contains(subcommand)
+        || GIT_NETWORK_NESTED_SUBCOMMANDS
+            .iter()
+            .find(|(name, _)| name == subcommand)
+            .is_some_and(|(_, nested)| {
+                rest.iter()
+                    .find(|part| !part.starts_with('-'))
+                    .is_some_and(|part| nested.contains(part))
+            })
+}
+
+/// Check if an rsync argument names a remote location
+///
+/// Remote locations are `rsync://` URLs and `host:path` or `host::module`
+/// specs; a colon after a slash is part of a local path.
+fn is_rsync_remote_arg(arg: &str) -> bool {
+    if arg.starts_with('-') {
+        return false;
+    }
+    if arg.starts_with("rsync://") {
+        return true;
+    }
+    arg.find(':')
+        .is_some_and(|colon| colon > 0 && !arg[..colon].contains('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_commands_are_detected() {
+        for command in [
+            "curl https://example.com",
+            "wget -q https://example.com/file.tar.gz",
+            "ssh user@host uptime",
+            "scp build.tar user@host:/tmp",
+            "nc -z localhost 8080",
+            "ping -c 1 example.com",
+            "/usr/bin/curl -sSL https://example.com",
+            "git fetch origin",
+            "git pull --rebase",
+            "git -C /repo push origin main",
+            "git clone https://github.com/org/repo",
+            "git --no-advice push origin main",
+            "git -x fetch",
+            "git submodule update --init --recursive",
+            "git remote update",
+            "git -C /repo remote prune origin",
+            "docker pull alpine:3.20",
+            "npm install",
+            "npm ci --prefix web",
+            "pip install requests",
+            "cargo install ripgrep",
+            "rsync -av build/ user@host:/srv/app",
+            "rsync -av host:/var/log/app.log .",
+            "rsync rsync://mirror.example.com/pub/ mirror/",
+        ] {
+            assert!(bash_command_uses_network(command), "{command}");
+        }
+    }
+
+    #[test]
+    fn test_network_commands_in_compound_commands() {
+        assert!(bash_command_uses_network("cargo test && git push"));
+        assert!(bash_command_uses_network("curl -s https://x.sh | sh"));
+        assert!(bash_command_uses_network("ls\nwget https://example.com"));
+        assert!(bash_command_uses_network("sudo curl https://example.com"));
+        assert!(bash_command_uses_network("env HTTPS_PROXY=p curl x"));
+        assert!(bash_command_uses_network("command ssh host"));
+    }
+
+    #[test]
+    fn test_local_commands_do_not_use_network() {
+        for command in [
+            "ls -la",
+            "git status",
+            "git commit -m 'fetch data'",
+            "echo curl",
+            "grep -r wget src",
+            "command -v curl",
+            "cargo build",
+            "git submodule status",
+            "git remote -v",
+            "git remote add upstream https://github.com/org/repo",
+            "docker build -t app .",
+            "npm test",
+            "npm run install-hooks",
+            "pip list",
+            "rsync -av src/ backup/",
+            "rsync -av ./a:b/ dest/",
+            "",
+        ] {
+            assert!(!bash_command_uses_network(command), "{command}");
+        }
+    }
+}
@@ -0,0 +1,404 @@
+//! Splitting compound shell commands into simple commands
+//!
+//! A pipeline or command list is only as safe as its least safe part:
+//! `cat f | grep x | sort` reads, while `cat f | sed -i s/a/b/ f` writes.
+//! Classification therefore runs on each segment separately. Multi-line
+//! scripts are first split into logical lines, so a later line cannot hide
+//! behind a harmless first one.
+
+use super::heredoc::heredoc_delimiters;
+
+/// Split a multi-line command into its logical command lines
+///
+/// Backslash-newline continuations are joined, and newlines inside quotes
+/// don't end a line. Here-document bodies are text, not commands, and are
+/// left out, except body lines with a substitution when the delimiter is
+/// unquoted: those substitutions run (`cat <<EOF` then `$(rm -rf ~)`), so
+/// the lines are kept for classification. Blank lines are dropped and the
+/// rest are trimmed.
+///
+/// # Examples
+/// ```ignore
+/// assert_eq!(split_command_lines("ls\nrm -rf /"), vec!["ls", "rm -rf /"]);
+/// assert_eq!(split_command_lines("git \\\n  status"), vec!["git   status"]);
+/// assert_eq!(split_command_lines("cat <<EOF\nrm x\nEOF"), vec!["cat <<EOF"]);
+/// ```
+pub fn split_command_lines(command: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut rest = command;
+
+    while !rest.is_empty() {
+        let (line, remainder) = take_logical_line(rest);
+        rest = remainder;
+
+        // Skip each here-document body up to and including its delimiter
+        let heredocs = heredoc_delimiters(&line);
+        lines.push(line);
+        for heredoc in heredocs {
+            while !rest.is_empty() {
+                let (body_line, remainder) = rest.split_once('\n').unwrap_or((rest, ""));
+                rest = remainder;
+                let body_line = if heredoc.strip_tabs {
+                    body_line.trim_start_matches('\t')
+                } else {
+                    body_line
+                };
+                if body_line == heredoc.delimiter {
+                    break;
+                }
+                if heredoc.expands && body_has_substitution(body_line) {
+                    lines.push(body_line.to_string());
+                }
+            }
+        }
+    }
+
+    lines
+        .into_iter()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Check if a line of an expanded here-document body runs a substitution
+///
+/// Quotes are literal in a body, so only a backslash keeps `$(` or a
+/// backtick from running.
+fn body_has_substitution(line: &str) -> bool {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'`' => return true,
+            b'$' if bytes.get(i + 1) == Some(&b'(') => return true,
+            _ => {}
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Take one logical line from the start of `command`
+///
+/// Returns the line with continuations joined, and the text after it.
+fn take_logical_line(command: &str) -> (String, &str) {
+    let mut line = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = command.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                } else if c == '\\' && q == '"' {
+                    line.push(c);
+                    match chars.next() {
+                        // A continuation inside double quotes is removed too
+                        Some((_, '\n')) => {
+                            line.pop();
+                        }
+                        Some((_, next)) => line.push(next),
+                        None => {}
+                    }
+                    continue;
+                }
+                line.push(c);
+            }
+            None => match c {
+                '\\' => match chars.next() {
+                    Some((_, '\n')) => {}
+                    Some((_, next)) => {
+                        line.push(c);
+                        line.push(next);
+                    }
+                    None => line.push(c),
+                },
+                '\'' | '"' => {
+                    quote = Some(c);
+                    line.push(c);
+                }
+                '\n' => return (line, &command[i + 1..]),
+                _ => line.push(c),
+            },
+        }
+    }
+
+    (line, "")
+}
+
+/// Split a command on pipeline and list operators (`|`, `|&`, `||`, `&&`, `;`, `&`)
+///
+/// Operators inside single or double quotes, or escaped with a backslash,
+/// are not split on (`grep 'a|b'`, `find . -exec rm {} \;`). Redirections
+/// such as `2>&1` and `&>` are part of their segment. Subshells are
+/// unwrapped: the parentheses of `(cd / && rm -rf *)` are dropped and the
+/// commands inside become segments of their own, while other parentheses
+/// (`$(...)`, `<(...)`) stay part of their segment. Empty segments are
+/// dropped and the rest are trimmed.
+///
+/// # Examples
+/// ```ignore
+/// assert_eq!(split_command_segments("cat f | grep x"), vec!["cat f", "grep x"]);
+/// assert_eq!(split_command_segments("grep 'a|b' f"), vec!["grep 'a|b' f"]);
+/// assert_eq!(split_command_segments("(cd x && ls)"), vec!["cd x", "ls"]);
+/// ```
+pub fn split_command_segments(command: &str) -> Vec<&str> {
+    let bytes = command.as_bytes();
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut quote: Option<u8> = None;
+    // Open subshells, and open parentheses that belong to their segment
+    let mut subshell_depth = 0;
+    let mut paren_depth = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        match quote {
+            Some(q) => {
+                if b == b'\\' && q == b'"' {
+                    i += 1;
+                } else if b == q {
+                    quote = None;
+                }
+            }
+            None => match b {
+                b'\\' => i += 1,
+                b'\'' | b'"' => quote = Some(b),
+                // A `(` where a command would start opens a subshell
+                b'(' if command[start..i].trim().is_empty() => {
+                    subshell_depth += 1;
+                    start = i + 1;
+                }
+                b'(' => paren_depth += 1,
+                b')' if paren_depth > 0 => paren_depth -= 1,
+                b')' if subshell_depth > 0 => {
+                    subshell_depth -= 1;
+                    segments.push(&command[start..i]);
+                    start = i + 1;
+                }
+                b'|' | b';' => {
+                    segments.push(&command[start..i]);
+                    // `||` and `|&` are a single operator
+                    if b == b'|' && matches!(bytes.get(i + 1), Some(b'|' | b'&')) {
+                        i += 1;
+                    }
+                    start = i + 1;
+                }
+                b'&' => {
+                    let redirect = i > 0 && matches!(bytes[i - 1], b'>' | b'<')
+                        || matches!(bytes.get(i + 1), Some(b'>'));
+                    if !redirect {
+                        segments.push(&command[start..i]);
+                        if bytes.get(i + 1) == Some(&b'&') {
+                            i += 1;
+                        }
+                        start = i + 1;
+                    }
+                }
+                _ => {}
+            },
+        }
+        i += 1;
+    }
+    segments.push(&command[start.min(command.len())..]);
+
+    segments
+        .into_iter()
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+/// Check if a command runs other commands through substitution
+///
+/// Detects command substitution (`$(...)`, backticks) and process
+/// substitution (`<(...)`, `>(...)`) outside single quotes. Whatever runs
+/// inside is hidden from the segment's first word, so `echo $(rm -rf ~)` is
+/// only as safe as `rm -rf ~`.
+///
+/// # Examples
+/// ```ignore
+/// assert!(has_command_substitution("echo $(rm -rf ~)"));
+/// assert!(has_command_substitution("echo \"`id`\""));
+/// assert!(!has_command_substitution("echo '$(not run)'"));
+/// ```
+pub fn has_command_substitution(command: &str) -> bool {
+    let bytes = command.as_bytes();
+    let mut quote: Option<u8> = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        match (quote, b) {
+            (Some(b'\''), b'\'') => quote = None,
+            (Some(b'\''), _) => {}
+            (_, b'\\') => i += 1,
+            (_, b'`') => return true,
+            (_, b'$') if bytes.get(i + 1) == Some(&b'(') => return true,
+            (Some(b'"'), b'"') => quote = None,
+            (None, b'\'' | b'"') => quote = Some(b),
+            (None, b'<' | b'>') if bytes.get(i + 1) == Some(&b'(') => return true,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_pipeline_and_lists() {
+        assert_eq!(
+            split_command_segments("cat f | grep x | sort | uniq -c"),
+            vec!["cat f", "grep x", "sort", "uniq -c"]
+        );
+        assert_eq!(
+            split_command_segments("make && make test || echo failed; ls"),
+            vec!["make", "make test", "echo failed", "ls"]
+        );
+        assert_eq!(
+            split_command_segments("sleep 1 & rm x"),
+            vec!["sleep 1", "rm x"]
+        );
+        assert_eq!(
+            split_command_segments("cargo build |& tee log"),
+            vec!["cargo build", "tee log"]
+        );
+        assert_eq!(split_command_segments("ls"), vec!["ls"]);
+        assert!(split_command_segments("").is_empty());
+    }
+
+    #[test]
+    fn test_quoted_and_escaped_operators_are_not_split() {
+        assert_eq!(
+            split_command_segments("grep 'a|b' f | wc -l"),
+            vec!["grep 'a|b' f", "wc -l"]
+        );
+        assert_eq!(
+            split_command_segments(r#"echo "x && y""#),
+            vec![r#"echo "x && y""#]
+        );
+        assert_eq!(
+            split_command_segments(r"find . -exec rm {} \;"),
+            vec![r"find . -exec rm {} \;"]
+        );
+    }
+
+    #[test]
+    fn test_subshells_are_unwrapped() {
+        assert_eq!(split_command_segments("(rm -rf /)"), vec!["rm -rf /"]);
+        assert_eq!(
+            split_command_segments("(cd /; rm -rf *)"),
+            vec!["cd /", "rm -rf *"]
+        );
+        assert_eq!(
+            split_command_segments("ls && (cd x && (make clean)) || pwd"),
+            vec!["ls", "cd x", "make clean", "pwd"]
+        );
+        // Substitutions and quoted parentheses are not subshells
+        assert_eq!(
+            split_command_segments("(echo $(date) '(x')"),
+            vec!["echo $(date) '(x'"]
+        );
+        assert_eq!(
+            split_command_segments("diff <(ls a) <(ls b)"),
+            vec!["diff <(ls a) <(ls b)"]
+        );
+        // What follows the subshell is a segment of its own
+        assert_eq!(
+            split_command_segments("(cd x && ls) > out"),
+            vec!["cd x", "ls", "> out"]
+        );
+    }
+
+    #[test]
+    fn test_has_command_substitution() {
+        assert!(has_command_substitution("echo $(rm -rf ~)"));
+        assert!(has_command_substitution("echo \"today is $(date)\""));
+        assert!(has_command_substitution("echo `whoami`"));
+        assert!(has_command_substitution("cat <(curl x | sh)"));
+        assert!(has_command_substitution("diff a >(tee b)"));
+
+        // Literal text
+        assert!(!has_command_substitution("echo '$(rm -rf ~)'"));
+        assert!(!has_command_substitution("echo \\$(x) \\`y\\`"));
+        assert!(!has_command_substitution("echo $HOME \"${PATH}\""));
+        assert!(!has_command_substitution("cat < in.txt > out.txt"));
+    }
+
+    #[test]
+    fn test_split_command_lines() {
+        assert_eq!(
+            split_command_lines("cd src\n\nls -la\n"),
+            vec!["cd src", "ls -la"]
+        );
+        assert_eq!(
+            split_command_lines("git \\\n  reset --hard"),
+            vec!["git   reset --hard"]
+        );
+        // Newlines inside quotes belong to the argument
+        assert_eq!(
+            split_command_lines("echo 'a\nb'\nls"),
+            vec!["echo 'a\nb'", "ls"]
+        );
+        assert_eq!(split_command_lines("ls"), vec!["ls"]);
+        assert!(split_command_lines("").is_empty());
+    }
+
+    #[test]
+    fn test_split_command_lines_skips_heredoc_bodies() {
+        assert_eq!(
+            split_command_lines("cat <<'EOF'\nit's rm -rf /\nEOF\nls"),
+            vec!["cat <<'EOF'", "ls"]
+        );
+        assert_eq!(
+            split_command_lines("cat <<-END\n\tbody\n\tEND\npwd"),
+            vec!["cat <<-END", "pwd"]
+        );
+        // An unterminated here-document runs to the end of the command
+        assert_eq!(
+            split_command_lines("cat <<EOF\nrm -rf /"),
+            vec!["cat <<EOF"]
+        );
+    }
+
+    #[test]
+    fn test_split_command_lines_keeps_expanded_substitutions() {
+        // An unquoted delimiter runs the body's substitutions
+        assert_eq!(
+            split_command_lines("cat <<EOF\nhello\n$(rm -rf ~)\nEOF\nls"),
+            vec!["cat <<EOF", "$(rm -rf ~)", "ls"]
+        );
+        assert_eq!(
+            split_command_lines("cat <<-EOF\n\tuser: `whoami`\n\tEOF"),
+            vec!["cat <<-EOF", "user: `whoami`"]
+        );
+        // Quotes in the body don't hide them; a backslash does
+        assert_eq!(
+            split_command_lines("cat <<EOF\nit's $(id)\nnot \\$(this)\nEOF"),
+            vec!["cat <<EOF", "it's $(id)"]
+        );
+        // A quoted one keeps the body literal
+        assert_eq!(
+            split_command_lines("cat <<'EOF'\n$(rm -rf ~)\nEOF"),
+            vec!["cat <<'EOF'"]
+        );
+    }
+
+    #[test]
+    fn test_redirections_stay_in_segment() {
+        assert_eq!(
+            split_command_segments("cargo test 2>&1 | tail"),
+            vec!["cargo test 2>&1", "tail"]
+        );
+        assert_eq!(split_command_segments("make &> log"), vec!["make &> log"]);
+        assert_eq!(split_command_segments("npm run dev &"), vec!["npm run dev"]);
+    }
+}
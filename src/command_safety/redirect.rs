@@ -0,0 +1,159 @@
+//! Output redirect detection
+//!
+//! `echo` only prints, but `echo x >> /etc/hosts` appends to a system file.
+//! A command with an output redirect to a file is a write whatever the
+//! command is, and a write into a system directory is dangerous.
+
+/// Redirect targets that don't write to a file
+const NON_FILE_TARGETS: &[&str] = &["/dev/null", "/dev/stdout", "/dev/stderr", "/dev/tty"];
+
+/// Directories whose files belong to the system rather than the project
+const SYSTEM_PATH_PREFIXES: &[&str] = &[
+    "/etc/", "/usr/", "/bin/", "/sbin/", "/lib/", "/lib64/", "/boot/", "/sys/", "/proc/", "/dev/",
+    "/var/",
+];
+
+/// Return the files a command line redirects its output to
+///
+/// Detects `>`, `>>`, `>|`, `&>` and `N>` redirects, in order. Redirects to
+/// file descriptors (`>&2`, `2>&1`) and to `/dev/null` are not writes.
+/// Quoted text is ignored, and only the first line is inspected, so a
+/// here-document body is never mistaken for redirects.
+///
+/// # Examples
+/// ```ignore
+/// assert_eq!(output_redirect_targets("echo x >> notes.txt"), vec!["notes.txt"]);
+/// assert!(output_redirect_targets("make 2>&1 | tee").is_empty());
+/// ```
+pub fn output_redirect_targets(command: &str) -> Vec<String> {
+    let line = command.lines().next().unwrap_or("");
+    let chars: Vec<char> = line.chars().collect();
+
+    let mut targets = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            } else if c == '\\' && q == '"' {
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '\'' | '"' => quote = Some(c),
+            '\\' => i += 1,
+            '>' => {
+                // Skip the rest of the operator: `>>`, `>|`
+                let mut j = i + 1;
+                if matches!(chars.get(j), Some('>' | '|')) {
+                    j += 1;
+                }
+                // `>&2` duplicates a file descriptor rather than opening a file
+                if chars.get(j) == Some(&'&') {
+                    i = j + 1;
+                    continue;
+                }
+                while chars.get(j).is_some_and(|c| c.is_whitespace()) {
+                    j += 1;
+                }
+                let (word, consumed) = read_word(&chars[j..]);
+                i = j + consumed;
+                if !word.is_empty() && !NON_FILE_TARGETS.contains(&word.as_str()) {
+                    targets.push(word);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    targets
+}
+
+/// Return the first system file a command line redirects its output to
+pub fn system_path_redirect_target(command: &str) -> Option<String> {
+    output_redirect_targets(command)
+        .into_iter()
+        .find(|target| is_system_path(target))
+}
+
+/// Check if a path is inside a system directory
+fn is_system_path(path: &str) -> bool {
+    SYSTEM_PATH_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+}
+
+/// Read a shell word, stripping quotes
+///
+/// Returns the word and the number of characters consumed from `chars`.
+pub(super) fn read_word(chars: &[char]) -> (String, usize) {
+    let mut word = String::new();
+    let mut quote: Option<char> = None;
+    let mut consumed = 0;
+    for &c in chars {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => word.push(c),
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c.is_whitespace() || matches!(c, ';' | '|' | '&' | '<' | '>') => break,
+            None => word.push(c),
+        }
+        consumed += 1;
+    }
+    (word, consumed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_redirect_targets() {
+        assert_eq!(
+            output_redirect_targets("echo x >> ./notes.txt"),
+            ["./notes.txt"]
+        );
+        assert_eq!(
+            output_redirect_targets("ls >out.txt 2>err.txt"),
+            ["out.txt", "err.txt"]
+        );
+        assert_eq!(
+            output_redirect_targets("make &> \"build log.txt\""),
+            ["build log.txt"]
+        );
+        assert_eq!(output_redirect_targets("echo x >| f"), ["f"]);
+    }
+
+    #[test]
+    fn test_non_file_redirects_are_ignored() {
+        assert!(output_redirect_targets("echo x").is_empty());
+        assert!(output_redirect_targets("echo x >&2").is_empty());
+        assert!(output_redirect_targets("make 2>&1").is_empty());
+        assert!(output_redirect_targets("ls 2>/dev/null").is_empty());
+        assert!(output_redirect_targets("echo '>> /etc/hosts'").is_empty());
+        assert!(output_redirect_targets("cat <<EOF\na > b\nEOF").is_empty());
+    }
+
+    #[test]
+    fn test_system_path_redirect_target() {
+        assert_eq!(
+            system_path_redirect_target("echo x >> /etc/hosts").as_deref(),
+            Some("/etc/hosts")
+        );
+        assert_eq!(
+            system_path_redirect_target("echo x > /usr/local/bin/tool").as_deref(),
+            Some("/usr/local/bin/tool")
+        );
+        assert_eq!(system_path_redirect_target("echo x >> ./notes.txt"), None);
+        assert_eq!(system_path_redirect_target("ls 2>/dev/null"), None);
+        assert_eq!(system_path_redirect_target("echo x > /etcetera.txt"), None);
+    }
+}
@@ -0,0 +1,110 @@
+//! Remote package execution detection
+//!
+//! `npx some-package` downloads a package from the registry and runs it, so
+//! what executes is whatever the registry serves at that moment. A
+//! `Bash(npx:*)` rule approves that for every package name, which is why
+//! these runners are recognized separately from ordinary commands.
+
+use super::command_builtin::{CommandBuiltin, classify_command_builtin};
+use super::env::extract_env_inner_command;
+use super::pipeline::{split_command_lines, split_command_segments};
+use super::sudo::extract_sudo_inner_command;
+use super::{extract_command_basename, strip_background_operator};
+
+/// Runners that fetch and execute a package in one step
+const REMOTE_EXEC_COMMANDS: &[&str] = &["npx", "bunx"];
+
+/// Package managers whose `dlx` subcommand fetches and executes a package
+const DLX_PACKAGE_MANAGERS: &[(&str, &str)] = &[("pnpm", "pnpm dlx"), ("yarn", "yarn dlx")];
+
+/// Find the first remote package runner used anywhere in `command`
+///
+/// Returns the runner as written in a prompt (`"npx"`, `"pnpm dlx"`, ...),
+/// or `None` if no segment runs one. Commands wrapped by `env`, `sudo` or
+/// `command` are checked by what they run.
+///
+/// # Examples
+/// ```ignore
+/// assert_eq!(find_remote_execution_command("npx create-react-app x"), Some("npx"));
+/// assert_eq!(find_remote_execution_command("pnpm install"), None);
+/// ```
+pub fn find_remote_execution_command(command: &str) -> Option<&'static str> {
+    split_command_lines(command)
+        .iter()
+        .flat_map(|line| split_command_segments(line))
+        .find_map(simple_remote_execution_command)
+}
+
+/// Check a single command (no pipes or command lists)
+fn simple_remote_execution_command(command: &str) -> Option<&'static str> {
+    let command = strip_background_operator(command);
+    let parts: Vec<&str> = command.split_whitespace().collect();
+
+    match extract_command_basename(parts.first()?) {
+        "env" => simple_remote_execution_command(&extract_env_inner_command(command)?),
+        "sudo" => simple_remote_execution_command(&extract_sudo_inner_command(command)?),
+        "command" => match classify_command_builtin(&parts) {
+            CommandBuiltin::Runs(inner) => simple_remote_execution_command(&inner),
+            _ => None,
+        },
+        name => {
+            if let Some(runner) = REMOTE_EXEC_COMMANDS.iter().find(|runner| **runner == name) {
+                return Some(*runner);
+            }
+            let (_, runner) = DLX_PACKAGE_MANAGERS
+                .iter()
+                .find(|(manager, _)| *manager == name)?;
+            // Global options (`pnpm --silent dlx x`) come before the subcommand
+            let subcommand = parts[1..].iter().find(|arg| !arg.starts_with('-'))?;
+            (*subcommand == "dlx").then_some(*runner)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_package_runners_are_detected() {
+        assert_eq!(
+            find_remote_execution_command("npx create-react-app x"),
+            Some("npx")
+        );
+        assert_eq!(
+            find_remote_execution_command("/usr/local/bin/bunx cowsay hi"),
+            Some("bunx")
+        );
+        assert_eq!(
+            find_remote_execution_command("pnpm --silent dlx create-vite app"),
+            Some("pnpm dlx")
+        );
+        assert_eq!(
+            find_remote_execution_command("cd app && yarn dlx prettier ."),
+            Some("yarn dlx")
+        );
+        assert_eq!(
+            find_remote_execution_command("env CI=1 npx eslint ."),
+            Some("npx")
+        );
+        assert_eq!(
+            find_remote_execution_command("sudo npx some-package"),
+            Some("npx")
+        );
+    }
+
+    #[test]
+    fn test_local_package_commands_are_not_remote_execution() {
+        for command in [
+            "pnpm install",
+            "yarn build",
+            "pnpm run dlx",
+            "npm install",
+            "echo npx",
+            "command -v npx",
+            "",
+        ] {
+            assert_eq!(find_remote_execution_command(command), None, "{command}");
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Sudo-wrapped command analysis
+//!
+//! `sudo` is always considered dangerous, but the command it wraps still
+//! matters: `sudo cat /etc/hosts` is a safe read with elevated privileges,
+//! while `sudo rm -rf /` is dangerous twice over. This module extracts the
+//! wrapped command so it can be re-classified.
+
+use super::{
+    command_might_be_dangerous, extract_command_basename, is_env_assignment, is_known_safe_command,
+};
+
+/// Short sudo options that consume an argument (e.g. `-u root`)
+const SUDO_SHORT_OPTIONS_WITH_ARG: &[char] =
+    &['u', 'g', 'C', 'D', 'h', 'p', 'r', 't', 'U', 'T', 'R'];
+
+/// Long sudo options that consume an argument when not given as `--opt=value`
+const SUDO_LONG_OPTIONS_WITH_ARG: &[&str] = &[
+    "--user",
+    "--group",
+    "--close-from",
+    "--chdir",
+    "--host",
+    "--prompt",
+    "--role",
+    "--type",
+    "--other-user",
+    "--command-timeout",
+    "--chroot",
+];
+
+/// Classification of a sudo-wrapped command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SudoCommandSafety {
+    /// The wrapped command is known safe, but runs with elevated privileges
+    SafeButPrivileged,
+    /// The wrapped command is itself dangerous (flagged by both sudo and the command)
+    Dangerous,
+    /// The wrapped command is neither known safe nor known dangerous
+    Privileged,
+}
+
+/// Extract the command wrapped by `sudo`, skipping sudo's own options
+///
+/// Returns `None` if the command is not a sudo invocation or if sudo is run
+/// without a command (e.g. `sudo -l`, `sudo -v`).
+///
+/// # Examples
+/// ```ignore
+/// assert_eq!(extract_sudo_inner_command("sudo -u root ls -la"), Some("ls -la".to_string()));
+/// assert_eq!(extract_sudo_inner_command("sudo -E npm install"), Some("npm install".to_string()));
+/// assert_eq!(extract_sudo_inner_command("ls -la"), None);
+/// ```
+pub fn extract_sudo_inner_command(command: &str) -> Option<String> {
+    let parts: Vec<&str> = command.split_whitespace().collect();
+
+    if extract_command_basename(parts.first()?) != "sudo" {
+        return None;
+    }
+
+    let mut index = 1;
+    while let Some(part) = parts.get(index) {
+        if *part == "--" {
+            // Explicit end of sudo options
+            index += 1;
+            break;
+        } else if let Some(long) = part.strip_prefix("--") {
+            // --opt=value consumes nothing extra; --opt value consumes the next token
+            if !long.contains('=') && SUDO_LONG_OPTIONS_WITH_ARG.contains(part) {
+                index += 1;
+            }
+        } else if let Some(flags) = part.strip_prefix('-')
+            && !flags.is_empty()
+        {
+            // Short option cluster, e.g. -E, -nE, -u root, -uroot
+            for (pos, flag) in flags.char_indices() {
+                if SUDO_SHORT_OPTIONS_WITH_ARG.contains(&flag) {
+                    // Argument is either the rest of the cluster or the next token
+                    if pos + flag.len_utf8() == flags.len() {
+                        index += 1;
+                    }
+                    break;
+                }
+            }
+        } else if is_env_assignment(part) {
+            // sudo accepts VAR=value assignments before the command
+        } else {
+            break;
+        }
+        index += 1;
+    }
+
+    let inner = parts.get(index..)?;
+    if inner.is_empty() {
+        None
+    } else {
+        Some(inner.join(" "))
+    }
+}
+
+/// Classify a sudo-wrapped command by inspecting the command it runs
+///
+/// Returns `None` if the command is not a sudo invocation with a wrapped command.
+pub fn classify_sudo_command(command: &str) -> Option<SudoCommandSafety> {
+    let inner = extract_sudo_inner_command(command)?;
+
+    Some(if command_might_be_dangerous(&inner) {
+        SudoCommandSafety::Dangerous
+    } else if is_known_safe_command(&inner) {
+        SudoCommandSafety::SafeButPrivileged
+    } else {
+        SudoCommandSafety::Privileged
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_simple_sudo() {
+        assert_eq!(
+            extract_sudo_inner_command("sudo cat /etc/hosts"),
+            Some("cat /etc/hosts".to_string())
+        );
+        assert_eq!(
+            extract_sudo_inner_command("/usr/bin/sudo rm -rf /"),
+            Some("rm -rf /".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extract_sudo_with_options() {
+        assert_eq!(
+            extract_sudo_inner_command("sudo -u root ls -la"),
+            Some("ls -la".to_string())
+        );
+        assert_eq!(
+            extract_sudo_inner_command("sudo -E npm install"),
+            Some("npm install".to_string())
+        );
+        assert_eq!(
+            extract_sudo_inner_command("sudo -uroot -E rm -rf /tmp/x"),
+            Some("rm -rf /tmp/x".to_string())
+        );
+        assert_eq!(
+            extract_sudo_inner_command("sudo -nEu deploy whoami"),
+            Some("whoami".to_string())
+        );
+        assert_eq!(
+            extract_sudo_inner_command("sudo --user=root whoami"),
+            Some("whoami".to_string())
+        );
+        assert_eq!(
+            extract_sudo_inner_command("sudo --user root --preserve-env whoami"),
+            Some("whoami".to_string())
+        );
+        assert_eq!(
+            extract_sudo_inner_command("sudo -- ls -la"),
+            Some("ls -la".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extract_sudo_with_env_assignment() {
+        assert_eq!(
+            extract_sudo_inner_command("sudo FOO=bar make install"),
+            Some("make install".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extract_sudo_without_command() {
+        assert_eq!(extract_sudo_inner_command("sudo"), None);
+        assert_eq!(extract_sudo_inner_command("sudo -l"), None);
+        assert_eq!(extract_sudo_inner_command("sudo -u root"), None);
+        assert_eq!(extract_sudo_inner_command("ls -la"), None);
+        assert_eq!(extract_sudo_inner_command(""), None);
+    }
+
+    #[test]
+    fn test_classify_sudo_command() {
+        assert_eq!(
+            classify_sudo_command("sudo cat /etc/hosts"),
+            Some(SudoCommandSafety::SafeButPrivileged)
+        );
+        assert_eq!(
+            classify_sudo_command("sudo rm -rf /"),
+            Some(SudoCommandSafety::Dangerous)
+        );
+        assert_eq!(
+            classify_sudo_command("sudo make install"),
+            Some(SudoCommandSafety::Privileged)
+        );
+        assert_eq!(classify_sudo_command("cat /etc/hosts"), None);
+    }
+
+    #[test]
+    fn test_sudo_is_still_dangerous_and_not_safe() {
+        // Wrapping a safe command in sudo never makes it auto-approvable
+        assert!(!is_known_safe_command("sudo cat /etc/hosts"));
+        assert!(command_might_be_dangerous("sudo cat /etc/hosts"));
+    }
+}
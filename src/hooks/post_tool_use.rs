@@ -1,6 +1,7 @@
 //! PostToolUse hook implementation
 //!
-//! Executes registered callbacks after tool execution completes.
+//! Executes registered callbacks after tool execution completes, and checks
+//! tool responses against the `outputRules` permission settings.
 
 use std::sync::Arc;
 use std::time::Instant;
@@ -9,26 +10,41 @@ use claude_code_agent_sdk::{
     HookCallback, HookContext, HookInput, HookJsonOutput, SyncHookJsonOutput,
 };
 use futures::future::BoxFuture;
+use tokio::sync::RwLock;
 use tracing::Instrument;
 
 use super::callback_registry::HookCallbackRegistry;
+use crate::settings::{PermissionChecker, check_tool_output};
+use crate::tools::{BashInput, ToolInputExt};
 
 /// Creates a PostToolUse hook that executes registered callbacks.
 ///
 /// This hook runs after tool execution and invokes any callbacks registered
 /// for the tool use ID. Callbacks can be used to send updates to the ACP client.
 ///
+/// Responses matching an output rule produce a warning notice; rules marked
+/// `interrupt` also stop the current turn. The tool has already run, so
+/// nothing is undone.
+///
+/// A `cd` run by the built-in Bash tool moves the agent's shell, so the
+/// permission checker follows it for later checks.
+///
 /// # Arguments
 ///
 /// * `callback_registry` - The callback registry to use for looking up callbacks
+/// * `permission_checker` - Optional permission checker providing output rules
 ///
 /// # Returns
 ///
 /// A hook callback that can be used with ClaudeAgentOptions
-pub fn create_post_tool_use_hook(callback_registry: Arc<HookCallbackRegistry>) -> HookCallback {
+pub fn create_post_tool_use_hook(
+    callback_registry: Arc<HookCallbackRegistry>,
+    permission_checker: Option<Arc<RwLock<PermissionChecker>>>,
+) -> HookCallback {
     Arc::new(
         move |input: HookInput, tool_use_id: Option<String>, _context: HookContext| {
             let callback_registry = callback_registry.clone();
+            let permission_checker = permission_checker.clone();
 
             // Extract tool name early for span naming
             let (tool_name, is_post_tool) = match &input {
@@ -86,6 +102,32 @@ pub fn create_post_tool_use_hook(callback_registry: Arc<HookCallbackRegistry>) -
                         "PostToolUse hook triggered"
                     );
 
+                    // Check output rules before the response is handed to the callback
+                    let violation = if let Some(checker) = &permission_checker {
+                        let checker = checker.read().await;
+                        check_tool_output(checker.output_rules(), &tool_name, &tool_response)
+                    } else {
+                        None
+                    };
+                    if let Some(violation) = &violation {
+                        tracing::warn!(
+                            tool_name = %tool_name,
+                            tool_use_id = ?tool_use_id,
+                            interrupt = violation.interrupt,
+                            message = %violation.message,
+                            "Tool output rule violated"
+                        );
+                    }
+
+                    // The built-in Bash tool keeps its shell between calls
+                    if tool_name == "Bash"
+                        && let Some(checker) = &permission_checker
+                        && let Some(command) = BashInput::from_value(&tool_input).command
+                        && command.trim_start().starts_with("cd ")
+                    {
+                        checker.write().await.follow_shell_command(&command);
+                    }
+
                     // Execute callback if registered
                     let callback_executed = if let Some(ref tool_use_id) = tool_use_id {
                         let callback_start = Instant::now();
@@ -134,10 +176,25 @@ pub fn create_post_tool_use_hook(callback_registry: Arc<HookCallbackRegistry>) -
                         "PostToolUse hook completed"
                     );
 
-                    HookJsonOutput::Sync(SyncHookJsonOutput {
-                        continue_: Some(true),
-                        ..Default::default()
-                    })
+                    match violation {
+                        Some(violation) if violation.interrupt => {
+                            HookJsonOutput::Sync(SyncHookJsonOutput {
+                                continue_: Some(false),
+                                stop_reason: Some(violation.message.clone()),
+                                system_message: Some(violation.message),
+                                ..Default::default()
+                            })
+                        }
+                        Some(violation) => HookJsonOutput::Sync(SyncHookJsonOutput {
+                            continue_: Some(true),
+                            system_message: Some(violation.message),
+                            ..Default::default()
+                        }),
+                        None => HookJsonOutput::Sync(SyncHookJsonOutput {
+                            continue_: Some(true),
+                            ..Default::default()
+                        }),
+                    }
                 }
                 .instrument(span),
             ) as BoxFuture<'static, HookJsonOutput>
@@ -148,8 +205,10 @@ pub fn create_post_tool_use_hook(callback_registry: Arc<HookCallbackRegistry>) -
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::settings::{PermissionSettings, Settings, ToolOutputRule};
     use futures::FutureExt;
     use serde_json::json;
+    use std::path::Path;
     use std::sync::atomic::{AtomicBool, Ordering};
 
     #[tokio::test]
@@ -169,7 +228,7 @@ mod tests {
             }),
         );
 
-        let hook = create_post_tool_use_hook(registry);
+        let hook = create_post_tool_use_hook(registry, None);
         let input = HookInput::PostToolUse(claude_code_agent_sdk::PostToolUseHookInput {
             session_id: "test".to_string(),
             transcript_path: "/tmp/test".to_string(),
@@ -195,7 +254,7 @@ mod tests {
     #[tokio::test]
     async fn test_post_tool_use_hook_no_callback() {
         let registry = Arc::new(HookCallbackRegistry::new());
-        let hook = create_post_tool_use_hook(registry);
+        let hook = create_post_tool_use_hook(registry, None);
 
         let input = HookInput::PostToolUse(claude_code_agent_sdk::PostToolUseHookInput {
             session_id: "test".to_string(),
@@ -225,7 +284,7 @@ mod tests {
     #[tokio::test]
     async fn test_post_tool_use_hook_no_tool_use_id() {
         let registry = Arc::new(HookCallbackRegistry::new());
-        let hook = create_post_tool_use_hook(registry);
+        let hook = create_post_tool_use_hook(registry, None);
 
         let input = HookInput::PostToolUse(claude_code_agent_sdk::PostToolUseHookInput {
             session_id: "test".to_string(),
@@ -250,7 +309,7 @@ mod tests {
     #[tokio::test]
     async fn test_post_tool_use_hook_ignores_other_events() {
         let registry = Arc::new(HookCallbackRegistry::new());
-        let hook = create_post_tool_use_hook(registry);
+        let hook = create_post_tool_use_hook(registry, None);
 
         let input = HookInput::PreToolUse(claude_code_agent_sdk::PreToolUseHookInput {
             session_id: "test".to_string(),
@@ -270,4 +329,117 @@ mod tests {
             HookJsonOutput::Async(_) => panic!("Expected sync output"),
         }
     }
+
+    fn checker_with_output_rule(rule: ToolOutputRule) -> Arc<RwLock<PermissionChecker>> {
+        let settings = Settings {
+            permissions: Some(PermissionSettings {
+                output_rules: Some(vec![rule]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        Arc::new(RwLock::new(PermissionChecker::new(settings, "/tmp")))
+    }
+
+    fn bash_output(response: serde_json::Value) -> HookInput {
+        HookInput::PostToolUse(claude_code_agent_sdk::PostToolUseHookInput {
+            session_id: "test".to_string(),
+            transcript_path: "/tmp/test".to_string(),
+            cwd: "/tmp".to_string(),
+            permission_mode: None,
+            tool_name: "Bash".to_string(),
+            tool_input: json!({"command": "cat dump.sql"}),
+            tool_response: response,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_post_tool_use_hook_follows_shell_cd() {
+        let checker = Arc::new(RwLock::new(PermissionChecker::new(
+            Settings::default(),
+            "/tmp",
+        )));
+        let hook =
+            create_post_tool_use_hook(Arc::new(HookCallbackRegistry::new()), Some(checker.clone()));
+        let cd = |tool_name: &str| {
+            HookInput::PostToolUse(claude_code_agent_sdk::PostToolUseHookInput {
+                session_id: "test".to_string(),
+                transcript_path: "/tmp/test".to_string(),
+                cwd: "/tmp".to_string(),
+                permission_mode: None,
+                tool_name: tool_name.to_string(),
+                tool_input: json!({"command": "cd project"}),
+                tool_response: json!(""),
+            })
+        };
+
+        // The ACP Bash tool starts a new shell in the cwd every time
+        hook(cd("mcp__acp__Bash"), None, HookContext::default()).await;
+        assert_eq!(checker.read().await.current_dir(), Path::new("/tmp"));
+
+        hook(cd("Bash"), None, HookContext::default()).await;
+        assert_eq!(
+            checker.read().await.current_dir(),
+            Path::new("/tmp/project")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post_tool_use_hook_interrupts_on_oversized_output() {
+        let checker = checker_with_output_rule(ToolOutputRule {
+            tool: Some("Bash".to_string()),
+            max_bytes: Some(16),
+            interrupt: true,
+            ..Default::default()
+        });
+        let hook = create_post_tool_use_hook(Arc::new(HookCallbackRegistry::new()), Some(checker));
+
+        let result = hook(
+            bash_output(json!("x".repeat(64))),
+            None,
+            HookContext::default(),
+        )
+        .await;
+
+        match result {
+            HookJsonOutput::Sync(output) => {
+                assert_eq!(output.continue_, Some(false));
+                assert!(output.stop_reason.unwrap().contains("64 bytes"));
+            }
+            HookJsonOutput::Async(_) => panic!("Expected sync output"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_post_tool_use_hook_warns_without_interrupt() {
+        let checker = checker_with_output_rule(ToolOutputRule {
+            max_bytes: Some(16),
+            ..Default::default()
+        });
+        let hook = create_post_tool_use_hook(Arc::new(HookCallbackRegistry::new()), Some(checker));
+
+        let result = hook(
+            bash_output(json!("x".repeat(64))),
+            None,
+            HookContext::default(),
+        )
+        .await;
+        match result {
+            HookJsonOutput::Sync(output) => {
+                assert_eq!(output.continue_, Some(true));
+                assert!(output.system_message.is_some());
+            }
+            HookJsonOutput::Async(_) => panic!("Expected sync output"),
+        }
+
+        // Output within the limit passes silently
+        let result = hook(bash_output(json!("small")), None, HookContext::default()).await;
+        match result {
+            HookJsonOutput::Sync(output) => {
+                assert_eq!(output.continue_, Some(true));
+                assert!(output.system_message.is_none());
+            }
+            HookJsonOutput::Async(_) => panic!("Expected sync output"),
+        }
+    }
 }
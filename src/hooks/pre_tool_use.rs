@@ -10,7 +10,6 @@ use claude_code_agent_sdk::{
     HookCallback, HookContext, HookInput, HookJsonOutput, HookSpecificOutput,
     PreToolUseHookSpecificOutput, SyncHookJsonOutput,
 };
-use dashmap::DashMap;
 use futures::future::BoxFuture;
 use sacp::{
     JrConnectionCx,
@@ -23,10 +22,18 @@ use sacp::{
 use tokio::sync::RwLock;
 use tracing::Instrument;
 
-use crate::command_safety::{command_might_be_dangerous, is_known_safe_command};
-use crate::session::{PermissionHandler, PermissionMode};
+use crate::command_safety::{
+    bash_command_uses_network, classify_sudo_command, command_might_be_dangerous_including,
+    extract_command_basename, find_configured_dangerous_command, find_remote_execution_command,
+    is_known_safe_command_excluding, split_command_lines, split_command_segments,
+    strip_background_operator, within_auto_approve_len,
+};
+use crate::session::{
+    HandoffCache, MAX_PAUSE_WAIT, PermissionHandler, PermissionMode, PermissionStateStore,
+    ToolCallControl, ToolPermissionResult,
+};
 use crate::settings::PermissionChecker;
-use crate::utils::is_plans_directory_path;
+use crate::utils::{is_path_in_any_plans_dir, tool_input_preview};
 
 /// Creates a PreToolUse hook that checks permissions using settings rules and permission mode.
 ///
@@ -42,12 +49,26 @@ use crate::utils::is_plans_directory_path;
 /// # Permission Mode Integration
 ///
 /// The hook respects the session's permission mode:
-/// - **BypassPermissions/AcceptEdits**: Allows all tools without checking rules
-///   (AcceptEdits behaves like BypassPermissions for root compatibility)
+/// - **BypassPermissions**: Allows all tools without checking rules
+/// - **AcceptEdits**: Allows every tool no deny rule matches (like
+///   BypassPermissions for root compatibility, but deny rules apply). With
+///   `strictAcceptEdits`, AcceptEdits auto-allows only reads, file edits inside
+///   the working directory or `additionalDirectories` and known safe Bash
+///   commands, and checks settings rules for the rest
 /// - **Plan**: Blocks write operations (Edit, Write, Bash, NotebookEdit)
 /// - **Default**: Auto-allows read-only operations (Read, Grep, Glob, LS, NotebookRead),
 ///   checks settings rules for other tools
 /// - **DontAsk**: Checks settings rules and mode-based auto-approval
+/// - **Interactive**: Never allows on its own; settings allow rules are downgraded to ask
+///
+/// Settings deny rules are checked before any of these modes auto-allows a
+/// tool, so e.g. `protectSecrets` still blocks reading `.env` in Default mode.
+/// Only BypassPermissions skips them.
+///
+/// A strategy registered for the active mode (see
+/// [`PermissionHandler::register_strategy`]) replaces all of the above: the
+/// hook asks the permission handler, which applies settings rules and then
+/// the strategy, just as `can_use_tool` does.
 ///
 /// # Architecture
 ///
@@ -64,19 +85,25 @@ use crate::utils::is_plans_directory_path;
 /// * `session_id` - Session ID for permission requests
 /// * `permission_checker` - Optional permission checker for settings-based rules
 /// * `permission` - Shared permission handler (contains mode that can be updated at runtime)
+/// * `tool_call_control` - Session-wide holds (emergency stop, pause) that override every decision
 /// * `permission_cache` - Cache for storing permission results (for can_use_tool callback)
 /// * `tool_use_id_cache` - Cache for storing tool_use_id (for can_use_tool callback)
+/// * `permission_state_store` - Where the session's permission state is saved when
+///   a use-limited rule is spent or idle rules expire (None = not saved)
 ///
 /// # Returns
 ///
 /// A hook callback that can be used with ClaudeAgentOptions
+#[allow(clippy::too_many_arguments)]
 pub fn create_pre_tool_use_hook(
     connection_cx_lock: Arc<OnceLock<JrConnectionCx<AgentToClient>>>,
     session_id: String,
     permission_checker: Option<Arc<RwLock<PermissionChecker>>>,
     permission: Arc<RwLock<PermissionHandler>>,
-    permission_cache: Arc<DashMap<String, bool>>,
-    tool_use_id_cache: Arc<DashMap<String, String>>,
+    tool_call_control: Arc<ToolCallControl>,
+    permission_cache: Arc<HandoffCache<bool>>,
+    tool_use_id_cache: Arc<HandoffCache<String>>,
+    permission_state_store: Option<Arc<PermissionStateStore>>,
 ) -> HookCallback {
     Arc::new(
         move |input: HookInput, tool_use_id: Option<String>, _context: HookContext| {
@@ -84,9 +111,11 @@ pub fn create_pre_tool_use_hook(
             let connection_cx_lock = Arc::clone(&connection_cx_lock);
             let permission_checker = permission_checker.clone();
             let permission = permission.clone();
+            let tool_call_control = Arc::clone(&tool_call_control);
             let session_id = session_id.clone();
             let _permission_cache = Arc::clone(&permission_cache);
             let tool_use_id_cache = Arc::clone(&tool_use_id_cache);
+            let permission_state_store = permission_state_store.clone();
 
             // Extract tool name early for span naming
             let (tool_name, is_pre_tool) = match &input {
@@ -129,27 +158,84 @@ pub fn create_pre_tool_use_hook(
                     tracing::debug!(
                         tool_name = %tool_name,
                         tool_use_id = ?tool_use_id,
+                        tool_input = %tool_input_preview(&tool_input, 100),
                         "PreToolUse hook triggered"
                     );
 
-                    // IMPORTANT: ExitPlanMode is handled specially by canUseTool callback
-                    // We skip all permission checks here to avoid double permission prompts
-                    // The canUseTool callback will handle the permission dialog
+                    // An emergency stop denies every tool call, before any mode or rule
+                    // could approve it (and before ExitPlanMode is handed to the callback)
+                    if tool_call_control.is_emergency_stopped() {
+                        tracing::info!(
+                            tool_name = %tool_name,
+                            tool_use_id = ?tool_use_id,
+                            "Emergency stop in effect - denying tool call"
+                        );
+                        return create_emergency_stop_response(
+                            &connection_cx_lock,
+                            &session_id,
+                            tool_use_id.as_ref(),
+                            &tool_name,
+                        );
+                    }
+
+                    // A paused session holds the tool call until resumed, so nothing the
+                    // hook approves runs meanwhile
+                    if tool_call_control.is_paused() {
+                        tracing::info!(
+                            tool_name = %tool_name,
+                            tool_use_id = ?tool_use_id,
+                            "Session paused - holding tool call until resumed"
+                        );
+                        tokio::select! {
+                            resumed = tool_call_control.wait_while_paused(MAX_PAUSE_WAIT) => {
+                                if !resumed {
+                                    tracing::warn!(
+                                        tool_name = %tool_name,
+                                        tool_use_id = ?tool_use_id,
+                                        "Session stayed paused too long - denying tool call"
+                                    );
+                                    return create_deny_response(
+                                        &connection_cx_lock,
+                                        &session_id,
+                                        tool_use_id.as_ref(),
+                                        &tool_name,
+                                        "Session is paused - tool call was not resumed in time"
+                                            .to_string(),
+                                    );
+                                }
+                            }
+                            () = tool_call_control.emergency_stopped() => {
+                                return create_emergency_stop_response(
+                                    &connection_cx_lock,
+                                    &session_id,
+                                    tool_use_id.as_ref(),
+                                    &tool_name,
+                                );
+                            }
+                        }
+                    }
+
+                    // IMPORTANT: ExitPlanMode and AskUserQuestion are handled specially by
+                    // canUseTool callback. We skip all permission checks here to avoid double
+                    // permission prompts (and so permissive modes still reach the callback)
+                    // The canUseTool callback will show the dialog or question
                     let stripped_tool_name = tool_name.strip_prefix("mcp__acp__").unwrap_or(&tool_name);
-                    if stripped_tool_name == "ExitPlanMode" {
+                    if matches!(stripped_tool_name, "ExitPlanMode" | "AskUserQuestion") {
                         tracing::info!(
                             tool_name = %tool_name,
                             tool_use_id = ?tool_use_id,
-                            "ExitPlanMode detected in pre_tool_use - skipping permission checks, delegating to canUseTool callback"
+                            "{} detected in pre_tool_use - skipping permission checks, delegating to canUseTool callback",
+                            stripped_tool_name
                         );
                         return HookJsonOutput::Sync(SyncHookJsonOutput {
                             continue_: Some(true),
                             hook_specific_output: Some(HookSpecificOutput::PreToolUse(
                                 PreToolUseHookSpecificOutput {
                                     permission_decision: Some("defer".to_string()),
-                                    permission_decision_reason: Some(
-                                        "ExitPlanMode permission handled by canUseTool callback".to_string()
-                                    ),
+                                    permission_decision_reason: Some(format!(
+                                        "{} permission handled by canUseTool callback",
+                                        stripped_tool_name
+                                    )),
                                     updated_input: None,
                                 },
                             )),
@@ -157,15 +243,222 @@ pub fn create_pre_tool_use_hook(
                         });
                     }
 
-                    // Get current permission mode
-                    let mode = permission.read().await.mode();
+                    // Get current permission mode (plus Plan mode's plans directories,
+                    // any safe commands the user disabled and any extra dangerous commands)
+                    let (
+                        mode,
+                        plans_dirs,
+                        disabled_safe_commands,
+                        max_auto_approve_command_len,
+                        safe_command_classifier,
+                        extra_dangerous_commands,
+                        prompt_for_network_commands,
+                        strict_accept_edits,
+                        registered_strategy,
+                        rules_expired,
+                    ) = {
+                        let permission = permission.read().await;
+                        // Expire idle "Always Allow" rules before any rule is consulted
+                        let rules_expired = permission.record_activity().await;
+                        (
+                            permission.mode(),
+                            permission.plans_dirs(),
+                            permission.disabled_safe_commands().to_vec(),
+                            permission.max_auto_approve_command_len(),
+                            permission.safe_command_classifier().cloned(),
+                            permission.extra_dangerous_commands().to_vec(),
+                            permission.prompt_for_network_commands(),
+                            permission.mode() == PermissionMode::AcceptEdits
+                                && permission.strict_accept_edits(),
+                            permission.uses_registered_strategy(),
+                            rules_expired,
+                        )
+                    };
+                    if rules_expired {
+                        save_permission_state(
+                            permission_state_store.as_deref(),
+                            &permission,
+                            permission_checker.as_deref(),
+                        )
+                        .await;
+                    }
 
-                    // BypassPermissions and AcceptEdits modes allow everything
-                    // (AcceptEdits behaves like BypassPermissions for root compatibility)
+                    // A registered strategy decides instead of the built-in mode handling
+                    if registered_strategy {
+                        let result = permission
+                            .read()
+                            .await
+                            .check_permission(&tool_name, &tool_input)
+                            .await;
+                        if result == ToolPermissionResult::Allowed {
+                            // The call may have spent a use-limited rule
+                            save_permission_state(
+                                permission_state_store.as_deref(),
+                                &permission,
+                                permission_checker.as_deref(),
+                            )
+                            .await;
+                        }
+                        tracing::info!(
+                            tool_name = %tool_name,
+                            tool_use_id = ?tool_use_id,
+                            result = ?result,
+                            elapsed_us = start_time.elapsed().as_micros(),
+                            "Permission decided by registered strategy"
+                        );
+                        return match result {
+                            ToolPermissionResult::Allowed => HookJsonOutput::Sync(SyncHookJsonOutput {
+                                continue_: Some(true),
+                                hook_specific_output: Some(HookSpecificOutput::PreToolUse(
+                                    PreToolUseHookSpecificOutput {
+                                        permission_decision: Some("allow".to_string()),
+                                        permission_decision_reason: Some(
+                                            "Allowed by registered permission strategy".to_string(),
+                                        ),
+                                        updated_input: None,
+                                    },
+                                )),
+                                ..Default::default()
+                            }),
+                            ToolPermissionResult::Blocked { reason } => {
+                                create_counted_deny_response(
+                                    &connection_cx_lock,
+                                    &session_id,
+                                    tool_use_id.as_ref(),
+                                    &tool_name,
+                                    &tool_input,
+                                    reason,
+                                    &tool_call_control,
+                                )
+                            }
+                            ToolPermissionResult::NeedsPermission => {
+                                // can_use_tool asks the same strategy and prompts
+                                if let Some(ref tuid) = tool_use_id {
+                                    let key = crate::session::stable_cache_key(&tool_name, &tool_input);
+                                    tool_use_id_cache.insert(key, tuid.clone());
+                                }
+                                HookJsonOutput::Sync(SyncHookJsonOutput {
+                                    continue_: Some(true),
+                                    hook_specific_output: None,
+                                    ..Default::default()
+                                })
+                            }
+                        };
+                    }
+
+                    // Deny rules beat every auto-allow below; only BypassPermissions skips
+                    // rules altogether
+                    if mode != PermissionMode::BypassPermissions
+                        && let Some(rule) =
+                            matching_deny_rule(&permission_checker, &tool_name, &tool_input).await
+                    {
+                        tracing::info!(
+                            tool_name = %tool_name,
+                            tool_use_id = ?tool_use_id,
+                            rule = %rule,
+                            elapsed_us = start_time.elapsed().as_micros(),
+                            "Tool execution denied by rule"
+                        );
+                        return create_counted_deny_response(
+                            &connection_cx_lock,
+                            &session_id,
+                            tool_use_id.as_ref(),
+                            &tool_name,
+                            &tool_input,
+                            rule,
+                            &tool_call_control,
+                        );
+                    }
+
+                    // Configured always-dangerous commands prompt in every mode, so they
+                    // are checked before any mode or rule can auto-approve them
+                    if stripped_tool_name == "Bash"
+                        && !extra_dangerous_commands.is_empty()
+                        && let Some(raw_cmd) = tool_input.get("command").and_then(|v| v.as_str())
+                    {
+                        let resolved_cmd = match &permission_checker {
+                            Some(checker) => {
+                                checker.read().await.resolve_command_alias(raw_cmd).into_owned()
+                            }
+                            None => raw_cmd.to_string(),
+                        };
+                        if let Some(dangerous) =
+                            find_configured_dangerous_command(&resolved_cmd, &extra_dangerous_commands)
+                        {
+                            tracing::warn!(
+                                tool_name = %tool_name,
+                                command = %resolved_cmd,
+                                tool_use_id = ?tool_use_id,
+                                "Bash command uses a configured dangerous command; confirmation required"
+                            );
+                            if let Some(ref tuid) = tool_use_id {
+                                let key = crate::session::stable_cache_key(&tool_name, &tool_input);
+                                tool_use_id_cache.insert(key, tuid.clone());
+                            }
+                            return HookJsonOutput::Sync(SyncHookJsonOutput {
+                                continue_: Some(true),
+                                hook_specific_output: Some(HookSpecificOutput::PreToolUse(
+                                    PreToolUseHookSpecificOutput {
+                                        permission_decision: Some("ask".to_string()),
+                                        permission_decision_reason: Some(format!(
+                                            "{} always requires confirmation (extraDangerousCommands)",
+                                            dangerous
+                                        )),
+                                        updated_input: None,
+                                    },
+                                )),
+                                ..Default::default()
+                            });
+                        }
+                    }
+
+                    // Network access prompts in every mode too, when configured
+                    if prompt_for_network_commands
+                        && stripped_tool_name == "Bash"
+                        && let Some(raw_cmd) = tool_input.get("command").and_then(|v| v.as_str())
+                    {
+                        let resolved_cmd = match &permission_checker {
+                            Some(checker) => {
+                                checker.read().await.resolve_command_alias(raw_cmd).into_owned()
+                            }
+                            None => raw_cmd.to_string(),
+                        };
+                        if bash_command_uses_network(&resolved_cmd) {
+                            tracing::info!(
+                                tool_name = %tool_name,
+                                command = %resolved_cmd,
+                                tool_use_id = ?tool_use_id,
+                                "Bash command accesses the network; confirmation required"
+                            );
+                            if let Some(ref tuid) = tool_use_id {
+                                let key = crate::session::stable_cache_key(&tool_name, &tool_input);
+                                tool_use_id_cache.insert(key, tuid.clone());
+                            }
+                            return HookJsonOutput::Sync(SyncHookJsonOutput {
+                                continue_: Some(true),
+                                hook_specific_output: Some(HookSpecificOutput::PreToolUse(
+                                    PreToolUseHookSpecificOutput {
+                                        permission_decision: Some("ask".to_string()),
+                                        permission_decision_reason: Some(
+                                            "Network access always requires confirmation (promptForNetworkCommands)"
+                                                .to_string(),
+                                        ),
+                                        updated_input: None,
+                                    },
+                                )),
+                                ..Default::default()
+                            });
+                        }
+                    }
+
+                    // BypassPermissions and AcceptEdits modes allow everything left
+                    // (AcceptEdits behaves like BypassPermissions for root compatibility,
+                    // unless strict AcceptEdits is enabled; deny rules were checked above)
                     if matches!(
                         mode,
                         PermissionMode::BypassPermissions | PermissionMode::AcceptEdits
-                    ) {
+                    ) && !strict_accept_edits
+                    {
                         let elapsed = start_time.elapsed();
                         let mode_str = match mode {
                             PermissionMode::BypassPermissions => "BypassPermissions",
@@ -196,12 +489,43 @@ pub fn create_pre_tool_use_hook(
                         });
                     }
 
+                    // Strict AcceptEdits mode: auto-allow file edits in the working directories
+                    if strict_accept_edits
+                        && matches!(
+                            stripped_tool_name,
+                            "Edit" | "Write" | "MultiEdit" | "NotebookEdit"
+                        )
+                        && is_edit_in_working_directories(&permission_checker, &tool_input).await
+                    {
+                        tracing::debug!(
+                            tool_name = %tool_name,
+                            tool_use_id = ?tool_use_id,
+                            mode = "acceptEdits",
+                            elapsed_us = start_time.elapsed().as_micros(),
+                            "Tool auto-allowed in strict AcceptEdits mode (file edit)"
+                        );
+                        return HookJsonOutput::Sync(SyncHookJsonOutput {
+                            continue_: Some(true),
+                            hook_specific_output: Some(HookSpecificOutput::PreToolUse(
+                                PreToolUseHookSpecificOutput {
+                                    permission_decision: Some("allow".to_string()),
+                                    permission_decision_reason: Some(
+                                        "Auto-allowed in AcceptEdits mode (file edit)".to_string(),
+                                    ),
+                                    updated_input: None,
+                                },
+                            )),
+                            ..Default::default()
+                        });
+                    }
+
                     // Default mode: auto-allow read-only operations
-                    // This allows tools like Read, Grep, Glob, LS, NotebookRead to execute without permission prompt
-                    if mode == PermissionMode::Default {
+                    // This allows tools like Read, Grep, Glob, LS, NotebookRead, BashOutput to execute without permission prompt
+                    // (strict AcceptEdits mode treats reads and Bash the same way)
+                    if mode == PermissionMode::Default || strict_accept_edits {
                         let is_read_only = matches!(
                             stripped_tool_name,
-                            "Read" | "Grep" | "Glob" | "LS" | "NotebookRead"
+                            "Read" | "Grep" | "Glob" | "LS" | "NotebookRead" | "BashOutput"
                         );
                         if is_read_only {
                             let elapsed = start_time.elapsed();
@@ -230,9 +554,24 @@ pub fn create_pre_tool_use_hook(
 
                         // Check Bash commands for known safe commands (auto-allow)
                         if stripped_tool_name == "Bash"
-                            && let Some(cmd) = tool_input.get("command").and_then(|v| v.as_str()) {
-                                // Check if this is a known safe command
-                                if is_known_safe_command(cmd) {
+                            && let Some(raw_cmd) = tool_input.get("command").and_then(|v| v.as_str()) {
+                                // Classify the command a configured wrapper or alias runs
+                                let resolved_cmd = match &permission_checker {
+                                    Some(checker) => {
+                                        checker.read().await.resolve_command_alias(raw_cmd).into_owned()
+                                    }
+                                    None => raw_cmd.to_string(),
+                                };
+                                let cmd = resolved_cmd.as_str();
+
+                                // Check if this is a known safe command, short enough to review
+                                let is_safe = match &safe_command_classifier {
+                                    Some(classifier) => classifier.is_safe_command(cmd),
+                                    None => is_known_safe_command_excluding(cmd, &disabled_safe_commands),
+                                };
+                                if within_auto_approve_len(raw_cmd, max_auto_approve_command_len)
+                                    && is_safe
+                                {
                                     let elapsed = start_time.elapsed();
                                     tracing::info!(
                                         tool_name = %tool_name,
@@ -247,10 +586,9 @@ pub fn create_pre_tool_use_hook(
                                         hook_specific_output: Some(HookSpecificOutput::PreToolUse(
                                             PreToolUseHookSpecificOutput {
                                                 permission_decision: Some("allow".to_string()),
-                                                permission_decision_reason: Some(format!(
-                                                    "Auto-allowed: known safe command ({})",
-                                                    cmd.split_whitespace().next().unwrap_or("")
-                                                )),
+                                                permission_decision_reason: Some(
+                                                    known_safe_command_reason(cmd),
+                                                ),
                                                 updated_input: None,
                                             },
                                         )),
@@ -259,11 +597,12 @@ pub fn create_pre_tool_use_hook(
                                 }
 
                                 // Check if this is a dangerous command (log warning for user awareness)
-                                if command_might_be_dangerous(cmd) {
+                                if command_might_be_dangerous_including(cmd, &extra_dangerous_commands) {
                                     tracing::warn!(
                                         tool_name = %tool_name,
                                         command = %cmd,
                                         tool_use_id = ?tool_use_id,
+                                        sudo = ?classify_sudo_command(cmd),
                                         "Bash command flagged as potentially dangerous"
                                     );
                                     // Continue to normal permission flow - user will be asked
@@ -285,8 +624,7 @@ pub fn create_pre_tool_use_hook(
                                     .get("file_path")
                                     .or_else(|| tool_input.get("path"))
                                     .and_then(|v| v.as_str())
-                                    .map(is_plans_directory_path)
-                                    .unwrap_or(false)
+                                    .is_some_and(|path| is_path_in_any_plans_dir(path, &plans_dirs))
                             } else {
                                 // Bash is never allowed in Plan mode
                                 false
@@ -294,8 +632,17 @@ pub fn create_pre_tool_use_hook(
 
                             if !is_plan_file {
                                 let reason = format!(
-                                    "Tool {} is not allowed in Plan mode (only read operations and writing to ~/.claude/plans/ are allowed)",
-                                    stripped_tool_name
+                                    "Tool {} is not allowed in Plan mode (only read operations and writing to {} are allowed)",
+                                    stripped_tool_name,
+                                    if plans_dirs.is_empty() {
+                                        "~/.claude/plans/".to_string()
+                                    } else {
+                                        plans_dirs
+                                            .iter()
+                                            .map(|dir| dir.display().to_string())
+                                            .collect::<Vec<_>>()
+                                            .join(" or ")
+                                    }
                                 );
                                 tracing::warn!(
                                     tool_name = %tool_name,
@@ -304,12 +651,14 @@ pub fn create_pre_tool_use_hook(
                                     elapsed_us = start_time.elapsed().as_micros(),
                                     "Tool blocked by Plan mode"
                                 );
-                                return create_deny_response(
+                                return create_counted_deny_response(
                                     &connection_cx_lock,
                                     &session_id,
                                     tool_use_id.as_ref(),
                                     &tool_name,
+                                    &tool_input,
                                     reason,
+                                    &tool_call_control,
                                 );
                             }
 
@@ -324,7 +673,7 @@ pub fn create_pre_tool_use_hook(
                         // Auto-allow read operations in Plan mode
                         let is_read_only = matches!(
                             stripped_tool_name,
-                            "Read" | "Grep" | "Glob" | "LS" | "NotebookRead"
+                            "Read" | "Grep" | "Glob" | "LS" | "NotebookRead" | "BashOutput"
                         );
                         if is_read_only {
                             return HookJsonOutput::Sync(SyncHookJsonOutput {
@@ -346,7 +695,28 @@ pub fn create_pre_tool_use_hook(
                     // Check permission (if checker is available, otherwise default to Ask)
                     let permission_check = if let Some(checker) = &permission_checker {
                         let checker = checker.read().await;
-                        checker.check_permission(&tool_name, &tool_input)
+                        let result = checker.check_permission(&tool_name, &tool_input);
+                        // Interactive mode prompts even for tools allowed by rules, and
+                        // no rule approves remote package execution (`npx pkg`)
+                        let rule_cannot_allow = mode == PermissionMode::Interactive
+                            || (stripped_tool_name == "Bash"
+                                && tool_input.get("command").and_then(|v| v.as_str()).is_some_and(
+                                    |cmd| {
+                                        find_remote_execution_command(
+                                            &checker.resolve_command_alias(cmd),
+                                        )
+                                        .is_some()
+                                    },
+                                ));
+                        if result.decision != crate::settings::PermissionDecision::Allow {
+                            result
+                        } else if rule_cannot_allow || !checker.spend_use(&result) {
+                            // A use-limited rule is only spent by a call it allows;
+                            // one whose last use was just taken allows nothing
+                            crate::settings::PermissionCheckResult::ask()
+                        } else {
+                            result
+                        }
                     } else {
                         // No permission checker - default to Ask
                         crate::settings::PermissionCheckResult {
@@ -355,6 +725,15 @@ pub fn create_pre_tool_use_hook(
                             source: None,
                         }
                     };
+                    if permission_check.is_use_limited() {
+                        // The rule has one use less (or is gone) from now on
+                        save_permission_state(
+                            permission_state_store.as_deref(),
+                            &permission,
+                            permission_checker.as_deref(),
+                        )
+                        .await;
+                    }
                     let elapsed = start_time.elapsed();
 
                     // Record permission decision to span (batched for performance)
@@ -392,7 +771,9 @@ pub fn create_pre_tool_use_hook(
                                 hook_specific_output: Some(HookSpecificOutput::PreToolUse(
                                     PreToolUseHookSpecificOutput {
                                         permission_decision: Some("allow".to_string()),
-                                        permission_decision_reason: permission_check.rule,
+                                        permission_decision_reason: permission_check
+                                            .rule
+                                            .map(|rule| format!("Allowed by rule: {}", rule)),
                                         updated_input: None,
                                     },
                                 )),
@@ -416,12 +797,14 @@ pub fn create_pre_tool_use_hook(
                                 };
                                 format!("Tool {} denied by permission settings", display_name)
                             });
-                            create_deny_response(
+                            create_counted_deny_response(
                                 &connection_cx_lock,
                                 &session_id,
                                 tool_use_id.as_ref(),
                                 &tool_name,
+                                &tool_input,
                                 reason,
+                                &tool_call_control,
                             )
                         }
                         crate::settings::PermissionDecision::Ask => {
@@ -442,7 +825,7 @@ pub fn create_pre_tool_use_hook(
                             // The CLI doesn't always pass tool_use_id in mcp_message requests,
                             // so we cache it here where we have it.
                             if let Some(ref tuid) = tool_use_id {
-                                let key = crate::session::stable_cache_key(&tool_input);
+                                let key = crate::session::stable_cache_key(&tool_name, &tool_input);
                                 tracing::debug!(
                                     tool_name = %tool_name,
                                     tool_use_id = %tuid,
@@ -545,6 +928,68 @@ fn send_denied_tool_result(
     }
 }
 
+/// Whether a file edit targets the working directory or an additional directory
+///
+/// Without a permission checker there is no working directory to compare
+/// against, so every edit counts as inside.
+async fn is_edit_in_working_directories(
+    permission_checker: &Option<Arc<RwLock<PermissionChecker>>>,
+    tool_input: &serde_json::Value,
+) -> bool {
+    let Some(checker) = permission_checker else {
+        return true;
+    };
+    let Some(path) = tool_input
+        .get("file_path")
+        .or_else(|| tool_input.get("notebook_path"))
+        .or_else(|| tool_input.get("path"))
+        .and_then(|v| v.as_str())
+    else {
+        return false;
+    };
+    checker.read().await.is_in_working_directory(path)
+}
+
+/// Get the deny rule blocking a tool call, before the hook auto-allows anything
+async fn matching_deny_rule(
+    permission_checker: &Option<Arc<RwLock<PermissionChecker>>>,
+    tool_name: &str,
+    tool_input: &serde_json::Value,
+) -> Option<String> {
+    let denied = permission_checker
+        .as_ref()?
+        .read()
+        .await
+        .check_deny(tool_name, tool_input)?;
+    Some(
+        denied
+            .rule
+            .unwrap_or_else(|| format!("Tool {} denied by permission settings", tool_name)),
+    )
+}
+
+/// Explain why a Bash command was auto-approved as a known safe command
+///
+/// Names every distinct command it runs, e.g. `git status && git log | head`
+/// gives "Auto-allowed: known safe command, read-only (git, head)".
+fn known_safe_command_reason(command: &str) -> String {
+    let mut names: Vec<&str> = Vec::new();
+    let lines = split_command_lines(command);
+    for segment in lines.iter().flat_map(|line| split_command_segments(line)) {
+        let Some(first) = strip_background_operator(segment).split_whitespace().next() else {
+            continue;
+        };
+        let name = extract_command_basename(first);
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    format!(
+        "Auto-allowed: known safe command, read-only ({})",
+        names.join(", ")
+    )
+}
+
 /// Create a deny hook response with tool_result notification
 ///
 /// This helper function ensures that when a tool is denied:
@@ -588,10 +1033,93 @@ fn create_deny_response(
     })
 }
 
+/// Create the deny response for a tool call made during an emergency stop
+///
+/// Like [`create_deny_response`], but also stops the agent's turn, matching
+/// the interrupting denial `can_use_tool` gives during a stop.
+fn create_emergency_stop_response(
+    connection_cx_lock: &Arc<OnceLock<JrConnectionCx<AgentToClient>>>,
+    session_id: &str,
+    tool_use_id: Option<&String>,
+    tool_name: &str,
+) -> HookJsonOutput {
+    let reason = "Emergency stop in effect - tool execution denied".to_string();
+    stop_turn(
+        create_deny_response(
+            connection_cx_lock,
+            session_id,
+            tool_use_id,
+            tool_name,
+            reason.clone(),
+        ),
+        reason,
+    )
+}
+
+/// Create the deny response for a tool call denied by a rule or strategy
+///
+/// The denial counts toward the session's escalation threshold, shared with
+/// denials made in `can_use_tool`; once the same call has been denied that
+/// many times, the response also stops the agent's turn.
+fn create_counted_deny_response(
+    connection_cx_lock: &Arc<OnceLock<JrConnectionCx<AgentToClient>>>,
+    session_id: &str,
+    tool_use_id: Option<&String>,
+    tool_name: &str,
+    tool_input: &serde_json::Value,
+    reason: String,
+    tool_call_control: &ToolCallControl,
+) -> HookJsonOutput {
+    let escalate = tool_call_control.record_denial(tool_name, tool_input);
+    let output = create_deny_response(
+        connection_cx_lock,
+        session_id,
+        tool_use_id,
+        tool_name,
+        reason.clone(),
+    );
+    if !escalate {
+        return output;
+    }
+
+    tracing::warn!(
+        tool_name = %tool_name,
+        threshold = ?tool_call_control.denial_escalation_threshold(),
+        "Tool call denied repeatedly - stopping the turn"
+    );
+    stop_turn(output, reason)
+}
+
+/// Make a hook response also stop the agent's turn
+fn stop_turn(output: HookJsonOutput, reason: String) -> HookJsonOutput {
+    match output {
+        HookJsonOutput::Sync(output) => HookJsonOutput::Sync(SyncHookJsonOutput {
+            continue_: Some(false),
+            stop_reason: Some(reason),
+            ..output
+        }),
+        output => output,
+    }
+}
+
+/// Save the session's permission state after the hook changed it
+///
+/// Does nothing without a store or a checker.
+async fn save_permission_state(
+    store: Option<&PermissionStateStore>,
+    permission: &RwLock<PermissionHandler>,
+    checker: Option<&RwLock<PermissionChecker>>,
+) {
+    if let (Some(store), Some(checker)) = (store, checker) {
+        store.save(permission, checker).await;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::settings::{PermissionSettings, Settings};
+    use crate::utils::is_plans_directory_path;
     use serde_json::json;
 
     fn make_permission_checker(permissions: PermissionSettings) -> Arc<RwLock<PermissionChecker>> {
@@ -609,20 +1137,36 @@ mod tests {
     fn make_test_hook_with_mode(
         checker: Arc<RwLock<PermissionChecker>>,
         mode: PermissionMode,
+    ) -> HookCallback {
+        // Create PermissionHandler with the specified mode
+        make_test_hook_with_handler(checker, PermissionHandler::with_mode(mode))
+    }
+
+    fn make_test_hook_with_handler(
+        checker: Arc<RwLock<PermissionChecker>>,
+        permission: PermissionHandler,
+    ) -> HookCallback {
+        make_test_hook_with_control(checker, permission, Arc::new(ToolCallControl::new()))
+    }
+
+    fn make_test_hook_with_control(
+        checker: Arc<RwLock<PermissionChecker>>,
+        permission: PermissionHandler,
+        control: Arc<ToolCallControl>,
     ) -> HookCallback {
         let connection_cx_lock: Arc<OnceLock<JrConnectionCx<AgentToClient>>> =
             Arc::new(OnceLock::new());
-        let permission_cache: Arc<DashMap<String, bool>> = Arc::new(DashMap::new());
-        let tool_use_id_cache: Arc<DashMap<String, String>> = Arc::new(DashMap::new());
-        // Create PermissionHandler with the specified mode
-        let permission = PermissionHandler::with_mode(mode);
+        let permission_cache: Arc<HandoffCache<bool>> = Arc::new(HandoffCache::default());
+        let tool_use_id_cache: Arc<HandoffCache<String>> = Arc::new(HandoffCache::default());
         create_pre_tool_use_hook(
             connection_cx_lock,
             "test-session".to_string(),
             Some(checker),
             Arc::new(RwLock::new(permission)),
+            control,
             permission_cache,
             tool_use_id_cache,
+            None,
         )
     }
 
@@ -659,6 +1203,43 @@ mod tests {
         }
     }
 
+    /// Run the hook on a Bash command and return the decision and its reason
+    async fn bash_decision(hook: &HookCallback, command: &str) -> (Option<String>, Option<String>) {
+        let input = make_pre_tool_input("Bash", json!({"command": command}));
+        match hook(input, None, HookContext::default()).await {
+            HookJsonOutput::Sync(SyncHookJsonOutput {
+                hook_specific_output: Some(HookSpecificOutput::PreToolUse(specific)),
+                ..
+            }) => (
+                specific.permission_decision,
+                specific.permission_decision_reason,
+            ),
+            _ => panic!("Expected sync PreToolUse output"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_allow_reasons() {
+        let checker = make_permission_checker(PermissionSettings {
+            allow: Some(vec!["Bash(npm run:*)".to_string()]),
+            ..Default::default()
+        });
+        let hook = make_test_hook(checker);
+
+        // A known safe command says it was deemed safe, naming the commands
+        let (decision, reason) = bash_decision(&hook, "git status && git log | head -5").await;
+        assert_eq!(decision.as_deref(), Some("allow"));
+        assert_eq!(
+            reason.as_deref(),
+            Some("Auto-allowed: known safe command, read-only (git, head)")
+        );
+
+        // A rule-based allow names the rule
+        let (decision, reason) = bash_decision(&hook, "npm run build").await;
+        assert_eq!(decision.as_deref(), Some("allow"));
+        assert_eq!(reason.as_deref(), Some("Allowed by rule: Bash(npm run:*)"));
+    }
+
     // TODO: Re-enable when implementing permission checks
     // #[tokio::test]
     // async fn test_pre_tool_use_hook_deny() {
@@ -939,6 +1520,494 @@ mod tests {
         }
     }
 
+    fn make_pre_tool_input(tool_name: &str, tool_input: serde_json::Value) -> HookInput {
+        HookInput::PreToolUse(claude_code_agent_sdk::PreToolUseHookInput {
+            session_id: "test".to_string(),
+            transcript_path: "/tmp/test".to_string(),
+            cwd: "/tmp".to_string(),
+            permission_mode: None,
+            tool_name: tool_name.to_string(),
+            tool_input,
+        })
+    }
+
+    /// Extract the permission decision from a hook output (None = delegated)
+    fn permission_decision(output: HookJsonOutput) -> Option<String> {
+        match output {
+            HookJsonOutput::Sync(output) => match output.hook_specific_output {
+                Some(HookSpecificOutput::PreToolUse(specific)) => specific.permission_decision,
+                _ => None,
+            },
+            HookJsonOutput::Async(_) => panic!("Expected sync output"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_emergency_stop_denies_hook_approved_calls() {
+        let control = Arc::new(ToolCallControl::new());
+        let hook = make_test_hook_with_control(
+            make_permission_checker(PermissionSettings::default()),
+            PermissionHandler::with_mode(PermissionMode::BypassPermissions),
+            Arc::clone(&control),
+        );
+        let run = |tool_name: &str, tool_input: serde_json::Value| {
+            hook(
+                make_pre_tool_input(tool_name, tool_input),
+                None,
+                HookContext::default(),
+            )
+        };
+
+        // BypassPermissions approves everything in the hook itself
+        let result = run("Bash", json!({"command": "rm -rf build"})).await;
+        assert_eq!(permission_decision(result), Some("allow".to_string()));
+
+        control.emergency_stop();
+        for (tool_name, tool_input) in [
+            ("Bash", json!({"command": "rm -rf build"})),
+            ("mcp__acp__Read", json!({"file_path": "/tmp/a"})),
+            ("ExitPlanMode", json!({"plan": "1. Do it"})),
+        ] {
+            match run(tool_name, tool_input).await {
+                HookJsonOutput::Sync(output) => {
+                    assert_eq!(output.continue_, Some(false), "{tool_name}");
+                    assert!(output.stop_reason.is_some());
+                    assert_eq!(
+                        permission_decision(HookJsonOutput::Sync(output)),
+                        Some("deny".to_string())
+                    );
+                }
+                HookJsonOutput::Async(_) => panic!("Expected sync output"),
+            }
+        }
+
+        // Clearing the stop restores the normal flow
+        control.clear_emergency_stop();
+        let result = run("Bash", json!({"command": "rm -rf build"})).await;
+        assert_eq!(permission_decision(result), Some("allow".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_rule_denials_escalate() {
+        let control = Arc::new(ToolCallControl::new());
+        control.set_denial_escalation_threshold(Some(2));
+        let hook = make_test_hook_with_control(
+            make_permission_checker(PermissionSettings {
+                deny: Some(vec!["Bash(rm:*)".to_string()]),
+                ..Default::default()
+            }),
+            PermissionHandler::with_mode(PermissionMode::Default),
+            Arc::clone(&control),
+        );
+        let run = || {
+            hook(
+                make_pre_tool_input("Bash", json!({"command": "rm -rf build"})),
+                None,
+                HookContext::default(),
+            )
+        };
+
+        let continues = |output: HookJsonOutput| match output {
+            HookJsonOutput::Sync(output) => {
+                let continue_ = output.continue_;
+                assert_eq!(
+                    permission_decision(HookJsonOutput::Sync(output)),
+                    Some("deny".to_string())
+                );
+                continue_
+            }
+            HookJsonOutput::Async(_) => panic!("Expected sync output"),
+        };
+        assert_eq!(continues(run().await), Some(true));
+        // The second identical denial stops the turn
+        assert_eq!(continues(run().await), Some(false));
+        // The count starts over after escalating
+        assert_eq!(continues(run().await), Some(true));
+
+        // A user rejection in can_use_tool shares the same count
+        assert!(control.record_denial("Bash", &json!({"command": "rm -rf build"})));
+    }
+
+    #[tokio::test]
+    async fn test_pause_holds_hook_approved_calls() {
+        let control = Arc::new(ToolCallControl::new());
+        let hook = make_test_hook_with_control(
+            make_permission_checker(PermissionSettings::default()),
+            PermissionHandler::with_mode(PermissionMode::BypassPermissions),
+            Arc::clone(&control),
+        );
+
+        control.pause();
+        let held = tokio::spawn(hook(
+            make_pre_tool_input("Bash", json!({"command": "rm -rf build"})),
+            None,
+            HookContext::default(),
+        ));
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert!(!held.is_finished(), "tool call should be held while paused");
+
+        control.resume();
+        let result = tokio::time::timeout(std::time::Duration::from_secs(1), held)
+            .await
+            .expect("resume should release the held call")
+            .unwrap();
+        assert_eq!(permission_decision(result), Some("allow".to_string()));
+
+        // An emergency stop releases a held call with an interrupting denial
+        control.pause();
+        let held = tokio::spawn(hook(
+            make_pre_tool_input("Read", json!({"file_path": "/tmp/a"})),
+            None,
+            HookContext::default(),
+        ));
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        control.emergency_stop();
+        match tokio::time::timeout(std::time::Duration::from_secs(1), held)
+            .await
+            .expect("an emergency stop should release the held call")
+            .unwrap()
+        {
+            HookJsonOutput::Sync(output) => assert_eq!(output.continue_, Some(false)),
+            HookJsonOutput::Async(_) => panic!("Expected sync output"),
+        }
+    }
+
+    /// Blocks reads, allows writes and asks for everything else
+    #[derive(Debug)]
+    struct NoReadsStrategy;
+
+    impl crate::permissions::strategies::PermissionModeStrategy for NoReadsStrategy {
+        fn mode(&self) -> PermissionMode {
+            PermissionMode::Default
+        }
+
+        fn should_auto_approve(&self, tool_name: &str, _tool_input: &serde_json::Value) -> bool {
+            tool_name.ends_with("Write")
+        }
+
+        fn is_tool_blocked(
+            &self,
+            tool_name: &str,
+            _tool_input: &serde_json::Value,
+        ) -> Option<String> {
+            tool_name
+                .ends_with("Read")
+                .then(|| "Reads are off limits".to_string())
+        }
+
+        fn check_permission(
+            &self,
+            tool_name: &str,
+            tool_input: &serde_json::Value,
+        ) -> ToolPermissionResult {
+            if let Some(reason) = self.is_tool_blocked(tool_name, tool_input) {
+                ToolPermissionResult::Blocked { reason }
+            } else if self.should_auto_approve(tool_name, tool_input) {
+                ToolPermissionResult::Allowed
+            } else {
+                ToolPermissionResult::NeedsPermission
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registered_strategy_decides_in_hook() {
+        let checker = make_permission_checker(PermissionSettings {
+            deny: Some(vec!["Write(/tmp/secret/**)".to_string()]),
+            ..Default::default()
+        });
+        let mut permission = PermissionHandler::with_mode(PermissionMode::Default);
+        permission.register_strategy("default", Arc::new(NoReadsStrategy));
+        let hook = make_test_hook_with_handler(checker, permission);
+        let run = |tool_name: &str, tool_input: serde_json::Value| {
+            hook(
+                make_pre_tool_input(tool_name, tool_input),
+                None,
+                HookContext::default(),
+            )
+        };
+
+        // Default mode would auto-allow the read and a known safe command
+        let result = run("mcp__acp__Read", json!({"file_path": "/tmp/a"})).await;
+        assert_eq!(permission_decision(result), Some("deny".to_string()));
+        let result = run("Bash", json!({"command": "ls"})).await;
+        assert_eq!(permission_decision(result), None);
+
+        let result = run("mcp__acp__Write", json!({"file_path": "/tmp/a"})).await;
+        assert_eq!(permission_decision(result), Some("allow".to_string()));
+        // Settings deny rules still come first
+        let result = run("mcp__acp__Write", json!({"file_path": "/tmp/secret/a"})).await;
+        assert_eq!(permission_decision(result), Some("deny".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_background_shell_tools() {
+        for mode in [PermissionMode::Default, PermissionMode::Plan] {
+            let checker = make_permission_checker(PermissionSettings::default());
+            let hook = make_test_hook_with_mode(checker, mode);
+
+            // BashOutput only reads output - auto-allowed
+            let input = make_pre_tool_input("mcp__acp__BashOutput", json!({"bash_id": "abc"}));
+            let result = hook(input, None, HookContext::default()).await;
+            assert_eq!(
+                permission_decision(result),
+                Some("allow".to_string()),
+                "BashOutput should be auto-allowed in {:?} mode",
+                mode
+            );
+
+            // KillShell has side effects - delegated to the permission prompt
+            let input = make_pre_tool_input("mcp__acp__KillShell", json!({"shell_id": "abc"}));
+            let result = hook(input, None, HookContext::default()).await;
+            assert_eq!(
+                permission_decision(result),
+                None,
+                "KillShell should need permission in {:?} mode",
+                mode
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_safe_command_classifier_decides_auto_allow() {
+        #[derive(Debug)]
+        struct CargoOnly;
+
+        impl crate::command_safety::SafeCommandClassifier for CargoOnly {
+            fn is_safe_command(&self, command: &str) -> bool {
+                command.starts_with("cargo ")
+            }
+        }
+
+        let checker = make_permission_checker(PermissionSettings::default());
+        let mut permission = PermissionHandler::with_mode(PermissionMode::Default);
+        permission.set_safe_command_classifier(Some(Arc::new(CargoOnly)));
+        let hook = make_test_hook_with_handler(checker, permission);
+
+        let input = make_pre_tool_input("Bash", json!({"command": "cargo publish"}));
+        let result = hook(input, None, HookContext::default()).await;
+        assert_eq!(permission_decision(result), Some("allow".to_string()));
+
+        let input = make_pre_tool_input("Bash", json!({"command": "ls -la"}));
+        let result = hook(input, None, HookContext::default()).await;
+        assert_eq!(permission_decision(result), None);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_safe_command_is_not_auto_allowed() {
+        let checker = make_permission_checker(PermissionSettings::default());
+        let mut permission = PermissionHandler::with_mode(PermissionMode::Default);
+        permission.set_disabled_safe_commands(vec!["find".to_string()]);
+        let hook = make_test_hook_with_handler(checker, permission);
+
+        let input = make_pre_tool_input("Bash", json!({"command": "find . -name x"}));
+        let result = hook(input, None, HookContext::default()).await;
+        assert_eq!(permission_decision(result), None);
+
+        let input = make_pre_tool_input("Bash", json!({"command": "ls -la"}));
+        let result = hook(input, None, HookContext::default()).await;
+        assert_eq!(permission_decision(result), Some("allow".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_long_safe_command_is_not_auto_allowed() {
+        let checker = make_permission_checker(PermissionSettings::default());
+        let mut permission = PermissionHandler::with_mode(PermissionMode::Default);
+        permission.set_max_auto_approve_command_len(Some(6));
+        let hook = make_test_hook_with_handler(checker, permission);
+
+        let input = make_pre_tool_input("Bash", json!({"command": "ls -la"}));
+        let result = hook(input, None, HookContext::default()).await;
+        assert_eq!(permission_decision(result), Some("allow".to_string()));
+
+        let input = make_pre_tool_input("Bash", json!({"command": "ls -la /"}));
+        let result = hook(input, None, HookContext::default()).await;
+        assert_eq!(permission_decision(result), None);
+    }
+
+    #[tokio::test]
+    async fn test_strict_accept_edits_does_not_allow_dangerous_bash() {
+        for strict in [false, true] {
+            let checker = make_permission_checker(PermissionSettings::default());
+            let mut permission = PermissionHandler::with_mode(PermissionMode::AcceptEdits);
+            permission.set_strict_accept_edits(strict);
+            let hook = make_test_hook_with_handler(checker, permission);
+
+            // Permissive AcceptEdits allows the command; strict delegates to the prompt
+            let input = make_pre_tool_input("Bash", json!({"command": "rm -rf /"}));
+            let result = hook(input, None, HookContext::default()).await;
+            let expected = (!strict).then(|| "allow".to_string());
+            assert_eq!(permission_decision(result), expected, "strict: {}", strict);
+
+            // Edits and safe commands are allowed either way
+            let input = make_pre_tool_input("Edit", json!({"file_path": "/tmp/a.rs"}));
+            let result = hook(input, None, HookContext::default()).await;
+            assert_eq!(permission_decision(result), Some("allow".to_string()));
+
+            let input = make_pre_tool_input("Bash", json!({"command": "git status"}));
+            let result = hook(input, None, HookContext::default()).await;
+            assert_eq!(permission_decision(result), Some("allow".to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_strict_accept_edits_outside_working_directories_asks() {
+        let checker = make_permission_checker(PermissionSettings {
+            additional_directories: Some(vec!["/opt/shared".to_string()]),
+            ..Default::default()
+        });
+        let mut permission = PermissionHandler::with_mode(PermissionMode::AcceptEdits);
+        permission.set_strict_accept_edits(true);
+        let hook = make_test_hook_with_handler(checker, permission);
+
+        for (path, expected) in [
+            ("/tmp/src/a.rs", Some("allow")),
+            ("/opt/shared/b.rs", Some("allow")),
+            ("/etc/hosts", None),
+            ("/opt/other/c.rs", None),
+        ] {
+            let input = make_pre_tool_input("Edit", json!({"file_path": path}));
+            let result = hook(input, None, HookContext::default()).await;
+            assert_eq!(permission_decision(result).as_deref(), expected, "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_strict_accept_edits_honors_deny_rules() {
+        let checker = make_permission_checker(PermissionSettings {
+            deny: Some(vec!["Edit(./secrets/**)".to_string()]),
+            ..Default::default()
+        });
+        let mut permission = PermissionHandler::with_mode(PermissionMode::AcceptEdits);
+        permission.set_strict_accept_edits(true);
+        let hook = make_test_hook_with_handler(checker, permission);
+
+        let input = make_pre_tool_input("Write", json!({"file_path": "/tmp/secrets/key.pem"}));
+        let result = hook(input, None, HookContext::default()).await;
+        assert_eq!(permission_decision(result), Some("deny".to_string()));
+
+        let input = make_pre_tool_input("Write", json!({"file_path": "/tmp/src/a.rs"}));
+        let result = hook(input, None, HookContext::default()).await;
+        assert_eq!(permission_decision(result), Some("allow".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_deny_rules_checked_before_auto_allows() {
+        let settings = Settings {
+            protect_secrets: Some(true),
+            ..Default::default()
+        };
+        let checker = Arc::new(RwLock::new(PermissionChecker::new(settings, "/tmp")));
+
+        for mode in [
+            PermissionMode::Default,
+            PermissionMode::Plan,
+            PermissionMode::AcceptEdits,
+        ] {
+            let hook = make_test_hook_with_mode(checker.clone(), mode);
+            let input = make_pre_tool_input("Read", json!({"file_path": "/tmp/app/.env"}));
+            let result = hook(input, None, HookContext::default()).await;
+            assert_eq!(
+                permission_decision(result),
+                Some("deny".to_string()),
+                "{mode:?}"
+            );
+
+            let input = make_pre_tool_input("Read", json!({"file_path": "/tmp/app/main.rs"}));
+            let result = hook(input, None, HookContext::default()).await;
+            assert_eq!(
+                permission_decision(result),
+                Some("allow".to_string()),
+                "{mode:?}"
+            );
+        }
+
+        // Known safe Bash commands are auto-allowed only when no rule denies them
+        let checker = make_permission_checker(PermissionSettings {
+            deny: Some(vec!["Bash(git log:*)".to_string()]),
+            ..Default::default()
+        });
+        let hook = make_test_hook(checker);
+        let (decision, _) = bash_decision(&hook, "git log -5").await;
+        assert_eq!(decision, Some("deny".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_deny_rule_beats_accept_edits() {
+        // Permissive AcceptEdits allows every tool, but not past a settings deny rule
+        let checker = make_permission_checker(PermissionSettings {
+            deny: Some(vec![
+                "Bash(rm:*)".to_string(),
+                "Write(/tmp/locked/**)".to_string(),
+            ]),
+            ..Default::default()
+        });
+        let hook = make_test_hook_with_mode(checker, PermissionMode::AcceptEdits);
+        let (decision, _) = bash_decision(&hook, "rm -rf build").await;
+        assert_eq!(decision, Some("deny".to_string()));
+        let input = make_pre_tool_input(
+            "Write",
+            json!({"file_path": "/tmp/locked/a.txt", "content": "x"}),
+        );
+        let result = hook(input, None, HookContext::default()).await;
+        assert_eq!(permission_decision(result), Some("deny".to_string()));
+        let (decision, _) = bash_decision(&hook, "cargo build").await;
+        assert_eq!(decision, Some("allow".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_extra_dangerous_command_asks_in_bypass_mode() {
+        let checker = make_permission_checker(PermissionSettings {
+            allow: Some(vec!["Bash".to_string()]),
+            ..Default::default()
+        });
+        let mut permission = PermissionHandler::with_mode(PermissionMode::BypassPermissions);
+        permission.set_extra_dangerous_commands(vec!["wipe-db".to_string()]);
+        let hook = make_test_hook_with_handler(checker, permission);
+
+        let input = make_pre_tool_input("Bash", json!({"command": "wipe-db --env staging"}));
+        let result = hook(input, None, HookContext::default()).await;
+        assert_eq!(permission_decision(result), Some("ask".to_string()));
+
+        // Unrelated commands keep the mode's behavior
+        let input = make_pre_tool_input("Bash", json!({"command": "make build"}));
+        let result = hook(input, None, HookContext::default()).await;
+        assert_eq!(permission_decision(result), Some("allow".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_allow_rule_does_not_approve_remote_package_execution() {
+        let checker = make_permission_checker(PermissionSettings {
+            allow: Some(vec!["Bash".to_string()]),
+            ..Default::default()
+        });
+        let hook = make_test_hook_with_mode(checker, PermissionMode::Default);
+
+        let input = make_pre_tool_input("Bash", json!({"command": "npx create-react-app x"}));
+        let result = hook(input, None, HookContext::default()).await;
+        assert_ne!(permission_decision(result), Some("allow".to_string()));
+
+        let input = make_pre_tool_input("Bash", json!({"command": "make build"}));
+        let result = hook(input, None, HookContext::default()).await;
+        assert_eq!(permission_decision(result), Some("allow".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_network_command_asks_in_bypass_mode() {
+        let checker = make_permission_checker(PermissionSettings::default());
+        let mut permission = PermissionHandler::with_mode(PermissionMode::BypassPermissions);
+        permission.set_prompt_for_network_commands(true);
+        let hook = make_test_hook_with_handler(checker, permission);
+
+        let input = make_pre_tool_input("Bash", json!({"command": "wget https://example.com"}));
+        let result = hook(input, None, HookContext::default()).await;
+        assert_eq!(permission_decision(result), Some("ask".to_string()));
+
+        let input = make_pre_tool_input("Bash", json!({"command": "make build"}));
+        let result = hook(input, None, HookContext::default()).await;
+        assert_eq!(permission_decision(result), Some("allow".to_string()));
+    }
+
     #[tokio::test]
     async fn test_default_mode_auto_allows_safe_bash_commands() {
         // Default mode should auto-allow known safe Bash commands
@@ -1063,8 +2132,8 @@ mod tests {
         // Test that create_deny_response handles missing tool_use_id gracefully
         let connection_cx_lock: Arc<OnceLock<JrConnectionCx<AgentToClient>>> =
             Arc::new(OnceLock::new());
-        let permission_cache: Arc<DashMap<String, bool>> = Arc::new(DashMap::new());
-        let tool_use_id_cache: Arc<DashMap<String, String>> = Arc::new(DashMap::new());
+        let permission_cache: Arc<HandoffCache<bool>> = Arc::new(HandoffCache::default());
+        let tool_use_id_cache: Arc<HandoffCache<String>> = Arc::new(HandoffCache::default());
         // Create PermissionHandler with Default mode
         let permission = PermissionHandler::with_mode(PermissionMode::Default);
         let hook = create_pre_tool_use_hook(
@@ -1072,8 +2141,10 @@ mod tests {
             "test-session".to_string(),
             None,
             Arc::new(RwLock::new(permission)),
+            Arc::new(ToolCallControl::new()),
             permission_cache,
             tool_use_id_cache,
+            None,
         );
 
         // Test with no tool_use_id - should not panic
@@ -1102,8 +2173,8 @@ mod tests {
         // Test that empty tool_name is handled gracefully
         let _connection_cx_lock: Arc<OnceLock<JrConnectionCx<AgentToClient>>> =
             Arc::new(OnceLock::new());
-        let _permission_cache: Arc<DashMap<String, bool>> = Arc::new(DashMap::new());
-        let _tool_use_id_cache: Arc<DashMap<String, String>> = Arc::new(DashMap::new());
+        let _permission_cache: Arc<HandoffCache<bool>> = Arc::new(HandoffCache::default());
+        let _tool_use_id_cache: Arc<HandoffCache<String>> = Arc::new(HandoffCache::default());
 
         // Test with empty tool_name - should not panic and should use fallback
         let empty_tool_name = "";
@@ -74,9 +74,11 @@ pub mod converter;
 pub mod hooks;
 pub mod mcp;
 pub mod permissions;
+pub mod plans;
 pub mod session;
 pub mod settings;
 pub mod terminal;
+pub mod tools;
 pub mod tracing;
 pub mod types;
 pub mod utils;
@@ -327,6 +327,12 @@ impl ExternalMcpServer {
                         let name = t.get("name")?.as_str()?;
                         let description =
                             t.get("description").and_then(|d| d.as_str()).unwrap_or("");
+                        // MCP tools may advertise a title directly or in their annotations
+                        let title = t
+                            .get("title")
+                            .or_else(|| t.get("annotations")?.get("title"))
+                            .and_then(|title| title.as_str())
+                            .map(str::to_string);
                         let input_schema = t
                             .get("inputSchema")
                             .cloned()
@@ -335,6 +341,7 @@ impl ExternalMcpServer {
                         Some(ToolSchema {
                             name: name.to_string(),
                             description: description.to_string(),
+                            title,
                             input_schema,
                         })
                     })
@@ -928,6 +935,7 @@ impl ExternalMcpManager {
                 tools.push(ToolSchema {
                     name: format!("mcp__{}_{}", server_name, tool.name),
                     description: format!("[{}] {}", server_name, tool.description),
+                    title: tool.title.clone(),
                     input_schema: tool.input_schema.clone(),
                 });
             }
@@ -936,6 +944,35 @@ impl ExternalMcpManager {
         tools
     }
 
+    /// Get the advertised titles of all tools that have one
+    ///
+    /// Returns `(full_tool_name, title)` pairs, with tool names in the
+    /// `mcp__<server>__<tool>` form the agent calls them by.
+    pub fn tool_titles(&self) -> Vec<(String, String)> {
+        let mut titles = Vec::new();
+
+        for entry in &self.servers {
+            let Ok(server_guard) = entry.value().try_lock() else {
+                tracing::warn!(
+                    server_name = %entry.key(),
+                    "MCP server is busy, skipping for tool titles"
+                );
+                continue;
+            };
+
+            for tool in server_guard.tools() {
+                if let Some(ref title) = tool.title {
+                    titles.push((
+                        format!("mcp__{}__{}", entry.key(), tool.name),
+                        title.clone(),
+                    ));
+                }
+            }
+        }
+
+        titles
+    }
+
     /// Call a tool on an external server
     ///
     /// Tool name should be prefixed with `mcp__<server>__`
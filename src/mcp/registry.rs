@@ -322,6 +322,7 @@ impl ToolRegistry {
             .map(|tool| ToolSchema {
                 name: tool.name().to_string(),
                 description: tool.description().to_string(),
+                title: None,
                 input_schema: tool.input_schema(),
             })
             .collect()
@@ -335,6 +336,9 @@ pub struct ToolSchema {
     pub name: String,
     /// Tool description
     pub description: String,
+    /// Human-readable title advertised by the tool, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
     /// JSON Schema for input
     pub input_schema: serde_json::Value,
 }
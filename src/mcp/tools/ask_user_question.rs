@@ -2,8 +2,8 @@
 //!
 //! Allows the agent to ask the user a question during execution.
 //!
-//! This tool is primarily for permission control. The actual question
-//! asking is handled by the Claude agent SDK through its own mechanisms.
+//! The question itself is sent to the client by the can_use_tool callback,
+//! which adds the user's answers to the tool input under `answers`.
 
 use async_trait::async_trait;
 use serde_json::{Value, json};
@@ -65,9 +65,19 @@ impl Tool for AskUserQuestionTool {
             .and_then(|v| v.as_bool())
             .unwrap_or(false);
 
-        // Return success - the actual question asking is handled by the SDK
-        ToolResult::success(format!("Question: '{}'", question)).with_metadata(json!({
+        // Report the answers collected by the can_use_tool callback, if any
+        let answer = input
+            .get("answers")
+            .and_then(|answers| answers.get(question))
+            .and_then(|v| v.as_str());
+        let output = match answer {
+            Some(answer) => format!("Question: '{}'\nAnswer: '{}'", question, answer),
+            None => format!("Question: '{}'", question),
+        };
+
+        ToolResult::success(output).with_metadata(json!({
             "question": question,
+            "answer": answer,
             "has_options": options.is_some(),
             "allow_freeform": allow_freeform
         }))
@@ -110,4 +120,20 @@ mod tests {
         // Should succeed
         assert!(!result.is_error);
     }
+
+    #[tokio::test]
+    async fn test_ask_user_question_reports_answer() {
+        let tool = AskUserQuestionTool;
+        let context = ToolContext::new("test-session", std::path::Path::new("/tmp"));
+
+        let input = json!({
+            "question": "Color?",
+            "options": ["Red", "Blue"],
+            "answers": {"Color?": "Blue"}
+        });
+
+        let result = tool.execute(input, &context).await;
+        assert!(!result.is_error);
+        assert!(result.content.contains("Answer: 'Blue'"));
+    }
 }
@@ -0,0 +1,301 @@
+//! AskUserQuestion routing
+//!
+//! When the agent calls AskUserQuestion, each question is shown to the
+//! client as a permission request whose options are the possible answers.
+//! The selected answers are fed back to the agent in the tool's updated
+//! input under `answers`, keyed by question text.
+
+use claude_code_agent_sdk::types::permissions::{
+    PermissionResult, PermissionResultAllow, PermissionResultDeny,
+};
+use sacp::schema::{
+    Content, ContentBlock, PermissionOption, PermissionOptionId, PermissionOptionKind,
+    RequestPermissionOutcome, RequestPermissionRequest, SessionId, TextContent, ToolCallContent,
+    ToolCallUpdate, ToolCallUpdateFields,
+};
+use sacp::{JrConnectionCx, link::AgentToClient};
+use serde_json::{Map, Value};
+use tracing::{info, warn};
+
+use crate::session::await_response;
+use crate::types::AgentError;
+
+/// Answers offered when a question comes without options
+const DEFAULT_ANSWERS: &[&str] = &["Yes", "No"];
+
+/// A single question from an AskUserQuestion tool call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Question {
+    /// The question text
+    pub question: String,
+    /// Short label shown as the dialog title, if given
+    pub header: Option<String>,
+    /// Answer labels the user can pick from
+    pub options: Vec<String>,
+}
+
+/// Read the questions from AskUserQuestion input
+///
+/// Accepts the SDK's `{"questions": [{"question", "header", "options":
+/// [{"label"}]}]}` shape as well as the single `{"question", "options":
+/// ["..."]}` shape of the ACP tool. Questions without text are skipped, and
+/// questions without options get Yes/No answers.
+pub(crate) fn parse_questions(input: &Value) -> Vec<Question> {
+    let entries: Vec<&Value> = match input.get("questions").and_then(Value::as_array) {
+        Some(questions) => questions.iter().collect(),
+        None => vec![input],
+    };
+
+    entries
+        .into_iter()
+        .filter_map(|entry| {
+            let question = entry.get("question")?.as_str()?.to_string();
+            let mut options: Vec<String> = entry
+                .get("options")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|option| {
+                    option
+                        .as_str()
+                        .or_else(|| option.get("label").and_then(Value::as_str))
+                        .map(str::to_string)
+                })
+                .collect();
+            if options.is_empty() {
+                options = DEFAULT_ANSWERS.iter().map(|s| s.to_string()).collect();
+            }
+            Some(Question {
+                question,
+                header: entry
+                    .get("header")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                options,
+            })
+        })
+        .collect()
+}
+
+/// Build the client request that asks one question
+///
+/// Each answer becomes an "allow once" option with id `answer-<index>`.
+pub(crate) fn build_question_request(
+    session_id: &str,
+    tool_use_id: &str,
+    question: &Question,
+) -> RequestPermissionRequest {
+    let options = question
+        .options
+        .iter()
+        .enumerate()
+        .map(|(index, label)| {
+            PermissionOption::new(
+                PermissionOptionId::new(format!("answer-{}", index)),
+                label.clone(),
+                PermissionOptionKind::AllowOnce,
+            )
+        })
+        .collect();
+
+    let tool_call_update = ToolCallUpdate::new(
+        tool_use_id.to_string(),
+        ToolCallUpdateFields::new()
+            .title(question.header.as_deref().unwrap_or("Question"))
+            .content(vec![ToolCallContent::Content(Content::new(
+                ContentBlock::Text(TextContent::new(question.question.clone())),
+            ))])
+            .raw_input(serde_json::json!({
+                "question": question.question,
+                "options": question.options,
+            })),
+    );
+
+    RequestPermissionRequest::new(
+        SessionId::new(session_id.to_string()),
+        tool_call_update,
+        options,
+    )
+}
+
+/// Map the client's response to the selected answer
+///
+/// Returns `None` if the question was cancelled or an unknown option came back.
+pub(crate) fn selected_answer(
+    question: &Question,
+    outcome: &RequestPermissionOutcome,
+) -> Option<String> {
+    match outcome {
+        RequestPermissionOutcome::Selected(selected) => {
+            let index: usize = selected.option_id.0.strip_prefix("answer-")?.parse().ok()?;
+            question.options.get(index).cloned()
+        }
+        _ => None,
+    }
+}
+
+/// Ask each question in turn and collect the answers
+///
+/// Returns `Ok(None)` as soon as one question is cancelled.
+pub(crate) async fn ask_questions(
+    session_id: &str,
+    tool_use_id: &str,
+    questions: &[Question],
+    connection_cx: &JrConnectionCx<AgentToClient>,
+) -> Result<Option<Map<String, Value>>, AgentError> {
+    let mut answers = Map::new();
+    for question in questions {
+        let request = build_question_request(session_id, tool_use_id, question);
+        info!(
+            session_id = %session_id,
+            tool_use_id = %tool_use_id,
+            "Sending AskUserQuestion request"
+        );
+        let response =
+            await_response(connection_cx.send_request(request).block_task(), None).await?;
+
+        let Some(answer) = selected_answer(question, &response.outcome) else {
+            info!(session_id = %session_id, "AskUserQuestion was cancelled");
+            return Ok(None);
+        };
+        answers.insert(question.question.clone(), Value::String(answer));
+    }
+    Ok(Some(answers))
+}
+
+/// Allow the tool call with the user's answers added to its input
+pub(crate) fn answered_result(tool_input: &Value, answers: Map<String, Value>) -> PermissionResult {
+    let mut updated_input = tool_input.clone();
+    if let Some(fields) = updated_input.as_object_mut() {
+        fields.insert("answers".to_string(), Value::Object(answers));
+    } else {
+        warn!("AskUserQuestion input is not an object; answers are sent on their own");
+        updated_input = serde_json::json!({"answers": answers});
+    }
+    PermissionResult::Allow(PermissionResultAllow {
+        updated_input: Some(updated_input),
+        updated_permissions: None,
+    })
+}
+
+/// Denial returned when the user dismisses a question
+///
+/// The turn is interrupted: the agent shouldn't guess an answer and carry on.
+pub(crate) fn cancelled_result() -> PermissionResult {
+    PermissionResult::Deny(PermissionResultDeny {
+        message: "The user dismissed the question without answering".to_string(),
+        interrupt: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sacp::schema::SelectedPermissionOutcome;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_sdk_questions() {
+        let input = json!({
+            "questions": [{
+                "question": "Which database?",
+                "header": "Database",
+                "options": [{"label": "Postgres", "description": "SQL"}, {"label": "SQLite"}],
+                "multiSelect": false
+            }]
+        });
+        assert_eq!(
+            parse_questions(&input),
+            vec![Question {
+                question: "Which database?".to_string(),
+                header: Some("Database".to_string()),
+                options: vec!["Postgres".to_string(), "SQLite".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_single_question() {
+        let questions = parse_questions(&json!({"question": "Proceed?"}));
+        assert_eq!(questions.len(), 1);
+        assert_eq!(questions[0].options, vec!["Yes", "No"]);
+        assert_eq!(questions[0].header, None);
+
+        let questions = parse_questions(&json!({"question": "Color?", "options": ["Red", "Blue"]}));
+        assert_eq!(questions[0].options, vec!["Red", "Blue"]);
+
+        assert!(parse_questions(&json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_build_question_request() {
+        let question = Question {
+            question: "Which database?".to_string(),
+            header: Some("Database".to_string()),
+            options: vec!["Postgres".to_string(), "SQLite".to_string()],
+        };
+        let request = build_question_request("session-1", "toolu_1", &question);
+        let json = serde_json::to_value(&request).unwrap();
+
+        assert_eq!(json["sessionId"], "session-1");
+        assert_eq!(json["toolCall"]["toolCallId"], "toolu_1");
+        assert_eq!(json["toolCall"]["title"], "Database");
+        assert_eq!(json["toolCall"]["rawInput"]["question"], "Which database?");
+        let options = json["options"].as_array().unwrap();
+        assert_eq!(options.len(), 2);
+        assert_eq!(options[0]["optionId"], "answer-0");
+        assert_eq!(options[0]["name"], "Postgres");
+        assert_eq!(options[1]["optionId"], "answer-1");
+        assert_eq!(options[1]["name"], "SQLite");
+    }
+
+    #[test]
+    fn test_selected_answer() {
+        let question = Question {
+            question: "Color?".to_string(),
+            header: None,
+            options: vec!["Red".to_string(), "Blue".to_string()],
+        };
+        let selected = |id: &str| {
+            RequestPermissionOutcome::Selected(SelectedPermissionOutcome::new(
+                PermissionOptionId::new(id.to_string()),
+            ))
+        };
+
+        assert_eq!(
+            selected_answer(&question, &selected("answer-1")),
+            Some("Blue".to_string())
+        );
+        assert_eq!(selected_answer(&question, &selected("answer-7")), None);
+        assert_eq!(selected_answer(&question, &selected("allow_once")), None);
+    }
+
+    #[test]
+    fn test_cancelled_question() {
+        let question = parse_questions(&json!({"question": "Proceed?"})).remove(0);
+        assert_eq!(
+            selected_answer(&question, &RequestPermissionOutcome::Cancelled),
+            None
+        );
+        match cancelled_result() {
+            PermissionResult::Deny(deny) => assert!(deny.interrupt),
+            _ => panic!("Expected Deny"),
+        }
+    }
+
+    #[test]
+    fn test_answers_are_added_to_input() {
+        let input = json!({"question": "Color?", "options": ["Red", "Blue"]});
+        let mut answers = Map::new();
+        answers.insert("Color?".to_string(), json!("Blue"));
+
+        match answered_result(&input, answers) {
+            PermissionResult::Allow(allow) => {
+                let updated = allow.updated_input.unwrap();
+                assert_eq!(updated["answers"]["Color?"], "Blue");
+                assert_eq!(updated["question"], "Color?");
+            }
+            _ => panic!("Expected Allow"),
+        }
+    }
+}
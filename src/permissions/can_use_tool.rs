@@ -17,11 +17,13 @@ use sacp::{JrConnectionCx, link::AgentToClient};
 use std::sync::{Arc, OnceLock};
 use tracing::{debug, info, warn};
 
+use super::ask_user_question;
+use crate::plans::read_current_plan;
 use crate::session::{
-    PermissionMode, PermissionOutcome, PermissionRequestBuilder, Session, ToolPermissionResult,
+    ExitPlanModeOption, MAX_PAUSE_WAIT, PermissionMode, PermissionOutcome,
+    PermissionRequestBuilder, Session, ToolPermissionResult, await_response,
 };
 use crate::types::AgentError;
-use std::fs;
 use std::path::PathBuf;
 
 /// ExitPlanMode specific permission outcome
@@ -33,160 +35,164 @@ enum ExitPlanModeOutcome {
     KeepPlanning,
 }
 
-/// Read the most recent plan file from ~/.claude/plans/
+/// Convert configured ExitPlanMode options into ACP permission options
 ///
-/// Returns Ok(Some(content)) if plan file is found and readable,
-/// Ok(None) if no plan file exists or file is too large (>20MB),
-/// or Err if there's an error reading.
-fn read_plan_file() -> Result<Option<String>, std::io::Error> {
-    // Maximum plan file size: 20MB
-    // Plan files are typically small (a few KB), but we add a safety limit
-    const MAX_PLAN_FILE_SIZE: u64 = 20 * 1024 * 1024; // 20MB
-
-    // Get the home directory
-    let Some(home) = dirs::home_dir() else {
-        return Ok(None);
-    };
-
-    let plans_dir = home.join(".claude").join("plans");
-
-    // Check if plans directory exists
-    if !plans_dir.exists() {
-        return Ok(None);
-    }
-
-    // Read the directory and find the most recently modified .md file
-    let entries = fs::read_dir(&plans_dir)?;
-    let mut most_recent_file: Option<PathBuf> = None;
-    let mut most_recent_mtime: std::time::SystemTime = std::time::SystemTime::UNIX_EPOCH;
-
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if path.extension().and_then(|s| s.to_str()) == Some("md")
-            && let Ok(metadata) = entry.metadata()
-            && let Ok(mtime) = metadata.modified()
-            && mtime > most_recent_mtime
-        {
-            most_recent_mtime = mtime;
-            most_recent_file = Some(path);
-        }
-    }
-
-    // Read the most recent plan file
-    if let Some(file_path) = most_recent_file {
-        // Check file size before reading
-        let metadata = fs::metadata(&file_path)?;
-        let file_size = metadata.len();
-
-        if file_size > MAX_PLAN_FILE_SIZE {
-            warn!(
-                "Plan file too large ({} bytes > {} limit), skipping: {:?}",
-                file_size, MAX_PLAN_FILE_SIZE, file_path
-            );
-            return Ok(None);
-        }
+/// The option kind is derived from the target mode: modes that auto-approve
+/// edits are "allow always", other target modes "allow once", and options
+/// without a target (keep planning) "reject once".
+fn build_exit_plan_mode_options(options: &[ExitPlanModeOption]) -> Vec<PermissionOption> {
+    options
+        .iter()
+        .map(|option| {
+            let kind = match option.target_mode {
+                Some(mode) if mode.auto_approve_edits() => PermissionOptionKind::AllowAlways,
+                Some(_) => PermissionOptionKind::AllowOnce,
+                None => PermissionOptionKind::RejectOnce,
+            };
+            PermissionOption::new(
+                PermissionOptionId::new(option.id.clone()),
+                option.label.clone(),
+                kind,
+            )
+        })
+        .collect()
+}
 
-        match fs::read_to_string(&file_path) {
-            Ok(content) => {
-                info!(
-                    "Read plan file: {:?} (size: {} bytes)",
-                    file_path, file_size
-                );
-                Ok(Some(content))
+/// Map a selected option id to an ExitPlanMode outcome
+///
+/// Unknown ids, and options targeting Plan mode itself, keep planning.
+fn exit_plan_mode_outcome(options: &[ExitPlanModeOption], option_id: &str) -> ExitPlanModeOutcome {
+    match options.iter().find(|option| option.id == option_id) {
+        Some(option) => match option.target_mode {
+            Some(PermissionMode::Plan) | None => {
+                info!("User selected: {}", option.label);
+                ExitPlanModeOutcome::KeepPlanning
             }
-            Err(e) => {
-                warn!("Failed to read plan file {:?}: {}", file_path, e);
-                Err(e)
+            Some(mode) => {
+                info!("User selected: {}", option.label);
+                ExitPlanModeOutcome::Approve(mode)
             }
+        },
+        None => {
+            warn!(
+                "Unknown option_id: {}, treating as keep planning",
+                option_id
+            );
+            ExitPlanModeOutcome::KeepPlanning
         }
-    } else {
-        Ok(None)
     }
 }
 
-/// Send ExitPlanMode permission request with custom options
-async fn send_exit_plan_mode_request(
-    session_id: &str,
-    tool_use_id: &str,
-    tool_input: &serde_json::Value,
-    connection_cx: &JrConnectionCx<AgentToClient>,
-) -> Result<ExitPlanModeOutcome, AgentError> {
-    // ExitPlanMode specific options matching TypeScript implementation
-    let options = vec![
-        PermissionOption::new(
-            PermissionOptionId::new("acceptEdits"),
-            "Yes, and auto-accept edits",
-            PermissionOptionKind::AllowAlways,
-        ),
-        PermissionOption::new(
-            PermissionOptionId::new("default"),
-            "Yes, and manually approve edits",
-            PermissionOptionKind::AllowOnce,
-        ),
-        PermissionOption::new(
-            PermissionOptionId::new("plan"),
-            "No, keep planning",
-            PermissionOptionKind::RejectOnce,
-        ),
-    ];
+/// Shown in the ExitPlanMode dialog instead of a blank plan
+const EMPTY_PLAN_WARNING: &str = "No plan content found.";
 
-    // Determine the raw input to display
-    // Priority: 1. Use 'plan' field from tool_input if provided
-    //           2. Try to read from plan file
-    //           3. Fall back to original tool_input
-    let (raw_input, plan_content_for_display) =
-        if let Some(plan_content) = tool_input.get("plan").and_then(|v| v.as_str()) {
-            // Agent provided plan content
-            (
-                serde_json::json!({"plan": plan_content}),
-                Some(plan_content.to_string()),
-            )
-        } else {
-            // Try to read the most recent plan file
-            match read_plan_file() {
-                Ok(Some(plan_content)) => {
-                    info!("Read plan file content for ExitPlanMode display");
-                    (
-                        serde_json::json!({"plan": plan_content}),
-                        Some(plan_content),
-                    )
-                }
-                Ok(None) => {
-                    warn!("No plan file found, using original tool_input");
-                    (tool_input.clone(), None)
-                }
-                Err(e) => {
-                    warn!("Failed to read plan file: {}, using original tool_input", e);
-                    (tool_input.clone(), None)
-                }
-            }
-        };
+/// Shown instead when exiting without a plan needs explicit confirmation
+const EMPTY_PLAN_CONFIRMATION: &str = "No plan content found. Exit plan mode anyway?";
 
+/// Build the ExitPlanMode permission request
+///
+/// The plan text, when known, is shown as the request's content. Without
+/// one the dialog says so; if `confirm_empty_plan` is set it also asks
+/// whether to exit anyway and lists the keep-planning options first.
+fn build_exit_plan_mode_request(
+    session_id: &str,
+    tool_use_id: &str,
+    title: &str,
+    raw_input: serde_json::Value,
+    plan_content: Option<String>,
+    exit_options: &[ExitPlanModeOption],
+    confirm_empty_plan: bool,
+) -> RequestPermissionRequest {
     // Build content array for plan display
     // This follows TypeScript implementation: content: [{ type: "content", content: { type: "text", text: plan } }]
-    let content = if let Some(plan_text) = plan_content_for_display {
-        vec![ToolCallContent::Content(Content::new(ContentBlock::Text(
-            TextContent::new(plan_text),
-        )))]
-    } else {
-        vec![]
+    let plan_content = plan_content.filter(|plan| !plan.trim().is_empty());
+    let mut options = exit_options.to_vec();
+    let text = match plan_content {
+        Some(plan_text) => plan_text,
+        None if confirm_empty_plan => {
+            // Stable sort: keep-planning options first, otherwise in order
+            options.sort_by_key(
+                |option| matches!(option.target_mode, Some(mode) if mode != PermissionMode::Plan),
+            );
+            EMPTY_PLAN_CONFIRMATION.to_string()
+        }
+        None => EMPTY_PLAN_WARNING.to_string(),
     };
+    let content = vec![ToolCallContent::Content(Content::new(ContentBlock::Text(
+        TextContent::new(text),
+    )))];
 
     // Build tool call update with title, content, and raw input
     // Following TypeScript implementation: toolInfoFromToolUse for ExitPlanMode
     let tool_call_update = ToolCallUpdate::new(
         tool_use_id.to_string(),
         ToolCallUpdateFields::new()
-            .title("Ready to code?")
+            .title(title)
             .content(content)
             .raw_input(raw_input),
     );
 
-    // Build the request
-    let request = RequestPermissionRequest::new(
+    RequestPermissionRequest::new(
         SessionId::new(session_id.to_string()),
         tool_call_update,
-        options,
+        build_exit_plan_mode_options(&options),
+    )
+}
+
+/// Send ExitPlanMode permission request with custom options
+async fn send_exit_plan_mode_request(
+    session_id: &str,
+    tool_use_id: &str,
+    tool_input: &serde_json::Value,
+    plans_dirs: &[PathBuf],
+    title: &str,
+    exit_options: &[ExitPlanModeOption],
+    confirm_empty_plan: bool,
+    connection_cx: &JrConnectionCx<AgentToClient>,
+) -> Result<ExitPlanModeOutcome, AgentError> {
+    // Determine the raw input to display
+    // Priority: 1. Use 'plan' field from tool_input if provided (and not blank)
+    //           2. Try to read from plan file
+    //           3. Fall back to original tool_input
+    let (raw_input, plan_content_for_display) = if let Some(plan_content) = tool_input
+        .get("plan")
+        .and_then(|v| v.as_str())
+        .filter(|plan| !plan.trim().is_empty())
+    {
+        // Agent provided plan content
+        (
+            serde_json::json!({"plan": plan_content}),
+            Some(plan_content.to_string()),
+        )
+    } else {
+        // Try to read the most recent plan file
+        match read_current_plan(session_id, plans_dirs) {
+            Ok(Some(plan_content)) => {
+                info!("Read plan file content for ExitPlanMode display");
+                (
+                    serde_json::json!({"plan": plan_content}),
+                    Some(plan_content),
+                )
+            }
+            Ok(None) => {
+                warn!("No plan file found, using original tool_input");
+                (tool_input.clone(), None)
+            }
+            Err(e) => {
+                warn!("Failed to read plan file: {}, using original tool_input", e);
+                (tool_input.clone(), None)
+            }
+        }
+    };
+
+    let request = build_exit_plan_mode_request(
+        session_id,
+        tool_use_id,
+        title,
+        raw_input,
+        plan_content_for_display,
+        exit_options,
+        confirm_empty_plan,
     );
 
     tracing::info!(
@@ -196,42 +202,21 @@ async fn send_exit_plan_mode_request(
     );
 
     // Send request and wait for response
-    let response = connection_cx
-        .send_request(request)
-        .block_task()
+    let response = await_response(connection_cx.send_request(request).block_task(), None)
         .await
-        .map_err(|e| {
+        .inspect_err(|e| {
             tracing::error!(
                 session_id = %session_id,
                 error = %e,
                 "ExitPlanMode permission request failed"
             );
-            AgentError::Internal(format!("Permission request failed: {}", e))
         })?;
 
     // Parse the response
     match response.outcome {
-        RequestPermissionOutcome::Selected(selected) => match &*selected.option_id.0 {
-            "acceptEdits" => {
-                info!("User selected: Yes, and auto-accept edits");
-                Ok(ExitPlanModeOutcome::Approve(PermissionMode::AcceptEdits))
-            }
-            "default" => {
-                info!("User selected: Yes, and manually approve edits");
-                Ok(ExitPlanModeOutcome::Approve(PermissionMode::Default))
-            }
-            "plan" => {
-                info!("User selected: No, keep planning");
-                Ok(ExitPlanModeOutcome::KeepPlanning)
-            }
-            _ => {
-                warn!(
-                    "Unknown option_id: {}, treating as keep planning",
-                    &*selected.option_id.0
-                );
-                Ok(ExitPlanModeOutcome::KeepPlanning)
-            }
-        },
+        RequestPermissionOutcome::Selected(selected) => {
+            Ok(exit_plan_mode_outcome(exit_options, &selected.option_id.0))
+        }
         RequestPermissionOutcome::Cancelled => {
             info!("ExitPlanMode permission request was cancelled");
             Ok(ExitPlanModeOutcome::KeepPlanning)
@@ -244,6 +229,107 @@ async fn send_exit_plan_mode_request(
     }
 }
 
+/// Denial returned for every tool call while an emergency stop is in effect
+fn emergency_stop_denial() -> PermissionResult {
+    PermissionResult::Deny(PermissionResultDeny {
+        message: "Emergency stop in effect - tool execution denied".to_string(),
+        interrupt: true,
+    })
+}
+
+/// Deny immediately (without contacting the client) if the session is emergency-stopped
+fn check_emergency_stop(session: &Session, tool_name: &str) -> Option<PermissionResult> {
+    if !session.is_emergency_stopped() {
+        return None;
+    }
+    info!(
+        session_id = %session.session_id,
+        tool_name = %tool_name,
+        "Emergency stop in effect - denying tool call"
+    );
+    Some(emergency_stop_denial())
+}
+
+/// Hold the tool call while the session is paused
+///
+/// Returns `None` once the session is running again, or a denial if the
+/// pause outlasts [`MAX_PAUSE_WAIT`] or an emergency stop arrives meanwhile.
+async fn wait_for_resume(session: &Session, tool_name: &str) -> Option<PermissionResult> {
+    if !session.is_paused() {
+        return None;
+    }
+    info!(
+        session_id = %session.session_id,
+        tool_name = %tool_name,
+        "Session paused - holding tool call until resumed"
+    );
+
+    tokio::select! {
+        resumed = session.wait_while_paused(MAX_PAUSE_WAIT) => {
+            if resumed {
+                None
+            } else {
+                warn!(
+                    session_id = %session.session_id,
+                    tool_name = %tool_name,
+                    "Session stayed paused too long - denying tool call"
+                );
+                Some(PermissionResult::Deny(PermissionResultDeny {
+                    message: "Session is paused - tool call was not resumed in time".to_string(),
+                    interrupt: false,
+                }))
+            }
+        }
+        () = session.emergency_stopped() => Some(emergency_stop_denial()),
+    }
+}
+
+/// Deny without re-prompting if an identical request was just sent to the client
+///
+/// Only checks: the prompt is recorded with [`Session::record_prompt`] once
+/// it is actually sent.
+fn check_prompt_rate_limit(
+    session: &Session,
+    tool_name: &str,
+    tool_input: &serde_json::Value,
+) -> Option<PermissionResult> {
+    if !session.should_throttle_prompt(tool_name, tool_input) {
+        return None;
+    }
+    info!(
+        session_id = %session.session_id,
+        tool_name = %tool_name,
+        "Identical permission request sent recently - throttling"
+    );
+    Some(PermissionResult::Deny(PermissionResultDeny {
+        message: "An identical permission request was just made - denied to avoid repeated prompts"
+            .to_string(),
+        interrupt: false,
+    }))
+}
+
+/// Deny a tool call, interrupting the turn if it keeps being denied
+///
+/// Denials made by the hook count toward the same total; see
+/// [`Session::set_denial_escalation_threshold`].
+fn deny_tool_call(
+    session: &Session,
+    tool_name: &str,
+    tool_input: &serde_json::Value,
+    message: String,
+) -> PermissionResult {
+    let interrupt = session.record_denial(tool_name, tool_input);
+    if interrupt {
+        warn!(
+            session_id = %session.session_id,
+            tool_name = %tool_name,
+            threshold = ?session.denial_escalation_threshold(),
+            "Tool call denied repeatedly - interrupting the turn"
+        );
+    }
+    PermissionResult::Deny(PermissionResultDeny { message, interrupt })
+}
+
 /// Handle ExitPlanMode tool with special permission dialog
 async fn handle_exit_plan_mode(
     session: &Session,
@@ -266,39 +352,39 @@ async fn handle_exit_plan_mode(
         });
     };
 
-    // Send ExitPlanMode permission request
-    match send_exit_plan_mode_request(&session.session_id, tool_use_id, &tool_input, connection_cx)
-        .await
-    {
-        Ok(ExitPlanModeOutcome::Approve(mode)) => {
-            info!(
-                session_id = %session.session_id,
-                mode = ?mode,
-                "ExitPlanMode approved, switching to new mode"
-            );
+    // Resolve the plans directories and dialog before awaiting the client
+    let (plans_dirs, title, exit_options, confirm_empty_plan) = {
+        let permission = session.permission().await;
+        (
+            permission.plans_dirs(),
+            permission.exit_plan_mode_title().to_string(),
+            permission.exit_plan_mode_options().to_vec(),
+            permission.confirm_empty_plan(),
+        )
+    };
 
-            // Update session permission mode
-            session.set_permission_mode(mode).await;
-
-            // Send session/update notification
-            session.send_mode_update(mode.as_str());
-
-            // Return Allow with updated_permissions (matching TypeScript implementation)
-            // This tells the SDK:
-            // 1. Allow the ExitPlanMode tool to execute
-            // 2. Apply the permission mode update
-            // 3. Subsequent tools will use the new mode
-            PermissionResult::Allow(PermissionResultAllow {
-                updated_input: Some(tool_input),
-                updated_permissions: Some(vec![PermissionUpdate {
-                    type_: PermissionUpdateType::SetMode,
-                    rules: None,
-                    behavior: None,
-                    mode: Some(mode.to_sdk_mode()),
-                    directories: None,
-                    destination: Some(PermissionUpdateDestination::Session),
-                }]),
-            })
+    // Send ExitPlanMode permission request (abandoned if an emergency stop arrives)
+    let outcome = tokio::select! {
+        outcome = async {
+            let _slot = session.acquire_prompt_slot().await;
+            send_exit_plan_mode_request(
+                &session.session_id,
+                tool_use_id,
+                &tool_input,
+                &plans_dirs,
+                &title,
+                &exit_options,
+                confirm_empty_plan,
+                connection_cx,
+            )
+            .await
+        } => outcome,
+        () = session.emergency_stopped() => return emergency_stop_denial(),
+    };
+
+    match outcome {
+        Ok(ExitPlanModeOutcome::Approve(mode)) => {
+            approve_exit_plan_mode(session, mode, tool_input).await
         }
         Ok(ExitPlanModeOutcome::KeepPlanning) => {
             info!(
@@ -324,6 +410,217 @@ async fn handle_exit_plan_mode(
     }
 }
 
+/// Switch to the mode chosen in an approved ExitPlanMode dialog
+async fn approve_exit_plan_mode(
+    session: &Session,
+    mode: PermissionMode,
+    tool_input: serde_json::Value,
+) -> PermissionResult {
+    info!(
+        session_id = %session.session_id,
+        mode = ?mode,
+        "ExitPlanMode approved, switching to new mode"
+    );
+
+    // Update session permission mode
+    session.set_permission_mode(mode).await;
+
+    // Send session/update notification
+    session.send_mode_update(mode.as_str());
+
+    // Return Allow with updated_permissions (matching TypeScript implementation)
+    // This tells the SDK:
+    // 1. Allow the ExitPlanMode tool to execute
+    // 2. Apply the permission mode update
+    // 3. Subsequent tools will use the new mode
+    PermissionResult::Allow(PermissionResultAllow {
+        updated_input: Some(tool_input),
+        updated_permissions: Some(vec![PermissionUpdate {
+            type_: PermissionUpdateType::SetMode,
+            rules: None,
+            behavior: None,
+            mode: Some(mode.to_sdk_mode()),
+            directories: None,
+            destination: Some(PermissionUpdateDestination::Session),
+        }]),
+    })
+}
+
+/// Handle AskUserQuestion by forwarding the questions to the client
+///
+/// The answers are returned to the agent in the tool's updated input.
+async fn handle_ask_user_question(
+    session: &Session,
+    tool_use_id: &str,
+    tool_input: &serde_json::Value,
+) -> PermissionResult {
+    let questions = ask_user_question::parse_questions(tool_input);
+    if questions.is_empty() {
+        return PermissionResult::Deny(PermissionResultDeny {
+            message: "AskUserQuestion needs at least one question".to_string(),
+            interrupt: false,
+        });
+    }
+
+    let Some(connection_cx) = session.get_connection_cx() else {
+        warn!(
+            session_id = %session.session_id,
+            "Connection not ready for AskUserQuestion"
+        );
+        return PermissionResult::Deny(PermissionResultDeny {
+            message: "Connection not ready for AskUserQuestion".to_string(),
+            interrupt: false,
+        });
+    };
+
+    // Abandoned if an emergency stop arrives while waiting for answers
+    let answers = tokio::select! {
+        answers = async {
+            let _slot = session.acquire_prompt_slot().await;
+            ask_user_question::ask_questions(
+                &session.session_id,
+                tool_use_id,
+                &questions,
+                connection_cx,
+            )
+            .await
+        } => answers,
+        () = session.emergency_stopped() => return emergency_stop_denial(),
+    };
+
+    match answers {
+        Ok(Some(answers)) => {
+            info!(
+                session_id = %session.session_id,
+                answered = answers.len(),
+                "AskUserQuestion answered"
+            );
+            ask_user_question::answered_result(tool_input, answers)
+        }
+        Ok(None) => ask_user_question::cancelled_result(),
+        Err(e) => {
+            warn!(
+                session_id = %session.session_id,
+                error = %e,
+                "AskUserQuestion request failed"
+            );
+            PermissionResult::Deny(PermissionResultDeny {
+                message: format!("AskUserQuestion failed: {}", e),
+                interrupt: false,
+            })
+        }
+    }
+}
+
+/// Act on the user's answer to a permission request shown in `turn`
+///
+/// "Always" answers add (and save) a rule before the call is allowed or
+/// denied; denials go through [`deny_tool_call`].
+async fn apply_permission_outcome(
+    session: &Session,
+    tool_name: &str,
+    tool_input: &serde_json::Value,
+    outcome: PermissionOutcome,
+    turn: u64,
+) -> PermissionResult {
+    match outcome {
+        PermissionOutcome::AllowOnce => {
+            info!(tool_name = %tool_name, "Permission allowed once by user");
+            session
+                .permission()
+                .await
+                .record_approval(tool_name, tool_input);
+            PermissionResult::Allow(PermissionResultAllow::default())
+        }
+        PermissionOutcome::AllowAlways => {
+            info!(tool_name = %tool_name, "Permission allowed always by user");
+            // Add rule to permission checker for future invocations
+            let handler_guard = session.permission().await;
+            handler_guard.record_approval(tool_name, tool_input);
+            handler_guard.add_allow_rule_for_tool_call(tool_name, tool_input);
+            drop(handler_guard);
+            session.save_permission_state().await;
+            PermissionResult::Allow(PermissionResultAllow::default())
+        }
+        PermissionOutcome::AllowAlwaysRule(rule) => {
+            info!(tool_name = %tool_name, rule = %rule, "Permission allowed always by user");
+            let handler_guard = session.permission().await;
+            handler_guard.record_approval(tool_name, tool_input);
+            handler_guard.add_allow_rule(&rule).await;
+            drop(handler_guard);
+            session.save_permission_state().await;
+            PermissionResult::Allow(PermissionResultAllow::default())
+        }
+        PermissionOutcome::AllowReadsForTurn => {
+            info!(tool_name = %tool_name, "All reads allowed for the turn by user");
+            session.permission().await.allow_reads_for_turn(turn);
+            PermissionResult::Allow(PermissionResultAllow::default())
+        }
+        PermissionOutcome::RejectAlways => {
+            info!(tool_name = %tool_name, "Permission rejected always by user");
+            // Save the deny rule to settings; if that fails, still
+            // block identical calls for the rest of the session and say so
+            let handler_guard = session.permission().await;
+            let message = match handler_guard
+                .persist_deny_rule_for_tool_call(tool_name, tool_input)
+                .await
+            {
+                Ok(()) => "User denied permission (always)".to_string(),
+                Err(e) => {
+                    warn!(
+                        tool_name = %tool_name,
+                        error = %e,
+                        "Failed to persist deny rule, keeping it for this session"
+                    );
+                    handler_guard
+                        .add_deny_rule_for_tool_call(tool_name, tool_input)
+                        .await;
+                    format!(
+                        "User denied permission (always), but the rule could not be saved to settings ({}); it only applies to this session",
+                        e
+                    )
+                }
+            };
+            drop(handler_guard);
+            session.save_permission_state().await;
+            deny_tool_call(session, tool_name, tool_input, message)
+        }
+        PermissionOutcome::RejectForSession => {
+            info!(tool_name = %tool_name, "Permission rejected for session by user");
+            // Block identical calls until the conversation is cleared
+            let handler_guard = session.permission().await;
+            handler_guard
+                .add_deny_rule_for_tool_call(tool_name, tool_input)
+                .await;
+            drop(handler_guard);
+            session.save_permission_state().await;
+            deny_tool_call(
+                session,
+                tool_name,
+                tool_input,
+                "User denied permission (for this session)".to_string(),
+            )
+        }
+        PermissionOutcome::Rejected => {
+            info!(tool_name = %tool_name, "Permission rejected by user");
+            deny_tool_call(
+                session,
+                tool_name,
+                tool_input,
+                "User denied permission".to_string(),
+            )
+        }
+        PermissionOutcome::Cancelled => {
+            // Not a decision about this call, so it doesn't count toward escalation
+            info!(tool_name = %tool_name, "Permission request cancelled");
+            PermissionResult::Deny(PermissionResultDeny {
+                message: "Permission request cancelled".to_string(),
+                interrupt: false,
+            })
+        }
+    }
+}
+
 /// Create a can_use_tool callback that receives Session via OnceLock
 ///
 /// Following TypeScript version's design, this callback:
@@ -356,6 +653,16 @@ pub fn create_can_use_tool_callback(
                     });
                 };
 
+                // Emergency stop overrides everything, including ExitPlanMode
+                if let Some(denied) = check_emergency_stop(session, &tool_name) {
+                    return denied;
+                }
+
+                // A paused session holds every tool call until resumed
+                if let Some(denied) = wait_for_resume(session, &tool_name).await {
+                    return denied;
+                }
+
                 // Special handling for ExitPlanMode - show custom permission dialog
                 // This must be done before the permission check, as ExitPlanMode
                 // needs to show a "Ready to code?" prompt regardless of current mode
@@ -369,7 +676,9 @@ pub fn create_can_use_tool_callback(
                     let tool_use_id = match context.tool_use_id {
                         Some(id) => id,
                         None => {
-                            if let Some(cached_id) = session.get_cached_tool_use_id(&tool_input) {
+                            if let Some(cached_id) =
+                                session.get_cached_tool_use_id(&tool_name, &tool_input)
+                            {
                                 cached_id
                             } else {
                                 warn!("No tool_use_id available for ExitPlanMode");
@@ -385,6 +694,22 @@ pub fn create_can_use_tool_callback(
                     return handle_exit_plan_mode(session, &tool_use_id, &tool_input).await;
                 }
 
+                // AskUserQuestion is forwarded to the client as a question, in every mode
+                if tool_name == "AskUserQuestion" || tool_name == "mcp__acp__AskUserQuestion" {
+                    let Some(tool_use_id) = context
+                        .tool_use_id
+                        .or_else(|| session.get_cached_tool_use_id(&tool_name, &tool_input))
+                    else {
+                        warn!("No tool_use_id available for AskUserQuestion");
+                        return PermissionResult::Deny(PermissionResultDeny {
+                            message: "No tool_use_id available for AskUserQuestion".to_string(),
+                            interrupt: false,
+                        });
+                    };
+
+                    return handle_ask_user_question(session, &tool_use_id, &tool_input).await;
+                }
+
                 // Check permission handler first
                 let handler_guard = session.permission().await;
                 let result = handler_guard
@@ -398,6 +723,8 @@ pub fn create_can_use_tool_callback(
                             tool_name = %tool_name,
                             "Permission allowed by handler"
                         );
+                        // The call may have spent a use-limited rule
+                        session.save_permission_state().await;
                         PermissionResult::Allow(PermissionResultAllow::default())
                     }
                     ToolPermissionResult::Blocked { reason } => {
@@ -406,10 +733,7 @@ pub fn create_can_use_tool_callback(
                             reason = %reason,
                             "Permission blocked by handler"
                         );
-                        PermissionResult::Deny(PermissionResultDeny {
-                            message: reason,
-                            interrupt: false,
-                        })
+                        deny_tool_call(session, &tool_name, &tool_input, reason)
                     }
                     ToolPermissionResult::NeedsPermission => {
                         // This is the "ask" case - send permission request to client
@@ -419,13 +743,21 @@ pub fn create_can_use_tool_callback(
                             "Permission needed - sending request to client"
                         );
 
+                        // Avoid flooding the client with repeated identical prompts
+                        if let Some(throttled) =
+                            check_prompt_rate_limit(session, &tool_name, &tool_input)
+                        {
+                            return throttled;
+                        }
+
                         // Get tool_use_id from context, or from cache if not provided
                         // The cache is populated by pre_tool_use hook when Ask decision is made
                         let tool_use_id = match context.tool_use_id {
                             Some(id) => id,
                             None => {
                                 // Try to get from cache (populated by pre_tool_use hook)
-                                if let Some(cached_id) = session.get_cached_tool_use_id(&tool_input)
+                                if let Some(cached_id) =
+                                    session.get_cached_tool_use_id(&tool_name, &tool_input)
                                 {
                                     debug!(
                                         tool_name = %tool_name,
@@ -459,35 +791,49 @@ pub fn create_can_use_tool_callback(
                             });
                         };
 
-                        // Send permission request and wait for response
-                        let outcome = PermissionRequestBuilder::new(
-                            &session.session_id,
-                            &tool_use_id,
-                            &tool_name,
-                            tool_input.clone(),
-                        )
-                        .request(connection_cx)
-                        .await;
+                        let (allow_always_rules, turn) = {
+                            let handler = session.permission().await;
+                            let rules =
+                                handler.suggested_allow_rules(&tool_name, &tool_input).await;
+                            (rules, handler.current_turn())
+                        };
 
-                        match outcome {
-                            Ok(PermissionOutcome::AllowOnce) => {
-                                info!(tool_name = %tool_name, "Permission allowed once by user");
-                                PermissionResult::Allow(PermissionResultAllow::default())
-                            }
-                            Ok(PermissionOutcome::AllowAlways) => {
-                                info!(tool_name = %tool_name, "Permission allowed always by user");
-                                // Add rule to permission checker for future invocations
-                                let handler_guard = session.permission().await;
-                                handler_guard.add_allow_rule_for_tool_call(&tool_name, &tool_input);
-                                drop(handler_guard);
-                                PermissionResult::Allow(PermissionResultAllow::default())
+                        // Send permission request and wait for response, queueing
+                        // behind other prompts if the session limits them
+                        // (abandoned if an emergency stop arrives while waiting)
+                        let outcome = tokio::select! {
+                            outcome = async {
+                                let _slot = session.acquire_prompt_slot().await;
+                                session.record_prompt(&tool_name, &tool_input);
+                                PermissionRequestBuilder::new(
+                                    &session.session_id,
+                                    &tool_use_id,
+                                    &tool_name,
+                                    tool_input.clone(),
+                                )
+                                .allow_always_rules(allow_always_rules)
+                                .request(connection_cx)
+                                .await
+                            } => outcome,
+                            () = session.emergency_stopped() => {
+                                info!(
+                                    tool_name = %tool_name,
+                                    "Permission request abandoned by emergency stop"
+                                );
+                                return emergency_stop_denial();
                             }
-                            Ok(PermissionOutcome::Rejected | PermissionOutcome::Cancelled) => {
-                                info!(tool_name = %tool_name, "Permission rejected/cancelled by user");
-                                PermissionResult::Deny(PermissionResultDeny {
-                                    message: "User denied permission".to_string(),
-                                    interrupt: false,
-                                })
+                        };
+
+                        match outcome {
+                            Ok(outcome) => {
+                                apply_permission_outcome(
+                                    session,
+                                    &tool_name,
+                                    &tool_input,
+                                    outcome,
+                                    turn,
+                                )
+                                .await
                             }
                             Err(e) => {
                                 warn!(
@@ -517,6 +863,15 @@ mod tests {
     // The basic test below verifies the callback compiles correctly.
     // Functional tests require integration testing with a real session.
 
+    /// A config that saves no state outside the test
+    fn test_config() -> crate::types::AgentConfig {
+        crate::types::AgentConfig {
+            permission_mode_state_file: None,
+            permission_state_dir: None,
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_callback_function_compiles() {
         // This test verifies the callback function signature is correct
@@ -524,4 +879,418 @@ mod tests {
         let _callback = create_can_use_tool_callback(session_lock);
         // If this compiles, the signature is correct
     }
+
+    #[tokio::test]
+    async fn test_reject_always_persists_deny_rule() {
+        let config = test_config();
+        let project = tempfile::TempDir::new().unwrap();
+        let session = Session::new(
+            "test-reject-always".to_string(),
+            project.path().to_path_buf(),
+            &config,
+            None,
+        )
+        .unwrap();
+        session.set_permission_mode(PermissionMode::Default).await;
+        let input = serde_json::json!({"command": "make deploy"});
+
+        match apply_permission_outcome(&session, "Bash", &input, PermissionOutcome::RejectAlways, 1)
+            .await
+        {
+            PermissionResult::Deny(deny) => {
+                assert_eq!(deny.message, "User denied permission (always)");
+                assert!(!deny.interrupt);
+            }
+            _ => panic!("Expected Deny for RejectAlways"),
+        }
+
+        // The rule is saved to the local settings...
+        let saved = std::fs::read_to_string(project.path().join(".claude/settings.local.json"))
+            .expect("deny rule should be persisted");
+        let saved: serde_json::Value = serde_json::from_str(&saved).unwrap();
+        let deny_rules = saved["permissions"]["deny"].as_array().unwrap();
+        assert_eq!(deny_rules.len(), 1);
+
+        // ...and blocks the same call without asking again
+        let result = session
+            .permission()
+            .await
+            .check_permission("Bash", &input)
+            .await;
+        assert!(
+            matches!(result, ToolPermissionResult::Blocked { .. }),
+            "{result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reject_always_reports_persist_failure() {
+        let config = test_config();
+        let project = tempfile::TempDir::new().unwrap();
+        // A directory where the local settings file should be can't be written
+        std::fs::create_dir_all(project.path().join(".claude/settings.local.json")).unwrap();
+        let session = Session::new(
+            "test-reject-always-fails".to_string(),
+            project.path().to_path_buf(),
+            &config,
+            None,
+        )
+        .unwrap();
+        let input = serde_json::json!({"command": "make deploy"});
+
+        match apply_permission_outcome(&session, "Bash", &input, PermissionOutcome::RejectAlways, 1)
+            .await
+        {
+            PermissionResult::Deny(deny) => {
+                assert!(
+                    deny.message.contains("could not be saved"),
+                    "{}",
+                    deny.message
+                );
+            }
+            _ => panic!("Expected Deny for RejectAlways"),
+        }
+
+        // The rule still blocks the call for this session
+        let result = session
+            .permission()
+            .await
+            .check_permission("Bash", &input)
+            .await;
+        assert!(
+            matches!(result, ToolPermissionResult::Blocked { .. }),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_default_exit_plan_mode_options_unchanged() {
+        let defaults = ExitPlanModeOption::defaults();
+        let options = build_exit_plan_mode_options(&defaults);
+        let ids: Vec<&str> = options.iter().map(|o| &*o.option_id.0).collect();
+        assert_eq!(ids, ["acceptEdits", "default", "plan"]);
+        assert_eq!(options[0].kind, PermissionOptionKind::AllowAlways);
+        assert_eq!(options[1].kind, PermissionOptionKind::AllowOnce);
+        assert_eq!(options[2].kind, PermissionOptionKind::RejectOnce);
+
+        assert_eq!(
+            exit_plan_mode_outcome(&defaults, "acceptEdits"),
+            ExitPlanModeOutcome::Approve(PermissionMode::AcceptEdits)
+        );
+        assert_eq!(
+            exit_plan_mode_outcome(&defaults, "default"),
+            ExitPlanModeOutcome::Approve(PermissionMode::Default)
+        );
+        assert_eq!(
+            exit_plan_mode_outcome(&defaults, "plan"),
+            ExitPlanModeOutcome::KeepPlanning
+        );
+        assert_eq!(
+            exit_plan_mode_outcome(&defaults, "bogus"),
+            ExitPlanModeOutcome::KeepPlanning
+        );
+    }
+
+    #[test]
+    fn test_custom_exit_plan_mode_options() {
+        // Offer bypass, drop auto-accept
+        let custom = vec![
+            ExitPlanModeOption::approve(
+                "yolo",
+                "Yes, and bypass permissions",
+                PermissionMode::BypassPermissions,
+            ),
+            ExitPlanModeOption::approve(
+                "default",
+                "Yes, and manually approve edits",
+                PermissionMode::Default,
+            ),
+            ExitPlanModeOption::keep_planning("plan", "No, keep planning"),
+        ];
+
+        assert_eq!(
+            exit_plan_mode_outcome(&custom, "yolo"),
+            ExitPlanModeOutcome::Approve(PermissionMode::BypassPermissions)
+        );
+        // Removed option is no longer recognized
+        assert_eq!(
+            exit_plan_mode_outcome(&custom, "acceptEdits"),
+            ExitPlanModeOutcome::KeepPlanning
+        );
+
+        let options = build_exit_plan_mode_options(&custom);
+        assert_eq!(&*options[0].option_id.0, "yolo");
+        assert_eq!(options[0].kind, PermissionOptionKind::AllowAlways);
+    }
+
+    #[test]
+    fn test_exit_plan_mode_request_title() {
+        let handler = crate::session::PermissionHandler::new();
+        let request = build_exit_plan_mode_request(
+            "session-1",
+            "toolu_1",
+            handler.exit_plan_mode_title(),
+            serde_json::json!({"plan": "1. Do it"}),
+            Some("1. Do it".to_string()),
+            handler.exit_plan_mode_options(),
+            false,
+        );
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["toolCall"]["toolCallId"], "toolu_1");
+        assert_eq!(json["toolCall"]["title"], "Ready to code?");
+        assert_eq!(json["toolCall"]["rawInput"]["plan"], "1. Do it");
+        assert_eq!(json["options"][0]["name"], "Yes, and auto-accept edits");
+
+        // A custom title and labels flow into the request
+        let options = vec![
+            ExitPlanModeOption::approve("default", "Oui", PermissionMode::Default),
+            ExitPlanModeOption::keep_planning("plan", "Non"),
+        ];
+        let request = build_exit_plan_mode_request(
+            "session-1",
+            "toolu_1",
+            "Prêt à coder ?",
+            serde_json::json!({}),
+            None,
+            &options,
+            false,
+        );
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["toolCall"]["title"], "Prêt à coder ?");
+        assert_eq!(json["options"][0]["name"], "Oui");
+        assert_eq!(json["options"][1]["name"], "Non");
+    }
+
+    #[test]
+    fn test_exit_plan_mode_request_without_plan() {
+        let defaults = ExitPlanModeOption::defaults();
+        let build = |plan_content: Option<&str>, confirm_empty_plan| {
+            let request = build_exit_plan_mode_request(
+                "session-1",
+                "toolu_1",
+                crate::session::DEFAULT_EXIT_PLAN_MODE_TITLE,
+                serde_json::json!({}),
+                plan_content.map(str::to_string),
+                &defaults,
+                confirm_empty_plan,
+            );
+            serde_json::to_value(&request).unwrap()
+        };
+
+        // A missing or blank plan is called out instead of shown as nothing
+        for plan_content in [None, Some(""), Some("  \n")] {
+            let json = build(plan_content, false);
+            assert_eq!(
+                json["toolCall"]["content"][0]["content"]["text"],
+                EMPTY_PLAN_WARNING
+            );
+            assert_eq!(json["options"][0]["optionId"], "acceptEdits");
+        }
+
+        // With confirmation the dialog asks, offering to keep planning first
+        let json = build(None, true);
+        assert_eq!(
+            json["toolCall"]["content"][0]["content"]["text"],
+            EMPTY_PLAN_CONFIRMATION
+        );
+        let option_ids: Vec<&str> = json["options"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|option| option["optionId"].as_str().unwrap())
+            .collect();
+        assert_eq!(option_ids, ["plan", "acceptEdits", "default"]);
+
+        // A real plan is shown as is, whatever the setting
+        let json = build(Some("1. Do it"), true);
+        assert_eq!(
+            json["toolCall"]["content"][0]["content"]["text"],
+            "1. Do it"
+        );
+        assert_eq!(json["options"][0]["optionId"], "acceptEdits");
+    }
+
+    #[test]
+    fn test_identical_prompts_are_throttled() {
+        let config = test_config();
+        let session = Session::new(
+            "test-throttle".to_string(),
+            PathBuf::from("/tmp"),
+            &config,
+            None,
+        )
+        .unwrap();
+        let input = serde_json::json!({"command": "make deploy"});
+
+        // Default off
+        assert!(check_prompt_rate_limit(&session, "Bash", &input).is_none());
+        assert!(check_prompt_rate_limit(&session, "Bash", &input).is_none());
+
+        session.set_prompt_rate_limit(Some(std::time::Duration::from_secs(30)));
+        assert!(check_prompt_rate_limit(&session, "Bash", &input).is_none());
+
+        // A call denied before its prompt was sent doesn't count
+        assert!(check_prompt_rate_limit(&session, "Bash", &input).is_none());
+
+        session.record_prompt("Bash", &input);
+        match check_prompt_rate_limit(&session, "Bash", &input) {
+            Some(PermissionResult::Deny(deny)) => assert!(!deny.interrupt),
+            _ => panic!("Expected Deny for a repeated identical request"),
+        }
+        assert!(
+            check_prompt_rate_limit(&session, "Bash", &serde_json::json!({"command": "make"}))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_repeated_denials_escalate_to_interrupt() {
+        let config = test_config();
+        let session = Session::new(
+            "test-denial-escalation".to_string(),
+            PathBuf::from("/tmp"),
+            &config,
+            None,
+        )
+        .unwrap();
+        let input = serde_json::json!({"command": "rm -rf /"});
+        let interrupts = |input: &serde_json::Value| match deny_tool_call(
+            &session,
+            "Bash",
+            input,
+            "denied".to_string(),
+        ) {
+            PermissionResult::Deny(deny) => deny.interrupt,
+            _ => panic!("Expected Deny"),
+        };
+
+        // Disabled by default: denials never interrupt
+        assert!(!interrupts(&input));
+        assert!(!interrupts(&input));
+
+        session.set_denial_escalation_threshold(Some(3));
+        assert!(!interrupts(&input));
+        assert!(!interrupts(&input));
+        // A different call has its own count
+        assert!(!interrupts(&serde_json::json!({"command": "rm -rf ~"})));
+        assert!(interrupts(&input));
+
+        // The count starts over after escalating
+        assert!(!interrupts(&input));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_prompts_do_not_escalate() {
+        let config = test_config();
+        let session = Session::new(
+            "test-cancel-escalation".to_string(),
+            PathBuf::from("/tmp"),
+            &config,
+            None,
+        )
+        .unwrap();
+        session.set_denial_escalation_threshold(Some(2));
+        let input = serde_json::json!({"command": "make deploy"});
+
+        for _ in 0..3 {
+            match apply_permission_outcome(
+                &session,
+                "Bash",
+                &input,
+                PermissionOutcome::Cancelled,
+                1,
+            )
+            .await
+            {
+                PermissionResult::Deny(deny) => assert!(!deny.interrupt),
+                _ => panic!("Expected Deny for Cancelled"),
+            }
+        }
+
+        // Rejections still count, toward the same total as denials by the hook
+        assert!(!session.record_denial("Bash", &input));
+        match apply_permission_outcome(&session, "Bash", &input, PermissionOutcome::Rejected, 1)
+            .await
+        {
+            PermissionResult::Deny(deny) => assert!(deny.interrupt),
+            _ => panic!("Expected Deny for Rejected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_paused_session_holds_tool_call() {
+        let config = test_config();
+        let session = Session::new(
+            "test-pause-callback".to_string(),
+            PathBuf::from("/tmp"),
+            &config,
+            None,
+        )
+        .unwrap();
+        assert!(wait_for_resume(&session, "Read").await.is_none());
+
+        session.pause();
+        let held = {
+            let session = Arc::clone(&session);
+            tokio::spawn(async move { wait_for_resume(&session, "Read").await.is_none() })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert!(!held.is_finished(), "tool call should be held while paused");
+
+        session.resume();
+        let released = tokio::time::timeout(std::time::Duration::from_secs(1), held)
+            .await
+            .expect("resume should release the held call")
+            .unwrap();
+        assert!(released);
+    }
+
+    #[tokio::test]
+    async fn test_emergency_stop_releases_paused_call_with_denial() {
+        let config = test_config();
+        let session = Session::new(
+            "test-pause-stop".to_string(),
+            PathBuf::from("/tmp"),
+            &config,
+            None,
+        )
+        .unwrap();
+        session.pause();
+        session.emergency_stop();
+        match wait_for_resume(&session, "Write").await {
+            Some(PermissionResult::Deny(deny)) => assert!(deny.interrupt),
+            _ => panic!("Expected interrupting Deny"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exit_plan_mode_approval_notifies_mode_change() {
+        let config = test_config();
+        let session = Session::new(
+            "test-exit-plan-mode-change".to_string(),
+            PathBuf::from("/tmp"),
+            &config,
+            None,
+        )
+        .unwrap();
+        session.set_permission_mode(PermissionMode::Plan).await;
+
+        let changes = Arc::new(std::sync::Mutex::new(Vec::new()));
+        {
+            let changes = Arc::clone(&changes);
+            session.on_mode_change(move |old, new| changes.lock().unwrap().push((old, new)));
+        }
+
+        let result = approve_exit_plan_mode(
+            &session,
+            PermissionMode::AcceptEdits,
+            serde_json::json!({"plan": "1. Do it"}),
+        )
+        .await;
+        assert!(matches!(result, PermissionResult::Allow(_)));
+        assert_eq!(
+            *changes.lock().unwrap(),
+            vec![(PermissionMode::Plan, PermissionMode::AcceptEdits)]
+        );
+    }
 }
@@ -3,6 +3,7 @@
 //! This module implements the SDK's can_use_tool callback for checking
 //! tool permissions before execution.
 
+mod ask_user_question;
 pub mod can_use_tool;
 pub mod strategies;
 
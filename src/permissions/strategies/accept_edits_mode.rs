@@ -2,6 +2,7 @@
 //!
 //! This strategy auto-approves ALL tools, behaving identically to BypassPermissions.
 //! It's compatible with root user environments where BypassPermissions cannot be used.
+//! See `StrictAcceptEditsModeStrategy` for the opt-in variant that gates Bash.
 
 use crate::permissions::strategies::PermissionModeStrategy;
 use crate::session::{PermissionMode, ToolPermissionResult};
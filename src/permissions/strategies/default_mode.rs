@@ -1,18 +1,69 @@
 //! Default mode strategy
 //!
 //! This strategy provides standard permission checking:
-//! - Auto-approves read operations
-//! - Auto-approves known safe Bash commands
+//! - Auto-approves read operations (including `BashOutput`)
+//! - Auto-approves known safe Bash commands (minus any disabled in settings,
+//!   and up to a configured length)
 //! - Requires user permission for other operations
 
-use crate::command_safety::is_known_safe_command;
+use std::sync::Arc;
+
+use crate::command_safety::{
+    SafeCommandClassifier, is_known_safe_command_excluding, within_auto_approve_len,
+};
 use crate::permissions::strategies::PermissionModeStrategy;
 use crate::session::{PermissionMode, ToolPermissionResult};
+use crate::tools::{BashInput, ToolInputExt};
 use serde_json::Value;
 
 /// Strategy for Default mode - standard permission prompts
-#[derive(Debug)]
-pub struct DefaultModeStrategy;
+#[derive(Debug, Default)]
+pub struct DefaultModeStrategy {
+    /// Command basenames removed from the built-in safe set
+    disabled_safe_commands: Vec<String>,
+    /// Longest Bash command auto-approved as safe (None = no limit)
+    max_auto_approve_command_len: Option<usize>,
+    /// Replaces the built-in safe command check (None = built-in)
+    safe_command_classifier: Option<Arc<dyn SafeCommandClassifier>>,
+}
+
+impl DefaultModeStrategy {
+    /// Create a strategy using the full built-in safe command set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a strategy that never auto-approves the given command basenames
+    pub fn with_disabled_safe_commands(disabled_safe_commands: Vec<String>) -> Self {
+        Self {
+            disabled_safe_commands,
+            max_auto_approve_command_len: None,
+            safe_command_classifier: None,
+        }
+    }
+
+    /// Never auto-approve Bash commands longer than `max_len` characters
+    pub fn with_max_auto_approve_command_len(mut self, max_len: Option<usize>) -> Self {
+        self.max_auto_approve_command_len = max_len;
+        self
+    }
+
+    /// Decide which Bash commands are safe with `classifier` instead of the built-in set
+    ///
+    /// The disabled safe commands only apply to the built-in set.
+    pub fn with_safe_command_classifier(
+        mut self,
+        classifier: Option<Arc<dyn SafeCommandClassifier>>,
+    ) -> Self {
+        self.safe_command_classifier = classifier;
+        self
+    }
+
+    /// Get the command basenames removed from the built-in safe set
+    pub fn disabled_safe_commands(&self) -> &[String] {
+        &self.disabled_safe_commands
+    }
+}
 
 impl PermissionModeStrategy for DefaultModeStrategy {
     fn mode(&self) -> PermissionMode {
@@ -20,16 +71,24 @@ impl PermissionModeStrategy for DefaultModeStrategy {
     }
 
     fn should_auto_approve(&self, tool_name: &str, tool_input: &Value) -> bool {
-        // Auto-approve read operations
-        if matches!(tool_name, "Read" | "Glob" | "Grep" | "LS" | "NotebookRead") {
+        // Auto-approve read operations (BashOutput only reads background shell output),
+        // named with or without the ACP prefix like KillShell
+        if matches!(
+            tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name),
+            "Read" | "Glob" | "Grep" | "LS" | "NotebookRead" | "BashOutput"
+        ) {
             return true;
         }
 
-        // Auto-approve known safe Bash commands
+        // Auto-approve known safe Bash commands that are short enough to review
         if tool_name == "Bash"
-            && let Some(cmd) = tool_input.get("command").and_then(|v| v.as_str())
+            && let Some(cmd) = BashInput::from_value(tool_input).command
         {
-            return is_known_safe_command(cmd);
+            return within_auto_approve_len(&cmd, self.max_auto_approve_command_len)
+                && match &self.safe_command_classifier {
+                    Some(classifier) => classifier.is_safe_command(&cmd),
+                    None => is_known_safe_command_excluding(&cmd, &self.disabled_safe_commands),
+                };
         }
 
         false
@@ -59,13 +118,13 @@ mod tests {
 
     #[test]
     fn test_mode() {
-        let strategy = DefaultModeStrategy;
+        let strategy = DefaultModeStrategy::new();
         assert_eq!(strategy.mode(), PermissionMode::Default);
     }
 
     #[test]
     fn test_auto_approves_reads() {
-        let strategy = DefaultModeStrategy;
+        let strategy = DefaultModeStrategy::new();
         assert!(strategy.should_auto_approve("Read", &json!({})));
         assert!(strategy.should_auto_approve("Glob", &json!({})));
         assert!(strategy.should_auto_approve("Grep", &json!({})));
@@ -75,28 +134,91 @@ mod tests {
 
     #[test]
     fn test_does_not_auto_approve_writes() {
-        let strategy = DefaultModeStrategy;
+        let strategy = DefaultModeStrategy::new();
         assert!(!strategy.should_auto_approve("Write", &json!({})));
         assert!(!strategy.should_auto_approve("Edit", &json!({})));
     }
 
     #[test]
     fn test_auto_approves_safe_bash_commands() {
-        let strategy = DefaultModeStrategy;
+        let strategy = DefaultModeStrategy::new();
         assert!(strategy.should_auto_approve("Bash", &json!({"command": "cat file.txt"})));
         assert!(strategy.should_auto_approve("Bash", &json!({"command": "echo test"})));
         assert!(!strategy.should_auto_approve("Bash", &json!({"command": "rm -rf /"})));
     }
 
+    /// Treats `make` targets as safe and nothing else
+    #[derive(Debug)]
+    struct MakeOnly;
+
+    impl SafeCommandClassifier for MakeOnly {
+        fn is_safe_command(&self, command: &str) -> bool {
+            command.starts_with("make ")
+        }
+    }
+
+    #[test]
+    fn test_custom_safe_command_classifier() {
+        let strategy =
+            DefaultModeStrategy::new().with_safe_command_classifier(Some(Arc::new(MakeOnly)));
+        assert!(strategy.should_auto_approve("Bash", &json!({"command": "make deploy"})));
+        assert!(!strategy.should_auto_approve("Bash", &json!({"command": "ls -la"})));
+        // Reads don't go through the classifier
+        assert!(strategy.should_auto_approve("Read", &json!({})));
+
+        // The length limit still applies
+        let limited = DefaultModeStrategy::new()
+            .with_safe_command_classifier(Some(Arc::new(MakeOnly)))
+            .with_max_auto_approve_command_len(Some(5));
+        assert!(!limited.should_auto_approve("Bash", &json!({"command": "make deploy"})));
+    }
+
+    #[test]
+    fn test_max_auto_approve_command_len() {
+        let command = "cat file.txt"; // 12 characters
+        let at_limit = DefaultModeStrategy::new().with_max_auto_approve_command_len(Some(12));
+        assert_eq!(
+            at_limit.check_permission("Bash", &json!({"command": command})),
+            ToolPermissionResult::Allowed
+        );
+
+        let below_limit = DefaultModeStrategy::new().with_max_auto_approve_command_len(Some(11));
+        assert_eq!(
+            below_limit.check_permission("Bash", &json!({"command": command})),
+            ToolPermissionResult::NeedsPermission
+        );
+        // Reads other than Bash are unaffected
+        assert!(below_limit.should_auto_approve("Read", &json!({"file_path": "/tmp/a"})));
+
+        // Characters are counted, not bytes
+        let unicode = DefaultModeStrategy::new().with_max_auto_approve_command_len(Some(7));
+        assert!(unicode.should_auto_approve("Bash", &json!({"command": "echo é"})));
+    }
+
+    #[test]
+    fn test_background_shell_tools() {
+        let strategy = DefaultModeStrategy::new();
+        for prefix in ["", "mcp__acp__"] {
+            let bash_output = format!("{prefix}BashOutput");
+            let kill_shell = format!("{prefix}KillShell");
+            assert!(strategy.should_auto_approve(&bash_output, &json!({"bash_id": "abc"})));
+            assert!(!strategy.should_auto_approve(&kill_shell, &json!({"shell_id": "abc"})));
+            assert_eq!(
+                strategy.check_permission(&kill_shell, &json!({"shell_id": "abc"})),
+                ToolPermissionResult::NeedsPermission
+            );
+        }
+    }
+
     #[test]
     fn test_never_blocks_explicitly() {
-        let strategy = DefaultModeStrategy;
+        let strategy = DefaultModeStrategy::new();
         assert!(strategy.is_tool_blocked("AnyTool", &json!({})).is_none());
     }
 
     #[test]
     fn test_check_permission_auto_approves_reads() {
-        let strategy = DefaultModeStrategy;
+        let strategy = DefaultModeStrategy::new();
         match strategy.check_permission("Read", &json!({})) {
             ToolPermissionResult::Allowed => {}
             _ => panic!("Expected Allowed for Read"),
@@ -105,10 +227,38 @@ mod tests {
 
     #[test]
     fn test_check_permission_needs_permission_for_writes() {
-        let strategy = DefaultModeStrategy;
+        let strategy = DefaultModeStrategy::new();
         match strategy.check_permission("Write", &json!({})) {
             ToolPermissionResult::NeedsPermission => {}
             _ => panic!("Expected NeedsPermission for Write"),
         }
     }
+
+    #[test]
+    fn test_disabled_safe_commands_need_permission() {
+        let strategy = DefaultModeStrategy::with_disabled_safe_commands(vec!["find".to_string()]);
+        let find = json!({"command": "find . -name x"});
+        assert!(!strategy.should_auto_approve("Bash", &find));
+        assert_eq!(
+            strategy.check_permission("Bash", &find),
+            ToolPermissionResult::NeedsPermission
+        );
+        // Other safe commands still auto-approve
+        assert!(strategy.should_auto_approve("Bash", &json!({"command": "ls -la"})));
+        assert!(strategy.should_auto_approve("Bash", &json!({"command": "git status"})));
+    }
+
+    #[test]
+    fn test_bash_input_shapes() {
+        let strategy = DefaultModeStrategy::new();
+        // Extra fields don't affect the decision
+        assert!(strategy.should_auto_approve(
+            "Bash",
+            &json!({"command": "ls", "description": "List files", "timeout": 1000})
+        ));
+        // Missing or non-string commands are never auto-approved
+        assert!(!strategy.should_auto_approve("Bash", &json!({})));
+        assert!(!strategy.should_auto_approve("Bash", &json!({"command": ["ls"]})));
+        assert!(!strategy.should_auto_approve("Bash", &json!("ls")));
+    }
 }
@@ -0,0 +1,97 @@
+//! Interactive mode strategy
+//!
+//! This strategy prompts for every tool call, including reads and known
+//! safe Bash commands. Intended for demos and high-trust review sessions
+//! where the user wants to see and approve each step.
+
+use crate::permissions::strategies::PermissionModeStrategy;
+use crate::session::{PermissionMode, ToolPermissionResult};
+use serde_json::Value;
+
+/// Strategy for Interactive mode - prompt for everything
+#[derive(Debug)]
+pub struct InteractiveModeStrategy;
+
+impl PermissionModeStrategy for InteractiveModeStrategy {
+    fn mode(&self) -> PermissionMode {
+        PermissionMode::Interactive
+    }
+
+    fn should_auto_approve(&self, _tool_name: &str, _tool_input: &Value) -> bool {
+        // Interactive mode: nothing is auto-approved
+        false
+    }
+
+    fn is_tool_blocked(&self, _tool_name: &str, _tool_input: &Value) -> Option<String> {
+        // Interactive mode asks instead of blocking
+        None
+    }
+
+    fn check_permission(&self, tool_name: &str, _tool_input: &Value) -> ToolPermissionResult {
+        // Tools that only talk to the user would otherwise prompt to ask a question
+        if matches!(
+            tool_name,
+            "AskUserQuestion" | "TodoWrite" | "mcp__acp__AskUserQuestion" | "mcp__acp__TodoWrite"
+        ) {
+            return ToolPermissionResult::Allowed;
+        }
+
+        ToolPermissionResult::NeedsPermission
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_mode() {
+        let strategy = InteractiveModeStrategy;
+        assert_eq!(strategy.mode(), PermissionMode::Interactive);
+    }
+
+    #[test]
+    fn test_never_auto_approves() {
+        let strategy = InteractiveModeStrategy;
+        assert!(!strategy.should_auto_approve("Read", &json!({})));
+        assert!(!strategy.should_auto_approve("Glob", &json!({})));
+        assert!(!strategy.should_auto_approve("Bash", &json!({"command": "ls"})));
+    }
+
+    #[test]
+    fn test_read_needs_permission() {
+        let strategy = InteractiveModeStrategy;
+        assert_eq!(
+            strategy.check_permission("Read", &json!({"file_path": "/tmp/a.txt"})),
+            ToolPermissionResult::NeedsPermission
+        );
+        assert_eq!(
+            strategy.check_permission("mcp__acp__Read", &json!({"file_path": "/tmp/a.txt"})),
+            ToolPermissionResult::NeedsPermission
+        );
+        assert_eq!(
+            strategy.check_permission("Bash", &json!({"command": "echo hi"})),
+            ToolPermissionResult::NeedsPermission
+        );
+    }
+
+    #[test]
+    fn test_user_interaction_tools_allowed() {
+        let strategy = InteractiveModeStrategy;
+        assert_eq!(
+            strategy.check_permission("AskUserQuestion", &json!({})),
+            ToolPermissionResult::Allowed
+        );
+        assert_eq!(
+            strategy.check_permission("TodoWrite", &json!({})),
+            ToolPermissionResult::Allowed
+        );
+    }
+
+    #[test]
+    fn test_never_blocks_explicitly() {
+        let strategy = InteractiveModeStrategy;
+        assert!(strategy.is_tool_blocked("Write", &json!({})).is_none());
+    }
+}
@@ -7,12 +7,18 @@ mod accept_edits_mode;
 mod bypass_permissions_mode;
 mod default_mode;
 mod dont_ask_mode;
+mod interactive_mode;
 mod plan_mode;
 mod strategy_trait;
+mod strict_accept_edits_mode;
+mod trust_on_first_use;
 
 pub use accept_edits_mode::AcceptEditsModeStrategy;
 pub use bypass_permissions_mode::BypassPermissionsModeStrategy;
 pub use default_mode::DefaultModeStrategy;
 pub use dont_ask_mode::DontAskModeStrategy;
+pub use interactive_mode::InteractiveModeStrategy;
 pub use plan_mode::PlanModeStrategy;
-pub use strategy_trait::PermissionModeStrategy;
+pub use strategy_trait::{PermissionModeStrategy, ToolCategory};
+pub use strict_accept_edits_mode::StrictAcceptEditsModeStrategy;
+pub use trust_on_first_use::TrustOnFirstUseStrategy;
@@ -4,15 +4,85 @@
 //! the ~/.claude/plans/ directory. This is used during planning phases where
 //! the user should be able to explore and write plans, but not make changes
 //! to the codebase.
+//!
+//! The plans directory can be overridden (e.g. in sandboxes without a home
+//! directory) via [`PlanModeStrategy::with_plans_dir`], and a project-local
+//! `.claude/plans/` can be allowed alongside it via
+//! [`PlanModeStrategy::with_project_plans_dir`].
+
+use std::path::{Path, PathBuf};
 
 use crate::permissions::strategies::PermissionModeStrategy;
 use crate::session::{PermissionMode, ToolPermissionResult};
-use crate::utils::is_plans_directory_path;
+use crate::tools::{FilePathInput, ToolInputExt};
+use crate::utils::{is_path_in_plans_dir, is_plans_directory_path};
 use serde_json::Value;
 
 /// Strategy for Plan mode - read-only with exceptions for plan files
-#[derive(Debug)]
-pub struct PlanModeStrategy;
+#[derive(Debug, Default)]
+pub struct PlanModeStrategy {
+    /// Custom plans directory (None = ~/.claude/plans/)
+    plans_dir: Option<PathBuf>,
+    /// Project-local plans directory, allowed in addition to `plans_dir`
+    project_plans_dir: Option<PathBuf>,
+}
+
+impl PlanModeStrategy {
+    /// Create a strategy using the default plans directory (~/.claude/plans/)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a strategy that allows writes to a custom plans directory
+    pub fn with_plans_dir(plans_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            plans_dir: Some(plans_dir.into()),
+            project_plans_dir: None,
+        }
+    }
+
+    /// Also allow writes to a project-local plans directory
+    pub fn with_project_plans_dir(mut self, project_plans_dir: impl Into<PathBuf>) -> Self {
+        self.project_plans_dir = Some(project_plans_dir.into());
+        self
+    }
+
+    /// Get the custom plans directory, if configured
+    pub fn plans_dir(&self) -> Option<&Path> {
+        self.plans_dir.as_deref()
+    }
+
+    /// Get the project-local plans directory, if configured
+    pub fn project_plans_dir(&self) -> Option<&Path> {
+        self.project_plans_dir.as_deref()
+    }
+
+    /// Check if a path is inside the (custom or default) or project plans directory
+    fn is_plan_file(&self, path: &str) -> bool {
+        let in_primary = match self.plans_dir {
+            Some(ref dir) => is_path_in_plans_dir(path, dir),
+            None => is_plans_directory_path(path),
+        };
+        in_primary
+            || self
+                .project_plans_dir
+                .as_deref()
+                .is_some_and(|dir| is_path_in_plans_dir(path, dir))
+    }
+
+    /// Human-readable plans directories for error messages
+    fn plans_dir_display(&self) -> String {
+        let primary = self
+            .plans_dir
+            .as_ref()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_else(|| "~/.claude/plans/".to_string());
+        match self.project_plans_dir {
+            Some(ref dir) => format!("{} or {}", primary, dir.display()),
+            None => primary,
+        }
+    }
+}
 
 impl PermissionModeStrategy for PlanModeStrategy {
     fn mode(&self) -> PermissionMode {
@@ -20,8 +90,12 @@ impl PermissionModeStrategy for PlanModeStrategy {
     }
 
     fn should_auto_approve(&self, tool_name: &str, _tool_input: &Value) -> bool {
-        // Auto-approve read operations
-        matches!(tool_name, "Read" | "Glob" | "Grep" | "LS" | "NotebookRead")
+        // Auto-approve read operations (BashOutput only reads background shell output),
+        // named with or without the ACP prefix like KillShell
+        matches!(
+            tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name),
+            "Read" | "Glob" | "Grep" | "LS" | "NotebookRead" | "BashOutput"
+        )
     }
 
     fn is_tool_blocked(&self, tool_name: &str, tool_input: &Value) -> Option<String> {
@@ -31,16 +105,14 @@ impl PermissionModeStrategy for PlanModeStrategy {
             return None; // Read operations are allowed
         }
 
-        // Check if this is a write to the plans directory (exception)
+        // Check if this is a write to the plans directory (exception).
+        // The path is the one the tool actually writes (`notebook_path` for
+        // NotebookEdit), so a stray field can't vouch for another target.
         if matches!(tool_name, "Edit" | "Write" | "NotebookEdit") {
-            let file_path = tool_input
-                .get("file_path")
-                .or_else(|| tool_input.get("path"))
-                .or_else(|| tool_input.get("notebook_path"))
-                .and_then(|v| v.as_str());
-
-            if let Some(path) = file_path
-                && is_plans_directory_path(path)
+            let input = FilePathInput::from_value(tool_input);
+
+            if let Some(path) = input.path_for_tool(tool_name)
+                && self.is_plan_file(path)
             {
                 return None; // Allow plan file writes
             }
@@ -48,8 +120,9 @@ impl PermissionModeStrategy for PlanModeStrategy {
 
         // Block all other write operations
         Some(format!(
-            "Tool {} is not allowed in Plan mode (only read operations and writing to ~/.claude/plans/ are allowed)",
-            tool_name
+            "Tool {} is not allowed in Plan mode (only read operations and writing to {} are allowed)",
+            tool_name,
+            self.plans_dir_display()
         ))
     }
 
@@ -64,6 +137,11 @@ impl PermissionModeStrategy for PlanModeStrategy {
             return ToolPermissionResult::Allowed;
         }
 
+        // Killing a background shell is a side effect - always confirm
+        if matches!(tool_name, "KillShell" | "mcp__acp__KillShell") {
+            return ToolPermissionResult::NeedsPermission;
+        }
+
         // Plan file writes are allowed (checked in is_tool_blocked)
         // If we reach here, it's an allowed plan file write
         ToolPermissionResult::Allowed
@@ -87,13 +165,13 @@ mod tests {
 
     #[test]
     fn test_mode() {
-        let strategy = PlanModeStrategy;
+        let strategy = PlanModeStrategy::new();
         assert_eq!(strategy.mode(), PermissionMode::Plan);
     }
 
     #[test]
     fn test_auto_approves_reads() {
-        let strategy = PlanModeStrategy;
+        let strategy = PlanModeStrategy::new();
         assert!(strategy.should_auto_approve("Read", &json!({})));
         assert!(strategy.should_auto_approve("Glob", &json!({})));
         assert!(strategy.should_auto_approve("Grep", &json!({})));
@@ -103,7 +181,7 @@ mod tests {
 
     #[test]
     fn test_does_not_auto_approve_writes() {
-        let strategy = PlanModeStrategy;
+        let strategy = PlanModeStrategy::new();
         assert!(!strategy.should_auto_approve("Write", &json!({})));
         assert!(!strategy.should_auto_approve("Edit", &json!({})));
         assert!(!strategy.should_auto_approve("Bash", &json!({})));
@@ -111,7 +189,7 @@ mod tests {
 
     #[test]
     fn test_blocks_non_plan_writes() {
-        let strategy = PlanModeStrategy;
+        let strategy = PlanModeStrategy::new();
         let result = strategy.is_tool_blocked(
             "Write",
             &json!({"file_path": "/tmp/test.txt", "content": "test"}),
@@ -122,7 +200,7 @@ mod tests {
 
     #[test]
     fn test_blocks_bash() {
-        let strategy = PlanModeStrategy;
+        let strategy = PlanModeStrategy::new();
         let result = strategy.is_tool_blocked("Bash", &json!({"command": "echo test"}));
         assert!(result.is_some());
         assert!(result.unwrap().contains("not allowed in Plan mode"));
@@ -130,7 +208,7 @@ mod tests {
 
     #[test]
     fn test_allows_plan_file_writes() {
-        let strategy = PlanModeStrategy;
+        let strategy = PlanModeStrategy::new();
         let plan_path = home_plans_path();
         let result = strategy.is_tool_blocked(
             "Write",
@@ -141,7 +219,7 @@ mod tests {
 
     #[test]
     fn test_check_permission_allows_reads() {
-        let strategy = PlanModeStrategy;
+        let strategy = PlanModeStrategy::new();
         match strategy.check_permission("Read", &json!({})) {
             ToolPermissionResult::Allowed => {}
             _ => panic!("Expected Allowed for Read"),
@@ -150,7 +228,7 @@ mod tests {
 
     #[test]
     fn test_check_permission_blocks_non_plan_writes() {
-        let strategy = PlanModeStrategy;
+        let strategy = PlanModeStrategy::new();
         match strategy.check_permission("Write", &json!({"file_path": "/tmp/test.txt"})) {
             ToolPermissionResult::Blocked { .. } => {}
             _ => panic!("Expected Blocked for non-plan file writes"),
@@ -159,11 +237,132 @@ mod tests {
 
     #[test]
     fn test_check_permission_allows_plan_writes() {
-        let strategy = PlanModeStrategy;
+        let strategy = PlanModeStrategy::new();
         let plan_path = home_plans_path();
         match strategy.check_permission("Write", &json!({"file_path": plan_path})) {
             ToolPermissionResult::Allowed => {}
             _ => panic!("Expected Allowed for plan file writes"),
         }
     }
+
+    #[test]
+    fn test_background_shell_tools() {
+        let strategy = PlanModeStrategy::new();
+        for prefix in ["", "mcp__acp__"] {
+            let bash_output = format!("{prefix}BashOutput");
+            assert!(strategy.should_auto_approve(&bash_output, &json!({"bash_id": "abc"})));
+            assert_eq!(
+                strategy.check_permission(&bash_output, &json!({"bash_id": "abc"})),
+                ToolPermissionResult::Allowed
+            );
+
+            let kill_shell = format!("{prefix}KillShell");
+            assert!(!strategy.should_auto_approve(&kill_shell, &json!({"shell_id": "abc"})));
+            assert_eq!(
+                strategy.check_permission(&kill_shell, &json!({"shell_id": "abc"})),
+                ToolPermissionResult::NeedsPermission
+            );
+        }
+    }
+
+    #[test]
+    fn test_project_plans_dir_is_honored() {
+        let project = tempfile::TempDir::new().unwrap();
+        let local = project.path().join(".claude").join("plans");
+        let strategy = PlanModeStrategy::new().with_project_plans_dir(&local);
+
+        let local_plan = local.join("plan.md");
+        assert!(
+            strategy
+                .is_tool_blocked("Write", &json!({"file_path": local_plan.to_str().unwrap()}))
+                .is_none()
+        );
+        // The home plans directory still works
+        assert!(
+            strategy
+                .is_tool_blocked("Write", &json!({"file_path": home_plans_path()}))
+                .is_none()
+        );
+
+        let source = project.path().join("src").join("lib.rs");
+        let reason = strategy
+            .is_tool_blocked("Edit", &json!({"file_path": source.to_str().unwrap()}))
+            .unwrap();
+        assert!(reason.contains(&local.display().to_string()));
+    }
+
+    #[test]
+    fn test_custom_plans_dir_is_honored() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let strategy = PlanModeStrategy::with_plans_dir(temp_dir.path());
+        let plan_path = temp_dir.path().join("plan.md");
+
+        let result = strategy.is_tool_blocked(
+            "Write",
+            &json!({"file_path": plan_path.to_str().unwrap(), "content": "# Plan"}),
+        );
+        assert!(
+            result.is_none(),
+            "Writes to custom plans dir should be allowed"
+        );
+
+        // The default plans directory is no longer an exception
+        let result = strategy.is_tool_blocked("Write", &json!({"file_path": home_plans_path()}));
+        assert!(result.is_some());
+        assert!(
+            result
+                .unwrap()
+                .contains(&temp_dir.path().display().to_string())
+        );
+    }
+
+    #[test]
+    fn test_notebook_edit_in_plans_dir() {
+        let plans = tempfile::TempDir::new().unwrap();
+        let strategy = PlanModeStrategy::with_plans_dir(plans.path());
+        let plan_notebook = plans.path().join("analysis.ipynb");
+        let plan_notebook = plan_notebook.to_str().unwrap();
+
+        assert_eq!(
+            strategy.check_permission("NotebookEdit", &json!({"notebook_path": plan_notebook})),
+            ToolPermissionResult::Allowed
+        );
+        assert!(matches!(
+            strategy.check_permission(
+                "NotebookEdit",
+                &json!({"notebook_path": "/tmp/project/analysis.ipynb"})
+            ),
+            ToolPermissionResult::Blocked { .. }
+        ));
+        // The notebook being edited decides, not another path field
+        assert!(
+            strategy
+                .is_tool_blocked(
+                    "NotebookEdit",
+                    &json!({
+                        "notebook_path": "/tmp/project/analysis.ipynb",
+                        "file_path": plan_notebook
+                    })
+                )
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_plan_file_path_fields() {
+        let strategy = PlanModeStrategy::new();
+        let plan_path = home_plans_path();
+        // Any path-bearing field is honored
+        assert!(
+            strategy
+                .is_tool_blocked("NotebookEdit", &json!({"notebook_path": plan_path}))
+                .is_none()
+        );
+        assert!(
+            strategy
+                .is_tool_blocked("Edit", &json!({"path": plan_path, "old_string": "a"}))
+                .is_none()
+        );
+        assert!(strategy.is_tool_blocked("Edit", &json!({})).is_some());
+    }
 }
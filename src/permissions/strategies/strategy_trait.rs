@@ -1,8 +1,50 @@
 //! Core strategy trait for permission modes
 
 use crate::session::{PermissionMode, ToolPermissionResult};
+use serde::Serialize;
 use serde_json::Value;
 
+/// What kind of operation a tool performs, for UI hints
+///
+/// Independent of the permission decision: a blocked Write is still a
+/// [`ToolCategory::Write`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolCategory {
+    /// Reads files or searches the workspace
+    Read,
+    /// Creates or modifies files
+    Write,
+    /// Runs commands, subagents or skills
+    Execute,
+    /// Fetches from or searches the web
+    Network,
+    /// Asks the user something or changes the session's mode
+    Interactive,
+    /// Anything else, such as unknown MCP tools
+    Unknown,
+}
+
+impl ToolCategory {
+    /// Categorize a tool by name (the `mcp__acp__` prefix is ignored)
+    ///
+    /// This is the default for [`PermissionModeStrategy::tool_category`].
+    /// External MCP tools are only recognized once the caller has mapped
+    /// them to the built-in tool they stand for.
+    pub fn of_tool(tool_name: &str) -> Self {
+        match tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name) {
+            "Read" | "Glob" | "Grep" | "LS" | "NotebookRead" | "BashOutput" | "TaskOutput" => {
+                Self::Read
+            }
+            "Edit" | "Write" | "MultiEdit" | "NotebookEdit" => Self::Write,
+            "Bash" | "KillShell" | "Task" | "SlashCommand" | "Skill" => Self::Execute,
+            "WebFetch" | "WebSearch" => Self::Network,
+            "AskUserQuestion" | "ExitPlanMode" | "EnterPlanMode" => Self::Interactive,
+            _ => Self::Unknown,
+        }
+    }
+}
+
 /// Strategy trait for permission mode checking
 ///
 /// Each strategy encapsulates the permission logic for a specific mode,
@@ -27,10 +69,54 @@ pub trait PermissionModeStrategy: Send + Sync {
     /// Returns the final permission decision for this tool invocation.
     /// This method is called after settings rules are checked.
     fn check_permission(&self, tool_name: &str, tool_input: &Value) -> ToolPermissionResult;
+
+    /// Categorize a tool call for UI hints
+    ///
+    /// Defaults to [`ToolCategory::of_tool`]; strategies may refine it using
+    /// the input.
+    fn tool_category(&self, tool_name: &str, _tool_input: &Value) -> ToolCategory {
+        ToolCategory::of_tool(tool_name)
+    }
+
+    /// Note that the user approved a tool call this strategy asked about
+    ///
+    /// Called once the prompt is answered with an allow, never for calls the
+    /// strategy approved itself. Does nothing by default.
+    fn record_approval(&self, _tool_name: &str, _tool_input: &Value) {}
 }
 
 #[cfg(test)]
 mod tests {
-    // Trait is tested through implementing strategies
+    // The trait's required methods are tested through implementing strategies
     // See individual strategy tests for details
+    use super::*;
+    use crate::permissions::strategies::DefaultModeStrategy;
+    use serde_json::json;
+
+    #[test]
+    fn test_tool_category() {
+        let strategy = DefaultModeStrategy::new();
+        let input = json!({});
+        let cases = [
+            ("Read", ToolCategory::Read),
+            ("mcp__acp__Grep", ToolCategory::Read),
+            ("Write", ToolCategory::Write),
+            ("mcp__acp__Edit", ToolCategory::Write),
+            ("Bash", ToolCategory::Execute),
+            ("WebFetch", ToolCategory::Network),
+            ("AskUserQuestion", ToolCategory::Interactive),
+            ("mcp__my-server__my_tool", ToolCategory::Unknown),
+        ];
+        for (tool_name, expected) in cases {
+            assert_eq!(
+                strategy.tool_category(tool_name, &input),
+                expected,
+                "{tool_name}"
+            );
+        }
+        assert_eq!(
+            serde_json::to_value(ToolCategory::Network).unwrap(),
+            json!("network")
+        );
+    }
 }
@@ -0,0 +1,174 @@
+//! Strict AcceptEdits mode strategy
+//!
+//! An opt-in variant of AcceptEdits (`strictAcceptEdits` in settings) that
+//! auto-approves what the mode's name promises:
+//! - Auto-approves read operations and file edits
+//! - Auto-approves known safe Bash commands (minus any disabled in settings)
+//! - Requires user permission for other Bash commands, such as `rm -rf`,
+//!   and for other tools
+
+use std::sync::Arc;
+
+use crate::command_safety::{
+    SafeCommandClassifier, is_known_safe_command_excluding, within_auto_approve_len,
+};
+use crate::permissions::strategies::PermissionModeStrategy;
+use crate::session::{PermissionMode, ToolPermissionResult};
+use crate::tools::{BashInput, ToolInputExt};
+use serde_json::Value;
+
+/// Strategy for strict AcceptEdits mode - auto-approve edits, gate Bash
+#[derive(Debug, Default)]
+pub struct StrictAcceptEditsModeStrategy {
+    /// Command basenames removed from the built-in safe set
+    disabled_safe_commands: Vec<String>,
+    /// Longest Bash command auto-approved as safe (None = no limit)
+    max_auto_approve_command_len: Option<usize>,
+    /// Replaces the built-in safe command check (None = built-in)
+    safe_command_classifier: Option<Arc<dyn SafeCommandClassifier>>,
+}
+
+impl StrictAcceptEditsModeStrategy {
+    /// Create a strategy using the full built-in safe command set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a strategy that never auto-approves the given command basenames
+    pub fn with_disabled_safe_commands(disabled_safe_commands: Vec<String>) -> Self {
+        Self {
+            disabled_safe_commands,
+            max_auto_approve_command_len: None,
+            safe_command_classifier: None,
+        }
+    }
+
+    /// Decide which Bash commands are safe with `classifier` instead of the built-in set
+    ///
+    /// The disabled safe commands only apply to the built-in set.
+    pub fn with_safe_command_classifier(
+        mut self,
+        classifier: Option<Arc<dyn SafeCommandClassifier>>,
+    ) -> Self {
+        self.safe_command_classifier = classifier;
+        self
+    }
+
+    /// Never auto-approve Bash commands longer than `max_len` characters
+    pub fn with_max_auto_approve_command_len(mut self, max_len: Option<usize>) -> Self {
+        self.max_auto_approve_command_len = max_len;
+        self
+    }
+}
+
+impl PermissionModeStrategy for StrictAcceptEditsModeStrategy {
+    fn mode(&self) -> PermissionMode {
+        PermissionMode::AcceptEdits
+    }
+
+    fn should_auto_approve(&self, tool_name: &str, tool_input: &Value) -> bool {
+        // Auto-approve read operations and file edits
+        if matches!(
+            tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name),
+            "Read"
+                | "Glob"
+                | "Grep"
+                | "LS"
+                | "NotebookRead"
+                | "BashOutput"
+                | "Edit"
+                | "Write"
+                | "MultiEdit"
+                | "NotebookEdit"
+        ) {
+            return true;
+        }
+
+        // Auto-approve known safe Bash commands
+        if tool_name == "Bash"
+            && let Some(cmd) = BashInput::from_value(tool_input).command
+        {
+            return within_auto_approve_len(&cmd, self.max_auto_approve_command_len)
+                && match &self.safe_command_classifier {
+                    Some(classifier) => classifier.is_safe_command(&cmd),
+                    None => is_known_safe_command_excluding(&cmd, &self.disabled_safe_commands),
+                };
+        }
+
+        false
+    }
+
+    fn is_tool_blocked(&self, _tool_name: &str, _tool_input: &Value) -> Option<String> {
+        // Nothing is blocked; tools that aren't auto-approved prompt instead
+        None
+    }
+
+    fn check_permission(&self, tool_name: &str, tool_input: &Value) -> ToolPermissionResult {
+        if self.should_auto_approve(tool_name, tool_input) {
+            return ToolPermissionResult::Allowed;
+        }
+
+        ToolPermissionResult::NeedsPermission
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permissions::strategies::AcceptEditsModeStrategy;
+    use serde_json::json;
+
+    #[test]
+    fn test_mode() {
+        let strategy = StrictAcceptEditsModeStrategy::new();
+        assert_eq!(strategy.mode(), PermissionMode::AcceptEdits);
+    }
+
+    #[test]
+    fn test_auto_approves_edits_and_reads() {
+        let strategy = StrictAcceptEditsModeStrategy::new();
+        for tool in ["Read", "Grep", "Edit", "Write", "MultiEdit", "NotebookEdit"] {
+            assert!(strategy.should_auto_approve(tool, &json!({})), "{tool}");
+        }
+        assert!(strategy.should_auto_approve("Bash", &json!({"command": "git status"})));
+    }
+
+    #[test]
+    fn test_dangerous_bash_needs_permission() {
+        let rm = json!({"command": "rm -rf /"});
+
+        // The default AcceptEdits strategy approves everything
+        assert_eq!(
+            AcceptEditsModeStrategy.check_permission("Bash", &rm),
+            ToolPermissionResult::Allowed
+        );
+
+        // The strict variant routes the command to a prompt
+        let strategy = StrictAcceptEditsModeStrategy::new();
+        assert!(!strategy.should_auto_approve("Bash", &rm));
+        assert_eq!(
+            strategy.check_permission("Bash", &rm),
+            ToolPermissionResult::NeedsPermission
+        );
+        assert!(strategy.is_tool_blocked("Bash", &rm).is_none());
+
+        for command in ["source venv/bin/activate", ". ./build.sh"] {
+            assert_eq!(
+                strategy.check_permission("Bash", &json!({"command": command})),
+                ToolPermissionResult::NeedsPermission,
+                "{command}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_disabled_safe_commands_need_permission() {
+        let strategy =
+            StrictAcceptEditsModeStrategy::with_disabled_safe_commands(vec!["find".to_string()]);
+        assert_eq!(
+            strategy.check_permission("Bash", &json!({"command": "find . -name x"})),
+            ToolPermissionResult::NeedsPermission
+        );
+        assert!(strategy.should_auto_approve("Bash", &json!({"command": "ls -la"})));
+    }
+}
@@ -0,0 +1,173 @@
+//! Trust-on-first-use strategy
+//!
+//! Wraps another strategy and prompts the first time each tool is used in a
+//! session; once the user approves it, later uses of the same tool are
+//! approved without asking.
+//! Register one instance per session with
+//! [`PermissionHandler::register_strategy`] so the seen tools don't leak
+//! across sessions.
+//!
+//! [`PermissionHandler::register_strategy`]: crate::session::PermissionHandler::register_strategy
+
+use std::sync::Arc;
+
+use dashmap::DashSet;
+use serde_json::Value;
+
+use crate::permissions::strategies::{PermissionModeStrategy, ToolCategory};
+use crate::session::{PermissionMode, ToolPermissionResult};
+
+/// Strategy that asks per tool name until the user approves it once
+///
+/// Tools the inner strategy approves or blocks are passed through unchanged.
+/// Settings deny rules are checked by the permission handler before any
+/// strategy, so a denied tool stays denied however often it was used.
+pub struct TrustOnFirstUseStrategy {
+    /// Strategy deciding everything not covered by first-use trust
+    inner: Arc<dyn PermissionModeStrategy>,
+    /// Tool names (without the `mcp__acp__` prefix) the user has approved
+    seen_tools: DashSet<String>,
+}
+
+impl TrustOnFirstUseStrategy {
+    /// Wrap `inner`, starting with no tools seen
+    pub fn new(inner: Arc<dyn PermissionModeStrategy>) -> Self {
+        Self {
+            inner,
+            seen_tools: DashSet::new(),
+        }
+    }
+
+    /// Whether the user has approved the tool before in this session
+    pub fn has_seen(&self, tool_name: &str) -> bool {
+        self.seen_tools.contains(tool_key(tool_name))
+    }
+}
+
+impl std::fmt::Debug for TrustOnFirstUseStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrustOnFirstUseStrategy")
+            .field("mode", &self.inner.mode())
+            .field("seen_tools", &self.seen_tools)
+            .finish()
+    }
+}
+
+impl PermissionModeStrategy for TrustOnFirstUseStrategy {
+    fn mode(&self) -> PermissionMode {
+        self.inner.mode()
+    }
+
+    fn should_auto_approve(&self, tool_name: &str, tool_input: &Value) -> bool {
+        self.has_seen(tool_name) || self.inner.should_auto_approve(tool_name, tool_input)
+    }
+
+    fn is_tool_blocked(&self, tool_name: &str, tool_input: &Value) -> Option<String> {
+        self.inner.is_tool_blocked(tool_name, tool_input)
+    }
+
+    fn check_permission(&self, tool_name: &str, tool_input: &Value) -> ToolPermissionResult {
+        match self.inner.check_permission(tool_name, tool_input) {
+            // Trust is only recorded once the user answers the prompt
+            ToolPermissionResult::NeedsPermission if self.has_seen(tool_name) => {
+                ToolPermissionResult::Allowed
+            }
+            other => other,
+        }
+    }
+
+    fn record_approval(&self, tool_name: &str, _tool_input: &Value) {
+        self.seen_tools.insert(tool_key(tool_name).to_string());
+    }
+
+    fn tool_category(&self, tool_name: &str, tool_input: &Value) -> ToolCategory {
+        self.inner.tool_category(tool_name, tool_input)
+    }
+}
+
+fn tool_key(tool_name: &str) -> &str {
+    tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permissions::strategies::{DefaultModeStrategy, PlanModeStrategy};
+    use serde_json::json;
+
+    fn strategy() -> TrustOnFirstUseStrategy {
+        TrustOnFirstUseStrategy::new(Arc::new(DefaultModeStrategy::new()))
+    }
+
+    #[test]
+    fn test_prompts_once_per_tool() {
+        let strategy = strategy();
+        let write = json!({"file_path": "/tmp/a.txt", "content": "x"});
+
+        assert_eq!(
+            strategy.check_permission("Write", &write),
+            ToolPermissionResult::NeedsPermission
+        );
+        strategy.record_approval("Write", &write);
+        assert_eq!(
+            strategy.check_permission("mcp__acp__Write", &write),
+            ToolPermissionResult::Allowed
+        );
+        assert!(strategy.should_auto_approve("Write", &write));
+
+        // Bash is a different tool and prompts on its own first use
+        let bash = json!({"command": "npm install"});
+        assert_eq!(
+            strategy.check_permission("Bash", &bash),
+            ToolPermissionResult::NeedsPermission
+        );
+        strategy.record_approval("mcp__acp__Bash", &bash);
+        assert_eq!(
+            strategy.check_permission("Bash", &bash),
+            ToolPermissionResult::Allowed
+        );
+    }
+
+    #[test]
+    fn test_rejected_first_use_asks_again() {
+        let strategy = strategy();
+        let bash = json!({"command": "npm install"});
+
+        // The user rejects the first prompt, so the retry still asks
+        for _ in 0..2 {
+            assert_eq!(
+                strategy.check_permission("Bash", &bash),
+                ToolPermissionResult::NeedsPermission
+            );
+        }
+        assert!(!strategy.has_seen("Bash"));
+
+        strategy.record_approval("Bash", &bash);
+        assert_eq!(
+            strategy.check_permission("Bash", &bash),
+            ToolPermissionResult::Allowed
+        );
+    }
+
+    #[test]
+    fn test_inner_decisions_pass_through() {
+        let strategy = strategy();
+        // Auto-approved by the inner strategy without being recorded
+        assert_eq!(
+            strategy.check_permission("Read", &json!({})),
+            ToolPermissionResult::Allowed
+        );
+        assert!(!strategy.has_seen("Read"));
+
+        // Blocked tools stay blocked
+        let plan = TrustOnFirstUseStrategy::new(Arc::new(PlanModeStrategy::new()));
+        let edit = json!({"file_path": "/tmp/a.txt"});
+        for _ in 0..2 {
+            assert!(matches!(
+                plan.check_permission("Edit", &edit),
+                ToolPermissionResult::Blocked { .. }
+            ));
+        }
+        assert_eq!(plan.mode(), PermissionMode::Plan);
+    }
+}
@@ -0,0 +1,213 @@
+//! Plan file discovery
+//!
+//! Plan mode writes its plan to a markdown file in a plans directory
+//! (`~/.claude/plans/` or the configured override, plus the project-local
+//! `.claude/plans/`). ExitPlanMode shows that plan in its dialog, and
+//! clients can fetch it on demand with [`read_current_plan`].
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use tracing::{info, warn};
+
+/// Maximum plan file size read (plans are typically a few KB)
+const MAX_PLAN_FILE_SIZE: u64 = 20 * 1024 * 1024; // 20MB
+
+/// Read the current plan for a session from the plans directories
+///
+/// `plans_dirs` is typically [`PermissionHandler::plans_dirs`]. A plan
+/// named after the session (`<session_id>.md`) is preferred; otherwise the
+/// most recently modified `.md` file across all directories is used.
+/// Missing or unreadable directories are skipped.
+///
+/// Returns `Ok(None)` if there is no plan file or it is larger than 20MB.
+///
+/// [`PermissionHandler::plans_dirs`]: crate::session::PermissionHandler::plans_dirs
+pub fn read_current_plan(session_id: &str, plans_dirs: &[PathBuf]) -> io::Result<Option<String>> {
+    if plans_dirs.is_empty() {
+        warn!("Could not determine home directory; set plansDirectory to read plan files");
+        return Ok(None);
+    }
+
+    let Some(file_path) = find_plan_file(session_id, plans_dirs) else {
+        return Ok(None);
+    };
+
+    // Check file size before reading
+    let file_size = fs::metadata(&file_path)?.len();
+    if file_size > MAX_PLAN_FILE_SIZE {
+        warn!(
+            "Plan file too large ({} bytes > {} limit), skipping: {:?}",
+            file_size, MAX_PLAN_FILE_SIZE, file_path
+        );
+        return Ok(None);
+    }
+
+    match fs::read_to_string(&file_path) {
+        Ok(content) => {
+            info!(
+                session_id = %session_id,
+                "Read plan file: {:?} (size: {} bytes)",
+                file_path,
+                file_size
+            );
+            Ok(Some(content))
+        }
+        Err(e) => {
+            warn!("Failed to read plan file {:?}: {}", file_path, e);
+            Err(e)
+        }
+    }
+}
+
+/// Find the session's plan file, falling back to the most recent one
+///
+/// A directory that can't be read doesn't hide the plans in the others.
+fn find_plan_file(session_id: &str, plans_dirs: &[PathBuf]) -> Option<PathBuf> {
+    let mut most_recent: Option<(SystemTime, PathBuf)> = None;
+
+    for plans_dir in plans_dirs {
+        if !plans_dir.exists() {
+            continue;
+        }
+
+        let entries = match fs::read_dir(plans_dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Skipping unreadable plans directory {:?}: {}", plans_dir, e);
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !is_markdown(&path) {
+                continue;
+            }
+            if !session_id.is_empty()
+                && path.file_stem().and_then(|s| s.to_str()) == Some(session_id)
+            {
+                return Some(path);
+            }
+            if let Ok(mtime) = entry.metadata().and_then(|m| m.modified())
+                && most_recent
+                    .as_ref()
+                    .is_none_or(|(newest, _)| mtime > *newest)
+            {
+                most_recent = Some((mtime, path));
+            }
+        }
+    }
+
+    most_recent.map(|(_, path)| path)
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension().and_then(|s| s.to_str()) == Some("md")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_read_current_plan_custom_dir() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dirs = [temp_dir.path().to_path_buf()];
+
+        // Empty directory: no plan file
+        assert!(read_current_plan("s1", &dirs).unwrap().is_none());
+
+        // Non-markdown files are ignored
+        fs::write(temp_dir.path().join("notes.txt"), "not a plan").unwrap();
+        assert!(read_current_plan("s1", &dirs).unwrap().is_none());
+
+        fs::write(temp_dir.path().join("plan.md"), "# My Plan").unwrap();
+        assert_eq!(
+            read_current_plan("s1", &dirs).unwrap(),
+            Some("# My Plan".to_string())
+        );
+    }
+
+    #[test]
+    fn test_read_current_plan_missing_dirs() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let missing = temp_dir.path().join("does-not-exist");
+        assert!(read_current_plan("s1", &[missing]).unwrap().is_none());
+        assert!(read_current_plan("s1", &[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_unreadable_plans_dir_is_skipped() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        // Exists, but read_dir fails on it
+        let not_a_dir = temp_dir.path().join("plans");
+        fs::write(&not_a_dir, "").unwrap();
+        let local = temp_dir.path().join("local");
+        fs::create_dir_all(&local).unwrap();
+        fs::write(local.join("plan.md"), "# Local Plan").unwrap();
+
+        assert_eq!(
+            read_current_plan("s1", &[not_a_dir, local]).unwrap(),
+            Some("# Local Plan".to_string())
+        );
+    }
+
+    #[test]
+    fn test_read_current_plan_project_local_dir() {
+        let home_dir = tempfile::TempDir::new().unwrap();
+        let project = tempfile::TempDir::new().unwrap();
+        let local = crate::utils::project_plans_dir(project.path());
+        fs::create_dir_all(&local).unwrap();
+        fs::write(local.join("feature.md"), "# Local Plan").unwrap();
+
+        // The project-local plan is found even when the home directory has none
+        let dirs = [home_dir.path().to_path_buf(), local];
+        assert_eq!(
+            read_current_plan("s1", &dirs).unwrap(),
+            Some("# Local Plan".to_string())
+        );
+    }
+
+    #[test]
+    fn test_session_plan_preferred_over_most_recent() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dirs = [temp_dir.path().to_path_buf()];
+        fs::write(temp_dir.path().join("session-a.md"), "# Plan A").unwrap();
+        fs::write(temp_dir.path().join("other.md"), "# Newer").unwrap();
+        // Set the mtimes explicitly; filesystem timestamps may be coarse
+        let now = SystemTime::now();
+        let set_modified = |name: &str, time: SystemTime| {
+            fs::File::options()
+                .write(true)
+                .open(temp_dir.path().join(name))
+                .unwrap()
+                .set_modified(time)
+                .unwrap();
+        };
+        set_modified("session-a.md", now - Duration::from_secs(60));
+        set_modified("other.md", now);
+
+        assert_eq!(
+            read_current_plan("session-a", &dirs).unwrap(),
+            Some("# Plan A".to_string())
+        );
+        // Without a plan of its own, a session gets the most recent one
+        assert_eq!(
+            read_current_plan("session-b", &dirs).unwrap(),
+            Some("# Newer".to_string())
+        );
+    }
+
+    #[test]
+    fn test_oversized_plan_is_skipped() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file = fs::File::create(temp_dir.path().join("huge.md")).unwrap();
+        file.set_len(MAX_PLAN_FILE_SIZE + 1).unwrap();
+
+        let dirs = [temp_dir.path().to_path_buf()];
+        assert!(read_current_plan("s1", &dirs).unwrap().is_none());
+    }
+}
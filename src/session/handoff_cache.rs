@@ -0,0 +1,150 @@
+//! Bounded hand-off caches between the PreToolUse hook and the callback
+//!
+//! The hook leaves an entry for a tool call it delegates to `can_use_tool`,
+//! which takes it when it runs. Entries the callback never takes, e.g. for
+//! calls cancelled before it ran, would pile up in a long session, so the
+//! caches can be bounded. The oldest entries are evicted first: the newest
+//! are the ones whose callback may still be on its way, and losing one of
+//! those (a tool_use_id in particular) would deny the call outright.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use dashmap::DashMap;
+
+/// A cache of one-shot entries keyed by [`super::stable_cache_key`]
+#[derive(Debug)]
+pub struct HandoffCache<V> {
+    /// Entries with the sequence number of their last insert
+    entries: DashMap<String, (u64, V)>,
+    /// Sequence number of the next insert
+    next_seq: AtomicU64,
+    /// Maximum number of entries (0 = unbounded), shared with the session
+    max_entries: Arc<AtomicUsize>,
+}
+
+impl<V> Default for HandoffCache<V> {
+    /// An unbounded cache
+    fn default() -> Self {
+        Self::new(Arc::new(AtomicUsize::new(0)))
+    }
+}
+
+impl<V> HandoffCache<V> {
+    /// Create a cache bounded by `max_entries` (0 = unbounded)
+    ///
+    /// The limit is read on every insert, so several caches can share one
+    /// and follow its changes.
+    pub fn new(max_entries: Arc<AtomicUsize>) -> Self {
+        Self {
+            entries: DashMap::new(),
+            next_seq: AtomicU64::new(0),
+            max_entries,
+        }
+    }
+
+    /// Insert an entry, evicting the oldest ones first if the cache is full
+    ///
+    /// Re-inserting a key replaces its value and makes it the newest entry.
+    pub fn insert(&self, key: String, value: V) {
+        let max = self.max_entries.load(Ordering::Acquire);
+        if max > 0 && !self.entries.contains_key(&key) {
+            self.evict_to(max - 1);
+        }
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        self.entries.insert(key, (seq, value));
+    }
+
+    /// Remove and return the entry for `key`
+    pub fn take(&self, key: &str) -> Option<V> {
+        self.entries.remove(key).map(|(_, (_, value))| value)
+    }
+
+    /// Number of entries in the cache
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove every entry
+    pub fn clear(&self) {
+        self.entries.clear();
+    }
+
+    /// Evict the oldest entries past the current limit
+    pub fn evict_to_limit(&self) {
+        let max = self.max_entries.load(Ordering::Acquire);
+        if max > 0 {
+            self.evict_to(max);
+        }
+    }
+
+    /// Evict the oldest entries until at most `max` are left
+    fn evict_to(&self, max: usize) {
+        while self.entries.len() > max {
+            // Collect the key first: removing while iterating would deadlock the shard
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|entry| entry.value().0)
+                .map(|entry| entry.key().clone())
+            else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_oldest_first() {
+        let cache = HandoffCache::new(Arc::new(AtomicUsize::new(2)));
+        cache.insert("a".to_string(), 1);
+        cache.insert("b".to_string(), 2);
+
+        // Re-inserting a key makes it the newest
+        cache.insert("a".to_string(), 3);
+        cache.insert("c".to_string(), 4);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.take("b"), None);
+        assert_eq!(cache.take("a"), Some(3));
+        assert_eq!(cache.take("c"), Some(4));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_pending_entry_survives_eviction() {
+        let cache = HandoffCache::new(Arc::new(AtomicUsize::new(3)));
+        for i in 0..10 {
+            cache.insert(format!("stale-{i}"), i);
+        }
+        cache.insert("pending".to_string(), 100);
+        cache.insert("next".to_string(), 101);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.take("pending"), Some(100));
+    }
+
+    #[test]
+    fn test_shared_limit_changes() {
+        let limit = Arc::new(AtomicUsize::new(0));
+        let cache = HandoffCache::new(Arc::clone(&limit));
+        for i in 0..5 {
+            cache.insert(format!("key-{i}"), i);
+        }
+        assert_eq!(cache.len(), 5);
+
+        limit.store(2, Ordering::Release);
+        cache.evict_to_limit();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.take("key-4"), Some(4));
+        assert_eq!(cache.take("key-3"), Some(3));
+    }
+}
@@ -147,6 +147,8 @@ mod tests {
             model: None,
             small_fast_model: None,
             max_thinking_tokens: None,
+            permission_mode_state_file: None,
+            permission_state_dir: None,
         }
     }
 
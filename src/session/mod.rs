@@ -9,26 +9,44 @@
 //! - Background process management
 
 mod background_processes;
+mod handoff_cache;
 mod manager;
+mod mode_state;
 mod permission;
 mod permission_manager;
 mod permission_request;
+mod permission_state;
 mod prompt_manager;
 #[allow(clippy::module_inception)]
 mod session;
+mod tool_call_control;
 mod usage;
 mod wrapped_child;
 
 pub use background_processes::{
     BackgroundProcessManager, BackgroundTerminal, ChildHandle, TerminalExitStatus,
 };
+pub use handoff_cache::HandoffCache;
 pub use manager::SessionManager;
-pub use permission::{PermissionHandler, PermissionMode, ToolPermissionResult};
+pub use mode_state::{
+    default_mode_state_path, load_last_permission_mode, save_last_permission_mode,
+};
+pub use permission::{
+    DEFAULT_DONT_ASK_DENY_MESSAGE, DEFAULT_EXIT_PLAN_MODE_TITLE, ExitPlanModeOption,
+    PermissionHandler, PermissionMode, ToolPermissionResult, resolve_initial_permission_mode,
+};
 pub use permission_manager::{
     PendingPermissionRequest, PermissionManager, PermissionManagerDecision,
 };
-pub use permission_request::{PermissionOutcome, PermissionRequestBuilder};
+pub(crate) use permission_request::await_response;
+pub use permission_request::{OPTION_SCOPE_META_KEY, PermissionOutcome, PermissionRequestBuilder};
+pub use permission_state::{
+    PERMISSION_STATE_MAX_AGE, PERMISSION_STATE_VERSION, PermissionState, PermissionStateStore,
+    UseLimitedRuleState, default_permission_state_dir, load_permission_state,
+    remove_stale_permission_states, save_permission_state,
+};
 pub use prompt_manager::{PromptId, PromptManager, PromptTask};
-pub use session::{Session, stable_cache_key};
+pub use session::{ModeChangeCallback, PromptSlot, Session, stable_cache_key};
+pub use tool_call_control::{MAX_PAUSE_WAIT, ToolCallControl};
 pub use usage::UsageTracker;
 pub use wrapped_child::WrappedChild;
@@ -0,0 +1,149 @@
+//! Last-used permission mode
+//!
+//! The mode a user switches to is saved to a small state file so the next
+//! session can start in it. The file is a convenience, not configuration: a
+//! missing or unreadable file just means there is no remembered mode.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::permission::PermissionMode;
+
+/// State file name inside `~/.claude/`
+const MODE_STATE_FILE: &str = "acp-permission-mode.json";
+
+/// Contents of the state file
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModeState {
+    permission_mode: String,
+}
+
+/// Default location of the state file (`~/.claude/acp-permission-mode.json`)
+pub fn default_mode_state_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".claude").join(MODE_STATE_FILE))
+}
+
+/// Read the last-used permission mode name from the state file
+///
+/// Returns `None` if the file is missing or corrupt; a corrupt file is
+/// logged and otherwise ignored. The name is not validated here, so an
+/// unknown mode is reported where it is resolved.
+pub fn load_last_permission_mode(path: &Path) -> Option<String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            tracing::warn!("Failed to read permission mode state {:?}: {}", path, e);
+            return None;
+        }
+    };
+    match serde_json::from_str::<ModeState>(&content) {
+        Ok(state) => Some(state.permission_mode),
+        Err(e) => {
+            tracing::warn!("Ignoring corrupt permission mode state {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Save the permission mode as the last used one
+///
+/// BypassPermissions is never remembered, so a new session only skips
+/// permission checks when asked to; switching to it leaves the previous
+/// state in place. The file is written to a unique temporary name and
+/// renamed, so concurrent sessions never see a half-written file.
+pub fn save_last_permission_mode(path: &Path, mode: PermissionMode) -> io::Result<()> {
+    if mode == PermissionMode::BypassPermissions {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let state = ModeState {
+        permission_mode: mode.as_str().to_string(),
+    };
+    let content = serde_json::to_string_pretty(&state).map_err(io::Error::other)?;
+    write_atomically(path, &content)
+}
+
+/// Write `content` to `path` through a uniquely named temporary file
+///
+/// The temporary name is unique per write, so two processes saving the same
+/// file never write to (or rename away) each other's temporary file.
+pub(super) fn write_atomically(path: &Path, content: &str) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let mut tmp_name = file_name.to_os_string();
+    tmp_name.push(format!(".{}.tmp", uuid::Uuid::new_v4()));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let result = fs::write(&tmp_path, content).and_then(|()| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_state_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(".claude").join(MODE_STATE_FILE);
+
+        assert_eq!(load_last_permission_mode(&path), None);
+        save_last_permission_mode(&path, PermissionMode::AcceptEdits).unwrap();
+        assert_eq!(
+            load_last_permission_mode(&path).as_deref(),
+            Some(PermissionMode::AcceptEdits.as_str())
+        );
+
+        // Bypass is not remembered
+        save_last_permission_mode(&path, PermissionMode::BypassPermissions).unwrap();
+        assert_eq!(
+            load_last_permission_mode(&path).as_deref(),
+            Some(PermissionMode::AcceptEdits.as_str())
+        );
+    }
+
+    #[test]
+    fn test_corrupt_mode_state_is_ignored() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(MODE_STATE_FILE);
+
+        fs::write(&path, "{not json").unwrap();
+        assert_eq!(load_last_permission_mode(&path), None);
+        fs::write(&path, r#"{"mode": "plan"}"#).unwrap();
+        assert_eq!(load_last_permission_mode(&path), None);
+
+        // A corrupt file is replaced on the next save
+        save_last_permission_mode(&path, PermissionMode::Plan).unwrap();
+        assert_eq!(
+            load_last_permission_mode(&path).as_deref(),
+            Some(PermissionMode::Plan.as_str())
+        );
+    }
+
+    #[test]
+    fn test_saves_leave_no_temporary_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(MODE_STATE_FILE);
+
+        save_last_permission_mode(&path, PermissionMode::Plan).unwrap();
+        save_last_permission_mode(&path, PermissionMode::AcceptEdits).unwrap();
+
+        let names: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, vec![std::ffi::OsString::from(MODE_STATE_FILE)]);
+    }
+}
@@ -4,33 +4,57 @@
 //! where each permission mode has its own strategy implementation.
 
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::sync::RwLock;
+use tokio::time::Instant;
 
+use crate::command_safety::{
+    SafeCommandClassifier, bash_command_uses_network, find_configured_dangerous_command,
+    find_remote_execution_command,
+};
+use crate::mcp::ExternalMcpManager;
 use crate::permissions::strategies::{
     AcceptEditsModeStrategy, BypassPermissionsModeStrategy, DefaultModeStrategy,
-    DontAskModeStrategy, PermissionModeStrategy, PlanModeStrategy,
+    DontAskModeStrategy, InteractiveModeStrategy, PermissionModeStrategy, PlanModeStrategy,
+    StrictAcceptEditsModeStrategy, ToolCategory,
+};
+use crate::settings::{
+    EmptyInputPolicy, PermissionChecker, PermissionDecision, persist_local_deny_rule,
 };
-use crate::settings::{PermissionChecker, PermissionDecision};
+use crate::tools::{BashInput, ToolInputExt};
+use crate::types::Result;
+use crate::utils::plans_dirs;
 use claude_code_agent_sdk::PermissionMode as SdkPermissionMode;
 
 /// Permission mode for tool execution
 ///
-/// Controls how tool calls are approved during a session.
+/// Controls how tool calls are approved during a session. The default,
+/// [`PermissionMode::Default`], is the fallback wherever no mode was chosen.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum PermissionMode {
     /// Default mode - prompt for dangerous operations
+    #[default]
     Default,
     /// Auto-approve file edits
+    ///
+    /// Without `strictAcceptEdits` every tool is auto-approved, except calls a
+    /// settings deny rule matches: unlike BypassPermissions, deny rules
+    /// (including `protectSecrets`) still apply.
     AcceptEdits,
     /// Planning mode - read-only operations
     Plan,
     /// Don't ask mode - deny if not pre-approved
     DontAsk,
-    /// Bypass all permission checks (default mode for development)
-    #[default]
+    /// Interactive mode - prompt for every tool, including reads
+    Interactive,
+    /// Bypass all permission checks
     BypassPermissions,
 }
 
@@ -42,6 +66,7 @@ impl PermissionMode {
             "acceptEdits" => Some(Self::AcceptEdits),
             "plan" => Some(Self::Plan),
             "dontAsk" => Some(Self::DontAsk),
+            "interactive" => Some(Self::Interactive),
             "bypassPermissions" => Some(Self::BypassPermissions),
             _ => None,
         }
@@ -54,13 +79,14 @@ impl PermissionMode {
             Self::AcceptEdits => "acceptEdits",
             Self::Plan => "plan",
             Self::DontAsk => "dontAsk",
+            Self::Interactive => "interactive",
             Self::BypassPermissions => "bypassPermissions",
         }
     }
 
     /// Convert to SDK PermissionMode
     ///
-    /// Note: SDK doesn't support DontAsk mode yet, so we map it to Default
+    /// Note: SDK doesn't support DontAsk or Interactive modes, so we map them to Default
     pub fn to_sdk_mode(&self) -> SdkPermissionMode {
         match self {
             PermissionMode::Default => SdkPermissionMode::Default,
@@ -70,6 +96,10 @@ impl PermissionMode {
                 // SDK doesn't support DontAsk yet, treat as Default
                 SdkPermissionMode::Default
             }
+            PermissionMode::Interactive => {
+                // Prompting is handled on our side; the SDK sees Default
+                SdkPermissionMode::Default
+            }
             PermissionMode::BypassPermissions => SdkPermissionMode::BypassPermissions,
         }
     }
@@ -78,7 +108,7 @@ impl PermissionMode {
     pub fn allows_writes(&self) -> bool {
         matches!(
             self,
-            Self::Default | Self::AcceptEdits | Self::BypassPermissions
+            Self::Default | Self::AcceptEdits | Self::BypassPermissions | Self::Interactive
         )
     }
 
@@ -86,8 +116,80 @@ impl PermissionMode {
     pub fn auto_approve_edits(&self) -> bool {
         matches!(self, Self::AcceptEdits | Self::BypassPermissions)
     }
+
+    /// Check if this mode blocks a tool outright, for previews like mode pickers
+    ///
+    /// Like [`PermissionHandler::is_tool_blocked`], this doesn't look at the
+    /// tool input and so errs on the side of blocking: Plan mode reports
+    /// `Write` as blocked although it allows writing plan files. Tools that a
+    /// mode merely prompts for are not blocked.
+    pub fn blocks_tool(&self, tool_name: &str) -> bool {
+        let tool_name = tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name);
+        PermissionHandler::new()
+            .create_strategy(*self)
+            .is_tool_blocked(tool_name, &serde_json::Value::Null)
+            .is_some()
+    }
+}
+
+/// An option offered in the ExitPlanMode ("Ready to code?") dialog
+///
+/// Selecting an option with a target mode exits Plan mode into that mode;
+/// an option without one keeps planning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExitPlanModeOption {
+    /// Option id sent to the client and returned on selection
+    pub id: String,
+    /// Label shown to the user
+    pub label: String,
+    /// Mode to switch to when selected (None = keep planning)
+    pub target_mode: Option<PermissionMode>,
+}
+
+impl ExitPlanModeOption {
+    /// Create an option that exits Plan mode into `target_mode`
+    pub fn approve(
+        id: impl Into<String>,
+        label: impl Into<String>,
+        target_mode: PermissionMode,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            label: label.into(),
+            target_mode: Some(target_mode),
+        }
+    }
+
+    /// Create an option that keeps the session in Plan mode
+    pub fn keep_planning(id: impl Into<String>, label: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            label: label.into(),
+            target_mode: None,
+        }
+    }
+
+    /// The default option set (matches the TypeScript implementation)
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::approve(
+                "acceptEdits",
+                "Yes, and auto-accept edits",
+                PermissionMode::AcceptEdits,
+            ),
+            Self::approve(
+                "default",
+                "Yes, and manually approve edits",
+                PermissionMode::Default,
+            ),
+            Self::keep_planning("plan", "No, keep planning"),
+        ]
+    }
 }
 
+/// Title of the ExitPlanMode dialog unless one is configured
+pub const DEFAULT_EXIT_PLAN_MODE_TITLE: &str = "Ready to code?";
+
 /// Permission check result from the handler
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ToolPermissionResult {
@@ -99,6 +201,12 @@ pub enum ToolPermissionResult {
     NeedsPermission,
 }
 
+/// Reason given to the agent when DontAsk mode denies a tool that no rule allows
+///
+/// Worded as an instruction so the agent asks the user instead of retrying.
+pub const DEFAULT_DONT_ASK_DENY_MESSAGE: &str = "This operation is not in the allow list and \
+     DontAsk mode never prompts. Don't retry it; ask the user to add a permission rule for it.";
+
 /// Permission handler for tool execution
 ///
 /// Uses a strategy pattern where each permission mode has its own strategy.
@@ -106,8 +214,45 @@ pub struct PermissionHandler {
     mode: PermissionMode,
     /// Strategy for current permission mode
     strategy: Arc<dyn PermissionModeStrategy>,
+    /// Integrator-registered strategies, keyed by mode name
+    custom_strategies: HashMap<String, Arc<dyn PermissionModeStrategy>>,
+    /// Name of the active registered mode, when one was selected by name
+    custom_mode: Option<String>,
     /// Shared permission checker from settings (shared with hook)
     checker: Option<Arc<RwLock<PermissionChecker>>>,
+    /// Custom plans directory for Plan mode (None = ~/.claude/plans/)
+    plans_dir: Option<PathBuf>,
+    /// Project-local plans directory, allowed alongside `plans_dir`
+    project_plans_dir: Option<PathBuf>,
+    /// Command basenames removed from Default mode's safe command set
+    disabled_safe_commands: Vec<String>,
+    /// Longest Bash command auto-approved as safe (None = no limit)
+    max_auto_approve_command_len: Option<usize>,
+    /// Integrator-provided replacement for the built-in safe command check
+    safe_command_classifier: Option<Arc<dyn SafeCommandClassifier>>,
+    /// Command basenames that always require confirmation, in every mode
+    extra_dangerous_commands: Vec<String>,
+    /// Whether Bash commands that access the network require confirmation in every mode
+    prompt_for_network_commands: bool,
+    /// Whether AcceptEdits mode uses the strict strategy (edits only, not all Bash)
+    strict_accept_edits: bool,
+    /// Reason given to the agent when DontAsk mode denies a tool (None = default)
+    dont_ask_deny_message: Option<String>,
+    /// Title of the ExitPlanMode dialog (None = default)
+    exit_plan_mode_title: Option<String>,
+    /// Options offered when leaving Plan mode via ExitPlanMode
+    exit_plan_mode_options: Vec<ExitPlanModeOption>,
+    /// Whether exiting Plan mode without any plan content needs explicit confirmation
+    confirm_empty_plan: bool,
+    /// Idle time after which runtime allow rules expire (None = never)
+    allow_rule_idle_timeout: Option<Duration>,
+    /// When the user last sent a prompt or the agent last called a tool
+    /// (tokio's clock, so tests can pause it)
+    last_activity: Mutex<Instant>,
+    /// The current turn, counted from 1 and advanced by [`Self::end_turn`]
+    turn: AtomicU64,
+    /// The turn in which the user allowed all reads (0 = none)
+    reads_allowed_in_turn: AtomicU64,
 }
 
 impl fmt::Debug for PermissionHandler {
@@ -115,7 +260,36 @@ impl fmt::Debug for PermissionHandler {
         f.debug_struct("PermissionHandler")
             .field("mode", &self.mode)
             .field("strategy", &"<strategy>")
+            .field(
+                "custom_strategies",
+                &self.custom_strategies.keys().collect::<Vec<_>>(),
+            )
+            .field("custom_mode", &self.custom_mode)
             .field("checker", &self.checker)
+            .field("plans_dir", &self.plans_dir)
+            .field("project_plans_dir", &self.project_plans_dir)
+            .field("disabled_safe_commands", &self.disabled_safe_commands)
+            .field(
+                "max_auto_approve_command_len",
+                &self.max_auto_approve_command_len,
+            )
+            .field("safe_command_classifier", &self.safe_command_classifier)
+            .field("extra_dangerous_commands", &self.extra_dangerous_commands)
+            .field(
+                "prompt_for_network_commands",
+                &self.prompt_for_network_commands,
+            )
+            .field("strict_accept_edits", &self.strict_accept_edits)
+            .field("dont_ask_deny_message", &self.dont_ask_deny_message)
+            .field("exit_plan_mode_title", &self.exit_plan_mode_title)
+            .field("exit_plan_mode_options", &self.exit_plan_mode_options)
+            .field("confirm_empty_plan", &self.confirm_empty_plan)
+            .field("allow_rule_idle_timeout", &self.allow_rule_idle_timeout)
+            .field("turn", &self.turn.load(Ordering::Relaxed))
+            .field(
+                "reads_allowed_in_turn",
+                &self.reads_allowed_in_turn.load(Ordering::Relaxed),
+            )
             .finish()
     }
 }
@@ -124,8 +298,26 @@ impl Default for PermissionHandler {
     fn default() -> Self {
         Self {
             mode: PermissionMode::Default,
-            strategy: Arc::new(DefaultModeStrategy),
+            strategy: Arc::new(DefaultModeStrategy::new()),
+            custom_strategies: HashMap::new(),
+            custom_mode: None,
             checker: None,
+            plans_dir: None,
+            project_plans_dir: None,
+            disabled_safe_commands: Vec::new(),
+            max_auto_approve_command_len: None,
+            safe_command_classifier: None,
+            extra_dangerous_commands: Vec::new(),
+            prompt_for_network_commands: false,
+            strict_accept_edits: false,
+            dont_ask_deny_message: None,
+            exit_plan_mode_title: None,
+            exit_plan_mode_options: ExitPlanModeOption::defaults(),
+            confirm_empty_plan: false,
+            allow_rule_idle_timeout: None,
+            last_activity: Mutex::new(Instant::now()),
+            turn: AtomicU64::new(1),
+            reads_allowed_in_turn: AtomicU64::new(0),
         }
     }
 }
@@ -140,11 +332,12 @@ impl PermissionHandler {
 
     /// Create with a specific mode
     pub fn with_mode(mode: PermissionMode) -> Self {
-        Self {
+        let mut handler = Self {
             mode,
-            strategy: Self::create_strategy(mode),
-            checker: None,
-        }
+            ..Self::default()
+        };
+        handler.rebuild_strategy();
+        handler
     }
 
     /// Create with settings-based checker
@@ -152,9 +345,8 @@ impl PermissionHandler {
     /// Uses Default mode (standard behavior with permission prompts).
     pub fn with_checker(checker: Arc<RwLock<PermissionChecker>>) -> Self {
         Self {
-            mode: PermissionMode::Default,
-            strategy: Arc::new(DefaultModeStrategy),
             checker: Some(checker),
+            ..Self::default()
         }
     }
 
@@ -162,20 +354,40 @@ impl PermissionHandler {
     ///
     /// Uses Default mode (standard behavior with permission prompts).
     pub fn with_checker_owned(checker: PermissionChecker) -> Self {
-        Self {
-            mode: PermissionMode::Default,
-            strategy: Arc::new(DefaultModeStrategy),
-            checker: Some(Arc::new(RwLock::new(checker))),
-        }
+        Self::with_checker(Arc::new(RwLock::new(checker)))
     }
 
-    /// Create strategy for a given mode
-    fn create_strategy(mode: PermissionMode) -> Arc<dyn PermissionModeStrategy> {
+    /// Create the built-in strategy for a mode from the handler's configuration
+    fn create_strategy(&self, mode: PermissionMode) -> Arc<dyn PermissionModeStrategy> {
+        let disabled_safe_commands = &self.disabled_safe_commands;
+        let max_auto_approve_command_len = self.max_auto_approve_command_len;
+        let safe_command_classifier = self.safe_command_classifier.as_ref();
         match mode {
-            PermissionMode::Default => Arc::new(DefaultModeStrategy),
+            PermissionMode::Default => Arc::new(
+                DefaultModeStrategy::with_disabled_safe_commands(disabled_safe_commands.to_vec())
+                    .with_max_auto_approve_command_len(max_auto_approve_command_len)
+                    .with_safe_command_classifier(safe_command_classifier.cloned()),
+            ),
+            PermissionMode::AcceptEdits if self.strict_accept_edits => Arc::new(
+                StrictAcceptEditsModeStrategy::with_disabled_safe_commands(
+                    disabled_safe_commands.to_vec(),
+                )
+                .with_max_auto_approve_command_len(max_auto_approve_command_len)
+                .with_safe_command_classifier(safe_command_classifier.cloned()),
+            ),
             PermissionMode::AcceptEdits => Arc::new(AcceptEditsModeStrategy),
-            PermissionMode::Plan => Arc::new(PlanModeStrategy),
+            PermissionMode::Plan => {
+                let strategy = match self.plans_dir.as_deref() {
+                    Some(dir) => PlanModeStrategy::with_plans_dir(dir),
+                    None => PlanModeStrategy::new(),
+                };
+                match self.project_plans_dir.as_deref() {
+                    Some(dir) => Arc::new(strategy.with_project_plans_dir(dir)),
+                    None => Arc::new(strategy),
+                }
+            }
             PermissionMode::DontAsk => Arc::new(DontAskModeStrategy),
+            PermissionMode::Interactive => Arc::new(InteractiveModeStrategy),
             PermissionMode::BypassPermissions => Arc::new(BypassPermissionsModeStrategy),
         }
     }
@@ -186,9 +398,345 @@ impl PermissionHandler {
     }
 
     /// Set permission mode
+    ///
+    /// A strategy registered under the mode's name (e.g. `"default"`)
+    /// replaces the built-in one.
     pub fn set_mode(&mut self, mode: PermissionMode) {
         self.mode = mode;
-        self.strategy = Self::create_strategy(mode);
+        self.custom_mode = None;
+        self.rebuild_strategy();
+    }
+
+    /// Get the name of the active registered mode, if one was selected by name
+    pub fn custom_mode(&self) -> Option<&str> {
+        self.custom_mode.as_deref()
+    }
+
+    /// Check if a registered strategy decides for the active mode
+    ///
+    /// True for a mode selected by name and for a built-in mode whose
+    /// strategy was overridden with [`register_strategy`](Self::register_strategy).
+    pub fn uses_registered_strategy(&self) -> bool {
+        let name = self.custom_mode.as_deref().unwrap_or(self.mode.as_str());
+        self.custom_strategies.contains_key(name)
+    }
+
+    /// Names of the registered modes that aren't built-in modes, sorted
+    pub fn custom_mode_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .custom_strategies
+            .keys()
+            .filter(|name| PermissionMode::parse(name).is_none())
+            .cloned()
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Register a strategy under a mode name
+    ///
+    /// Registering a built-in name (see [`PermissionMode::as_str`]) overrides
+    /// that mode's strategy; any other name adds a mode that can be selected
+    /// with [`set_custom_mode`](Self::set_custom_mode). If the named mode is
+    /// active, the new strategy takes effect immediately.
+    pub fn register_strategy(
+        &mut self,
+        name: impl Into<String>,
+        strategy: Arc<dyn PermissionModeStrategy>,
+    ) {
+        self.custom_strategies.insert(name.into(), strategy);
+        self.rebuild_strategy();
+    }
+
+    /// Switch to a registered mode by name
+    ///
+    /// The handler's [`mode`](Self::mode) becomes the strategy's own
+    /// [`PermissionModeStrategy::mode`], which decides the SDK mapping and
+    /// mode-specific handling such as DontAsk's blocking of prompts.
+    /// Returns false, leaving the mode unchanged, if nothing is registered
+    /// under `name`.
+    pub fn set_custom_mode(&mut self, name: &str) -> bool {
+        let Some(strategy) = self.custom_strategies.get(name) else {
+            return false;
+        };
+        self.mode = strategy.mode();
+        self.strategy = strategy.clone();
+        self.custom_mode = Some(name.to_string());
+        true
+    }
+
+    /// Get the custom plans directory, if configured
+    pub fn plans_dir(&self) -> Option<&Path> {
+        self.plans_dir.as_deref()
+    }
+
+    /// Override the plans directory used by Plan mode
+    ///
+    /// Useful in sandboxed environments where `~/.claude/plans/` is unavailable.
+    pub fn set_plans_dir(&mut self, plans_dir: Option<PathBuf>) {
+        self.plans_dir = plans_dir;
+        self.rebuild_strategy();
+    }
+
+    /// Get the project-local plans directory, if configured
+    pub fn project_plans_dir(&self) -> Option<&Path> {
+        self.project_plans_dir.as_deref()
+    }
+
+    /// Allow Plan mode writes to (and plan reads from) a project-local directory
+    ///
+    /// Typically `<cwd>/.claude/plans/`. It is used in addition to the
+    /// home or overridden plans directory, not instead of it.
+    pub fn set_project_plans_dir(&mut self, project_plans_dir: Option<PathBuf>) {
+        self.project_plans_dir = project_plans_dir;
+        self.rebuild_strategy();
+    }
+
+    /// Get every plans directory in effect (primary first, then project-local)
+    pub fn plans_dirs(&self) -> Vec<PathBuf> {
+        plans_dirs(self.plans_dir.as_deref(), self.project_plans_dir.as_deref())
+    }
+
+    /// Get the command basenames excluded from Default mode auto-approval
+    pub fn disabled_safe_commands(&self) -> &[String] {
+        &self.disabled_safe_commands
+    }
+
+    /// Remove command basenames from the built-in safe set used by Default mode
+    pub fn set_disabled_safe_commands(&mut self, disabled_safe_commands: Vec<String>) {
+        self.disabled_safe_commands = disabled_safe_commands;
+        self.rebuild_strategy();
+    }
+
+    /// Get the integrator-provided safe command check, if one is installed
+    pub fn safe_command_classifier(&self) -> Option<&Arc<dyn SafeCommandClassifier>> {
+        self.safe_command_classifier.as_ref()
+    }
+
+    /// Replace the built-in safe command check used to auto-approve Bash commands
+    ///
+    /// Applies to Default mode, strict AcceptEdits mode and the PreToolUse
+    /// hook; the disabled safe commands no longer apply while a classifier is
+    /// installed. Strategies registered with [`Self::register_strategy`]
+    /// are unaffected. `None` restores the built-in check.
+    pub fn set_safe_command_classifier(
+        &mut self,
+        classifier: Option<Arc<dyn SafeCommandClassifier>>,
+    ) {
+        self.safe_command_classifier = classifier;
+        self.rebuild_strategy();
+    }
+
+    /// Get the longest Bash command auto-approved as a known safe command
+    pub fn max_auto_approve_command_len(&self) -> Option<usize> {
+        self.max_auto_approve_command_len
+    }
+
+    /// Limit auto-approval of known safe Bash commands to `max_len` characters
+    ///
+    /// Longer commands prompt instead; `None` removes the limit.
+    pub fn set_max_auto_approve_command_len(&mut self, max_len: Option<usize>) {
+        self.max_auto_approve_command_len = max_len;
+        self.rebuild_strategy();
+    }
+
+    /// Get the command basenames that always require confirmation
+    pub fn extra_dangerous_commands(&self) -> &[String] {
+        &self.extra_dangerous_commands
+    }
+
+    /// Set command basenames that always require confirmation
+    ///
+    /// Matching Bash commands prompt even in BypassPermissions mode or when an
+    /// allow rule matches; only deny rules take precedence. DontAsk mode,
+    /// which never prompts, blocks them instead.
+    pub fn set_extra_dangerous_commands(&mut self, extra_dangerous_commands: Vec<String>) {
+        self.extra_dangerous_commands = extra_dangerous_commands;
+    }
+
+    /// Whether Bash commands that access the network require confirmation
+    pub fn prompt_for_network_commands(&self) -> bool {
+        self.prompt_for_network_commands
+    }
+
+    /// Require confirmation for Bash commands that access the network
+    ///
+    /// Like [`Self::set_extra_dangerous_commands`], this applies in every
+    /// mode and over allow rules, and DontAsk mode blocks the commands.
+    /// See [`bash_command_uses_network`] for what counts as network access.
+    pub fn set_prompt_for_network_commands(&mut self, prompt: bool) {
+        self.prompt_for_network_commands = prompt;
+    }
+
+    /// Whether AcceptEdits mode only auto-approves edits and safe Bash commands
+    pub fn strict_accept_edits(&self) -> bool {
+        self.strict_accept_edits
+    }
+
+    /// Choose between the permissive and strict AcceptEdits strategies
+    ///
+    /// AcceptEdits approves every tool by default. When strict, it
+    /// auto-approves reads, file edits and known safe Bash commands, and
+    /// prompts for anything else (e.g. `rm -rf`).
+    pub fn set_strict_accept_edits(&mut self, strict: bool) {
+        self.strict_accept_edits = strict;
+        self.rebuild_strategy();
+    }
+
+    /// Get the reason given to the agent when DontAsk mode denies a tool
+    pub fn dont_ask_deny_message(&self) -> &str {
+        self.dont_ask_deny_message
+            .as_deref()
+            .unwrap_or(DEFAULT_DONT_ASK_DENY_MESSAGE)
+    }
+
+    /// Set the reason given to the agent when DontAsk mode denies a tool
+    ///
+    /// The agent sees it as the tool's error, so it should say what to do
+    /// next. `None` restores [`DEFAULT_DONT_ASK_DENY_MESSAGE`].
+    pub fn set_dont_ask_deny_message(&mut self, message: Option<String>) {
+        self.dont_ask_deny_message = message;
+    }
+
+    /// Get the title of the ExitPlanMode dialog
+    pub fn exit_plan_mode_title(&self) -> &str {
+        self.exit_plan_mode_title
+            .as_deref()
+            .unwrap_or(DEFAULT_EXIT_PLAN_MODE_TITLE)
+    }
+
+    /// Set the title of the ExitPlanMode dialog, e.g. to localize it
+    ///
+    /// `None` restores [`DEFAULT_EXIT_PLAN_MODE_TITLE`]. The option labels
+    /// are set with [`Self::set_exit_plan_mode_options`].
+    pub fn set_exit_plan_mode_title(&mut self, title: Option<String>) {
+        self.exit_plan_mode_title = title;
+    }
+
+    /// Get the options offered in the ExitPlanMode dialog
+    pub fn exit_plan_mode_options(&self) -> &[ExitPlanModeOption] {
+        &self.exit_plan_mode_options
+    }
+
+    /// Replace the options offered in the ExitPlanMode dialog
+    ///
+    /// An empty list restores the defaults so the dialog is never optionless.
+    pub fn set_exit_plan_mode_options(&mut self, options: Vec<ExitPlanModeOption>) {
+        self.exit_plan_mode_options = if options.is_empty() {
+            ExitPlanModeOption::defaults()
+        } else {
+            options
+        };
+    }
+
+    /// Check if exiting Plan mode without a plan needs explicit confirmation
+    pub fn confirm_empty_plan(&self) -> bool {
+        self.confirm_empty_plan
+    }
+
+    /// Require explicit confirmation when ExitPlanMode finds no plan content
+    ///
+    /// The dialog always warns that no plan was found; with confirmation it
+    /// also asks whether to exit anyway and lists the keep-planning options
+    /// first.
+    pub fn set_confirm_empty_plan(&mut self, confirm: bool) {
+        self.confirm_empty_plan = confirm;
+    }
+
+    /// Get the idle time after which "Always Allow" rules granted in the session expire
+    pub fn allow_rule_idle_timeout(&self) -> Option<Duration> {
+        self.allow_rule_idle_timeout
+    }
+
+    /// Expire runtime allow rules after `timeout` without permission checks
+    ///
+    /// Once a session has been idle that long, the next tool call prompts
+    /// again even if the user chose "Always Allow" for it earlier. Allow
+    /// rules from settings files are unaffected. `None` disables expiry.
+    pub fn set_allow_rule_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.allow_rule_idle_timeout = timeout;
+    }
+
+    /// Record session activity, expiring runtime allow rules if the session was idle
+    ///
+    /// Returns true if rules were expired. Called once per tool call by the
+    /// PreToolUse hook before it consults the rules, and when the user sends
+    /// a prompt.
+    pub async fn record_activity(&self) -> bool {
+        let idle = {
+            let mut last_activity = self
+                .last_activity
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let now = Instant::now();
+            let idle = now.duration_since(*last_activity);
+            *last_activity = now;
+            idle
+        };
+
+        let Some(timeout) = self.allow_rule_idle_timeout else {
+            return false;
+        };
+        if idle < timeout {
+            return false;
+        }
+        let Some(ref checker) = self.checker else {
+            return false;
+        };
+        tracing::info!(
+            idle_secs = idle.as_secs(),
+            timeout_secs = timeout.as_secs(),
+            "Session was idle; Always Allow rules need confirming again"
+        );
+        checker.write().await.clear_runtime_allow_rules();
+        true
+    }
+
+    /// The current turn, for grants that only last for it
+    pub fn current_turn(&self) -> u64 {
+        self.turn.load(Ordering::Acquire)
+    }
+
+    /// Allow every read-only tool call for the rest of `turn`
+    ///
+    /// Backs the "Allow All Reads This Turn" choice in permission prompts;
+    /// `turn` is the [`Self::current_turn`] the prompt was shown in. An
+    /// answer arriving after that turn ended grants nothing. Covers the
+    /// tools of the `Read` rule group (Read, Grep, Glob and LS) in every
+    /// mode; deny rules still apply, and nothing is saved.
+    pub fn allow_reads_for_turn(&self, turn: u64) {
+        if turn != self.current_turn() {
+            tracing::info!(turn, "Reads not allowed: the turn has already ended");
+            return;
+        }
+        tracing::info!(turn, "All reads allowed for the rest of the turn");
+        self.reads_allowed_in_turn.store(turn, Ordering::Release);
+    }
+
+    /// Whether [`Self::allow_reads_for_turn`] was granted in the current turn
+    pub fn reads_allowed_for_turn(&self) -> bool {
+        self.reads_allowed_in_turn.load(Ordering::Acquire) == self.current_turn()
+    }
+
+    /// End the current turn, dropping grants that only last for it
+    pub fn end_turn(&self) {
+        self.turn.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Recreate the strategy after a mode or configuration change
+    ///
+    /// Registered strategies are consulted before the built-ins.
+    fn rebuild_strategy(&mut self) {
+        let name = self.custom_mode.as_deref().unwrap_or(self.mode.as_str());
+        self.strategy = self.strategy_for(name, self.mode);
+    }
+
+    /// The strategy registered under `name`, or the configured built-in for `mode`
+    fn strategy_for(&self, name: &str, mode: PermissionMode) -> Arc<dyn PermissionModeStrategy> {
+        match self.custom_strategies.get(name) {
+            Some(strategy) => strategy.clone(),
+            None => self.create_strategy(mode),
+        }
     }
 
     /// Set the permission checker
@@ -216,6 +764,24 @@ impl PermissionHandler {
         self.strategy.should_auto_approve(tool_name, input)
     }
 
+    /// Categorize a tool call for UI hints, whatever the permission decision
+    ///
+    /// Delegates to the current strategy, with known external MCP tools
+    /// (e.g. a web-fetch server's `webReader`) named as the built-in tool
+    /// they stand for.
+    pub fn tool_category(&self, tool_name: &str, input: &serde_json::Value) -> ToolCategory {
+        let friendly_name = ExternalMcpManager::get_friendly_tool_name(tool_name);
+        self.strategy
+            .tool_category(friendly_name.as_deref().unwrap_or(tool_name), input)
+    }
+
+    /// Tell the current strategy the user approved a tool call it asked about
+    ///
+    /// See [`PermissionModeStrategy::record_approval`].
+    pub fn record_approval(&self, tool_name: &str, input: &serde_json::Value) {
+        self.strategy.record_approval(tool_name, input);
+    }
+
     /// Check if a tool is blocked in current mode
     ///
     /// Returns true if the tool is blocked.
@@ -231,13 +797,74 @@ impl PermissionHandler {
 
     /// Check permission for a tool with full context
     ///
-    /// Combines strategy-based checking with settings rules.
-    /// Returns the permission result.
+    /// Combines strategy-based checking with settings rules. A call allowed
+    /// by a use-limited rule spends one of its uses.
+    /// Returns the permission result. Doesn't count as activity for
+    /// [`Self::set_allow_rule_idle_timeout`]; see [`Self::record_activity`].
     pub async fn check_permission(
         &self,
         tool_name: &str,
         tool_input: &serde_json::Value,
     ) -> ToolPermissionResult {
+        self.check_permission_with(
+            self.mode,
+            self.strategy.as_ref(),
+            tool_name,
+            tool_input,
+            true,
+        )
+        .await
+    }
+
+    /// Check what `mode` would decide for a tool call, without switching to it
+    ///
+    /// Uses the same settings rules and configuration as
+    /// [`Self::check_permission`], with the strategy for `mode` built on the
+    /// fly (a strategy registered under the mode's name is used as-is). The
+    /// handler's own mode is left untouched, and no use-limited rule is spent.
+    pub async fn check_permission_as(
+        &self,
+        mode: PermissionMode,
+        tool_name: &str,
+        tool_input: &serde_json::Value,
+    ) -> ToolPermissionResult {
+        let strategy = self.strategy_for(mode.as_str(), mode);
+        self.check_permission_with(mode, strategy.as_ref(), tool_name, tool_input, false)
+            .await
+    }
+
+    /// Check permission for a tool as `mode`, decided by `strategy`
+    ///
+    /// A use-limited rule that allows the call is spent only if `spend_uses`
+    /// is set.
+    async fn check_permission_with(
+        &self,
+        mode: PermissionMode,
+        strategy: &dyn PermissionModeStrategy,
+        tool_name: &str,
+        tool_input: &serde_json::Value,
+        spend_uses: bool,
+    ) -> ToolPermissionResult {
+        // Strategies classify the command a configured wrapper or alias runs
+        let strategy_input = match self.checker {
+            Some(ref checker) => checker
+                .read()
+                .await
+                .resolve_command_aliases(tool_name, tool_input),
+            None => Cow::Borrowed(tool_input),
+        };
+
+        // Configured always-dangerous commands must be confirmed in every mode
+        let forced_prompt = self.configured_dangerous_command(tool_name, &strategy_input);
+        // So must network access, when configured
+        let network_prompt = self.is_gated_network_command(tool_name, &strategy_input);
+        // Package runners (`npx`, `pnpm dlx`, ...) run code no rule can vouch for,
+        // so an allow rule never approves them; the mode still decides
+        let remote_exec = remote_execution_command(tool_name, &strategy_input);
+
+        // Calls with empty input that no rule matches may be denied instead of prompting
+        let mut deny_empty_input = false;
+
         // Check settings rules first (if available)
         if let Some(ref checker) = self.checker {
             let checker_read = checker.read().await;
@@ -251,38 +878,128 @@ impl PermissionHandler {
                             .unwrap_or_else(|| "Denied by settings".to_string()),
                     };
                 }
-                PermissionDecision::Allow => {
+                // Interactive mode prompts even for tools allowed by rules
+                PermissionDecision::Allow
+                    if mode != PermissionMode::Interactive
+                        && forced_prompt.is_none()
+                        && !network_prompt
+                        && remote_exec.is_none()
+                        && (!spend_uses || checker_read.spend_use(&result)) =>
+                {
                     return ToolPermissionResult::Allowed;
                 }
+                PermissionDecision::Allow => {}
                 PermissionDecision::Ask => {
+                    // Unknown external MCP tools are blocked outright when configured
+                    // (an explicit ask rule still prompts; BypassPermissions is unaffected)
+                    if result.rule.is_none()
+                        && mode != PermissionMode::BypassPermissions
+                        && checker_read.deny_unknown_mcp_tools()
+                        && is_external_mcp_tool(tool_name)
+                    {
+                        return ToolPermissionResult::Blocked {
+                            reason: format!(
+                                "MCP tool {} is not allowed by settings rules (denyUnknownMcpTools)",
+                                tool_name
+                            ),
+                        };
+                    }
+                    deny_empty_input = result.rule.is_none()
+                        && checker_read.empty_input_default() == EmptyInputPolicy::Deny;
                     // Fall through to strategy-based check
                 }
             }
         }
 
+        // The user allowed all reads for this turn
+        if self.reads_allowed_for_turn() && is_read_category_tool(tool_name) {
+            return ToolPermissionResult::Allowed;
+        }
+
+        if let Some(command) = forced_prompt {
+            if mode == PermissionMode::DontAsk {
+                return ToolPermissionResult::Blocked {
+                    reason: format!(
+                        "{} requires confirmation (extraDangerousCommands), which DontAsk mode never asks for",
+                        command
+                    ),
+                };
+            }
+            return ToolPermissionResult::NeedsPermission;
+        }
+
+        if network_prompt {
+            if mode == PermissionMode::DontAsk {
+                return ToolPermissionResult::Blocked {
+                    reason: "Network access requires confirmation (promptForNetworkCommands), which DontAsk mode never asks for".to_string(),
+                };
+            }
+            return ToolPermissionResult::NeedsPermission;
+        }
+
         // Use strategy for mode-specific logic
-        let strategy_result = self.strategy.check_permission(tool_name, tool_input);
+        let strategy_result = strategy.check_permission(tool_name, &strategy_input);
 
         // Special handling for DontAsk mode: convert NeedsPermission to Blocked
-        if self.mode == PermissionMode::DontAsk
+        if mode == PermissionMode::DontAsk
             && strategy_result == ToolPermissionResult::NeedsPermission
         {
             return ToolPermissionResult::Blocked {
-                reason: "Tool not pre-approved by settings rules in DontAsk mode".to_string(),
+                reason: self.dont_ask_deny_message().to_string(),
             };
         }
 
         // User interaction tools should always be allowed
-        if matches!(
-            tool_name,
-            "AskUserQuestion" | "Task" | "TodoWrite" | "SlashCommand"
-        ) {
+        // (Interactive mode's strategy decides for itself, so Task still prompts)
+        if mode != PermissionMode::Interactive
+            && matches!(
+                tool_name,
+                "AskUserQuestion" | "Task" | "TodoWrite" | "SlashCommand"
+            )
+        {
             return ToolPermissionResult::Allowed;
         }
 
+        // Pattern rules can't tell empty-input calls apart, so only a name rule approves them
+        if deny_empty_input
+            && strategy_result == ToolPermissionResult::NeedsPermission
+            && is_empty_tool_input(tool_input)
+        {
+            return ToolPermissionResult::Blocked {
+                reason: format!(
+                    "{} was called without input and no rule matches it (emptyInputDefault)",
+                    tool_name
+                ),
+            };
+        }
+
         strategy_result
     }
 
+    /// Find a configured always-dangerous command run by a Bash tool call
+    fn configured_dangerous_command(
+        &self,
+        tool_name: &str,
+        tool_input: &serde_json::Value,
+    ) -> Option<&str> {
+        if self.extra_dangerous_commands.is_empty()
+            || tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name) != "Bash"
+        {
+            return None;
+        }
+        let command = BashInput::from_value(tool_input).command?;
+        find_configured_dangerous_command(&command, &self.extra_dangerous_commands)
+    }
+
+    /// Check if a Bash tool call accesses the network while that requires confirmation
+    fn is_gated_network_command(&self, tool_name: &str, tool_input: &serde_json::Value) -> bool {
+        self.prompt_for_network_commands
+            && tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name) == "Bash"
+            && BashInput::from_value(tool_input)
+                .command
+                .is_some_and(|command| bash_command_uses_network(&command))
+    }
+
     /// Add a runtime allow rule (e.g., from user's "Always Allow" choice)
     pub async fn add_allow_rule(&self, tool_name: &str) {
         if let Some(ref checker) = self.checker {
@@ -291,6 +1008,33 @@ impl PermissionHandler {
         }
     }
 
+    /// Record advertised tool titles so `ToolTitle(...)` rules can match them
+    pub async fn set_tool_titles(&self, titles: impl IntoIterator<Item = (String, String)>) {
+        if let Some(ref checker) = self.checker {
+            let mut checker_write = checker.write().await;
+            for (tool_name, title) in titles {
+                checker_write.set_tool_title(&tool_name, title);
+            }
+        }
+    }
+
+    /// The "Always Allow" rules to offer for a tool call, narrowest first
+    ///
+    /// Empty when there is no checker to add them to.
+    pub async fn suggested_allow_rules(
+        &self,
+        tool_name: &str,
+        tool_input: &serde_json::Value,
+    ) -> Vec<String> {
+        match self.checker {
+            Some(ref checker) => checker
+                .read()
+                .await
+                .suggested_allow_rules(tool_name, tool_input),
+            None => Vec::new(),
+        }
+    }
+
     /// Add a fine-grained allow rule based on tool call details
     /// This is used for "Always Allow" with specific parameters
     pub fn add_allow_rule_for_tool_call(&self, tool_name: &str, tool_input: &serde_json::Value) {
@@ -301,6 +1045,126 @@ impl PermissionHandler {
             }
         }
     }
+
+    /// Add a fine-grained deny rule based on tool call details
+    /// This is used for "Reject for Session"; unlike allow rules it waits for
+    /// the checker lock, so a standing deny is never silently dropped
+    pub async fn add_deny_rule_for_tool_call(
+        &self,
+        tool_name: &str,
+        tool_input: &serde_json::Value,
+    ) {
+        if let Some(ref checker) = self.checker {
+            checker
+                .write()
+                .await
+                .add_deny_rule_for_tool_call(tool_name, tool_input);
+        }
+    }
+
+    /// Add a deny rule for "Always Reject" and save it to the local settings
+    ///
+    /// The rule goes into `.claude/settings.local.json` under the checker's
+    /// project directory, so it outlives `/clear` and later sessions.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the settings file can't be updated; the rule is
+    /// then not added.
+    pub async fn persist_deny_rule_for_tool_call(
+        &self,
+        tool_name: &str,
+        tool_input: &serde_json::Value,
+    ) -> Result<()> {
+        if let Some(ref checker) = self.checker {
+            let mut checker = checker.write().await;
+            let rule = checker.suggested_deny_rule(tool_name, tool_input);
+            persist_local_deny_rule(checker.project_dir(), &rule)?;
+            checker.add_settings_deny_rule(&rule);
+        }
+        Ok(())
+    }
+
+    /// Drop the "Reject for Session" deny rules, keeping persisted ones
+    pub async fn clear_session_deny_rules(&self) {
+        if let Some(ref checker) = self.checker {
+            checker.write().await.clear_session_deny_rules();
+        }
+    }
+}
+
+/// Check if a tool only reads files, as covered by a turn-long read grant
+///
+/// These are the tools a `Read` rule matches; see
+/// [`PermissionHandler::allow_reads_for_turn`].
+pub(super) fn is_read_category_tool(tool_name: &str) -> bool {
+    let stripped = tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name);
+    matches!(stripped, "Read" | "Grep" | "Glob" | "LS")
+}
+
+/// Resolve the permission mode a new session starts in
+///
+/// Precedence, highest first:
+/// 1. `requested` - mode explicitly requested by the client for this session
+/// 2. `settings_default` - `permissions.defaultMode` from settings files
+/// 3. `last_used` - mode the user last switched to, from the mode state file
+/// 4. `fallback` - the crate-level default chosen when constructing the session
+///
+/// A configured default beats the remembered mode, so a project that sets
+/// `defaultMode` starts in it even after the user switched elsewhere.
+/// Unrecognized mode names are logged and skipped, so a typo in settings
+/// falls through to the next source instead of failing session creation.
+pub fn resolve_initial_permission_mode(
+    requested: Option<&str>,
+    settings_default: Option<&str>,
+    last_used: Option<&str>,
+    fallback: PermissionMode,
+) -> PermissionMode {
+    let sources = [
+        ("session request", requested),
+        ("settings", settings_default),
+        ("last used mode", last_used),
+    ];
+    for (source, value) in sources {
+        let Some(value) = value else {
+            continue;
+        };
+        match PermissionMode::parse(value) {
+            Some(mode) => return mode,
+            None => tracing::warn!(
+                source = source,
+                mode = %value,
+                "Ignoring unknown permission mode"
+            ),
+        }
+    }
+    fallback
+}
+
+/// Check if a tool call has no input to match rule patterns against (`null` or `{}`)
+fn is_empty_tool_input(tool_input: &serde_json::Value) -> bool {
+    match tool_input {
+        serde_json::Value::Null => true,
+        serde_json::Value::Object(fields) => fields.is_empty(),
+        _ => false,
+    }
+}
+
+/// Find the remote package runner a Bash tool call uses, if any
+fn remote_execution_command(
+    tool_name: &str,
+    tool_input: &serde_json::Value,
+) -> Option<&'static str> {
+    if tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name) != "Bash" {
+        return None;
+    }
+    let command = BashInput::from_value(tool_input).command?;
+    find_remote_execution_command(&command)
+}
+
+/// Check if a tool comes from an external MCP server (not the built-in `acp` server)
+fn is_external_mcp_tool(tool_name: &str) -> bool {
+    tool_name.starts_with("mcp__") && !tool_name.starts_with("mcp__acp__")
 }
 
 #[cfg(test)]
@@ -323,6 +1187,10 @@ mod tests {
             PermissionMode::parse("bypassPermissions"),
             Some(PermissionMode::BypassPermissions)
         );
+        assert_eq!(
+            PermissionMode::parse("interactive"),
+            Some(PermissionMode::Interactive)
+        );
         assert_eq!(PermissionMode::parse("invalid"), None);
     }
 
@@ -335,10 +1203,21 @@ mod tests {
             PermissionMode::BypassPermissions.as_str(),
             "bypassPermissions"
         );
+        assert_eq!(PermissionMode::Interactive.as_str(), "interactive");
     }
 
     #[test]
-    fn test_permission_handler_default() {
+    fn test_interactive_mode_sdk_mapping() {
+        assert!(matches!(
+            PermissionMode::Interactive.to_sdk_mode(),
+            SdkPermissionMode::Default
+        ));
+        assert!(PermissionMode::Interactive.allows_writes());
+        assert!(!PermissionMode::Interactive.auto_approve_edits());
+    }
+
+    #[test]
+    fn test_permission_handler_default() {
         let handler = PermissionHandler::new();
         let input = json!({});
 
@@ -448,6 +1327,417 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_disabled_safe_commands_survive_mode_changes() {
+        let mut handler = PermissionHandler::new();
+        handler.set_disabled_safe_commands(vec!["find".to_string()]);
+        handler.set_mode(PermissionMode::Plan);
+        handler.set_mode(PermissionMode::Default);
+
+        assert_eq!(
+            handler
+                .check_permission("Bash", &json!({"command": "find . -name x"}))
+                .await,
+            ToolPermissionResult::NeedsPermission
+        );
+        assert_eq!(
+            handler
+                .check_permission("Bash", &json!({"command": "ls -la"}))
+                .await,
+            ToolPermissionResult::Allowed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_strict_accept_edits_prompts_for_dangerous_bash() {
+        let rm = json!({"command": "rm -rf /"});
+
+        let mut handler = PermissionHandler::with_mode(PermissionMode::AcceptEdits);
+        assert_eq!(
+            handler.check_permission("Bash", &rm).await,
+            ToolPermissionResult::Allowed
+        );
+
+        handler.set_strict_accept_edits(true);
+        assert_eq!(handler.mode(), PermissionMode::AcceptEdits);
+        assert_eq!(
+            handler.check_permission("Bash", &rm).await,
+            ToolPermissionResult::NeedsPermission
+        );
+        assert_eq!(
+            handler
+                .check_permission("Edit", &json!({"file_path": "/tmp/a.rs"}))
+                .await,
+            ToolPermissionResult::Allowed
+        );
+
+        // The choice survives mode changes
+        handler.set_mode(PermissionMode::Default);
+        handler.set_mode(PermissionMode::AcceptEdits);
+        assert_eq!(
+            handler.check_permission("Bash", &rm).await,
+            ToolPermissionResult::NeedsPermission
+        );
+    }
+
+    /// Registered strategy that blocks every tool and approves nothing
+    struct LockdownStrategy;
+
+    impl PermissionModeStrategy for LockdownStrategy {
+        fn mode(&self) -> PermissionMode {
+            PermissionMode::Default
+        }
+
+        fn should_auto_approve(&self, _tool_name: &str, _tool_input: &serde_json::Value) -> bool {
+            false
+        }
+
+        fn is_tool_blocked(
+            &self,
+            tool_name: &str,
+            _tool_input: &serde_json::Value,
+        ) -> Option<String> {
+            Some(format!("{} is locked down", tool_name))
+        }
+
+        fn check_permission(
+            &self,
+            tool_name: &str,
+            tool_input: &serde_json::Value,
+        ) -> ToolPermissionResult {
+            ToolPermissionResult::Blocked {
+                reason: self.is_tool_blocked(tool_name, tool_input).unwrap(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_permission_as_leaves_mode_unchanged() {
+        let handler = PermissionHandler::new();
+        let edit = json!({"file_path": "/tmp/src/main.rs", "old_string": "a", "new_string": "b"});
+
+        assert_eq!(
+            handler
+                .check_permission_as(PermissionMode::Default, "Edit", &edit)
+                .await,
+            ToolPermissionResult::NeedsPermission
+        );
+        assert!(matches!(
+            handler
+                .check_permission_as(PermissionMode::Plan, "Edit", &edit)
+                .await,
+            ToolPermissionResult::Blocked { .. }
+        ));
+        assert_eq!(
+            handler
+                .check_permission_as(PermissionMode::DontAsk, "Edit", &edit)
+                .await,
+            ToolPermissionResult::Blocked {
+                reason: DEFAULT_DONT_ASK_DENY_MESSAGE.to_string()
+            }
+        );
+
+        assert_eq!(handler.mode(), PermissionMode::Default);
+        assert_eq!(
+            handler.check_permission("Edit", &edit).await,
+            ToolPermissionResult::NeedsPermission
+        );
+    }
+
+    #[tokio::test]
+    async fn test_registered_strategy_used_for_custom_mode() {
+        let mut handler = PermissionHandler::new();
+        assert!(!handler.set_custom_mode("lockdown"));
+
+        handler.register_strategy("lockdown", Arc::new(LockdownStrategy));
+        assert!(handler.set_custom_mode("lockdown"));
+        assert_eq!(handler.custom_mode(), Some("lockdown"));
+        assert_eq!(handler.mode(), PermissionMode::Default);
+        assert_eq!(
+            handler.check_permission("Read", &json!({})).await,
+            ToolPermissionResult::Blocked {
+                reason: "Read is locked down".to_string()
+            }
+        );
+
+        // Unrelated configuration changes keep the registered strategy
+        handler.set_disabled_safe_commands(vec!["find".to_string()]);
+        assert!(handler.is_tool_blocked("Read"));
+
+        // Selecting a built-in mode leaves the registered mode
+        assert!(handler.uses_registered_strategy());
+        handler.set_mode(PermissionMode::Default);
+        assert!(!handler.uses_registered_strategy());
+        assert_eq!(handler.custom_mode(), None);
+        assert_eq!(
+            handler.check_permission("Read", &json!({})).await,
+            ToolPermissionResult::Allowed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_registered_strategy_overrides_builtin_mode() {
+        let mut handler = PermissionHandler::with_mode(PermissionMode::AcceptEdits);
+        handler.register_strategy("acceptEdits", Arc::new(LockdownStrategy));
+
+        // Takes effect for the active mode and on later set_mode calls
+        assert!(handler.uses_registered_strategy());
+        assert!(handler.is_tool_blocked("Edit"));
+        handler.set_mode(PermissionMode::Plan);
+        assert!(!handler.is_tool_blocked("Read"));
+        handler.set_mode(PermissionMode::AcceptEdits);
+        assert!(matches!(
+            handler.check_permission("Edit", &json!({})).await,
+            ToolPermissionResult::Blocked { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_project_plans_dir_allows_plan_writes() {
+        let project = tempfile::TempDir::new().unwrap();
+        let local = project.path().join(".claude").join("plans");
+        let mut handler = PermissionHandler::new();
+        handler.set_project_plans_dir(Some(local.clone()));
+        handler.set_mode(PermissionMode::Plan);
+
+        assert_eq!(handler.plans_dirs().last(), Some(&local));
+        let plan = local.join("plan.md");
+        assert_eq!(
+            handler
+                .check_permission("Write", &json!({"file_path": plan.to_str().unwrap()}))
+                .await,
+            ToolPermissionResult::Allowed
+        );
+        let source = project.path().join("main.rs");
+        assert!(matches!(
+            handler
+                .check_permission("Write", &json!({"file_path": source.to_str().unwrap()}))
+                .await,
+            ToolPermissionResult::Blocked { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_use_limited_rule_spent_only_when_allowing() {
+        let mut checker = PermissionChecker::new(Default::default(), "/tmp");
+        checker.add_allow_rule_with_uses("Bash(make:*)", 2);
+        let mut handler = PermissionHandler::with_checker_owned(checker);
+        let make = json!({"command": "make build"});
+
+        // Asking what another mode would decide spends nothing
+        for _ in 0..3 {
+            assert_eq!(
+                handler
+                    .check_permission_as(PermissionMode::AcceptEdits, "Bash", &make)
+                    .await,
+                ToolPermissionResult::Allowed
+            );
+        }
+        // Interactive mode prompts anyway, so the rule isn't spent either
+        handler.set_mode(PermissionMode::Interactive);
+        assert_eq!(
+            handler.check_permission("Bash", &make).await,
+            ToolPermissionResult::NeedsPermission
+        );
+        handler.set_mode(PermissionMode::Default);
+
+        for _ in 0..2 {
+            assert_eq!(
+                handler.check_permission("Bash", &make).await,
+                ToolPermissionResult::Allowed
+            );
+        }
+        assert_eq!(
+            handler.check_permission("Bash", &make).await,
+            ToolPermissionResult::NeedsPermission
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reject_always_blocks_identical_calls() {
+        let checker = PermissionChecker::new(Default::default(), "/tmp");
+        let handler = PermissionHandler::with_checker_owned(checker);
+        let input = json!({"command": "curl https://example.com"});
+
+        assert_eq!(
+            handler.check_permission("Bash", &input).await,
+            ToolPermissionResult::NeedsPermission
+        );
+        handler
+            .add_deny_rule_for_tool_call("mcp__acp__Bash", &input)
+            .await;
+
+        match handler.check_permission("Bash", &input).await {
+            ToolPermissionResult::Blocked { reason } => assert!(reason.contains("Bash(curl:*)")),
+            other => panic!("Expected Blocked after Always Reject, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_deny_cleared_persisted_deny_survives() {
+        let project = tempfile::TempDir::new().unwrap();
+        let mut checker = PermissionChecker::new(Default::default(), project.path());
+        // Runtime deny rules added through the API aren't session rejections
+        checker.add_deny_rule("Bash(ssh:*)");
+        let handler = PermissionHandler::with_checker_owned(checker);
+        let curl = json!({"command": "curl https://example.com"});
+        let wget = json!({"command": "wget https://example.com"});
+        let ssh = json!({"command": "ssh prod"});
+
+        handler.add_deny_rule_for_tool_call("Bash", &curl).await;
+        handler
+            .persist_deny_rule_for_tool_call("Bash", &wget)
+            .await
+            .unwrap();
+        for input in [&curl, &wget] {
+            assert!(matches!(
+                handler.check_permission("Bash", input).await,
+                ToolPermissionResult::Blocked { .. }
+            ));
+        }
+
+        // `/clear` drops the session-scoped deny only
+        handler.clear_session_deny_rules().await;
+        assert_eq!(
+            handler.check_permission("Bash", &curl).await,
+            ToolPermissionResult::NeedsPermission
+        );
+        for input in [&wget, &ssh] {
+            assert!(matches!(
+                handler.check_permission("Bash", input).await,
+                ToolPermissionResult::Blocked { .. }
+            ));
+        }
+
+        // The persisted deny is read back by a new session
+        let settings = crate::settings::SettingsManager::new(project.path()).unwrap();
+        let fresh = PermissionHandler::with_checker_owned(PermissionChecker::new(
+            settings.settings().clone(),
+            project.path(),
+        ));
+        assert!(matches!(
+            fresh.check_permission("Bash", &wget).await,
+            ToolPermissionResult::Blocked { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reads_allowed_for_turn() {
+        use crate::settings::{PermissionSettings, Settings};
+
+        let settings = Settings {
+            permissions: Some(PermissionSettings {
+                deny: Some(vec!["Read(./.env)".to_string()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let checker = PermissionChecker::new(settings, "/tmp");
+        let mut handler = PermissionHandler::with_checker_owned(checker);
+        handler.set_mode(PermissionMode::Interactive);
+        let read = json!({"file_path": "/tmp/src/main.rs"});
+        let grep = json!({"pattern": "TODO", "path": "/tmp/src"});
+        let write = json!({"file_path": "/tmp/src/main.rs", "content": ""});
+
+        assert_eq!(
+            handler.check_permission("Read", &read).await,
+            ToolPermissionResult::NeedsPermission
+        );
+
+        let turn = handler.current_turn();
+        handler.allow_reads_for_turn(turn);
+        assert_eq!(
+            handler.check_permission("Read", &read).await,
+            ToolPermissionResult::Allowed
+        );
+        assert_eq!(
+            handler.check_permission("mcp__acp__Grep", &grep).await,
+            ToolPermissionResult::Allowed
+        );
+        // Only reads are covered, and deny rules still apply
+        assert_eq!(
+            handler.check_permission("Write", &write).await,
+            ToolPermissionResult::NeedsPermission
+        );
+        assert!(matches!(
+            handler
+                .check_permission("Read", &json!({"file_path": "/tmp/.env"}))
+                .await,
+            ToolPermissionResult::Blocked { .. }
+        ));
+
+        // The grant ends with the turn
+        handler.end_turn();
+        assert_eq!(
+            handler.check_permission("Grep", &grep).await,
+            ToolPermissionResult::NeedsPermission
+        );
+
+        // An answer to a prompt from a turn that has ended grants nothing
+        handler.allow_reads_for_turn(turn);
+        assert!(!handler.reads_allowed_for_turn());
+        assert_eq!(
+            handler.check_permission("Read", &read).await,
+            ToolPermissionResult::NeedsPermission
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_session_requires_reconfirming_allow_always() {
+        let checker = PermissionChecker::new(Default::default(), "/tmp");
+        let mut handler = PermissionHandler::with_checker_owned(checker);
+        handler.set_allow_rule_idle_timeout(Some(Duration::from_secs(600)));
+        let input = json!({"command": "curl https://example.com"});
+        handler.add_allow_rule_for_tool_call("Bash", &input);
+
+        // Activity within the timeout keeps the rule alive
+        for _ in 0..3 {
+            tokio::time::advance(Duration::from_secs(300)).await;
+            assert!(!handler.record_activity().await);
+            assert_eq!(
+                handler.check_permission("Bash", &input).await,
+                ToolPermissionResult::Allowed
+            );
+        }
+
+        // Checks alone aren't activity, so they don't hold off expiry
+        tokio::time::advance(Duration::from_secs(400)).await;
+        assert_eq!(
+            handler.check_permission("Bash", &input).await,
+            ToolPermissionResult::Allowed
+        );
+        tokio::time::advance(Duration::from_secs(400)).await;
+
+        // After sitting idle, the next use prompts again
+        assert!(handler.record_activity().await);
+        assert_eq!(
+            handler.check_permission("Bash", &input).await,
+            ToolPermissionResult::NeedsPermission
+        );
+
+        // Confirming again restores the rule
+        handler.add_allow_rule_for_tool_call("Bash", &input);
+        assert_eq!(
+            handler.check_permission("Bash", &input).await,
+            ToolPermissionResult::Allowed
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_allow_rules_never_expire_by_default() {
+        let checker = PermissionChecker::new(Default::default(), "/tmp");
+        let handler = PermissionHandler::with_checker_owned(checker);
+        let input = json!({"command": "curl https://example.com"});
+        handler.add_allow_rule_for_tool_call("Bash", &input);
+
+        tokio::time::advance(Duration::from_secs(24 * 60 * 60)).await;
+        assert!(!handler.record_activity().await);
+        assert_eq!(
+            handler.check_permission("Bash", &input).await,
+            ToolPermissionResult::Allowed
+        );
+    }
+
     #[tokio::test]
     async fn test_bypass_permissions_strategy() {
         let handler = PermissionHandler::with_mode(PermissionMode::BypassPermissions);
@@ -462,6 +1752,226 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_extra_dangerous_commands_always_prompt() {
+        use crate::settings::{PermissionSettings, Settings};
+
+        let checker = PermissionChecker::new(
+            Settings {
+                permissions: Some(PermissionSettings {
+                    allow: Some(vec!["Bash(wipe-db:*)".to_string()]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            "/tmp",
+        );
+        let mut handler = PermissionHandler::with_checker_owned(checker);
+        handler.set_extra_dangerous_commands(vec!["wipe-db".to_string()]);
+        let wipe = json!({"command": "wipe-db --all"});
+
+        // An allow rule does not skip the prompt
+        assert_eq!(
+            handler.check_permission("Bash", &wipe).await,
+            ToolPermissionResult::NeedsPermission
+        );
+
+        handler.set_mode(PermissionMode::BypassPermissions);
+        assert_eq!(
+            handler.check_permission("mcp__acp__Bash", &wipe).await,
+            ToolPermissionResult::NeedsPermission
+        );
+        assert_eq!(
+            handler
+                .check_permission("Bash", &json!({"command": "ls"}))
+                .await,
+            ToolPermissionResult::Allowed
+        );
+
+        // DontAsk never prompts, so the command is blocked
+        handler.set_mode(PermissionMode::DontAsk);
+        assert!(matches!(
+            handler.check_permission("Bash", &wipe).await,
+            ToolPermissionResult::Blocked { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_safe_command_classifier_replaces_builtin_set() {
+        /// Safe only if it runs the project's own scripts
+        #[derive(Debug)]
+        struct ScriptsOnly;
+
+        impl SafeCommandClassifier for ScriptsOnly {
+            fn is_safe_command(&self, command: &str) -> bool {
+                command.starts_with("./scripts/")
+            }
+        }
+
+        let mut handler = PermissionHandler::new();
+        let script = json!({"command": "./scripts/reset-db.sh"});
+        let ls = json!({"command": "ls -la"});
+        assert_eq!(
+            handler.check_permission("Bash", &script).await,
+            ToolPermissionResult::NeedsPermission
+        );
+        assert_eq!(
+            handler.check_permission("Bash", &ls).await,
+            ToolPermissionResult::Allowed
+        );
+
+        handler.set_safe_command_classifier(Some(Arc::new(ScriptsOnly)));
+        assert_eq!(
+            handler.check_permission("Bash", &script).await,
+            ToolPermissionResult::Allowed
+        );
+        assert_eq!(
+            handler.check_permission("Bash", &ls).await,
+            ToolPermissionResult::NeedsPermission
+        );
+
+        // Strict AcceptEdits uses it too; None restores the built-in set
+        handler.set_strict_accept_edits(true);
+        handler.set_mode(PermissionMode::AcceptEdits);
+        assert_eq!(
+            handler.check_permission("Bash", &script).await,
+            ToolPermissionResult::Allowed
+        );
+        handler.set_safe_command_classifier(None);
+        assert_eq!(
+            handler.check_permission("Bash", &ls).await,
+            ToolPermissionResult::Allowed
+        );
+    }
+
+    #[test]
+    fn test_mode_blocks_tool() {
+        for tool in ["Write", "Edit", "Bash", "NotebookEdit", "mcp__acp__Write"] {
+            assert!(PermissionMode::Plan.blocks_tool(tool), "{tool}");
+        }
+        assert!(!PermissionMode::Plan.blocks_tool("Read"));
+        assert!(!PermissionMode::Plan.blocks_tool("Grep"));
+
+        // The other modes prompt or allow, but never block a tool outright
+        for mode in [
+            PermissionMode::Default,
+            PermissionMode::AcceptEdits,
+            PermissionMode::DontAsk,
+            PermissionMode::Interactive,
+            PermissionMode::BypassPermissions,
+        ] {
+            for tool in ["Write", "Edit", "Bash", "Read", "mcp__server__tool"] {
+                assert!(!mode.blocks_tool(tool), "{} blocks {tool}", mode.as_str());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remote_package_execution_is_not_approved_by_rules() {
+        use crate::settings::{PermissionSettings, Settings};
+
+        let npx = json!({"command": "npx create-react-app x"});
+        for allow in ["Bash", "Bash(npx:*)"] {
+            let checker = PermissionChecker::new(
+                Settings {
+                    permissions: Some(PermissionSettings {
+                        allow: Some(vec![allow.to_string()]),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                "/tmp",
+            );
+            let mut handler = PermissionHandler::with_checker_owned(checker);
+            handler.set_extra_dangerous_commands(vec!["wipe-db".to_string()]);
+
+            assert_eq!(
+                handler.check_permission("Bash", &npx).await,
+                ToolPermissionResult::NeedsPermission,
+                "allow rule: {allow}"
+            );
+            // Other commands are still approved by the rule
+            if allow == "Bash" {
+                assert_eq!(
+                    handler
+                        .check_permission("Bash", &json!({"command": "npm test"}))
+                        .await,
+                    ToolPermissionResult::Allowed
+                );
+            }
+
+            handler.set_mode(PermissionMode::DontAsk);
+            assert!(matches!(
+                handler.check_permission("Bash", &npx).await,
+                ToolPermissionResult::Blocked { .. }
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_network_commands_prompt_when_configured() {
+        let mut handler = PermissionHandler::with_mode(PermissionMode::BypassPermissions);
+        let fetch = json!({"command": "git pull && cargo build"});
+        assert_eq!(
+            handler.check_permission("Bash", &fetch).await,
+            ToolPermissionResult::Allowed
+        );
+
+        handler.set_prompt_for_network_commands(true);
+        assert_eq!(
+            handler.check_permission("Bash", &fetch).await,
+            ToolPermissionResult::NeedsPermission
+        );
+        assert_eq!(
+            handler
+                .check_permission("Bash", &json!({"command": "cargo build"}))
+                .await,
+            ToolPermissionResult::Allowed
+        );
+
+        handler.set_mode(PermissionMode::AcceptEdits);
+        assert_eq!(
+            handler
+                .check_permission("Bash", &json!({"command": "curl https://example.com"}))
+                .await,
+            ToolPermissionResult::NeedsPermission
+        );
+
+        handler.set_mode(PermissionMode::DontAsk);
+        assert!(matches!(
+            handler.check_permission("Bash", &fetch).await,
+            ToolPermissionResult::Blocked { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_dont_ask_deny_message() {
+        let mut handler = PermissionHandler::with_mode(PermissionMode::DontAsk);
+        let write = json!({"file_path": "/tmp/a.txt", "content": "x"});
+
+        assert_eq!(
+            handler.check_permission("Write", &write).await,
+            ToolPermissionResult::Blocked {
+                reason: DEFAULT_DONT_ASK_DENY_MESSAGE.to_string()
+            }
+        );
+
+        let message = "Not allowed here; ask the user to update .claude/settings.json";
+        handler.set_dont_ask_deny_message(Some(message.to_string()));
+        assert_eq!(
+            handler.check_permission("Write", &write).await,
+            ToolPermissionResult::Blocked {
+                reason: message.to_string()
+            }
+        );
+
+        handler.set_dont_ask_deny_message(None);
+        assert_eq!(
+            handler.dont_ask_deny_message(),
+            DEFAULT_DONT_ASK_DENY_MESSAGE
+        );
+    }
+
     #[tokio::test]
     async fn test_accept_edits_strategy() {
         let handler = PermissionHandler::with_mode(PermissionMode::AcceptEdits);
@@ -472,4 +1982,374 @@ mod tests {
             _ => panic!("Expected Allowed for Write in AcceptEdits mode"),
         }
     }
+
+    #[test]
+    fn test_tool_category_names_external_mcp_tools() {
+        let handler = PermissionHandler::new();
+        let input = json!({});
+        assert_eq!(
+            handler.tool_category("mcp__web-fetch__webReader", &input),
+            ToolCategory::Network
+        );
+        assert_eq!(
+            handler.tool_category("mcp__acp__Read", &input),
+            ToolCategory::Read
+        );
+        assert_eq!(
+            handler.tool_category("mcp__my-server__my_tool", &input),
+            ToolCategory::Unknown
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trust_on_first_use_respects_deny_rules() {
+        use crate::permissions::strategies::TrustOnFirstUseStrategy;
+        use crate::settings::{PermissionSettings, Settings};
+
+        let settings = Settings {
+            permissions: Some(PermissionSettings {
+                deny: Some(vec!["Bash(rm:*)".to_string()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut handler =
+            PermissionHandler::with_checker_owned(PermissionChecker::new(settings, "/tmp"));
+        handler.register_strategy(
+            "trustOnFirstUse",
+            Arc::new(TrustOnFirstUseStrategy::new(Arc::new(
+                DefaultModeStrategy::new(),
+            ))),
+        );
+        assert!(handler.set_custom_mode("trustOnFirstUse"));
+
+        let write = json!({"file_path": "/tmp/a.txt", "content": "x"});
+        assert_eq!(
+            handler.check_permission("Write", &write).await,
+            ToolPermissionResult::NeedsPermission
+        );
+        handler.record_approval("Write", &write);
+        assert_eq!(
+            handler.check_permission("Write", &write).await,
+            ToolPermissionResult::Allowed
+        );
+
+        // Trusting Bash doesn't override deny rules
+        let bash = json!({"command": "npm install"});
+        assert_eq!(
+            handler.check_permission("Bash", &bash).await,
+            ToolPermissionResult::NeedsPermission
+        );
+        handler.record_approval("Bash", &bash);
+        assert_eq!(
+            handler.check_permission("Bash", &bash).await,
+            ToolPermissionResult::Allowed
+        );
+        assert!(matches!(
+            handler
+                .check_permission("Bash", &json!({"command": "rm -rf build"}))
+                .await,
+            ToolPermissionResult::Blocked { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_empty_input_tools_follow_name_rules_and_default() {
+        use crate::settings::{PermissionSettings, Settings};
+
+        let handler_with = |permissions: PermissionSettings| {
+            let settings = Settings {
+                permissions: Some(permissions),
+                ..Default::default()
+            };
+            PermissionHandler::with_checker_owned(PermissionChecker::new(settings, "/tmp"))
+        };
+        let tool = "mcp__weather__refresh";
+
+        // Name rules decide for empty-input tools
+        let allowing = handler_with(PermissionSettings {
+            allow: Some(vec![tool.to_string()]),
+            empty_input_default: Some(EmptyInputPolicy::Deny),
+            ..Default::default()
+        });
+        assert_eq!(
+            allowing.check_permission(tool, &json!({})).await,
+            ToolPermissionResult::Allowed
+        );
+        let denying = handler_with(PermissionSettings {
+            deny: Some(vec![tool.to_string()]),
+            ..Default::default()
+        });
+        assert!(matches!(
+            denying.check_permission(tool, &json!({})).await,
+            ToolPermissionResult::Blocked { .. }
+        ));
+
+        // Without a rule, the default policy asks
+        let default = handler_with(PermissionSettings::default());
+        assert_eq!(
+            default.check_permission(tool, &json!({})).await,
+            ToolPermissionResult::NeedsPermission
+        );
+
+        // The deny policy blocks empty-input calls only
+        let strict = handler_with(PermissionSettings {
+            empty_input_default: Some(EmptyInputPolicy::Deny),
+            ..Default::default()
+        });
+        match strict.check_permission(tool, &json!({})).await {
+            ToolPermissionResult::Blocked { reason } => {
+                assert!(reason.contains("emptyInputDefault"))
+            }
+            other => panic!("Expected Blocked for empty input, got {:?}", other),
+        }
+        assert_eq!(
+            strict
+                .check_permission(tool, &json!({"city": "Oslo"}))
+                .await,
+            ToolPermissionResult::NeedsPermission
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deny_unknown_mcp_tools() {
+        use crate::settings::{PermissionSettings, Settings};
+
+        let settings = Settings {
+            permissions: Some(PermissionSettings {
+                allow: Some(vec!["mcp__github__search_issues".to_string()]),
+                ask: Some(vec!["mcp__github__create_issue".to_string()]),
+                deny_unknown_mcp_tools: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let handler =
+            PermissionHandler::with_checker_owned(PermissionChecker::new(settings, "/tmp"));
+
+        // Allowed MCP tool passes
+        assert_eq!(
+            handler
+                .check_permission("mcp__github__search_issues", &json!({}))
+                .await,
+            ToolPermissionResult::Allowed
+        );
+
+        // MCP tool with an explicit ask rule still prompts
+        assert_eq!(
+            handler
+                .check_permission("mcp__github__create_issue", &json!({}))
+                .await,
+            ToolPermissionResult::NeedsPermission
+        );
+
+        // Unknown MCP tool is blocked
+        assert!(matches!(
+            handler
+                .check_permission("mcp__github__delete_repo", &json!({}))
+                .await,
+            ToolPermissionResult::Blocked { .. }
+        ));
+
+        // Built-in tools (including the acp server's) still prompt
+        assert_eq!(
+            handler.check_permission("Write", &json!({})).await,
+            ToolPermissionResult::NeedsPermission
+        );
+        assert_eq!(
+            handler
+                .check_permission("mcp__acp__Write", &json!({}))
+                .await,
+            ToolPermissionResult::NeedsPermission
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_mcp_tools_prompt_by_default() {
+        let handler = PermissionHandler::with_checker_owned(PermissionChecker::default());
+        assert_eq!(
+            handler
+                .check_permission("mcp__github__delete_repo", &json!({}))
+                .await,
+            ToolPermissionResult::NeedsPermission
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interactive_mode_prompts_for_everything() {
+        use crate::settings::{PermissionSettings, Settings};
+
+        let settings = Settings {
+            permissions: Some(PermissionSettings {
+                allow: Some(vec!["Read".to_string()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut handler =
+            PermissionHandler::with_checker_owned(PermissionChecker::new(settings, "/tmp"));
+        handler.set_mode(PermissionMode::Interactive);
+
+        // Reads prompt, even when allowed by a settings rule
+        assert!(!handler.should_auto_approve("Read", &json!({})));
+        assert_eq!(
+            handler.check_permission("Read", &json!({})).await,
+            ToolPermissionResult::NeedsPermission
+        );
+        assert_eq!(
+            handler
+                .check_permission("Bash", &json!({"command": "ls"}))
+                .await,
+            ToolPermissionResult::NeedsPermission
+        );
+        assert_eq!(
+            handler.check_permission("Task", &json!({})).await,
+            ToolPermissionResult::NeedsPermission
+        );
+
+        // Genuine user-interaction tools don't prompt
+        assert_eq!(
+            handler
+                .check_permission("AskUserQuestion", &json!({}))
+                .await,
+            ToolPermissionResult::Allowed
+        );
+    }
+
+    #[test]
+    fn test_exit_plan_mode_options_configuration() {
+        let mut handler = PermissionHandler::new();
+        assert_eq!(
+            handler.exit_plan_mode_options(),
+            ExitPlanModeOption::defaults().as_slice()
+        );
+
+        let custom = vec![ExitPlanModeOption::approve(
+            "bypass",
+            "Yes, and bypass permissions",
+            PermissionMode::BypassPermissions,
+        )];
+        handler.set_exit_plan_mode_options(custom.clone());
+        assert_eq!(handler.exit_plan_mode_options(), custom.as_slice());
+
+        // An empty option set falls back to the defaults
+        handler.set_exit_plan_mode_options(Vec::new());
+        assert_eq!(handler.exit_plan_mode_options().len(), 3);
+    }
+
+    #[test]
+    fn test_exit_plan_mode_title_configuration() {
+        let mut handler = PermissionHandler::new();
+        assert_eq!(handler.exit_plan_mode_title(), DEFAULT_EXIT_PLAN_MODE_TITLE);
+
+        handler.set_exit_plan_mode_title(Some("Prêt à coder ?".to_string()));
+        assert_eq!(handler.exit_plan_mode_title(), "Prêt à coder ?");
+
+        handler.set_exit_plan_mode_title(None);
+        assert_eq!(handler.exit_plan_mode_title(), DEFAULT_EXIT_PLAN_MODE_TITLE);
+
+        assert!(!handler.confirm_empty_plan());
+        handler.set_confirm_empty_plan(true);
+        assert!(handler.confirm_empty_plan());
+    }
+
+    #[tokio::test]
+    async fn test_command_alias_used_for_safety_classification() {
+        use crate::settings::Settings;
+
+        let settings = Settings {
+            command_aliases: Some(
+                [("gitw".to_string(), "git".to_string())]
+                    .into_iter()
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        let handler =
+            PermissionHandler::with_checker_owned(PermissionChecker::new(settings, "/tmp"));
+
+        // `git status` is a known safe command, so its wrapper is too
+        assert_eq!(
+            handler
+                .check_permission("Bash", &json!({"command": "./scripts/gitw status"}))
+                .await,
+            ToolPermissionResult::Allowed
+        );
+        // An unconfigured wrapper is classified by its own name
+        assert_eq!(
+            handler
+                .check_permission("Bash", &json!({"command": "./scripts/hgw status"}))
+                .await,
+            ToolPermissionResult::NeedsPermission
+        );
+    }
+
+    #[test]
+    fn test_permission_mode_default_prompts() {
+        assert_eq!(PermissionMode::default(), PermissionMode::Default);
+        assert_eq!(
+            PermissionHandler::default().mode(),
+            PermissionMode::default()
+        );
+    }
+
+    #[test]
+    fn test_resolve_initial_permission_mode_precedence() {
+        // Session request wins over settings, the last-used mode and the fallback
+        assert_eq!(
+            resolve_initial_permission_mode(
+                Some("plan"),
+                Some("acceptEdits"),
+                Some("dontAsk"),
+                PermissionMode::Default
+            ),
+            PermissionMode::Plan
+        );
+        // Settings win over the last-used mode
+        assert_eq!(
+            resolve_initial_permission_mode(
+                None,
+                Some("acceptEdits"),
+                Some("dontAsk"),
+                PermissionMode::Default
+            ),
+            PermissionMode::AcceptEdits
+        );
+        // The last-used mode applies when settings don't set a default
+        assert_eq!(
+            resolve_initial_permission_mode(None, None, Some("dontAsk"), PermissionMode::Default),
+            PermissionMode::DontAsk
+        );
+        // Fallback applies when none is set
+        assert_eq!(
+            resolve_initial_permission_mode(None, None, None, PermissionMode::DontAsk),
+            PermissionMode::DontAsk
+        );
+    }
+
+    #[test]
+    fn test_resolve_initial_permission_mode_skips_unknown_names() {
+        assert_eq!(
+            resolve_initial_permission_mode(
+                Some("yolo"),
+                None,
+                Some("plan"),
+                PermissionMode::Default
+            ),
+            PermissionMode::Plan
+        );
+        assert_eq!(
+            resolve_initial_permission_mode(None, None, Some("typo"), PermissionMode::Default),
+            PermissionMode::Default
+        );
+        assert_eq!(
+            resolve_initial_permission_mode(
+                None,
+                Some("garbage"),
+                Some("plan"),
+                PermissionMode::Default
+            ),
+            PermissionMode::Plan
+        );
+    }
 }
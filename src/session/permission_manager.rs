@@ -16,6 +16,7 @@ use sacp::schema::{
 };
 
 use crate::types::AgentError;
+use crate::utils::redact_tool_input;
 
 /// Permission decision result
 #[derive(Debug, Clone, PartialEq)]
@@ -43,7 +44,7 @@ impl std::fmt::Debug for PendingPermissionRequest {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("PendingPermissionRequest")
             .field("tool_name", &self.tool_name)
-            .field("tool_input", &self.tool_input)
+            .field("tool_input", &redact_tool_input(&self.tool_input))
             .field("tool_call_id", &self.tool_call_id)
             .field("session_id", &self.session_id)
             .field("response_tx", &"<oneshot::Sender>")
@@ -3,15 +3,28 @@
 //! Implements the ACP permission request/response protocol for asking users
 //! whether to allow tool execution.
 
+use std::future::Future;
+use std::time::Duration;
+
 use sacp::JrConnectionCx;
 use sacp::link::AgentToClient;
 use sacp::schema::{
-    PermissionOption, PermissionOptionId, PermissionOptionKind, RequestPermissionOutcome,
-    RequestPermissionRequest, SessionId, ToolCallUpdate, ToolCallUpdateFields,
+    Content, ContentBlock, PermissionOption, PermissionOptionId, PermissionOptionKind,
+    RequestPermissionOutcome, RequestPermissionRequest, SessionId, TextContent, ToolCallContent,
+    ToolCallUpdate, ToolCallUpdateFields,
 };
 
+use super::permission::is_read_category_tool;
+use crate::command_safety::explain_command;
 use crate::types::AgentError;
 
+/// `_meta` key on each permission option saying how long its decision lasts
+///
+/// One of `"once"`, `"turn"`, `"session"` or `"always"`. ACP has only four
+/// option kinds, so options of the same [`PermissionOptionKind`] (e.g.
+/// "Reject for Session" and "Always Reject") are told apart by scope.
+pub const OPTION_SCOPE_META_KEY: &str = "scope";
+
 /// Permission request outcome after user interaction
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PermissionOutcome {
@@ -19,8 +32,19 @@ pub enum PermissionOutcome {
     AllowOnce,
     /// User allowed this tool call and wants to always allow this pattern
     AllowAlways,
+    /// User allowed this tool call and picked which rule to always allow,
+    /// one of those given to [`PermissionRequestBuilder::allow_always_rules`]
+    AllowAlwaysRule(String),
+    /// User allowed this read and every other read for the rest of the turn
+    AllowReadsForTurn,
     /// User rejected this tool call
     Rejected,
+    /// User rejected this tool call and wants to always reject this pattern,
+    /// persisted to the project's local settings
+    RejectAlways,
+    /// User rejected this tool call and wants to reject this pattern for the
+    /// rest of the session only
+    RejectForSession,
     /// Permission request was cancelled
     Cancelled,
 }
@@ -33,6 +57,8 @@ pub struct PermissionRequestBuilder {
     title: String,
     tool_name: String,
     tool_input: serde_json::Value,
+    timeout: Option<Duration>,
+    allow_always_rules: Vec<String>,
 }
 
 impl PermissionRequestBuilder {
@@ -51,6 +77,8 @@ impl PermissionRequestBuilder {
             title,
             tool_name: tool_name_str,
             tool_input,
+            timeout: None,
+            allow_always_rules: Vec::new(),
         }
     }
 
@@ -60,6 +88,27 @@ impl PermissionRequestBuilder {
         self
     }
 
+    /// Give up with [`AgentError::PermissionTimeout`] if the client doesn't answer in time
+    ///
+    /// Without a timeout the request waits for the user indefinitely.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Offer one "Always Allow" option per rule instead of a single one
+    ///
+    /// Rules are listed in the given order, e.g. narrowest first from
+    /// [`PermissionChecker::suggested_allow_rules`]; choosing one yields
+    /// [`PermissionOutcome::AllowAlwaysRule`] with that rule. With fewer
+    /// than two rules the single generic option is kept.
+    ///
+    /// [`PermissionChecker::suggested_allow_rules`]: crate::settings::PermissionChecker::suggested_allow_rules
+    pub fn allow_always_rules(mut self, rules: Vec<String>) -> Self {
+        self.allow_always_rules = rules;
+        self
+    }
+
     /// Build the request and send it to the client
     ///
     /// Returns the user's decision as a `PermissionOutcome`.
@@ -67,47 +116,7 @@ impl PermissionRequestBuilder {
         self,
         connection_cx: &JrConnectionCx<AgentToClient>,
     ) -> Result<PermissionOutcome, AgentError> {
-        // Build the options
-        let options = vec![
-            PermissionOption::new(
-                PermissionOptionId::new("allow_always"),
-                "Always Allow",
-                PermissionOptionKind::AllowAlways,
-            ),
-            PermissionOption::new(
-                PermissionOptionId::new("allow_once"),
-                "Allow",
-                PermissionOptionKind::AllowOnce,
-            ),
-            PermissionOption::new(
-                PermissionOptionId::new("reject_once"),
-                "Reject",
-                PermissionOptionKind::RejectOnce,
-            ),
-        ];
-
-        // Build the tool call update with title
-        let tool_call_update = ToolCallUpdate::new(
-            self.tool_call_id.clone(),
-            ToolCallUpdateFields::new()
-                .title(&self.title)
-                .raw_input(self.tool_input.clone()),
-        );
-
-        // Debug: Log the tool call update being sent
-        tracing::debug!(
-            tool_call_id = %self.tool_call_id,
-            title = %self.title,
-            tool_name = %self.tool_name,
-            "Building permission request with ToolCallUpdate"
-        );
-
-        // Build the request
-        let request = RequestPermissionRequest::new(
-            SessionId::new(self.session_id.clone()),
-            tool_call_update,
-            options,
-        );
+        let request = self.build_request();
 
         // Debug: Log the serialized request for protocol debugging
         if let Ok(json) = serde_json::to_string_pretty(&request) {
@@ -125,18 +134,18 @@ impl PermissionRequestBuilder {
             "Sending permission request, waiting for user response..."
         );
 
-        let response = connection_cx
-            .send_request(request)
-            .block_task()
-            .await
-            .map_err(|e| {
-                tracing::error!(
-                    tool_call_id = %self.tool_call_id,
-                    error = %e,
-                    "Permission request failed"
-                );
-                AgentError::Internal(format!("Permission request failed: {}", e))
-            })?;
+        let response = await_response(
+            connection_cx.send_request(request).block_task(),
+            self.timeout,
+        )
+        .await
+        .inspect_err(|e| {
+            tracing::error!(
+                tool_call_id = %self.tool_call_id,
+                error = %e,
+                "Permission request failed"
+            );
+        })?;
 
         tracing::info!(
             tool_call_id = %self.tool_call_id,
@@ -144,7 +153,123 @@ impl PermissionRequestBuilder {
         );
 
         // Parse the response
-        Ok(parse_permission_response(response.outcome))
+        Ok(self.parse_response(response.outcome))
+    }
+
+    /// Build the `session/request_permission` request
+    fn build_request(&self) -> RequestPermissionRequest {
+        // Build the options
+        let mut options = if self.allow_always_rules.len() > 1 {
+            self.allow_always_rules
+                .iter()
+                .enumerate()
+                .map(|(index, rule)| {
+                    scoped_option(
+                        PermissionOptionId::new(format!("allow_always:{}", index)),
+                        format!("Always Allow {}", rule),
+                        PermissionOptionKind::AllowAlways,
+                        "always",
+                    )
+                })
+                .collect()
+        } else {
+            vec![scoped_option(
+                PermissionOptionId::new("allow_always"),
+                "Always Allow",
+                PermissionOptionKind::AllowAlways,
+                "always",
+            )]
+        };
+        options.push(scoped_option(
+            PermissionOptionId::new("allow_once"),
+            "Allow",
+            PermissionOptionKind::AllowOnce,
+            "once",
+        ));
+        // Reads tend to come in bursts, so they can be allowed for the whole turn
+        if is_read_category_tool(&self.tool_name) {
+            options.push(scoped_option(
+                PermissionOptionId::new("allow_reads_turn"),
+                "Allow All Reads This Turn",
+                PermissionOptionKind::AllowOnce,
+                "turn",
+            ));
+        }
+        options.extend([
+            scoped_option(
+                PermissionOptionId::new("reject_once"),
+                "Reject",
+                PermissionOptionKind::RejectOnce,
+                "once",
+            ),
+            scoped_option(
+                PermissionOptionId::new("reject_session"),
+                "Reject for Session",
+                PermissionOptionKind::RejectAlways,
+                "session",
+            ),
+            scoped_option(
+                PermissionOptionId::new("reject_always"),
+                "Always Reject",
+                PermissionOptionKind::RejectAlways,
+                "always",
+            ),
+        ]);
+
+        // Build the tool call update with title, explaining Bash commands
+        let mut fields = ToolCallUpdateFields::new()
+            .title(&self.title)
+            .raw_input(self.tool_input.clone());
+        if let Some(explanation) = self.command_explanation() {
+            fields = fields.content(vec![ToolCallContent::Content(Content::new(
+                ContentBlock::Text(TextContent::new(explanation)),
+            ))]);
+        }
+        let tool_call_update = ToolCallUpdate::new(self.tool_call_id.clone(), fields);
+
+        // Debug: Log the tool call update being sent
+        tracing::debug!(
+            tool_call_id = %self.tool_call_id,
+            title = %self.title,
+            tool_name = %self.tool_name,
+            "Building permission request with ToolCallUpdate"
+        );
+
+        RequestPermissionRequest::new(
+            SessionId::new(self.session_id.clone()),
+            tool_call_update,
+            options,
+        )
+    }
+
+    /// Parse the response, resolving a chosen allow-always rule
+    fn parse_response(&self, outcome: RequestPermissionOutcome) -> PermissionOutcome {
+        if let RequestPermissionOutcome::Selected(selected) = &outcome
+            && let Some(index) = selected.option_id.0.strip_prefix("allow_always:")
+        {
+            return match index
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| self.allow_always_rules.get(index))
+            {
+                Some(rule) => PermissionOutcome::AllowAlwaysRule(rule.clone()),
+                None => PermissionOutcome::Rejected, // Not an option we offered
+            };
+        }
+        parse_permission_response(outcome)
+    }
+
+    /// Describe what a Bash command does, if it matches a known pattern
+    fn command_explanation(&self) -> Option<String> {
+        let stripped_name = self
+            .tool_name
+            .strip_prefix("mcp__acp__")
+            .unwrap_or(&self.tool_name);
+        if stripped_name != "Bash" {
+            return None;
+        }
+        let command = self.tool_input.get("command").and_then(|v| v.as_str())?;
+        explain_command(command).map(|explanation| format!("This command {}.", explanation))
     }
 
     /// Get the tool name
@@ -153,6 +278,73 @@ impl PermissionRequestBuilder {
     }
 }
 
+/// Create a permission option tagged with its [`OPTION_SCOPE_META_KEY`] scope
+fn scoped_option(
+    id: PermissionOptionId,
+    name: impl Into<String>,
+    kind: PermissionOptionKind,
+    scope: &str,
+) -> PermissionOption {
+    let mut meta = serde_json::Map::new();
+    meta.insert(
+        OPTION_SCOPE_META_KEY.to_string(),
+        serde_json::Value::String(scope.to_string()),
+    );
+    PermissionOption::new(id, name, kind).meta(meta)
+}
+
+/// Await the client's response to a request, mapping failures to specific errors
+///
+/// Timeouts become [`AgentError::PermissionTimeout`]; request errors are
+/// classified by [`request_error`].
+pub(crate) async fn await_response<T>(
+    response: impl Future<Output = Result<T, sacp::Error>>,
+    timeout: Option<Duration>,
+) -> Result<T, AgentError> {
+    let result = match timeout {
+        Some(limit) => tokio::time::timeout(limit, response).await.map_err(|_| {
+            AgentError::PermissionTimeout(u64::try_from(limit.as_millis()).unwrap_or(u64::MAX))
+        })?,
+        None => response.await,
+    };
+    result.map_err(|e| request_error(&e))
+}
+
+/// Message fragments reported when the connection went away mid-request
+const CONNECTION_CLOSED_MARKERS: &[&str] = &[
+    "connection closed",
+    "channel closed",
+    "disconnected",
+    "broken pipe",
+    "end of file",
+];
+
+/// Classify a failed request as a lost connection or a protocol error
+fn request_error(error: &sacp::Error) -> AgentError {
+    // Read the JSON-RPC error object ({"code", "message", "data"}) as sent
+    let value = serde_json::to_value(error).unwrap_or_default();
+    let code = value.get("code").and_then(serde_json::Value::as_i64);
+    let message = value
+        .get("message")
+        .and_then(serde_json::Value::as_str)
+        .map_or_else(|| error.to_string(), str::to_string);
+    classify_request_error(code, message)
+}
+
+fn classify_request_error(code: Option<i64>, message: String) -> AgentError {
+    let lower = message.to_lowercase();
+    if CONNECTION_CLOSED_MARKERS
+        .iter()
+        .any(|marker| lower.contains(marker))
+    {
+        return AgentError::ConnectionClosed(message);
+    }
+    match code {
+        Some(code) => AgentError::ProtocolError(format!("{} (code {})", message, code)),
+        None => AgentError::ProtocolError(message),
+    }
+}
+
 /// Parse a permission response outcome into our outcome type
 fn parse_permission_response(outcome: RequestPermissionOutcome) -> PermissionOutcome {
     match outcome {
@@ -160,7 +352,10 @@ fn parse_permission_response(outcome: RequestPermissionOutcome) -> PermissionOut
             match selected.option_id.0.as_ref() {
                 "allow_always" => PermissionOutcome::AllowAlways,
                 "allow_once" => PermissionOutcome::AllowOnce,
+                "allow_reads_turn" => PermissionOutcome::AllowReadsForTurn,
                 "reject_once" => PermissionOutcome::Rejected,
+                "reject_session" => PermissionOutcome::RejectForSession,
+                "reject_always" => PermissionOutcome::RejectAlways,
                 _ => PermissionOutcome::Rejected, // Unknown option, treat as reject
             }
         }
@@ -248,6 +443,145 @@ mod tests {
         assert_eq!(title, "List files");
     }
 
+    #[test]
+    fn test_bash_request_explains_command() {
+        let request = PermissionRequestBuilder::new(
+            "session-1",
+            "toolu_1",
+            "mcp__acp__Bash",
+            json!({"command": "rm -rf x"}),
+        )
+        .build_request();
+        let json = serde_json::to_value(&request).unwrap();
+        let text = json["toolCall"]["content"][0]["content"]["text"]
+            .as_str()
+            .unwrap();
+        assert!(text.contains("force-removes files recursively"));
+
+        // Unknown commands and other tools carry no explanation
+        for (tool_name, input) in [
+            ("Bash", json!({"command": "cargo build"})),
+            ("Write", json!({"file_path": "/tmp/rm -rf x"})),
+        ] {
+            let request = PermissionRequestBuilder::new("session-1", "toolu_1", tool_name, input)
+                .build_request();
+            let json = serde_json::to_value(&request).unwrap();
+            assert!(json["toolCall"].get("content").is_none());
+        }
+    }
+
+    #[test]
+    fn test_read_requests_offer_allowing_reads_for_turn() {
+        let option_ids = |tool_name: &str| {
+            let request =
+                PermissionRequestBuilder::new("session-1", "toolu_1", tool_name, json!({}))
+                    .build_request();
+            request
+                .options
+                .iter()
+                .map(|option| option.option_id.0.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(option_ids("mcp__acp__Read").contains(&"allow_reads_turn".to_string()));
+        assert!(option_ids("Grep").contains(&"allow_reads_turn".to_string()));
+        assert!(!option_ids("Write").contains(&"allow_reads_turn".to_string()));
+        assert!(!option_ids("Bash").contains(&"allow_reads_turn".to_string()));
+
+        let selected = RequestPermissionOutcome::Selected(SelectedPermissionOutcome::new(
+            PermissionOptionId::new("allow_reads_turn"),
+        ));
+        assert_eq!(
+            parse_permission_response(selected),
+            PermissionOutcome::AllowReadsForTurn
+        );
+    }
+
+    #[test]
+    fn test_options_distinguished_by_scope() {
+        let request = PermissionRequestBuilder::new("session-1", "toolu_1", "Read", json!({}))
+            .build_request();
+        let json = serde_json::to_value(&request).unwrap();
+        let options: Vec<(String, String)> = json["options"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|option| {
+                (
+                    option["kind"].as_str().unwrap().to_string(),
+                    option["_meta"][OPTION_SCOPE_META_KEY]
+                        .as_str()
+                        .unwrap()
+                        .to_string(),
+                )
+            })
+            .collect();
+
+        // No two options share both kind and scope
+        for (index, option) in options.iter().enumerate() {
+            assert!(!options[index + 1..].contains(option), "{option:?}");
+        }
+        assert!(options.contains(&("allow_once".to_string(), "turn".to_string())));
+        assert!(options.contains(&("reject_always".to_string(), "session".to_string())));
+        assert!(options.contains(&("reject_always".to_string(), "always".to_string())));
+    }
+
+    #[test]
+    fn test_allow_always_options_by_granularity() {
+        let builder = PermissionRequestBuilder::new(
+            "session-1",
+            "toolu_1",
+            "Bash",
+            json!({"command": "git push origin main"}),
+        )
+        .allow_always_rules(vec![
+            "Bash(git push:*)".to_string(),
+            "Bash(git:*)".to_string(),
+        ]);
+        let request = builder.build_request();
+        let options: Vec<(String, String)> = request
+            .options
+            .iter()
+            .filter(|option| option.kind == PermissionOptionKind::AllowAlways)
+            .map(|option| (option.option_id.0.to_string(), option.name.clone()))
+            .collect();
+        assert_eq!(
+            options,
+            vec![
+                (
+                    "allow_always:0".to_string(),
+                    "Always Allow Bash(git push:*)".to_string()
+                ),
+                (
+                    "allow_always:1".to_string(),
+                    "Always Allow Bash(git:*)".to_string()
+                ),
+            ]
+        );
+
+        let select = |option_id: &str| {
+            builder.parse_response(RequestPermissionOutcome::Selected(
+                SelectedPermissionOutcome::new(PermissionOptionId::new(option_id.to_string())),
+            ))
+        };
+        assert_eq!(
+            select("allow_always:1"),
+            PermissionOutcome::AllowAlwaysRule("Bash(git:*)".to_string())
+        );
+        assert_eq!(
+            select("allow_always:0"),
+            PermissionOutcome::AllowAlwaysRule("Bash(git push:*)".to_string())
+        );
+        assert_eq!(select("allow_always:7"), PermissionOutcome::Rejected);
+        assert_eq!(select("allow_always"), PermissionOutcome::AllowAlways);
+
+        // A single rule keeps the generic option
+        let request = PermissionRequestBuilder::new("session-1", "toolu_1", "WebSearch", json!({}))
+            .allow_always_rules(vec!["WebSearch".to_string()])
+            .build_request();
+        assert_eq!(request.options[0].option_id.0.as_ref(), "allow_always");
+    }
+
     #[test]
     fn test_format_tool_title_long_command() {
         let long_cmd = "echo 'this is a very long command that should be truncated'";
@@ -289,6 +623,22 @@ mod tests {
             parse_permission_response(selected_reject),
             PermissionOutcome::Rejected
         );
+
+        let selected_reject_always = RequestPermissionOutcome::Selected(
+            SelectedPermissionOutcome::new(PermissionOptionId::new("reject_always")),
+        );
+        assert_eq!(
+            parse_permission_response(selected_reject_always),
+            PermissionOutcome::RejectAlways
+        );
+
+        let selected_reject_session = RequestPermissionOutcome::Selected(
+            SelectedPermissionOutcome::new(PermissionOptionId::new("reject_session")),
+        );
+        assert_eq!(
+            parse_permission_response(selected_reject_session),
+            PermissionOutcome::RejectForSession
+        );
     }
 
     #[test]
@@ -300,6 +650,38 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_unanswered_request_times_out() {
+        let pending = std::future::pending::<Result<(), sacp::Error>>();
+        let result = await_response(pending, Some(Duration::from_millis(10))).await;
+        assert!(matches!(result, Err(AgentError::PermissionTimeout(10))));
+    }
+
+    #[tokio::test]
+    async fn test_error_response_is_protocol_error() {
+        let failed = std::future::ready(Err::<(), _>(sacp::Error::method_not_found()));
+        match await_response(failed, None).await {
+            Err(AgentError::ProtocolError(message)) => assert!(message.contains("-32601")),
+            other => panic!("Expected ProtocolError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_lost_connection_is_connection_closed() {
+        assert!(matches!(
+            classify_request_error(Some(-32603), "Connection closed by peer".to_string()),
+            AgentError::ConnectionClosed(_)
+        ));
+        assert!(matches!(
+            classify_request_error(None, "outgoing channel closed".to_string()),
+            AgentError::ConnectionClosed(_)
+        ));
+        assert!(matches!(
+            classify_request_error(Some(-32602), "Invalid params".to_string()),
+            AgentError::ProtocolError(_)
+        ));
+    }
+
     #[test]
     fn test_permission_outcome_unknown() {
         // Unknown option should be treated as rejected
@@ -0,0 +1,465 @@
+//! Recoverable session permission state
+//!
+//! Approvals given during a session ("Always Allow", "Reject for Session",
+//! use-limited grants) only live in memory, so an agent that restarts would
+//! ask for all of them again. A session's mode and runtime rules are saved
+//! as a small versioned JSON file named after the session id and restored
+//! when a session with that id is created again. Files not written for
+//! [`PERMISSION_STATE_MAX_AGE`] are removed at startup.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, RwLock};
+
+use super::mode_state::write_atomically;
+use super::permission::{PermissionHandler, PermissionMode};
+use crate::settings::PermissionChecker;
+
+/// Current version of the saved state format
+///
+/// Fields may be added without a new version, as long as older files still
+/// deserialize (`#[serde(default)]`); files with a newer version are ignored.
+pub const PERMISSION_STATE_VERSION: u32 = 1;
+
+/// Directory name inside `~/.claude/`
+const PERMISSION_STATE_DIR: &str = "acp-permission-state";
+
+/// How long a saved state is kept after it was last written
+pub const PERMISSION_STATE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// A session's permission mode and the rules granted during it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionState {
+    /// Format version, [`PERMISSION_STATE_VERSION`] when written
+    pub version: u32,
+    /// Name of the active permission mode
+    pub mode: String,
+    /// Allow rules added in the session ("Always Allow")
+    #[serde(default)]
+    pub allow_rules: Vec<String>,
+    /// Deny rules added in the session that outlast `/clear`
+    #[serde(default)]
+    pub deny_rules: Vec<String>,
+    /// Deny rules added with "Reject for Session", dropped on `/clear`
+    #[serde(default)]
+    pub session_deny_rules: Vec<String>,
+    /// Allow rules limited to a number of uses, with the uses left
+    #[serde(default)]
+    pub use_limited_allow_rules: Vec<UseLimitedRuleState>,
+}
+
+/// A use-limited allow rule and how many uses it has left
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UseLimitedRuleState {
+    /// The rule as written, e.g. `Bash(rm:*)`
+    pub rule: String,
+    /// Uses left when the state was captured
+    pub remaining: u32,
+}
+
+impl PermissionState {
+    /// Capture the mode and the checker's runtime rules
+    ///
+    /// Rules from settings files are not included; they are loaded again
+    /// anyway.
+    pub fn capture(mode: PermissionMode, checker: &PermissionChecker) -> Self {
+        let (session_deny_rules, deny_rules) = checker
+            .runtime_deny_rules()
+            .into_iter()
+            .partition(|rule| checker.is_session_deny_rule(rule));
+        Self {
+            version: PERMISSION_STATE_VERSION,
+            mode: mode.as_str().to_string(),
+            allow_rules: checker.runtime_allow_rules(),
+            deny_rules,
+            session_deny_rules,
+            use_limited_allow_rules: checker
+                .use_limited_allow_rules()
+                .into_iter()
+                .map(|(rule, remaining)| UseLimitedRuleState { rule, remaining })
+                .collect(),
+        }
+    }
+
+    /// The saved mode, if it is one this version knows
+    pub fn permission_mode(&self) -> Option<PermissionMode> {
+        PermissionMode::parse(&self.mode)
+    }
+
+    /// Add the saved runtime rules to `checker`
+    pub fn restore_rules(&self, checker: &mut PermissionChecker) {
+        for rule in &self.allow_rules {
+            checker.add_allow_rule(rule);
+        }
+        for rule in &self.deny_rules {
+            checker.add_deny_rule(rule);
+        }
+        for rule in &self.session_deny_rules {
+            checker.add_session_deny_rule(rule);
+        }
+        for limited in &self.use_limited_allow_rules {
+            checker.add_allow_rule_with_uses(&limited.rule, limited.remaining);
+        }
+    }
+}
+
+/// Default directory for saved state (`~/.claude/acp-permission-state/`)
+pub fn default_permission_state_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".claude").join(PERMISSION_STATE_DIR))
+}
+
+/// The state file for a session, or `None` if the id can't be a file name
+fn permission_state_path(dir: &Path, session_id: &str) -> Option<PathBuf> {
+    let valid = !session_id.is_empty()
+        && session_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    valid.then(|| dir.join(format!("{session_id}.json")))
+}
+
+/// Load the saved permission state of a session
+///
+/// Returns `None` if nothing was saved for the session, or the file is
+/// corrupt or from a newer version; those are logged and otherwise ignored.
+pub fn load_permission_state(dir: &Path, session_id: &str) -> Option<PermissionState> {
+    let path = permission_state_path(dir, session_id)?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            tracing::warn!("Failed to read permission state {:?}: {}", path, e);
+            return None;
+        }
+    };
+
+    let value: serde_json::Value = match serde_json::from_str(&content) {
+        Ok(value) => value,
+        Err(e) => {
+            tracing::warn!("Ignoring corrupt permission state {:?}: {}", path, e);
+            return None;
+        }
+    };
+    match value.get("version").and_then(serde_json::Value::as_u64) {
+        Some(version) if version <= u64::from(PERMISSION_STATE_VERSION) => {}
+        version => {
+            tracing::warn!(
+                "Ignoring permission state {:?} with unsupported version {:?}",
+                path,
+                version
+            );
+            return None;
+        }
+    }
+    serde_json::from_value(value)
+        .inspect_err(|e| tracing::warn!("Ignoring corrupt permission state {:?}: {}", path, e))
+        .ok()
+}
+
+/// Save a session's permission state
+///
+/// Written to a unique temporary name and renamed, so a crash mid-write
+/// leaves the previous state in place.
+///
+/// # Errors
+///
+/// Returns an error if the session id can't be used as a file name or the
+/// file can't be written.
+pub fn save_permission_state(
+    dir: &Path,
+    session_id: &str,
+    state: &PermissionState,
+) -> io::Result<()> {
+    let path = permission_state_path(dir, session_id).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("session id {session_id:?} can't be used as a file name"),
+        )
+    })?;
+    fs::create_dir_all(dir)?;
+
+    let content = serde_json::to_string_pretty(state).map_err(io::Error::other)?;
+    write_atomically(&path, &content)
+}
+
+/// Remove saved states last written more than `max_age` ago
+///
+/// Only files named like a saved state are considered. Returns how many
+/// were removed; a file that can't be inspected or removed is skipped.
+///
+/// # Errors
+///
+/// Returns an error if `dir` exists but can't be listed.
+pub fn remove_stale_permission_states(dir: &Path, max_age: Duration) -> io::Result<usize> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+
+    let now = SystemTime::now();
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_state_file = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| {
+                permission_state_path(dir, stem).as_deref() == Some(path.as_path())
+            });
+        if !is_state_file {
+            continue;
+        }
+        let stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| now.duration_since(modified).is_ok_and(|age| age > max_age));
+        if !stale {
+            continue;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => removed += 1,
+            Err(e) => tracing::warn!("Failed to remove stale permission state {:?}: {}", path, e),
+        }
+    }
+    Ok(removed)
+}
+
+/// Where one session's permission state is saved
+///
+/// Writes happen on the blocking thread pool, one at a time, and are
+/// skipped when the state hasn't changed since the last one.
+#[derive(Debug)]
+pub struct PermissionStateStore {
+    dir: PathBuf,
+    session_id: String,
+    /// The state last loaded or written; also serializes the writes
+    last_saved: Mutex<Option<PermissionState>>,
+}
+
+impl PermissionStateStore {
+    /// Create a store for `session_id`'s state in `dir`
+    pub fn new(dir: impl Into<PathBuf>, session_id: impl Into<String>) -> Self {
+        Self {
+            dir: dir.into(),
+            session_id: session_id.into(),
+            last_saved: Mutex::new(None),
+        }
+    }
+
+    /// Load the saved state, see [`load_permission_state`]
+    pub fn load(&mut self) -> Option<PermissionState> {
+        let state = load_permission_state(&self.dir, &self.session_id);
+        self.last_saved.get_mut().clone_from(&state);
+        state
+    }
+
+    /// Save the current mode and runtime rules, if they changed
+    ///
+    /// The state is captured once earlier writes are done, so the file
+    /// always ends up with the latest state. Failures are logged.
+    pub async fn save(
+        &self,
+        permission: &RwLock<PermissionHandler>,
+        checker: &RwLock<PermissionChecker>,
+    ) {
+        let mut last_saved = self.last_saved.lock().await;
+        let mode = permission.read().await.mode();
+        let state = PermissionState::capture(mode, &*checker.read().await);
+        if last_saved.as_ref() == Some(&state) {
+            return;
+        }
+
+        let dir = self.dir.clone();
+        let session_id = self.session_id.clone();
+        let to_save = state.clone();
+        let result =
+            tokio::task::spawn_blocking(move || save_permission_state(&dir, &session_id, &to_save))
+                .await
+                .unwrap_or_else(|e| Err(io::Error::other(e)));
+        match result {
+            Ok(()) => *last_saved = Some(state),
+            Err(e) => tracing::warn!(
+                session_id = %self.session_id,
+                error = %e,
+                "Failed to save permission state to {:?}",
+                self.dir
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permission_state_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut checker = PermissionChecker::new(Default::default(), "/tmp");
+        checker.add_allow_rule("Bash(npm test:*)");
+        checker.add_allow_rule("Edit(/tmp/src/**)");
+        checker.add_deny_rule("Bash(git push:*)");
+        checker.add_session_deny_rule("Bash(curl:*)");
+        checker.add_allow_rule_with_uses("Bash(rm:*)", 3);
+
+        let state = PermissionState::capture(PermissionMode::AcceptEdits, &checker);
+        assert_eq!(state.deny_rules, ["Bash(git push:*)"]);
+        assert_eq!(state.session_deny_rules, ["Bash(curl:*)"]);
+        save_permission_state(dir.path(), "session-1", &state).unwrap();
+        let loaded = load_permission_state(dir.path(), "session-1").unwrap();
+        assert_eq!(loaded, state);
+        assert_eq!(loaded.permission_mode(), Some(PermissionMode::AcceptEdits));
+
+        // The restored checker decides like the original
+        let mut restored = PermissionChecker::new(Default::default(), "/tmp");
+        loaded.restore_rules(&mut restored);
+        assert_eq!(
+            PermissionState::capture(PermissionMode::AcceptEdits, &restored),
+            state
+        );
+        for (tool_name, input) in [
+            ("Bash", serde_json::json!({"command": "npm test --watch"})),
+            ("Bash", serde_json::json!({"command": "git push origin"})),
+            ("Bash", serde_json::json!({"command": "curl example.com"})),
+            ("Bash", serde_json::json!({"command": "rm a.txt"})),
+            ("Edit", serde_json::json!({"file_path": "/tmp/src/lib.rs"})),
+        ] {
+            assert_eq!(
+                restored.check_permission(tool_name, &input).decision,
+                checker.check_permission(tool_name, &input).decision,
+                "{input}"
+            );
+        }
+
+        // Restored session denies are still dropped on `/clear`
+        restored.clear_session_deny_rules();
+        assert_eq!(restored.runtime_deny_rules(), ["Bash(git push:*)"]);
+
+        // Other sessions have no state
+        assert_eq!(load_permission_state(dir.path(), "session-2"), None);
+    }
+
+    #[test]
+    fn test_permission_state_versions() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("session-1.json");
+
+        // Older files lacking newer fields still load
+        fs::write(&path, r#"{"version": 1, "mode": "plan"}"#).unwrap();
+        let state = load_permission_state(dir.path(), "session-1").unwrap();
+        assert_eq!(state.permission_mode(), Some(PermissionMode::Plan));
+        assert!(state.allow_rules.is_empty());
+
+        // Newer, unversioned and corrupt files are ignored
+        for content in [
+            r#"{"version": 99, "mode": "plan"}"#,
+            r#"{"mode": "plan"}"#,
+            "{not json",
+        ] {
+            fs::write(&path, content).unwrap();
+            assert_eq!(load_permission_state(dir.path(), "session-1"), None);
+        }
+    }
+
+    #[test]
+    fn test_session_ids_that_are_not_file_names_are_rejected() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = PermissionState::capture(
+            PermissionMode::Default,
+            &PermissionChecker::new(Default::default(), "/tmp"),
+        );
+        for session_id in ["", "../escape", "a/b"] {
+            assert!(save_permission_state(dir.path(), session_id, &state).is_err());
+            assert_eq!(load_permission_state(dir.path(), session_id), None);
+        }
+    }
+
+    #[test]
+    fn test_stale_states_are_removed() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let state = PermissionState::capture(
+            PermissionMode::Default,
+            &PermissionChecker::new(Default::default(), "/tmp"),
+        );
+        save_permission_state(dir.path(), "old", &state).unwrap();
+        save_permission_state(dir.path(), "recent", &state).unwrap();
+        fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        let long_ago = SystemTime::now() - 2 * PERMISSION_STATE_MAX_AGE;
+        for name in ["old.json", "notes.txt"] {
+            fs::File::options()
+                .write(true)
+                .open(dir.path().join(name))
+                .unwrap()
+                .set_modified(long_ago)
+                .unwrap();
+        }
+
+        assert_eq!(
+            remove_stale_permission_states(dir.path(), PERMISSION_STATE_MAX_AGE).unwrap(),
+            1
+        );
+        assert_eq!(load_permission_state(dir.path(), "old"), None);
+        assert_eq!(load_permission_state(dir.path(), "recent"), Some(state));
+        // Files that aren't saved states are left alone
+        assert!(dir.path().join("notes.txt").exists());
+
+        // A directory that was never created has nothing to remove
+        let missing = dir.path().join("missing");
+        assert_eq!(
+            remove_stale_permission_states(&missing, PERMISSION_STATE_MAX_AGE).unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_store_writes_only_changed_state() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("session-1.json");
+        let permission = RwLock::new(PermissionHandler::with_mode(PermissionMode::Default));
+        let checker = RwLock::new(PermissionChecker::new(Default::default(), "/tmp"));
+        checker
+            .write()
+            .await
+            .add_allow_rule_with_uses("Bash(rm:*)", 2);
+
+        let store = PermissionStateStore::new(dir.path(), "session-1");
+        store.save(&permission, &checker).await;
+        assert!(path.exists());
+
+        // An unchanged state isn't written again
+        fs::remove_file(&path).unwrap();
+        store.save(&permission, &checker).await;
+        assert!(!path.exists());
+
+        // Spending a use changes the state
+        {
+            let checker = checker.read().await;
+            let result =
+                checker.check_permission("Bash", &serde_json::json!({"command": "rm a.txt"}));
+            assert!(checker.spend_use(&result));
+        }
+        store.save(&permission, &checker).await;
+        let saved = load_permission_state(dir.path(), "session-1").unwrap();
+        assert_eq!(
+            saved.use_limited_allow_rules,
+            [UseLimitedRuleState {
+                rule: "Bash(rm:*)".to_string(),
+                remaining: 1,
+            }]
+        );
+
+        // A store that loaded the state doesn't write it back unchanged
+        let mut reopened = PermissionStateStore::new(dir.path(), "session-1");
+        assert_eq!(reopened.load(), Some(saved));
+        fs::remove_file(&path).unwrap();
+        reopened.save(&permission, &checker).await;
+        assert!(!path.exists());
+    }
+}
@@ -6,12 +6,12 @@
 use dashmap::DashMap;
 use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::OnceLock;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Instant;
-use tokio::sync::broadcast;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, Notify, broadcast};
 
 use claude_code_agent_sdk::types::config::PermissionMode as SdkPermissionMode;
 use claude_code_agent_sdk::types::mcp::McpSdkServerConfig;
@@ -27,17 +27,27 @@ use sacp::schema::{
 use tokio::sync::RwLock;
 use tracing::instrument;
 
+use crate::agent::available_commands_notification;
+use crate::command_safety::SafeCommandClassifier;
 use crate::converter::NotificationConverter;
 use crate::hooks::{HookCallbackRegistry, create_post_tool_use_hook, create_pre_tool_use_hook};
 use crate::mcp::AcpMcpServer;
 use crate::permissions::create_can_use_tool_callback;
+use crate::permissions::strategies::PermissionModeStrategy;
 use crate::settings::{PermissionChecker, SettingsManager};
 use crate::terminal::TerminalClient;
 use crate::types::{AgentConfig, AgentError, NewSessionMeta, Result};
+use crate::utils::project_plans_dir;
 
 use super::BackgroundProcessManager;
 use super::background_processes::BackgroundTerminal;
-use super::permission::{PermissionHandler, PermissionMode};
+use super::handoff_cache::HandoffCache;
+use super::mode_state::{load_last_permission_mode, save_last_permission_mode};
+use super::permission::{
+    ExitPlanModeOption, PermissionHandler, PermissionMode, resolve_initial_permission_mode,
+};
+use super::permission_state::{PermissionState, PermissionStateStore};
+use super::tool_call_control::ToolCallControl;
 use super::usage::UsageTracker;
 
 /// Get the list of tools that should be replaced by ACP MCP server tools.
@@ -69,6 +79,9 @@ pub struct Session {
     pub session_id: String,
     /// Working directory for this session
     pub cwd: PathBuf,
+    /// Workspace root whose settings and permission rules apply
+    /// (the cwd unless the session request names another workspace)
+    pub workspace: PathBuf,
     /// The Claude client for this session
     client: RwLock<ClaudeClient>,
     /// Permission handler for tool execution (wrapped in Arc for can_use_tool callback)
@@ -103,24 +116,79 @@ pub struct Session {
     /// PreToolUse hook saves authorized results here, can_use_tool callback checks it
     /// Key: JSON string of tool_input, Value: true if authorized
     /// Only stores authorized results (denied tools don't execute, no need to cache)
-    permission_cache: Arc<DashMap<String, bool>>,
+    permission_cache: Arc<HandoffCache<bool>>,
     /// Cache for tool_use_id by tool_input
     /// PreToolUse hook caches this when Ask decision is made
     /// can_use_tool callback uses this to get tool_use_id when CLI doesn't provide it
     /// Key: stable cache key of tool_input, Value: tool_use_id
-    tool_use_id_cache: Arc<DashMap<String, String>>,
+    tool_use_id_cache: Arc<HandoffCache<String>>,
+    /// Maximum entries kept in each of the caches above (0 = unbounded),
+    /// shared with them
+    cache_max_entries: Arc<AtomicUsize>,
     /// Whether this session has been cancelled by user
     /// Set to true when cancel() is called, reset to false at start of new prompt
     /// Used to distinguish user cancellation from execution errors
     cancelled: AtomicBool,
+    /// Emergency stop and pause, shared with the PreToolUse hook so every tool
+    /// call is held. Unlike a mode change, an emergency stop also aborts
+    /// permission prompts already in flight
+    tool_call_control: Arc<ToolCallControl>,
+    /// Window in milliseconds during which identical permission prompts are
+    /// throttled (0 = rate limiting disabled)
+    prompt_rate_limit_ms: AtomicU64,
+    /// When each recent permission prompt was sent
+    /// Key: tool name + stable cache key of tool_input
+    recent_prompts: DashMap<String, Instant>,
+    /// Permission prompts allowed to be outstanding at once (0 = unlimited)
+    max_concurrent_prompts: AtomicUsize,
+    /// Permission prompts currently outstanding
+    active_prompts: AtomicUsize,
+    /// Wakes the next queued prompt when an outstanding one finishes
+    prompt_slot_notify: Notify,
+    /// Queue of prompts waiting for a slot; the fair mutex admits them in arrival order
+    prompt_queue: Mutex<()>,
+    /// File the permission mode is saved to when it changes (`None` = not remembered)
+    mode_state_file: Option<PathBuf>,
+    /// Where the session's permission state is saved (`None` = not saved)
+    permission_state_store: Option<Arc<PermissionStateStore>>,
+    /// Observers notified when the permission mode changes
+    mode_change_callbacks: std::sync::Mutex<Vec<ModeChangeCallback>>,
+}
+
+/// Observer of permission mode changes, called with the old and new modes
+///
+/// Registered with [`Session::on_mode_change`].
+pub type ModeChangeCallback = Arc<dyn Fn(PermissionMode, PermissionMode) + Send + Sync>;
+
+/// A permission prompt's place among the session's outstanding prompts
+///
+/// Returned by [`Session::acquire_prompt_slot`]. Dropping it, whether the
+/// prompt was answered, timed out or was abandoned, lets the next queued
+/// prompt through.
+#[must_use = "the slot is released as soon as it is dropped"]
+pub struct PromptSlot<'a> {
+    session: &'a Session,
+}
+
+impl Drop for PromptSlot<'_> {
+    fn drop(&mut self) {
+        self.session.active_prompts.fetch_sub(1, Ordering::AcqRel);
+        self.session.prompt_slot_notify.notify_waiters();
+    }
 }
 
-/// Generate a stable cache key from JSON value
+/// Generate a stable cache key for a tool invocation
 ///
 /// JSON serialization order is not guaranteed to be stable.
 /// This function canonicalizes the JSON by sorting object keys using BTreeMap,
 /// ensuring identical content always produces the same cache key.
-pub fn stable_cache_key(tool_input: &serde_json::Value) -> String {
+///
+/// The key is the canonical serialization of `[tool_name, tool_input]` rather
+/// than a digest, so it is deterministic across processes and two distinct
+/// invocations can never collide. The ACP prefix is stripped from the tool
+/// name so `Bash` and `mcp__acp__Bash` share entries, while identical inputs
+/// to different tools (e.g. `Read` and `Edit` on one path) do not.
+pub fn stable_cache_key(tool_name: &str, tool_input: &serde_json::Value) -> String {
     fn canonicalize(value: &serde_json::Value) -> serde_json::Value {
         match value {
             serde_json::Value::Object(map) => {
@@ -137,7 +205,14 @@ pub fn stable_cache_key(tool_input: &serde_json::Value) -> String {
             other => other.clone(),
         }
     }
-    canonicalize(tool_input).to_string()
+    let tool_name = tool_name
+        .strip_prefix(crate::mcp::ACP_TOOL_PREFIX)
+        .unwrap_or(tool_name);
+    serde_json::Value::Array(vec![
+        serde_json::Value::String(tool_name.to_string()),
+        canonicalize(tool_input),
+    ])
+    .to_string()
 }
 
 impl Session {
@@ -152,6 +227,25 @@ impl Session {
     /// * `cwd` - Working directory
     /// * `config` - Agent configuration from environment
     /// * `meta` - Session metadata from the new session request
+    ///
+    /// The session starts in the mode requested in `meta`, else the settings
+    /// `defaultMode`, else the mode last switched to (see
+    /// [`AgentConfig::permission_mode_state_file`]), else
+    /// [`PermissionMode::default()`]. Use [`Session::new_with_default_mode`]
+    /// to change that last fallback.
+    pub fn new(
+        session_id: String,
+        cwd: PathBuf,
+        config: &AgentConfig,
+        meta: Option<&NewSessionMeta>,
+    ) -> Result<Arc<Self>> {
+        Self::new_with_default_mode(session_id, cwd, config, meta, PermissionMode::default())
+    }
+
+    /// Create a new session with a custom fallback permission mode
+    ///
+    /// `default_mode` is used only when neither the session request nor the
+    /// settings choose a mode; see [`resolve_initial_permission_mode`].
     #[instrument(
         name = "session_create",
         skip(config, meta),
@@ -161,11 +255,12 @@ impl Session {
             has_meta = meta.is_some(),
         )
     )]
-    pub fn new(
+    pub fn new_with_default_mode(
         session_id: String,
         cwd: PathBuf,
         config: &AgentConfig,
         meta: Option<&NewSessionMeta>,
+        default_mode: PermissionMode,
     ) -> Result<Arc<Self>> {
         let start_time = Instant::now();
 
@@ -178,11 +273,17 @@ impl Session {
         // Create hook callback registry
         let hook_callback_registry = Arc::new(HookCallbackRegistry::new());
 
+        // Sessions in one process may belong to different workspaces; each
+        // resolves settings and rules from its own
+        let workspace = meta
+            .and_then(NewSessionMeta::get_workspace_root)
+            .map_or_else(|| cwd.clone(), Path::to_path_buf);
+
         // Create permission checker for hooks
         // Load settings from ~/.claude/settings.json, .claude/settings.json, etc.
-        let settings_manager = SettingsManager::new(&cwd)
+        let settings_manager = SettingsManager::new(&workspace)
             .unwrap_or_else(|e| {
-                tracing::warn!("Failed to load settings manager from cwd: {}. Using default settings.", e);
+                tracing::warn!("Failed to load settings from workspace {:?}: {}. Using default settings.", workspace, e);
                 // Fallback: try to load settings from home directory
                 if let Some(home) = dirs::home_dir() {
                     tracing::info!("Attempting to load settings from home directory");
@@ -198,17 +299,124 @@ impl Session {
             });
         // Create shared permission checker that will be used by both hook and permission handler
         // This ensures that runtime rule changes (e.g., "Always Allow") are reflected in both places
-        let permission_checker = Arc::new(RwLock::new(PermissionChecker::new(
-            settings_manager.settings().clone(),
-            &cwd,
-        )));
+        // Tool paths and relative rules resolve where the session works; the
+        // workspace only supplies the settings files
+        let mut checker = PermissionChecker::from_layers(settings_manager.layers(), &cwd)
+            .with_project_dir(&workspace);
+        // A session recreated with a saved id (e.g. after a restart) gets its
+        // in-session approvals back
+        let mut permission_state_store = config
+            .permission_state_dir
+            .as_ref()
+            .map(|dir| PermissionStateStore::new(dir, session_id.as_str()));
+        let restored_state = permission_state_store
+            .as_mut()
+            .and_then(PermissionStateStore::load);
+        let permission_state_store = permission_state_store.map(Arc::new);
+        if let Some(ref state) = restored_state {
+            tracing::info!(
+                session_id = %session_id,
+                mode = %state.mode,
+                allow_rules = state.allow_rules.len(),
+                deny_rules = state.deny_rules.len(),
+                use_limited_allow_rules = state.use_limited_allow_rules.len(),
+                "Restoring saved permission state"
+            );
+            state.restore_rules(&mut checker);
+        }
+        let permission_checker = Arc::new(RwLock::new(checker));
 
         // Create PermissionHandler with shared PermissionChecker
         // This ensures both pre_tool_use_hook and can_use_tool callback use the same rules
         // PermissionHandler uses AcceptEdits mode (compatible with root, allows all tools)
-        let permission_handler = Arc::new(RwLock::new(PermissionHandler::with_checker(
-            permission_checker.clone(),
-        )));
+        let mut permission_handler = PermissionHandler::with_checker(permission_checker.clone());
+        // Allow sandboxed environments (no home directory) to configure where
+        // Plan mode may write plan files
+        if let Some(plans_dir) = settings_manager.plans_directory() {
+            tracing::info!(
+                session_id = %session_id,
+                plans_dir = ?plans_dir,
+                "Using custom plans directory from settings"
+            );
+            permission_handler.set_plans_dir(Some(plans_dir));
+        }
+        // Plans kept in the project (`.claude/plans/`) work alongside the home directory
+        permission_handler.set_project_plans_dir(Some(project_plans_dir(&workspace)));
+        let disabled_safe_commands = settings_manager.disable_safe_commands();
+        if !disabled_safe_commands.is_empty() {
+            tracing::info!(
+                session_id = %session_id,
+                disabled = ?disabled_safe_commands,
+                "Disabling built-in safe commands from settings"
+            );
+            permission_handler.set_disabled_safe_commands(disabled_safe_commands.to_vec());
+        }
+        let extra_dangerous_commands = settings_manager.extra_dangerous_commands();
+        if !extra_dangerous_commands.is_empty() {
+            tracing::info!(
+                session_id = %session_id,
+                commands = ?extra_dangerous_commands,
+                "Requiring confirmation for extra dangerous commands from settings"
+            );
+            permission_handler.set_extra_dangerous_commands(extra_dangerous_commands.to_vec());
+        }
+        if settings_manager.prompt_for_network_commands() {
+            tracing::info!(
+                session_id = %session_id,
+                "Requiring confirmation for network commands from settings"
+            );
+            permission_handler.set_prompt_for_network_commands(true);
+        }
+        if let Some(max_len) = settings_manager.max_auto_approve_command_len() {
+            permission_handler.set_max_auto_approve_command_len(Some(max_len));
+        }
+        if let Some(timeout) = settings_manager.allow_rule_idle_timeout() {
+            permission_handler.set_allow_rule_idle_timeout(Some(timeout));
+        }
+        if settings_manager.strict_accept_edits() {
+            tracing::info!(
+                session_id = %session_id,
+                "Using strict AcceptEdits mode from settings"
+            );
+            permission_handler.set_strict_accept_edits(true);
+        }
+        if let Some(message) = settings_manager.dont_ask_deny_message() {
+            permission_handler.set_dont_ask_deny_message(Some(message.to_string()));
+        }
+        if settings_manager.confirm_empty_plan() {
+            permission_handler.set_confirm_empty_plan(true);
+        }
+        // A restored session resumes in its own saved mode unless the client
+        // asks for another; like the mode state file, it never brings back
+        // BypassPermissions. The mode last used anywhere only applies when
+        // settings don't set a default.
+        let restored_mode = restored_state
+            .map(|state| state.mode)
+            .filter(|mode| mode != PermissionMode::BypassPermissions.as_str());
+        let last_used_mode = config
+            .permission_mode_state_file
+            .as_deref()
+            .and_then(load_last_permission_mode);
+        let initial_mode = resolve_initial_permission_mode(
+            meta.and_then(NewSessionMeta::get_permission_mode)
+                .or(restored_mode.as_deref()),
+            settings_manager
+                .settings()
+                .permissions
+                .as_ref()
+                .and_then(|p| p.default_mode.as_deref()),
+            last_used_mode.as_deref(),
+            default_mode,
+        );
+        if initial_mode != permission_handler.mode() {
+            tracing::info!(
+                session_id = %session_id,
+                mode = %initial_mode.as_str(),
+                "Starting session in resolved permission mode"
+            );
+            permission_handler.set_mode(initial_mode);
+        }
+        let permission_handler = Arc::new(RwLock::new(permission_handler));
 
         // Create shared connection_cx_lock for hook permission requests
         let connection_cx_lock: Arc<OnceLock<JrConnectionCx<AgentToClient>>> =
@@ -216,12 +424,19 @@ impl Session {
 
         // Create shared permission_cache for hook-to-callback communication
         // PreToolUse hook caches permission results, can_use_tool callback checks it
-        let permission_cache: Arc<DashMap<String, bool>> = Arc::new(DashMap::new());
+        // Both caches are bounded by the same limit, set from settings
+        let cache_max_entries = Arc::new(AtomicUsize::new(0));
+        let permission_cache = Arc::new(HandoffCache::new(Arc::clone(&cache_max_entries)));
 
         // Create shared tool_use_id_cache for hook-to-callback tool_use_id passing
         // PreToolUse hook caches tool_use_id when Ask decision is made
         // can_use_tool callback uses this when CLI doesn't provide tool_use_id
-        let tool_use_id_cache: Arc<DashMap<String, String>> = Arc::new(DashMap::new());
+        let tool_use_id_cache = Arc::new(HandoffCache::new(Arc::clone(&cache_max_entries)));
+
+        // Emergency stops must reach the hook too, since it approves calls on its own
+        let tool_call_control = Arc::new(ToolCallControl::new());
+        tool_call_control
+            .set_denial_escalation_threshold(settings_manager.denial_escalation_threshold());
 
         // Create hooks with shared permission checker and handler
         let pre_tool_use_hook = create_pre_tool_use_hook(
@@ -229,10 +444,15 @@ impl Session {
             session_id.clone(),
             Some(permission_checker.clone()),
             permission_handler.clone(),
+            tool_call_control.clone(),
             permission_cache.clone(),
             tool_use_id_cache.clone(),
+            permission_state_store.clone(),
+        );
+        let post_tool_use_hook = create_post_tool_use_hook(
+            hook_callback_registry.clone(),
+            Some(permission_checker.clone()),
         );
-        let post_tool_use_hook = create_post_tool_use_hook(hook_callback_registry.clone());
 
         // Build hooks map
         let mut hooks_map: HashMap<HookEvent, Vec<HookMatcher>> = HashMap::new();
@@ -427,6 +647,7 @@ impl Session {
         let session = Self {
             session_id,
             cwd,
+            workspace,
             client: RwLock::new(client),
             permission: permission_handler,
             usage_tracker: UsageTracker::new(),
@@ -443,8 +664,22 @@ impl Session {
             cancel_sender: broadcast::channel(1).0,
             permission_cache,
             tool_use_id_cache,
+            cache_max_entries,
             cancelled: AtomicBool::new(false),
+            tool_call_control,
+            prompt_rate_limit_ms: AtomicU64::new(0),
+            recent_prompts: DashMap::new(),
+            max_concurrent_prompts: AtomicUsize::new(0),
+            active_prompts: AtomicUsize::new(0),
+            prompt_slot_notify: Notify::new(),
+            prompt_queue: Mutex::new(()),
+            mode_state_file: config.permission_mode_state_file.clone(),
+            permission_state_store,
+            mode_change_callbacks: std::sync::Mutex::new(Vec::new()),
         };
+        session.set_prompt_rate_limit(settings_manager.prompt_rate_limit());
+        session.set_cache_max_entries(settings_manager.permission_cache_max_entries());
+        session.set_max_concurrent_prompts(settings_manager.max_concurrent_prompts());
 
         // Wrap in Arc
         let session_arc = Arc::new(session);
@@ -528,12 +763,12 @@ impl Session {
         self.connection_cx_lock.get()
     }
 
-    /// Cache a permission result for a tool_input
+    /// Cache a permission result for a tool invocation
     ///
     /// Called by PreToolUse hook after user grants permission.
     /// The can_use_tool callback checks this cache before sending permission requests.
-    pub fn cache_permission(&self, tool_input: &serde_json::Value, allowed: bool) {
-        let key = stable_cache_key(tool_input);
+    pub fn cache_permission(&self, tool_name: &str, tool_input: &serde_json::Value, allowed: bool) {
+        let key = stable_cache_key(tool_name, tool_input);
         tracing::debug!(
             key_len = key.len(),
             allowed = allowed,
@@ -542,27 +777,36 @@ impl Session {
         self.permission_cache.insert(key, allowed);
     }
 
-    /// Check if a tool_input has cached permission
+    /// Check if a tool invocation has cached permission
     ///
     /// Called by can_use_tool callback to check if permission was already granted.
     /// Returns Some(true) if allowed, Some(false) if denied, None if not cached.
     /// Removes the entry from cache after retrieval (one-time use).
-    pub fn check_cached_permission(&self, tool_input: &serde_json::Value) -> Option<bool> {
-        let key = stable_cache_key(tool_input);
-        self.permission_cache.remove(&key).map(|(_, v)| v)
+    pub fn check_cached_permission(
+        &self,
+        tool_name: &str,
+        tool_input: &serde_json::Value,
+    ) -> Option<bool> {
+        let key = stable_cache_key(tool_name, tool_input);
+        self.permission_cache.take(&key)
     }
 
     /// Get a reference to the permission_cache for sharing with hooks
-    pub fn permission_cache(&self) -> Arc<DashMap<String, bool>> {
+    pub fn permission_cache(&self) -> Arc<HandoffCache<bool>> {
         Arc::clone(&self.permission_cache)
     }
 
-    /// Cache tool_use_id for a tool_input
+    /// Cache tool_use_id for a tool invocation
     ///
     /// Called by PreToolUse hook when Ask decision is made.
     /// The can_use_tool callback uses this to get tool_use_id when CLI doesn't provide it.
-    pub fn cache_tool_use_id(&self, tool_input: &serde_json::Value, tool_use_id: &str) {
-        let key = stable_cache_key(tool_input);
+    pub fn cache_tool_use_id(
+        &self,
+        tool_name: &str,
+        tool_input: &serde_json::Value,
+        tool_use_id: &str,
+    ) {
+        let key = stable_cache_key(tool_name, tool_input);
         tracing::debug!(
             key_len = key.len(),
             tool_use_id = %tool_use_id,
@@ -571,21 +815,195 @@ impl Session {
         self.tool_use_id_cache.insert(key, tool_use_id.to_string());
     }
 
-    /// Get cached tool_use_id for a tool_input
+    /// Get cached tool_use_id for a tool invocation
     ///
     /// Called by can_use_tool callback to get tool_use_id when CLI doesn't provide it.
     /// Returns the tool_use_id if cached, None otherwise.
     /// Removes the entry from cache after retrieval (one-time use).
-    pub fn get_cached_tool_use_id(&self, tool_input: &serde_json::Value) -> Option<String> {
-        let key = stable_cache_key(tool_input);
-        self.tool_use_id_cache.remove(&key).map(|(_, v)| v)
+    pub fn get_cached_tool_use_id(
+        &self,
+        tool_name: &str,
+        tool_input: &serde_json::Value,
+    ) -> Option<String> {
+        let key = stable_cache_key(tool_name, tool_input);
+        self.tool_use_id_cache.take(&key)
     }
 
     /// Get a reference to the tool_use_id_cache for sharing with hooks
-    pub fn tool_use_id_cache(&self) -> Arc<DashMap<String, String>> {
+    pub fn tool_use_id_cache(&self) -> Arc<HandoffCache<String>> {
         Arc::clone(&self.tool_use_id_cache)
     }
 
+    /// Number of entries currently in the permission_cache
+    pub fn permission_cache_len(&self) -> usize {
+        self.permission_cache.len()
+    }
+
+    /// Number of entries currently in the tool_use_id_cache
+    pub fn tool_use_id_cache_len(&self) -> usize {
+        self.tool_use_id_cache.len()
+    }
+
+    /// Clear both the permission_cache and the tool_use_id_cache
+    ///
+    /// Used when the conversation is reset (e.g. `/clear`) so stale entries
+    /// from earlier tool calls can't leak into new ones.
+    pub fn clear_caches(&self) {
+        tracing::debug!(
+            session_id = %self.session_id,
+            permission_entries = self.permission_cache.len(),
+            tool_use_id_entries = self.tool_use_id_cache.len(),
+            "Clearing permission caches"
+        );
+        self.permission_cache.clear();
+        self.tool_use_id_cache.clear();
+    }
+
+    /// Reset per-conversation permission state for `/clear`
+    ///
+    /// Clears the caches and the "Reject for Session" deny rules. Rules
+    /// persisted with "Always Reject" live in settings and are kept.
+    pub async fn reset_conversation(&self) {
+        self.clear_caches();
+        self.permission().await.clear_session_deny_rules().await;
+        self.save_permission_state().await;
+    }
+
+    /// Get the maximum number of entries kept per cache (None = unbounded)
+    pub fn cache_max_entries(&self) -> Option<usize> {
+        match self.cache_limit() {
+            0 => None,
+            max => Some(max),
+        }
+    }
+
+    /// Bound the number of entries kept per cache
+    ///
+    /// The oldest entries are evicted when an insert would exceed the bound,
+    /// in the hook as well as here. Existing entries over a newly lowered
+    /// bound are evicted immediately. Passing `None` (or `Some(0)`) removes
+    /// the bound.
+    pub fn set_cache_max_entries(&self, max_entries: Option<usize>) {
+        self.cache_max_entries
+            .store(max_entries.unwrap_or(0), Ordering::Release);
+        self.permission_cache.evict_to_limit();
+        self.tool_use_id_cache.evict_to_limit();
+    }
+
+    fn cache_limit(&self) -> usize {
+        self.cache_max_entries.load(Ordering::Acquire)
+    }
+
+    /// Get the window for throttling identical permission prompts (None = disabled)
+    pub fn prompt_rate_limit(&self) -> Option<Duration> {
+        match self.prompt_rate_limit_ms.load(Ordering::Acquire) {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
+    }
+
+    /// Throttle identical permission prompts sent within `window`
+    ///
+    /// A request is identical when both the tool name and the tool input
+    /// match. Passing `None` (or a zero window) disables throttling.
+    pub fn set_prompt_rate_limit(&self, window: Option<Duration>) {
+        let ms = window.map_or(0, |w| u64::try_from(w.as_millis()).unwrap_or(u64::MAX));
+        self.prompt_rate_limit_ms.store(ms, Ordering::Release);
+        if ms == 0 {
+            self.recent_prompts.clear();
+        }
+    }
+
+    /// Check whether a permission prompt should be throttled
+    ///
+    /// Returns `true` when an identical prompt was recorded with
+    /// [`record_prompt`](Self::record_prompt) within the window, and always
+    /// `false` when rate limiting is off.
+    pub fn should_throttle_prompt(&self, tool_name: &str, tool_input: &serde_json::Value) -> bool {
+        let Some(window) = self.prompt_rate_limit() else {
+            return false;
+        };
+
+        let now = Instant::now();
+        self.recent_prompts
+            .retain(|_, sent| now.duration_since(*sent) < window);
+        self.recent_prompts
+            .contains_key(&stable_cache_key(tool_name, tool_input))
+    }
+
+    /// Record a permission prompt sent to the client
+    ///
+    /// Only prompts actually sent count toward throttling: a call denied
+    /// before its prompt (e.g. for lack of a connection) can be retried.
+    pub fn record_prompt(&self, tool_name: &str, tool_input: &serde_json::Value) {
+        if self.prompt_rate_limit().is_some() {
+            self.recent_prompts
+                .insert(stable_cache_key(tool_name, tool_input), Instant::now());
+        }
+    }
+
+    /// Get the number of identical denials that escalate to an interrupt (None = disabled)
+    pub fn denial_escalation_threshold(&self) -> Option<usize> {
+        self.tool_call_control.denial_escalation_threshold()
+    }
+
+    /// Interrupt the turn once the same tool call has been denied `threshold` times
+    ///
+    /// See [`ToolCallControl::set_denial_escalation_threshold`].
+    pub fn set_denial_escalation_threshold(&self, threshold: Option<usize>) {
+        self.tool_call_control
+            .set_denial_escalation_threshold(threshold);
+    }
+
+    /// Record a denied tool call, returning `true` if the denial should interrupt
+    ///
+    /// See [`ToolCallControl::record_denial`].
+    pub fn record_denial(&self, tool_name: &str, tool_input: &serde_json::Value) -> bool {
+        self.tool_call_control.record_denial(tool_name, tool_input)
+    }
+
+    /// Get how many permission prompts may be outstanding at once (None = unlimited)
+    pub fn max_concurrent_prompts(&self) -> Option<usize> {
+        match self.max_concurrent_prompts.load(Ordering::Acquire) {
+            0 => None,
+            max => Some(max),
+        }
+    }
+
+    /// Limit how many permission prompts may be outstanding at once
+    ///
+    /// When the agent issues several tool calls together, prompts beyond the
+    /// limit wait for earlier ones to finish instead of stacking up dialogs
+    /// in the client. Passing `None` (or `Some(0)`) removes the limit.
+    pub fn set_max_concurrent_prompts(&self, max: Option<usize>) {
+        self.max_concurrent_prompts
+            .store(max.unwrap_or(0), Ordering::Release);
+        // A raised limit (or none) may admit the prompt at the head of the queue
+        self.prompt_slot_notify.notify_waiters();
+    }
+
+    /// Wait for a slot to show a permission prompt
+    ///
+    /// Resolves immediately when no limit is set or fewer than the limit are
+    /// outstanding; otherwise waits for one to finish. Waiting prompts are
+    /// admitted in the order they arrived. Hold the returned slot for as
+    /// long as the prompt is outstanding.
+    pub async fn acquire_prompt_slot(&self) -> PromptSlot<'_> {
+        let _turn = self.prompt_queue.lock().await;
+        loop {
+            let notified = self.prompt_slot_notify.notified();
+            tokio::pin!(notified);
+            // Register before checking the count so a concurrent release isn't missed
+            notified.as_mut().enable();
+            let max = self.max_concurrent_prompts.load(Ordering::Acquire);
+            if max == 0 || self.active_prompts.load(Ordering::Acquire) < max {
+                self.active_prompts.fetch_add(1, Ordering::AcqRel);
+                return PromptSlot { session: self };
+            }
+            notified.await;
+        }
+    }
+
     /// Connect to external MCP servers
     ///
     /// This should be called before the first prompt to ensure all
@@ -725,6 +1143,12 @@ impl Session {
             "Finished connecting external MCP servers"
         );
 
+        // Let permission rules match the connected tools by their titles
+        let titles = external_manager.tool_titles();
+        if !titles.is_empty() {
+            self.permission().await.set_tool_titles(titles).await;
+        }
+
         self.external_mcp_connected.store(true, Ordering::SeqCst);
         Ok(())
     }
@@ -908,29 +1332,259 @@ impl Session {
         self.cancelled.store(false, Ordering::Release);
     }
 
+    /// Trigger an emergency stop
+    ///
+    /// Until [`Session::clear_emergency_stop`] is called, every tool call is
+    /// denied with `interrupt: true` and pending permission requests are
+    /// abandoned. The permission mode is left untouched.
+    pub fn emergency_stop(&self) {
+        self.tool_call_control.emergency_stop();
+        tracing::warn!(
+            session_id = %self.session_id,
+            "Emergency stop triggered - denying all tool calls"
+        );
+    }
+
+    /// Clear a previous emergency stop, restoring normal permission flow
+    pub fn clear_emergency_stop(&self) {
+        self.tool_call_control.clear_emergency_stop();
+        tracing::info!(
+            session_id = %self.session_id,
+            "Emergency stop cleared"
+        );
+    }
+
+    /// Check if an emergency stop is in effect
+    pub fn is_emergency_stopped(&self) -> bool {
+        self.tool_call_control.is_emergency_stopped()
+    }
+
+    /// Wait until an emergency stop is in effect
+    ///
+    /// Resolves immediately if already stopped. Used to race in-flight
+    /// permission requests against an emergency stop.
+    pub async fn emergency_stopped(&self) {
+        self.tool_call_control.emergency_stopped().await;
+    }
+
+    /// Pause the session
+    ///
+    /// While paused, tool calls are held before their permission check and
+    /// don't execute until [`Session::resume`] is called.
+    pub fn pause(&self) {
+        self.tool_call_control.pause();
+        tracing::info!(
+            session_id = %self.session_id,
+            "Session paused - holding tool calls"
+        );
+    }
+
+    /// Resume a paused session, releasing held tool calls in arrival order
+    pub fn resume(&self) {
+        self.tool_call_control.resume();
+        tracing::info!(
+            session_id = %self.session_id,
+            "Session resumed - releasing held tool calls"
+        );
+    }
+
+    /// Check if the session is paused
+    pub fn is_paused(&self) -> bool {
+        self.tool_call_control.is_paused()
+    }
+
+    /// Wait while the session is paused, for at most `max_wait`
+    ///
+    /// Returns `true` once the session is (or already was) running, or
+    /// `false` if still paused after `max_wait`. Concurrent callers are
+    /// released one at a time in the order they started waiting.
+    pub async fn wait_while_paused(&self, max_wait: Duration) -> bool {
+        self.tool_call_control.wait_while_paused(max_wait).await
+    }
+
     /// Get the permission handler
     pub async fn permission(&self) -> tokio::sync::RwLockReadGuard<'_, PermissionHandler> {
         self.permission.read().await
     }
 
+    /// Replace the check that decides which Bash commands are safe to auto-approve
+    ///
+    /// See [`PermissionHandler::set_safe_command_classifier`]; `None`
+    /// restores the built-in safe command set.
+    pub async fn set_safe_command_classifier(
+        &self,
+        classifier: Option<Arc<dyn SafeCommandClassifier>>,
+    ) {
+        self.permission
+            .write()
+            .await
+            .set_safe_command_classifier(classifier);
+    }
+
     /// Get the current permission mode
     pub async fn permission_mode(&self) -> PermissionMode {
         self.permission.read().await.mode()
     }
 
+    /// Get the current permission mode without waiting
+    ///
+    /// Returns `None` if the permission handler is currently being updated.
+    pub fn try_permission_mode(&self) -> Option<PermissionMode> {
+        self.permission
+            .try_read()
+            .ok()
+            .map(|handler| handler.mode())
+    }
+
     /// Set the permission mode
     ///
     /// Updates the PermissionHandler. The hook will read the mode
-    /// from the same PermissionHandler, ensuring consistency.
+    /// from the same PermissionHandler, ensuring consistency. The mode is
+    /// also saved as the last-used one, so new sessions start in it.
     pub async fn set_permission_mode(&self, mode: PermissionMode) {
         // Update the permission handler (single source of truth)
-        self.permission.write().await.set_mode(mode);
+        let old_mode = {
+            let mut permission = self.permission.write().await;
+            let old_mode = permission.mode();
+            permission.set_mode(mode);
+            old_mode
+        };
 
         tracing::info!(
             session_id = %self.session_id,
             mode = mode.as_str(),
             "Permission mode updated"
         );
+
+        // Written on the blocking thread pool, off the async runtime
+        if let Some(path) = self.mode_state_file.clone() {
+            let saved = tokio::task::spawn_blocking(move || {
+                save_last_permission_mode(&path, mode).map_err(|e| (path, e))
+            })
+            .await;
+            if let Ok(Err((path, e))) = saved {
+                tracing::warn!(
+                    session_id = %self.session_id,
+                    error = %e,
+                    "Failed to save permission mode state to {:?}",
+                    path
+                );
+            }
+        }
+        self.save_permission_state().await;
+        self.notify_mode_change(old_mode, mode);
+    }
+
+    /// Switch to a mode registered with [`Self::register_permission_strategy`]
+    ///
+    /// Like [`Self::set_permission_mode`] with the strategy's own mode,
+    /// except the name isn't saved as the last-used mode, since other
+    /// sessions may not register it. Returns false, leaving the mode
+    /// unchanged, if nothing is registered under `name`.
+    pub async fn set_custom_permission_mode(&self, name: &str) -> bool {
+        let (old_mode, mode) = {
+            let mut permission = self.permission.write().await;
+            let old_mode = permission.mode();
+            if !permission.set_custom_mode(name) {
+                return false;
+            }
+            (old_mode, permission.mode())
+        };
+
+        tracing::info!(
+            session_id = %self.session_id,
+            custom_mode = %name,
+            mode = mode.as_str(),
+            "Permission mode updated to a registered strategy"
+        );
+        self.save_permission_state().await;
+        self.notify_mode_change(old_mode, mode);
+        true
+    }
+
+    /// Register a strategy under a mode name
+    ///
+    /// See [`PermissionHandler::register_strategy`]. A name that isn't a
+    /// built-in mode is offered to the client as a mode of its own.
+    pub async fn register_permission_strategy(
+        &self,
+        name: impl Into<String>,
+        strategy: Arc<dyn PermissionModeStrategy>,
+    ) {
+        self.permission
+            .write()
+            .await
+            .register_strategy(name, strategy);
+    }
+
+    /// Names of the registered modes that aren't built-in modes, sorted
+    pub async fn custom_mode_names(&self) -> Vec<String> {
+        self.permission.read().await.custom_mode_names()
+    }
+
+    /// Get the id of the active mode, a registered mode's name if one is active
+    pub async fn permission_mode_id(&self) -> String {
+        let permission = self.permission.read().await;
+        permission
+            .custom_mode()
+            .map_or_else(|| permission.mode().as_str().to_string(), str::to_string)
+    }
+
+    /// Call the mode change callbacks, if the mode changed
+    fn notify_mode_change(&self, old_mode: PermissionMode, mode: PermissionMode) {
+        if old_mode != mode {
+            // Call outside the lock so an observer can register another callback
+            let callbacks = self
+                .mode_change_callbacks
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clone();
+            for callback in callbacks {
+                callback(old_mode, mode);
+            }
+        }
+    }
+
+    /// Register a callback for permission mode changes
+    ///
+    /// The callback runs after [`set_permission_mode`](Self::set_permission_mode)
+    /// applies a different mode, whether it came from the client's setMode
+    /// request or an approved ExitPlanMode, and receives the old and new
+    /// modes. Setting the mode that is already active does not call it.
+    pub fn on_mode_change(
+        &self,
+        callback: impl Fn(PermissionMode, PermissionMode) + Send + Sync + 'static,
+    ) {
+        self.mode_change_callbacks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(Arc::new(callback));
+    }
+
+    /// Replace the options offered in the ExitPlanMode dialog
+    ///
+    /// Lets integrators add target modes (e.g. bypass) or remove auto-accept.
+    pub async fn set_exit_plan_mode_options(&self, options: Vec<ExitPlanModeOption>) {
+        self.permission
+            .write()
+            .await
+            .set_exit_plan_mode_options(options);
+    }
+
+    /// Set the title of the ExitPlanMode dialog (`None` = "Ready to code?")
+    pub async fn set_exit_plan_mode_title(&self, title: Option<String>) {
+        self.permission
+            .write()
+            .await
+            .set_exit_plan_mode_title(title);
+    }
+
+    /// Require explicit confirmation when ExitPlanMode finds no plan content
+    pub async fn set_confirm_empty_plan(&self, confirm: bool) {
+        self.permission
+            .write()
+            .await
+            .set_confirm_empty_plan(confirm);
     }
 
     /// Send session/update notification for permission mode change
@@ -968,6 +1622,76 @@ impl Session {
                 "Sent CurrentModeUpdate notification"
             );
         }
+
+        if let Some(mode) = PermissionMode::parse(mode) {
+            self.send_available_commands_update(mode);
+        }
+    }
+
+    /// Send the slash commands available in `mode` to the client
+    ///
+    /// The command set differs by mode, so this follows every mode change.
+    pub fn send_available_commands_update(&self, mode: PermissionMode) {
+        let Some(connection_cx) = self.get_connection_cx() else {
+            tracing::warn!(
+                session_id = %self.session_id,
+                "Connection not ready for available commands update"
+            );
+            return;
+        };
+
+        let notification = available_commands_notification(&self.session_id, mode);
+        if let Err(e) = connection_cx.send_notification(notification) {
+            tracing::warn!(
+                session_id = %self.session_id,
+                error = %e,
+                "Failed to send available commands update"
+            );
+        }
+    }
+
+    /// Reload settings from disk without recreating the session
+    ///
+    /// Re-reads `~/.claude/settings.json`, `.claude/settings.json` and
+    /// `.claude/settings.local.json` and swaps the file-sourced permission rules
+    /// under the checker's write lock. Runtime rules (e.g. "Always Allow") are kept.
+    pub async fn reload_settings(&self) -> Result<()> {
+        let settings_manager = SettingsManager::new(&self.workspace)?;
+        self.permission_checker
+            .write()
+            .await
+            .reload_from_layers(settings_manager.layers());
+        {
+            let mut permission = self.permission.write().await;
+            permission
+                .set_disabled_safe_commands(settings_manager.disable_safe_commands().to_vec());
+            permission
+                .set_max_auto_approve_command_len(settings_manager.max_auto_approve_command_len());
+            permission.set_allow_rule_idle_timeout(settings_manager.allow_rule_idle_timeout());
+            permission.set_strict_accept_edits(settings_manager.strict_accept_edits());
+            permission
+                .set_prompt_for_network_commands(settings_manager.prompt_for_network_commands());
+            permission.set_dont_ask_deny_message(
+                settings_manager.dont_ask_deny_message().map(str::to_string),
+            );
+            permission.set_confirm_empty_plan(settings_manager.confirm_empty_plan());
+        }
+        self.tool_call_control
+            .set_denial_escalation_threshold(settings_manager.denial_escalation_threshold());
+        self.set_prompt_rate_limit(settings_manager.prompt_rate_limit());
+        self.set_cache_max_entries(settings_manager.permission_cache_max_entries());
+        self.set_max_concurrent_prompts(settings_manager.max_concurrent_prompts());
+
+        // Let the client refresh its command list after a settings change
+        if self.get_connection_cx().is_some() {
+            self.send_available_commands_update(self.permission_mode().await);
+        }
+
+        tracing::info!(
+            session_id = %self.session_id,
+            "Settings reloaded"
+        );
+        Ok(())
     }
 
     /// Add an allow rule for a tool
@@ -975,6 +1699,25 @@ impl Session {
     /// This is called when user selects "Always Allow" in permission prompt.
     pub async fn add_permission_allow_rule(&self, tool_name: &str) {
         self.permission.read().await.add_allow_rule(tool_name).await;
+        self.save_permission_state().await;
+    }
+
+    /// Snapshot the permission mode and the rules added in this session
+    pub async fn export_permission_state(&self) -> PermissionState {
+        let mode = self.permission_mode().await;
+        PermissionState::capture(mode, &*self.permission_checker.read().await)
+    }
+
+    /// Save the permission state for recovery, if a directory is configured
+    ///
+    /// Called whenever the mode or the session's rules change, including
+    /// when a use-limited rule is spent or idle rules expire. Use-limited
+    /// rules are saved with the uses left at that point. Failures are
+    /// logged; the session carries on with its in-memory state.
+    pub async fn save_permission_state(&self) {
+        if let Some(ref store) = self.permission_state_store {
+            store.save(&self.permission, &self.permission_checker).await;
+        }
     }
 
     /// Get the current model ID
@@ -1196,6 +1939,7 @@ impl std::fmt::Debug for Session {
         f.debug_struct("Session")
             .field("session_id", &self.session_id)
             .field("cwd", &self.cwd)
+            .field("workspace", &self.workspace)
             .field("connected", &self.connected.load(Ordering::Relaxed))
             .finish()
     }
@@ -1204,6 +1948,7 @@ impl std::fmt::Debug for Session {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::settings::PermissionDecision;
 
     fn test_config() -> AgentConfig {
         AgentConfig {
@@ -1212,6 +1957,8 @@ mod tests {
             model: None,
             small_fast_model: None,
             max_thinking_tokens: None,
+            permission_mode_state_file: None,
+            permission_state_dir: None,
         }
     }
 
@@ -1310,9 +2057,9 @@ mod tests {
         let json2 = json!({"c": 3, "b": 2, "a": 1});
         let json3 = json!({"b": 2, "a": 1, "c": 3});
 
-        let key1 = stable_cache_key(&json1);
-        let key2 = stable_cache_key(&json2);
-        let key3 = stable_cache_key(&json3);
+        let key1 = stable_cache_key("Bash", &json1);
+        let key2 = stable_cache_key("Bash", &json2);
+        let key3 = stable_cache_key("Bash", &json3);
 
         assert_eq!(
             key1, key2,
@@ -1338,8 +2085,8 @@ mod tests {
             "command": "cargo build"
         });
 
-        let key1 = stable_cache_key(&json1);
-        let key2 = stable_cache_key(&json2);
+        let key1 = stable_cache_key("Bash", &json1);
+        let key2 = stable_cache_key("Bash", &json2);
 
         assert_eq!(key1, key2, "Nested objects should also produce stable keys");
     }
@@ -1356,12 +2103,49 @@ mod tests {
             "items": [{"b": 2, "a": 1}, {"d": 4, "c": 3}]
         });
 
-        let key1 = stable_cache_key(&json1);
-        let key2 = stable_cache_key(&json2);
+        let key1 = stable_cache_key("Bash", &json1);
+        let key2 = stable_cache_key("Bash", &json2);
 
         assert_eq!(key1, key2, "Arrays with objects should produce stable keys");
     }
 
+    #[test]
+    fn test_stable_cache_key_includes_tool_name() {
+        use serde_json::json;
+
+        let input = json!({"file_path": "/tmp/a.txt"});
+
+        // Identical input to different tools must not share a cache entry
+        assert_ne!(
+            stable_cache_key("Read", &input),
+            stable_cache_key("Edit", &input)
+        );
+        // The ACP prefix does not change the key
+        assert_eq!(
+            stable_cache_key("Read", &input),
+            stable_cache_key("mcp__acp__Read", &input)
+        );
+    }
+
+    #[test]
+    fn test_cached_permission_is_scoped_to_tool() {
+        let session = Session::new(
+            "test-cache-tool-scope".to_string(),
+            PathBuf::from("/tmp"),
+            &test_config(),
+            None,
+        )
+        .unwrap();
+        let input = serde_json::json!({"file_path": "/tmp/a.txt"});
+
+        session.cache_permission("Read", &input, true);
+        assert_eq!(session.check_cached_permission("Edit", &input), None);
+        assert_eq!(
+            session.check_cached_permission("mcp__acp__Read", &input),
+            Some(true)
+        );
+    }
+
     #[test]
     fn test_stable_cache_key_different_content() {
         use serde_json::json;
@@ -1370,8 +2154,8 @@ mod tests {
         let json1 = json!({"command": "cargo build"});
         let json2 = json!({"command": "cargo test"});
 
-        let key1 = stable_cache_key(&json1);
-        let key2 = stable_cache_key(&json2);
+        let key1 = stable_cache_key("Bash", &json1);
+        let key2 = stable_cache_key("Bash", &json2);
 
         assert_ne!(
             key1, key2,
@@ -1426,4 +2210,491 @@ mod tests {
         let result = session.cleanup().await;
         assert!(result.is_ok(), "Cleanup should succeed");
     }
+
+    #[test]
+    fn test_cache_sizes_and_clear() {
+        let session = Session::new(
+            "test-cache-clear".to_string(),
+            PathBuf::from("/tmp"),
+            &test_config(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(session.permission_cache_len(), 0);
+        assert_eq!(session.tool_use_id_cache_len(), 0);
+
+        session.cache_permission("Bash", &serde_json::json!({"command": "ls"}), true);
+        session.cache_permission("Bash", &serde_json::json!({"command": "pwd"}), true);
+        session.cache_tool_use_id("Bash", &serde_json::json!({"command": "ls"}), "toolu_1");
+        assert_eq!(session.permission_cache_len(), 2);
+        assert_eq!(session.tool_use_id_cache_len(), 1);
+
+        // Re-inserting the same input doesn't grow the cache
+        session.cache_permission("Bash", &serde_json::json!({"command": "ls"}), true);
+        assert_eq!(session.permission_cache_len(), 2);
+
+        session.clear_caches();
+        assert_eq!(session.permission_cache_len(), 0);
+        assert_eq!(session.tool_use_id_cache_len(), 0);
+        assert_eq!(
+            session.check_cached_permission("Bash", &serde_json::json!({"command": "ls"})),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cache_eviction_past_bound() {
+        let session = Session::new(
+            "test-cache-bound".to_string(),
+            PathBuf::from("/tmp"),
+            &test_config(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(session.cache_max_entries(), None);
+
+        for i in 0..5 {
+            session.cache_permission("Bash", &serde_json::json!({"n": i}), true);
+        }
+        assert_eq!(session.permission_cache_len(), 5);
+
+        // Lowering the bound evicts existing entries immediately
+        session.set_cache_max_entries(Some(3));
+        assert_eq!(session.cache_max_entries(), Some(3));
+        assert_eq!(session.permission_cache_len(), 3);
+
+        // Inserts past the bound evict, and the newest entry is always kept
+        for i in 5..10 {
+            session.cache_tool_use_id("Bash", &serde_json::json!({"n": i}), &format!("toolu_{i}"));
+            session.cache_permission("Bash", &serde_json::json!({"n": i}), true);
+        }
+        assert_eq!(session.permission_cache_len(), 3);
+        assert_eq!(session.tool_use_id_cache_len(), 3);
+        assert_eq!(
+            session.get_cached_tool_use_id("Bash", &serde_json::json!({"n": 9})),
+            Some("toolu_9".to_string())
+        );
+
+        // The hook inserts through its own handle, under the same bound
+        let hook_cache = session.tool_use_id_cache();
+        for i in 10..20 {
+            hook_cache.insert(format!("hook-{i}"), format!("toolu_{i}"));
+        }
+        assert_eq!(session.tool_use_id_cache_len(), 3);
+        assert_eq!(hook_cache.take("hook-19"), Some("toolu_19".to_string()));
+
+        // Removing the bound allows growth again
+        session.set_cache_max_entries(None);
+        for i in 10..15 {
+            session.cache_permission("Bash", &serde_json::json!({"n": i}), true);
+        }
+        assert_eq!(session.permission_cache_len(), 8);
+    }
+
+    #[test]
+    fn test_prompt_rate_limit() {
+        let session = Session::new(
+            "test-prompt-rate-limit".to_string(),
+            PathBuf::from("/tmp"),
+            &test_config(),
+            None,
+        )
+        .unwrap();
+        let input = serde_json::json!({"command": "rm -rf build"});
+
+        // Off by default: identical prompts are never throttled
+        assert_eq!(session.prompt_rate_limit(), None);
+        assert!(!session.should_throttle_prompt("Bash", &input));
+        assert!(!session.should_throttle_prompt("Bash", &input));
+
+        session.set_prompt_rate_limit(Some(Duration::from_secs(60)));
+        assert_eq!(session.prompt_rate_limit(), Some(Duration::from_secs(60)));
+        assert!(!session.should_throttle_prompt("Bash", &input));
+        assert!(!session.should_throttle_prompt("Bash", &input));
+        session.record_prompt("Bash", &input);
+        assert!(session.should_throttle_prompt("Bash", &input));
+
+        // Distinct input or tool name is not throttled
+        assert!(!session.should_throttle_prompt("Bash", &serde_json::json!({"command": "ls"})));
+        assert!(!session.should_throttle_prompt("mcp__acp__Bash", &input));
+
+        // Disabling clears the recorded prompts
+        session.set_prompt_rate_limit(None);
+        assert!(!session.should_throttle_prompt("Bash", &input));
+    }
+
+    #[test]
+    fn test_prompt_rate_limit_window_expires() {
+        let session = Session::new(
+            "test-prompt-rate-window".to_string(),
+            PathBuf::from("/tmp"),
+            &test_config(),
+            None,
+        )
+        .unwrap();
+        let input = serde_json::json!({"file_path": "/tmp/a.txt"});
+
+        session.set_prompt_rate_limit(Some(Duration::from_millis(20)));
+        session.record_prompt("Write", &input);
+        assert!(session.should_throttle_prompt("Write", &input));
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(!session.should_throttle_prompt("Write", &input));
+    }
+
+    #[tokio::test]
+    async fn test_emergency_stop_lifecycle() {
+        let session = Session::new(
+            "test-emergency-stop".to_string(),
+            PathBuf::from("/tmp"),
+            &test_config(),
+            None,
+        )
+        .unwrap();
+        assert!(!session.is_emergency_stopped());
+
+        // A waiter registered before the stop is woken by it
+        let waiter = {
+            let session = Arc::clone(&session);
+            tokio::spawn(async move { session.emergency_stopped().await })
+        };
+        tokio::task::yield_now().await;
+        session.emergency_stop();
+        assert!(session.is_emergency_stopped());
+        tokio::time::timeout(std::time::Duration::from_secs(1), waiter)
+            .await
+            .expect("waiter should wake on emergency stop")
+            .unwrap();
+
+        // Already stopped: resolves immediately
+        session.emergency_stopped().await;
+
+        session.clear_emergency_stop();
+        assert!(!session.is_emergency_stopped());
+    }
+
+    #[tokio::test]
+    async fn test_pause_holds_tool_calls_until_resume() {
+        let session = Session::new(
+            "test-pause".to_string(),
+            PathBuf::from("/tmp"),
+            &test_config(),
+            None,
+        )
+        .unwrap();
+        let long = Duration::from_secs(5);
+
+        // Not paused: returns immediately
+        assert!(session.wait_while_paused(long).await);
+
+        session.pause();
+        assert!(session.is_paused());
+        let released = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut waiters = Vec::new();
+        for id in 0..3 {
+            let session = Arc::clone(&session);
+            let released = Arc::clone(&released);
+            waiters.push(tokio::spawn(async move {
+                let running = session.wait_while_paused(long).await;
+                released.lock().unwrap().push(id);
+                running
+            }));
+            // Let each call queue up before the next arrives
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(
+            released.lock().unwrap().is_empty(),
+            "paused calls must be held"
+        );
+
+        session.resume();
+        for waiter in waiters {
+            assert!(tokio::time::timeout(long, waiter).await.unwrap().unwrap());
+        }
+        assert_eq!(*released.lock().unwrap(), vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_pause_wait_is_capped() {
+        let session = Session::new(
+            "test-pause-cap".to_string(),
+            PathBuf::from("/tmp"),
+            &test_config(),
+            None,
+        )
+        .unwrap();
+        session.pause();
+        assert!(!session.wait_while_paused(Duration::from_millis(20)).await);
+        session.resume();
+        assert!(!session.is_paused());
+    }
+
+    #[tokio::test]
+    async fn test_prompts_are_serialized_with_concurrency_one() {
+        let session = Session::new(
+            "test-prompt-slots".to_string(),
+            PathBuf::from("/tmp"),
+            &test_config(),
+            None,
+        )
+        .unwrap();
+        session.set_max_concurrent_prompts(Some(1));
+        assert_eq!(session.max_concurrent_prompts(), Some(1));
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut prompts = Vec::new();
+        for id in 0..2 {
+            let session = Arc::clone(&session);
+            let events = Arc::clone(&events);
+            prompts.push(tokio::spawn(async move {
+                let _slot = session.acquire_prompt_slot().await;
+                events.lock().unwrap().push(format!("start {}", id));
+                // Stand-in for waiting on the user's answer
+                tokio::time::sleep(Duration::from_millis(30)).await;
+                events.lock().unwrap().push(format!("end {}", id));
+            }));
+        }
+        for prompt in prompts {
+            prompt.await.unwrap();
+        }
+
+        let events = events.lock().unwrap().clone();
+        assert_eq!(events.len(), 4);
+        // The second prompt starts only after the first one ended
+        assert!(events[0].starts_with("start"));
+        assert!(events[1].starts_with("end"));
+        assert_eq!(events[0][6..], events[1][4..]);
+        assert!(events[2].starts_with("start"));
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_prompt_releases_its_slot() {
+        let session = Session::new(
+            "test-prompt-slot-release".to_string(),
+            PathBuf::from("/tmp"),
+            &test_config(),
+            None,
+        )
+        .unwrap();
+        session.set_max_concurrent_prompts(Some(1));
+        let short = Duration::from_millis(20);
+
+        let held = session.acquire_prompt_slot().await;
+        // A prompt queued behind it gives up (e.g. timed out or cancelled)...
+        assert!(
+            tokio::time::timeout(short, session.acquire_prompt_slot())
+                .await
+                .is_err()
+        );
+        // ...and neither it nor the finished prompt blocks the next one
+        drop(held);
+        let slot = tokio::time::timeout(short, session.acquire_prompt_slot())
+            .await
+            .expect("slot should be free");
+        drop(slot);
+
+        // Without a limit prompts don't wait for each other
+        session.set_max_concurrent_prompts(None);
+        let _a = session.acquire_prompt_slot().await;
+        let _b = tokio::time::timeout(short, session.acquire_prompt_slot())
+            .await
+            .expect("unlimited prompts should not queue");
+    }
+
+    #[tokio::test]
+    async fn test_initial_permission_mode_resolution() {
+        // No request and no settings: the constructor's fallback is used
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let session = Session::new_with_default_mode(
+            "test-initial-mode-fallback".to_string(),
+            temp_dir.path().to_path_buf(),
+            &test_config(),
+            None,
+            PermissionMode::Plan,
+        )
+        .unwrap();
+        assert_eq!(session.permission_mode().await, PermissionMode::Plan);
+        assert_eq!(session.try_permission_mode(), Some(PermissionMode::Plan));
+
+        // An explicit session request wins
+        let meta = NewSessionMeta::from_request_meta(Some(&serde_json::json!({
+            "claudeCode": { "options": { "permissionMode": "acceptEdits" } }
+        })));
+        let session = Session::new_with_default_mode(
+            "test-initial-mode-request".to_string(),
+            temp_dir.path().to_path_buf(),
+            &test_config(),
+            Some(&meta),
+            PermissionMode::Plan,
+        )
+        .unwrap();
+        assert_eq!(session.permission_mode().await, PermissionMode::AcceptEdits);
+    }
+
+    #[tokio::test]
+    async fn test_permission_mode_is_remembered_across_sessions() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = AgentConfig {
+            permission_mode_state_file: Some(temp_dir.path().join("mode.json")),
+            ..test_config()
+        };
+        let new_session = |id: &str, meta: Option<&NewSessionMeta>| {
+            Session::new(id.to_string(), temp_dir.path().to_path_buf(), &config, meta).unwrap()
+        };
+
+        // Setting a mode writes it to the state file
+        let first = new_session("test-sticky-mode-1", None);
+        assert_eq!(first.permission_mode().await, PermissionMode::Default);
+        first.set_permission_mode(PermissionMode::AcceptEdits).await;
+        assert_eq!(
+            load_last_permission_mode(&temp_dir.path().join("mode.json")).as_deref(),
+            Some("acceptEdits")
+        );
+
+        // A new session resumes in it
+        let second = new_session("test-sticky-mode-2", None);
+        assert_eq!(second.permission_mode().await, PermissionMode::AcceptEdits);
+
+        // unless the client asks for a mode
+        let meta = NewSessionMeta::from_request_meta(Some(&serde_json::json!({
+            "claudeCode": { "options": { "permissionMode": "plan" } }
+        })));
+        let third = new_session("test-sticky-mode-3", Some(&meta));
+        assert_eq!(third.permission_mode().await, PermissionMode::Plan);
+
+        // A corrupt state file is ignored
+        std::fs::write(temp_dir.path().join("mode.json"), "not json").unwrap();
+        let fourth = new_session("test-sticky-mode-4", None);
+        assert_eq!(fourth.permission_mode().await, PermissionMode::Default);
+
+        // A project's defaultMode beats the remembered mode
+        fourth
+            .set_permission_mode(PermissionMode::AcceptEdits)
+            .await;
+        let project = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(project.path().join(".claude")).unwrap();
+        std::fs::write(
+            project.path().join(".claude/settings.json"),
+            r#"{"permissions": {"defaultMode": "plan"}}"#,
+        )
+        .unwrap();
+        let fifth = Session::new(
+            "test-sticky-mode-5".to_string(),
+            project.path().to_path_buf(),
+            &config,
+            None,
+        )
+        .unwrap();
+        assert_eq!(fifth.permission_mode().await, PermissionMode::Plan);
+    }
+
+    #[tokio::test]
+    async fn test_permission_state_is_restored_by_session_id() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = AgentConfig {
+            permission_state_dir: Some(temp_dir.path().join("state")),
+            ..test_config()
+        };
+        let new_session = |id: &str| {
+            Session::new(id.to_string(), temp_dir.path().to_path_buf(), &config, None).unwrap()
+        };
+        let curl = serde_json::json!({"command": "curl https://example.com"});
+        let rm = serde_json::json!({"command": "rm -rf build"});
+
+        let first = new_session("test-restore-1");
+        first.set_permission_mode(PermissionMode::AcceptEdits).await;
+        {
+            let permission = first.permission().await;
+            permission.add_allow_rule_for_tool_call("Bash", &curl);
+            permission.add_deny_rule_for_tool_call("Bash", &rm).await;
+        }
+        first
+            .permission_checker
+            .write()
+            .await
+            .add_allow_rule_with_uses("Bash(make:*)", 2);
+        first.save_permission_state().await;
+        let saved = first.export_permission_state().await;
+        assert_eq!(saved.allow_rules.len(), 1);
+        assert_eq!(saved.deny_rules.len(), 1);
+        assert_eq!(saved.use_limited_allow_rules.len(), 1);
+        drop(first);
+
+        // A session created again with the same id picks up where it left off
+        let restored = new_session("test-restore-1");
+        assert_eq!(
+            restored.permission_mode().await,
+            PermissionMode::AcceptEdits
+        );
+        assert_eq!(restored.export_permission_state().await, saved);
+
+        // Other sessions start fresh
+        let other = new_session("test-restore-2");
+        assert_eq!(other.permission_mode().await, PermissionMode::Default);
+        let fresh = other.export_permission_state().await;
+        assert!(fresh.allow_rules.is_empty() && fresh.deny_rules.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sessions_use_their_workspace_settings() {
+        fn workspace_with(permissions: serde_json::Value) -> tempfile::TempDir {
+            let dir = tempfile::TempDir::new().unwrap();
+            std::fs::create_dir(dir.path().join(".claude")).unwrap();
+            std::fs::write(
+                dir.path().join(".claude/settings.json"),
+                serde_json::json!({ "permissions": permissions }).to_string(),
+            )
+            .unwrap();
+            dir
+        }
+        async fn decision(session: &Session) -> PermissionDecision {
+            session
+                .permission_checker()
+                .read()
+                .await
+                .check_permission("Bash", &serde_json::json!({"command": "cargo publish"}))
+                .decision
+        }
+
+        let allowing = workspace_with(serde_json::json!({ "allow": ["Bash(cargo:*)"] }));
+        let denying = workspace_with(serde_json::json!({ "deny": ["Bash(cargo:*)"] }));
+
+        let first = Session::new(
+            "test-workspace-allow".to_string(),
+            allowing.path().to_path_buf(),
+            &test_config(),
+            None,
+        )
+        .unwrap();
+        let second = Session::new(
+            "test-workspace-deny".to_string(),
+            denying.path().to_path_buf(),
+            &test_config(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(first.workspace, first.cwd);
+        assert_eq!(decision(&first).await, PermissionDecision::Allow);
+        assert_eq!(decision(&second).await, PermissionDecision::Deny);
+
+        // An explicit workspace root selects that workspace's settings
+        let meta = NewSessionMeta::from_request_meta(Some(&serde_json::json!({
+            "workspaceRoot": allowing.path()
+        })));
+        let third = Session::new(
+            "test-workspace-override".to_string(),
+            denying.path().to_path_buf(),
+            &test_config(),
+            Some(&meta),
+        )
+        .unwrap();
+        assert_eq!(third.workspace, allowing.path());
+        assert_eq!(decision(&third).await, PermissionDecision::Allow);
+        {
+            let checker = third.permission_checker().read().await;
+            assert_eq!(checker.cwd(), denying.path());
+            assert_eq!(checker.project_dir(), allowing.path());
+        }
+    }
 }
@@ -0,0 +1,162 @@
+//! Session-wide holds on tool execution
+//!
+//! The PreToolUse hook approves most tool calls on its own, so `can_use_tool`
+//! never sees them. A hold that must stop every tool call, like an emergency
+//! stop or a pause, therefore lives here, shared by the session, its hook and
+//! its callback. So does the denial count, since both of them deny calls.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use dashmap::DashMap;
+use tokio::sync::{Mutex, Notify};
+
+use super::stable_cache_key;
+
+/// Longest a tool call is held while the session is paused before it is denied
+pub const MAX_PAUSE_WAIT: Duration = Duration::from_secs(30 * 60);
+
+/// Holds on tool execution, shared by a session's hook and callback
+#[derive(Debug, Default)]
+pub struct ToolCallControl {
+    /// Emergency stop flag - while set, every tool call is denied immediately
+    emergency_stopped: AtomicBool,
+    /// Wakes in-flight permission requests when an emergency stop is triggered
+    emergency_stop_notify: Notify,
+    /// Pause flag - while set, tool calls wait until resumed
+    paused: AtomicBool,
+    /// Wakes tool calls held by a pause when the session is resumed
+    resume_notify: Notify,
+    /// Queue of held tool calls; the fair mutex releases them in arrival order
+    pause_queue: Mutex<()>,
+    /// Denials of the same tool call after which the denial interrupts the
+    /// turn (0 = escalation disabled)
+    denial_escalation_threshold: AtomicUsize,
+    /// How many times each tool call has been denied since its last escalation
+    /// Key: stable cache key of tool name and tool_input
+    denial_counts: DashMap<String, usize>,
+}
+
+impl ToolCallControl {
+    /// Create a control with no hold in effect
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deny every tool call until [`Self::clear_emergency_stop`]
+    pub fn emergency_stop(&self) {
+        self.emergency_stopped.store(true, Ordering::Release);
+        self.emergency_stop_notify.notify_waiters();
+    }
+
+    /// Clear a previous emergency stop
+    pub fn clear_emergency_stop(&self) {
+        self.emergency_stopped.store(false, Ordering::Release);
+    }
+
+    /// Check if an emergency stop is in effect
+    pub fn is_emergency_stopped(&self) -> bool {
+        self.emergency_stopped.load(Ordering::Acquire)
+    }
+
+    /// Wait until an emergency stop is in effect
+    ///
+    /// Resolves immediately if already stopped.
+    pub async fn emergency_stopped(&self) {
+        loop {
+            let notified = self.emergency_stop_notify.notified();
+            tokio::pin!(notified);
+            // Register before checking the flag so a concurrent stop isn't missed
+            notified.as_mut().enable();
+            if self.is_emergency_stopped() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Hold tool calls until [`Self::resume`]
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Release);
+    }
+
+    /// Release held tool calls in arrival order
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Release);
+        self.resume_notify.notify_waiters();
+    }
+
+    /// Check if tool calls are being held
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
+    /// Wait while paused, for at most `max_wait`
+    ///
+    /// Returns `true` once running (or if not paused), or `false` if still
+    /// paused after `max_wait`. Concurrent callers are released one at a time
+    /// in the order they started waiting.
+    pub async fn wait_while_paused(&self, max_wait: Duration) -> bool {
+        if !self.is_paused() {
+            return true;
+        }
+
+        tokio::time::timeout(max_wait, async {
+            let _turn = self.pause_queue.lock().await;
+            loop {
+                let notified = self.resume_notify.notified();
+                tokio::pin!(notified);
+                // Register before checking the flag so a concurrent resume isn't missed
+                notified.as_mut().enable();
+                if !self.is_paused() {
+                    return;
+                }
+                notified.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+
+    /// Get the number of identical denials that escalate to an interrupt (None = disabled)
+    pub fn denial_escalation_threshold(&self) -> Option<usize> {
+        match self.denial_escalation_threshold.load(Ordering::Acquire) {
+            0 => None,
+            threshold => Some(threshold),
+        }
+    }
+
+    /// Interrupt the turn once the same tool call has been denied `threshold` times
+    ///
+    /// Breaks loops where the agent keeps retrying an operation that is
+    /// denied every time. Passing `None` (or `Some(0)`) disables escalation.
+    pub fn set_denial_escalation_threshold(&self, threshold: Option<usize>) {
+        let threshold = threshold.unwrap_or(0);
+        self.denial_escalation_threshold
+            .store(threshold, Ordering::Release);
+        if threshold == 0 {
+            self.denial_counts.clear();
+        }
+    }
+
+    /// Record a denied tool call, returning `true` if the denial should interrupt
+    ///
+    /// Denials by rules in the hook and by the user in the callback count
+    /// toward the same total. The count for that call starts over after each
+    /// escalation. Always returns `false` when escalation is disabled.
+    pub fn record_denial(&self, tool_name: &str, tool_input: &serde_json::Value) -> bool {
+        let Some(threshold) = self.denial_escalation_threshold() else {
+            return false;
+        };
+
+        let key = stable_cache_key(tool_name, tool_input);
+        let mut count = self.denial_counts.entry(key).or_insert(0);
+        *count += 1;
+        if *count >= threshold {
+            *count = 0;
+            true
+        } else {
+            false
+        }
+    }
+}
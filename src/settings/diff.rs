@@ -0,0 +1,149 @@
+//! Comparison of two settings snapshots
+//!
+//! Reports how permission rules and the default mode changed between an old
+//! and a new settings file, for change review and audit logs.
+
+use super::manager::Settings;
+use super::rule::PermissionSettings;
+
+/// Rules added to and removed from one rule list (allow, deny or ask)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleChanges {
+    /// Rules present only in the new settings, in their new order
+    pub added: Vec<String>,
+    /// Rules present only in the old settings, in their old order
+    pub removed: Vec<String>,
+}
+
+impl RuleChanges {
+    /// Compare two rule lists, ignoring order and duplicates
+    fn between(old: &[String], new: &[String]) -> Self {
+        Self {
+            added: new
+                .iter()
+                .filter(|rule| !old.contains(rule))
+                .cloned()
+                .collect(),
+            removed: old
+                .iter()
+                .filter(|rule| !new.contains(rule))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Whether no rule was added or removed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Differences between two [`Settings`] values
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SettingsDiff {
+    /// Changes to `permissions.allow`
+    pub allow: RuleChanges,
+    /// Changes to `permissions.deny`
+    pub deny: RuleChanges,
+    /// Changes to `permissions.ask`
+    pub ask: RuleChanges,
+    /// `permissions.defaultMode` as `(old, new)`, if it changed
+    pub default_mode: Option<(Option<String>, Option<String>)>,
+}
+
+impl SettingsDiff {
+    /// Whether the two settings have the same rules and default mode
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty()
+            && self.deny.is_empty()
+            && self.ask.is_empty()
+            && self.default_mode.is_none()
+    }
+}
+
+impl Settings {
+    /// Report rule and default mode changes from `self` (old) to `other` (new)
+    pub fn diff(&self, other: &Settings) -> SettingsDiff {
+        let old_mode = self
+            .permissions
+            .as_ref()
+            .and_then(|p| p.default_mode.clone());
+        let new_mode = other
+            .permissions
+            .as_ref()
+            .and_then(|p| p.default_mode.clone());
+
+        SettingsDiff {
+            allow: RuleChanges::between(rules(self, |p| &p.allow), rules(other, |p| &p.allow)),
+            deny: RuleChanges::between(rules(self, |p| &p.deny), rules(other, |p| &p.deny)),
+            ask: RuleChanges::between(rules(self, |p| &p.ask), rules(other, |p| &p.ask)),
+            default_mode: (old_mode != new_mode).then_some((old_mode, new_mode)),
+        }
+    }
+}
+
+/// One rule list from settings, treating missing permissions as no rules
+fn rules(settings: &Settings, pick: fn(&PermissionSettings) -> &Option<Vec<String>>) -> &[String] {
+    settings
+        .permissions
+        .as_ref()
+        .and_then(|p| pick(p).as_deref())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(json: &str) -> Settings {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_added_allow_rule() {
+        let old = settings(r#"{"permissions": {"allow": ["Read"]}}"#);
+        let new = settings(r#"{"permissions": {"allow": ["Read", "Bash(npm test)"]}}"#);
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.allow.added, vec!["Bash(npm test)".to_string()]);
+        assert!(diff.allow.removed.is_empty());
+        assert!(diff.deny.is_empty() && diff.ask.is_empty());
+        assert_eq!(diff.default_mode, None);
+    }
+
+    #[test]
+    fn test_removed_deny_rule() {
+        let old = settings(r#"{"permissions": {"deny": ["Read(./.env)", "Bash(rm:*)"]}}"#);
+        let new = settings(r#"{"permissions": {"deny": ["Bash(rm:*)"]}}"#);
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.deny.removed, vec!["Read(./.env)".to_string()]);
+        assert!(diff.deny.added.is_empty());
+
+        // Losing the permissions section entirely removes every rule
+        let diff = old.diff(&Settings::default());
+        assert_eq!(diff.deny.removed.len(), 2);
+    }
+
+    #[test]
+    fn test_default_mode_change() {
+        let old = settings(r#"{"permissions": {"defaultMode": "default"}}"#);
+        let new = settings(r#"{"permissions": {"defaultMode": "acceptEdits"}}"#);
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.default_mode,
+            Some((Some("default".to_string()), Some("acceptEdits".to_string())))
+        );
+        assert!(diff.allow.is_empty());
+    }
+
+    #[test]
+    fn test_identical_settings_have_empty_diff() {
+        let old =
+            settings(r#"{"permissions": {"allow": ["Read", "Edit"], "defaultMode": "plan"}}"#);
+        let reordered =
+            settings(r#"{"permissions": {"allow": ["Edit", "Read"], "defaultMode": "plan"}}"#);
+        assert!(old.diff(&reordered).is_empty());
+    }
+}
@@ -4,11 +4,12 @@
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
 use super::rule::PermissionSettings;
-use crate::types::Result;
+use crate::types::{AgentError, Result};
 
 /// Settings file names
 const USER_SETTINGS_DIR: &str = ".claude";
@@ -16,6 +17,12 @@ const PROJECT_SETTINGS_DIR: &str = ".claude";
 const SETTINGS_FILE: &str = "settings.json";
 const LOCAL_SETTINGS_FILE: &str = "settings.local.json";
 
+/// Environment variable listing extra settings files, comma-separated
+///
+/// The files are merged after the discovered settings, each overriding the
+/// ones before it, so the last listed file has the highest precedence.
+pub const SETTINGS_PATHS_ENV: &str = "CLAUDE_ACP_SETTINGS";
+
 /// Claude Code settings structure
 ///
 /// This mirrors the settings structure used by Claude Code.
@@ -67,6 +74,82 @@ pub struct Settings {
     #[serde(default)]
     pub env: Option<HashMap<String, String>>,
 
+    /// Plans directory override for Plan mode (default: ~/.claude/plans/)
+    /// Relative paths are resolved against the project directory
+    #[serde(default)]
+    pub plans_directory: Option<String>,
+
+    /// Command basenames removed from the built-in safe command set
+    /// (e.g. `["find"]` stops `find` from being auto-approved in Default mode)
+    #[serde(default)]
+    pub disable_safe_commands: Option<Vec<String>>,
+
+    /// Command basenames treated as dangerous in addition to the built-in list.
+    /// They always require confirmation, even in permissive modes
+    /// (e.g. `["wipe-db"]` for an internal destructive tool)
+    #[serde(default)]
+    pub extra_dangerous_commands: Option<Vec<String>>,
+
+    /// Require confirmation for Bash commands that access the network
+    /// (`curl`, `ssh`, `git push`, ...), even in permissive modes
+    #[serde(default)]
+    pub prompt_for_network_commands: Option<bool>,
+
+    /// Make AcceptEdits mode auto-approve only reads, file edits and known
+    /// safe Bash commands instead of every tool
+    #[serde(default)]
+    pub strict_accept_edits: Option<bool>,
+
+    /// Longest Bash command (in characters) that may be auto-approved as a
+    /// known safe command; longer commands always prompt
+    #[serde(default)]
+    pub max_auto_approve_command_len: Option<usize>,
+
+    /// Seconds without prompts or tool calls after which "Always Allow" rules
+    /// granted in the session need confirming again (unset or 0 = never)
+    #[serde(default)]
+    pub allow_rule_idle_timeout_secs: Option<u64>,
+
+    /// Add the built-in deny rules for secrets and credentials
+    /// (`.env` files, private keys, `.git/`); see `SECRETS_DENY_RULES`
+    #[serde(default)]
+    pub protect_secrets: Option<bool>,
+
+    /// Reason given to the agent when DontAsk mode denies a tool, replacing
+    /// the default instruction to ask the user for a rule
+    #[serde(default)]
+    pub dont_ask_deny_message: Option<String>,
+
+    /// Times the same tool call may be denied before the denial also stops
+    /// the agent's turn (unset or 0 = never)
+    #[serde(default)]
+    pub denial_escalation_threshold: Option<usize>,
+
+    /// Permission prompts that may be outstanding at once; later prompts
+    /// wait for a slot (unset or 0 = unlimited)
+    #[serde(default)]
+    pub max_concurrent_prompts: Option<usize>,
+
+    /// Seconds during which an identical permission prompt is denied
+    /// instead of shown again (unset or 0 = never)
+    #[serde(default)]
+    pub prompt_rate_limit_secs: Option<u64>,
+
+    /// Entries each hook-to-callback permission cache keeps before evicting
+    /// the oldest (unset or 0 = unbounded)
+    #[serde(default)]
+    pub permission_cache_max_entries: Option<usize>,
+
+    /// Ask before exiting Plan mode even when no plan was written
+    #[serde(default)]
+    pub confirm_empty_plan: Option<bool>,
+
+    /// Wrapper scripts or aliases mapped to the command they run
+    /// (e.g. `{"gitw": "git"}` lets `Bash(git:*)` rules and git's safety
+    /// classification apply to `./scripts/gitw status`)
+    #[serde(default)]
+    pub command_aliases: Option<HashMap<String, String>>,
+
     /// Additional settings as raw JSON
     #[serde(flatten)]
     pub extra: HashMap<String, serde_json::Value>,
@@ -98,6 +181,15 @@ impl Settings {
         Self::default()
     }
 
+    /// Merge settings layers in order, later layers taking precedence
+    pub(crate) fn merged(layers: &[Settings]) -> Self {
+        let mut settings = Self::new();
+        for layer in layers {
+            settings.merge(layer.clone());
+        }
+        settings
+    }
+
     /// Merge another settings into this one
     ///
     /// Values from `other` take precedence over `self`.
@@ -146,6 +238,11 @@ impl Settings {
                 let ask = perms.ask.get_or_insert_with(Vec::new);
                 ask.extend(other_ask);
             }
+            // Merge output rules
+            if let Some(other_output) = other_perms.output_rules {
+                let output = perms.output_rules.get_or_insert_with(Vec::new);
+                output.extend(other_output);
+            }
             // Override additional_directories and default_mode
             if other_perms.additional_directories.is_some() {
                 perms.additional_directories = other_perms.additional_directories;
@@ -153,6 +250,18 @@ impl Settings {
             if other_perms.default_mode.is_some() {
                 perms.default_mode = other_perms.default_mode;
             }
+            if other_perms.deny_unknown_mcp_tools.is_some() {
+                perms.deny_unknown_mcp_tools = other_perms.deny_unknown_mcp_tools;
+            }
+            if other_perms.allow_always_overrides_deny.is_some() {
+                perms.allow_always_overrides_deny = other_perms.allow_always_overrides_deny;
+            }
+            if other_perms.strict_read_rule.is_some() {
+                perms.strict_read_rule = other_perms.strict_read_rule;
+            }
+            if other_perms.empty_input_default.is_some() {
+                perms.empty_input_default = other_perms.empty_input_default;
+            }
         }
         if other.mcp_servers.is_some() {
             // Merge MCP servers
@@ -164,6 +273,66 @@ impl Settings {
             }
             self.mcp_servers = Some(servers);
         }
+        if other.plans_directory.is_some() {
+            self.plans_directory = other.plans_directory;
+        }
+        if let Some(other_disabled) = other.disable_safe_commands {
+            // Merge disabled commands so any settings level can opt out
+            let disabled = self.disable_safe_commands.get_or_insert_with(Vec::new);
+            for name in other_disabled {
+                if !disabled.contains(&name) {
+                    disabled.push(name);
+                }
+            }
+        }
+        if let Some(other_dangerous) = other.extra_dangerous_commands {
+            // Merge so any settings level can add commands, but none can remove them
+            let dangerous = self.extra_dangerous_commands.get_or_insert_with(Vec::new);
+            for name in other_dangerous {
+                if !dangerous.contains(&name) {
+                    dangerous.push(name);
+                }
+            }
+        }
+        if other.prompt_for_network_commands.is_some() {
+            self.prompt_for_network_commands = other.prompt_for_network_commands;
+        }
+        if other.strict_accept_edits.is_some() {
+            self.strict_accept_edits = other.strict_accept_edits;
+        }
+        if other.max_auto_approve_command_len.is_some() {
+            self.max_auto_approve_command_len = other.max_auto_approve_command_len;
+        }
+        if other.allow_rule_idle_timeout_secs.is_some() {
+            self.allow_rule_idle_timeout_secs = other.allow_rule_idle_timeout_secs;
+        }
+        if other.protect_secrets.is_some() {
+            self.protect_secrets = other.protect_secrets;
+        }
+        if other.dont_ask_deny_message.is_some() {
+            self.dont_ask_deny_message = other.dont_ask_deny_message;
+        }
+        if other.denial_escalation_threshold.is_some() {
+            self.denial_escalation_threshold = other.denial_escalation_threshold;
+        }
+        if other.max_concurrent_prompts.is_some() {
+            self.max_concurrent_prompts = other.max_concurrent_prompts;
+        }
+        if other.prompt_rate_limit_secs.is_some() {
+            self.prompt_rate_limit_secs = other.prompt_rate_limit_secs;
+        }
+        if other.permission_cache_max_entries.is_some() {
+            self.permission_cache_max_entries = other.permission_cache_max_entries;
+        }
+        if other.confirm_empty_plan.is_some() {
+            self.confirm_empty_plan = other.confirm_empty_plan;
+        }
+        if let Some(other_aliases) = other.command_aliases {
+            // Merge aliases; later sources override the same alias
+            self.command_aliases
+                .get_or_insert_with(HashMap::new)
+                .extend(other_aliases);
+        }
         if other.env.is_some() {
             // Merge env vars
             let mut env = self.env.take().unwrap_or_default();
@@ -181,13 +350,95 @@ impl Settings {
     }
 }
 
+/// Parse a [`SETTINGS_PATHS_ENV`] value into the settings files it lists
+///
+/// Entries are comma-separated and trimmed, and empty ones are skipped.
+/// Relative paths are taken relative to the process's working directory.
+/// Files that don't exist are skipped with a warning, so a bad entry never
+/// costs the other settings.
+pub fn parse_settings_paths(value: &str) -> Vec<PathBuf> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(PathBuf::from)
+        .filter(|path| {
+            let exists = path.is_file();
+            if !exists {
+                tracing::warn!(
+                    "{} lists a settings file that does not exist, skipping it: {:?}",
+                    SETTINGS_PATHS_ENV,
+                    path
+                );
+            }
+            exists
+        })
+        .collect()
+}
+
+/// Settings files listed in [`SETTINGS_PATHS_ENV`], if it is set
+pub fn settings_paths_from_env() -> Vec<PathBuf> {
+    std::env::var(SETTINGS_PATHS_ENV)
+        .map(|value| parse_settings_paths(&value))
+        .unwrap_or_default()
+}
+
+/// Add a deny rule to the project's local settings (.claude/settings.local.json)
+///
+/// The file and its directory are created if needed. Other keys in the file
+/// are kept as they are, and a rule that is already listed isn't added again.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read or written, or if it exists
+/// but isn't a JSON object with an object `permissions` and array `deny`.
+pub fn persist_local_deny_rule(project_dir: impl AsRef<Path>, rule: &str) -> Result<()> {
+    let settings_dir = project_dir.as_ref().join(PROJECT_SETTINGS_DIR);
+    let path = settings_dir.join(LOCAL_SETTINGS_FILE);
+
+    let mut root = if path.exists() {
+        serde_json::from_str(&std::fs::read_to_string(&path)?)?
+    } else {
+        serde_json::Value::Object(serde_json::Map::new())
+    };
+    let invalid =
+        |what: &str| AgentError::ConfigError(format!("{:?}: {} is not a JSON object", path, what));
+    let permissions = root
+        .as_object_mut()
+        .ok_or_else(|| invalid("settings"))?
+        .entry("permissions")
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+        .ok_or_else(|| invalid("permissions"))?;
+    let deny = permissions
+        .entry("deny")
+        .or_insert_with(|| serde_json::json!([]))
+        .as_array_mut()
+        .ok_or_else(|| {
+            AgentError::ConfigError(format!("{:?}: permissions.deny is not a JSON array", path))
+        })?;
+    if deny.iter().any(|existing| existing.as_str() == Some(rule)) {
+        return Ok(());
+    }
+    deny.push(serde_json::Value::String(rule.to_string()));
+
+    std::fs::create_dir_all(&settings_dir)?;
+    std::fs::write(&path, serde_json::to_string_pretty(&root)? + "\n")?;
+    tracing::info!(rule = %rule, path = ?path, "Persisted deny rule to local settings");
+    Ok(())
+}
+
 /// Settings manager for loading and accessing settings
 #[derive(Debug)]
 pub struct SettingsManager {
     /// The merged settings
     settings: Settings,
+    /// Settings of each loaded source, lowest precedence first
+    layers: Vec<Settings>,
     /// Project working directory
     project_dir: PathBuf,
+    /// Extra settings files from [`SETTINGS_PATHS_ENV`], lowest precedence first
+    explicit_paths: Vec<PathBuf>,
 }
 
 impl SettingsManager {
@@ -196,14 +447,35 @@ impl SettingsManager {
     /// # Arguments
     ///
     /// * `project_dir` - The project working directory
+    ///
+    /// Files listed in [`SETTINGS_PATHS_ENV`] are merged last; see
+    /// [`parse_settings_paths`] for how bad entries are handled.
     pub fn new(project_dir: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::with_explicit_paths(
+            project_dir,
+            settings_paths_from_env(),
+        ))
+    }
+
+    /// Create a settings manager that also merges the given settings files
+    ///
+    /// The files are merged after the discovered settings, in order; this is
+    /// what [`SettingsManager::new`] does with the files from
+    /// [`SETTINGS_PATHS_ENV`]. Files that can't be read are skipped with a
+    /// warning.
+    pub fn with_explicit_paths(
+        project_dir: impl AsRef<Path>,
+        explicit_paths: Vec<PathBuf>,
+    ) -> Self {
         let project_dir = project_dir.as_ref().to_path_buf();
-        let settings = Self::load_all_settings(&project_dir);
+        let layers = Self::load_all_settings(&project_dir, &explicit_paths);
 
-        Ok(Self {
-            settings,
+        Self {
+            settings: Settings::merged(&layers),
+            layers,
             project_dir,
-        })
+            explicit_paths,
+        }
     }
 
     /// Create a new settings manager with provided settings (fallback)
@@ -216,36 +488,52 @@ impl SettingsManager {
         let project_dir = project_dir.as_ref().to_path_buf();
 
         Self {
+            layers: vec![settings.clone()],
             settings,
             project_dir,
+            explicit_paths: Vec::new(),
         }
     }
 
-    /// Load and merge all settings sources
+    /// Load all settings sources, lowest precedence first
     ///
-    /// Priority: Local > Project > User
-    fn load_all_settings(project_dir: &Path) -> Settings {
-        let mut settings = Settings::new();
+    /// Priority: explicit files (last listed first) > Local > Project > User
+    fn load_all_settings(project_dir: &Path, explicit_paths: &[PathBuf]) -> Vec<Settings> {
+        let mut layers = Vec::new();
 
         // 1. Load user settings (~/.claude/settings.json)
         if let Some(user_settings) = Self::load_user_settings() {
             tracing::debug!("Loaded user settings");
-            settings.merge(user_settings);
+            layers.push(user_settings);
         }
 
         // 2. Load project settings (.claude/settings.json)
         if let Some(project_settings) = Self::load_project_settings(project_dir) {
             tracing::debug!("Loaded project settings from {:?}", project_dir);
-            settings.merge(project_settings);
+            layers.push(project_settings);
         }
 
         // 3. Load local settings (.claude/settings.local.json)
         if let Some(local_settings) = Self::load_local_settings(project_dir) {
             tracing::debug!("Loaded local settings from {:?}", project_dir);
-            settings.merge(local_settings);
+            layers.push(local_settings);
         }
 
-        settings
+        // 4. Load files listed in CLAUDE_ACP_SETTINGS, in order
+        for path in explicit_paths {
+            if let Some(explicit_settings) = Self::load_settings_file(path) {
+                tracing::debug!("Loaded settings from {:?} ({})", path, SETTINGS_PATHS_ENV);
+                layers.push(explicit_settings);
+            } else {
+                tracing::warn!(
+                    "Settings file {:?} from {} was not loaded",
+                    path,
+                    SETTINGS_PATHS_ENV
+                );
+            }
+        }
+
+        layers
     }
 
     /// Load user settings from ~/.claude/settings.json
@@ -295,14 +583,32 @@ impl SettingsManager {
         &self.settings
     }
 
+    /// Get the settings of each loaded source, lowest precedence first
+    ///
+    /// User, project and local settings, then the files from
+    /// [`SETTINGS_PATHS_ENV`]; sources that weren't found are left out.
+    /// [`Self::settings`] is these merged in order.
+    pub fn layers(&self) -> &[Settings] {
+        &self.layers
+    }
+
     /// Get the project directory
     pub fn project_dir(&self) -> &Path {
         &self.project_dir
     }
 
     /// Reload settings from all sources
+    ///
+    /// Files from [`SETTINGS_PATHS_ENV`] are the ones listed when the
+    /// manager was created.
     pub fn reload(&mut self) {
-        self.settings = Self::load_all_settings(&self.project_dir);
+        self.layers = Self::load_all_settings(&self.project_dir, &self.explicit_paths);
+        self.settings = Settings::merged(&self.layers);
+    }
+
+    /// Get the extra settings files listed in [`SETTINGS_PATHS_ENV`]
+    pub fn explicit_paths(&self) -> &[PathBuf] {
+        &self.explicit_paths
     }
 
     /// Get the system prompt if configured
@@ -345,6 +651,92 @@ impl SettingsManager {
         self.settings.env.as_ref()
     }
 
+    /// Get the plans directory override, resolved against the project directory
+    pub fn plans_directory(&self) -> Option<PathBuf> {
+        self.settings.plans_directory.as_ref().map(|dir| {
+            let path = PathBuf::from(dir);
+            if path.is_absolute() {
+                path
+            } else {
+                self.project_dir.join(path)
+            }
+        })
+    }
+
+    /// Get command basenames that should never be auto-approved as safe
+    pub fn disable_safe_commands(&self) -> &[String] {
+        self.settings
+            .disable_safe_commands
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    /// Get command basenames that always require confirmation
+    pub fn extra_dangerous_commands(&self) -> &[String] {
+        self.settings
+            .extra_dangerous_commands
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    /// Whether Bash commands that access the network always require confirmation
+    pub fn prompt_for_network_commands(&self) -> bool {
+        self.settings.prompt_for_network_commands.unwrap_or(false)
+    }
+
+    /// Whether AcceptEdits mode should use the strict strategy
+    pub fn strict_accept_edits(&self) -> bool {
+        self.settings.strict_accept_edits.unwrap_or(false)
+    }
+
+    /// Get the longest Bash command that may be auto-approved, if limited
+    pub fn max_auto_approve_command_len(&self) -> Option<usize> {
+        self.settings.max_auto_approve_command_len
+    }
+
+    /// Get the idle time after which session "Always Allow" rules expire
+    pub fn allow_rule_idle_timeout(&self) -> Option<Duration> {
+        match self.settings.allow_rule_idle_timeout_secs {
+            None | Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    /// Get the configured DontAsk denial message, if any
+    pub fn dont_ask_deny_message(&self) -> Option<&str> {
+        self.settings.dont_ask_deny_message.as_deref()
+    }
+
+    /// Get the number of identical denials that stop the turn, if enabled
+    pub fn denial_escalation_threshold(&self) -> Option<usize> {
+        self.settings.denial_escalation_threshold
+    }
+
+    /// Get how many permission prompts may be outstanding at once, if limited
+    pub fn max_concurrent_prompts(&self) -> Option<usize> {
+        self.settings.max_concurrent_prompts
+    }
+
+    /// Get the window for throttling identical permission prompts, if enabled
+    pub fn prompt_rate_limit(&self) -> Option<Duration> {
+        match self.settings.prompt_rate_limit_secs {
+            None | Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    /// Get the number of entries kept per permission cache, if bounded
+    pub fn permission_cache_max_entries(&self) -> Option<usize> {
+        self.settings
+            .permission_cache_max_entries
+            .filter(|&max| max > 0)
+    }
+
+    /// Whether exiting Plan mode without a plan should still ask
+    pub fn confirm_empty_plan(&self) -> bool {
+        self.settings.confirm_empty_plan.unwrap_or(false)
+    }
+
     /// Check if a tool is allowed
     pub fn is_tool_allowed(&self, tool_name: &str) -> bool {
         // If denied_tools is set and contains the tool, deny it
@@ -508,6 +900,147 @@ mod tests {
         assert_eq!(manager.model(), Some("claude-sonnet"));
         // System prompt from project should remain
         assert_eq!(manager.system_prompt(), Some("Project prompt"));
+
+        // Each source is also kept on its own, project before local
+        let models: Vec<_> = manager
+            .layers()
+            .iter()
+            .rev()
+            .take(2)
+            .map(|layer| layer.model.as_deref())
+            .collect();
+        assert_eq!(models, [Some("claude-sonnet"), Some("claude-opus")]);
+    }
+
+    #[test]
+    fn test_persist_local_deny_rule() {
+        let temp_dir = TempDir::new().unwrap();
+        let settings_dir = temp_dir.path().join(".claude");
+        std::fs::create_dir_all(&settings_dir).unwrap();
+        let local_settings = settings_dir.join("settings.local.json");
+        std::fs::write(
+            &local_settings,
+            r#"{"model": "claude-sonnet", "permissions": {"allow": ["Bash(ls:*)"]}}"#,
+        )
+        .unwrap();
+
+        persist_local_deny_rule(temp_dir.path(), "Bash(curl:*)").unwrap();
+        persist_local_deny_rule(temp_dir.path(), "Bash(curl:*)").unwrap();
+
+        let manager = SettingsManager::new(temp_dir.path()).unwrap();
+        assert_eq!(manager.model(), Some("claude-sonnet"));
+        let permissions = manager.settings().permissions.as_ref().unwrap();
+        assert!(
+            permissions
+                .allow
+                .as_ref()
+                .unwrap()
+                .contains(&"Bash(ls:*)".to_string())
+        );
+        let deny = permissions.deny.as_ref().unwrap();
+        assert_eq!(
+            deny.iter().filter(|rule| *rule == "Bash(curl:*)").count(),
+            1
+        );
+
+        // A missing file is created
+        let fresh_dir = TempDir::new().unwrap();
+        persist_local_deny_rule(fresh_dir.path(), "Edit(./secrets.txt)").unwrap();
+        let content =
+            std::fs::read_to_string(fresh_dir.path().join(".claude/settings.local.json")).unwrap();
+        assert!(content.contains("Edit(./secrets.txt)"));
+    }
+
+    #[test]
+    fn test_plans_directory_resolution() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut settings = Settings::new();
+        settings.plans_directory = Some("my-plans".to_string());
+        let manager = SettingsManager::new_with_settings(settings, temp_dir.path());
+        assert_eq!(
+            manager.plans_directory(),
+            Some(temp_dir.path().join("my-plans"))
+        );
+
+        let mut settings = Settings::new();
+        settings.plans_directory = Some("/var/plans".to_string());
+        let manager = SettingsManager::new_with_settings(settings, temp_dir.path());
+        assert_eq!(manager.plans_directory(), Some(PathBuf::from("/var/plans")));
+
+        let manager = SettingsManager::new_with_settings(Settings::new(), temp_dir.path());
+        assert!(manager.plans_directory().is_none());
+    }
+
+    #[test]
+    fn test_command_aliases_merge() {
+        let mut base: Settings =
+            serde_json::from_str(r#"{"commandAliases": {"gitw": "git", "k": "kubectl"}}"#).unwrap();
+        let local: Settings =
+            serde_json::from_str(r#"{"commandAliases": {"k": "kubecolor"}}"#).unwrap();
+        base.merge(local);
+
+        let aliases = base.command_aliases.unwrap();
+        assert_eq!(aliases.get("gitw").map(String::as_str), Some("git"));
+        assert_eq!(aliases.get("k").map(String::as_str), Some("kubecolor"));
+    }
+
+    #[test]
+    fn test_disable_safe_commands_merge() {
+        let mut base: Settings =
+            serde_json::from_str(r#"{"disableSafeCommands": ["find"]}"#).unwrap();
+        let local: Settings =
+            serde_json::from_str(r#"{"disableSafeCommands": ["find", "rg"]}"#).unwrap();
+        base.merge(local);
+        assert_eq!(
+            base.disable_safe_commands,
+            Some(vec!["find".to_string(), "rg".to_string()])
+        );
+
+        let temp_dir = TempDir::new().unwrap();
+        let manager = SettingsManager::new_with_settings(base, temp_dir.path());
+        assert_eq!(manager.disable_safe_commands(), ["find", "rg"]);
+
+        let manager = SettingsManager::new_with_settings(Settings::new(), temp_dir.path());
+        assert!(manager.disable_safe_commands().is_empty());
+    }
+
+    #[test]
+    fn test_prompt_settings() {
+        let mut base: Settings = serde_json::from_str(
+            r#"{"maxConcurrentPrompts": 1, "promptRateLimitSecs": 30, "confirmEmptyPlan": true}"#,
+        )
+        .unwrap();
+        let local: Settings = serde_json::from_str(
+            r#"{"maxConcurrentPrompts": 2, "promptRateLimitSecs": 0, "permissionCacheMaxEntries": 500}"#,
+        )
+        .unwrap();
+        base.merge(local);
+
+        let temp_dir = TempDir::new().unwrap();
+        let manager = SettingsManager::new_with_settings(base, temp_dir.path());
+        assert_eq!(manager.max_concurrent_prompts(), Some(2));
+        assert_eq!(manager.prompt_rate_limit(), None);
+        assert_eq!(manager.permission_cache_max_entries(), Some(500));
+        assert!(manager.confirm_empty_plan());
+
+        let manager = SettingsManager::new_with_settings(Settings::new(), temp_dir.path());
+        assert_eq!(manager.max_concurrent_prompts(), None);
+        assert_eq!(manager.permission_cache_max_entries(), None);
+        assert!(!manager.confirm_empty_plan());
+    }
+
+    #[test]
+    fn test_extra_dangerous_commands_merge() {
+        let mut base: Settings =
+            serde_json::from_str(r#"{"extraDangerousCommands": ["wipe-db"]}"#).unwrap();
+        let local: Settings =
+            serde_json::from_str(r#"{"extraDangerousCommands": ["reset-env", "wipe-db"]}"#)
+                .unwrap();
+        base.merge(local);
+
+        let temp_dir = TempDir::new().unwrap();
+        let manager = SettingsManager::new_with_settings(base, temp_dir.path());
+        assert_eq!(manager.extra_dangerous_commands(), ["wipe-db", "reset-env"]);
     }
 
     #[test]
@@ -564,6 +1097,109 @@ mod tests {
         assert_eq!(manager.model(), Some("claude-sonnet"));
     }
 
+    #[test]
+    fn test_explicit_settings_merge_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let settings_dir = temp_dir.path().join(".claude");
+        std::fs::create_dir_all(&settings_dir).unwrap();
+        std::fs::write(
+            settings_dir.join("settings.local.json"),
+            r#"{"model": "local-model", "systemPrompt": "local prompt"}"#,
+        )
+        .unwrap();
+
+        let base = temp_dir.path().join("base.json");
+        std::fs::write(
+            &base,
+            r#"{"model": "base-model", "permissionMode": "plan", "env": {"A": "1"}}"#,
+        )
+        .unwrap();
+        let ci = temp_dir.path().join("ci.json");
+        std::fs::write(&ci, r#"{"model": "ci-model", "env": {"B": "2"}}"#).unwrap();
+
+        let paths = parse_settings_paths(&format!("{}, {}", base.display(), ci.display()));
+        assert_eq!(paths, vec![base.clone(), ci.clone()]);
+        let mut manager = SettingsManager::with_explicit_paths(temp_dir.path(), paths);
+
+        // Later files override earlier ones and the discovered settings
+        assert_eq!(manager.model(), Some("ci-model"));
+        assert_eq!(manager.permission_mode(), Some("plan"));
+        assert_eq!(manager.system_prompt(), Some("local prompt"));
+        let env = manager.env().unwrap();
+        assert_eq!(env.get("A").map(String::as_str), Some("1"));
+        assert_eq!(env.get("B").map(String::as_str), Some("2"));
+
+        // Reloading re-reads the same files
+        std::fs::write(&ci, r#"{"model": "ci-model-2"}"#).unwrap();
+        manager.reload();
+        assert_eq!(manager.model(), Some("ci-model-2"));
+    }
+
+    #[test]
+    fn test_parse_settings_paths_skips_missing_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let missing = temp_dir.path().join("missing.json");
+        let present = temp_dir.path().join("present.json");
+        std::fs::write(&present, "{}").unwrap();
+
+        let paths = parse_settings_paths(&format!("{},{}", missing.display(), present.display()));
+        assert_eq!(paths, vec![present]);
+
+        assert!(parse_settings_paths(" , ").is_empty());
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_settings_paths_env() {
+        let temp_dir = TempDir::new().unwrap();
+        // Only keys no other test reads, since the variable is process-wide
+        let first = temp_dir.path().join("first.json");
+        std::fs::write(
+            &first,
+            r#"{"acpTestMarker": "first", "acpTestOnlyFirst": true}"#,
+        )
+        .unwrap();
+        let second = temp_dir.path().join("second.json");
+        std::fs::write(&second, r#"{"acpTestMarker": "second"}"#).unwrap();
+        // A missing file is skipped instead of failing the whole load
+        let missing = temp_dir.path().join("missing.json");
+        let settings_dir = temp_dir.path().join(".claude");
+        std::fs::create_dir_all(&settings_dir).unwrap();
+        std::fs::write(
+            settings_dir.join("settings.json"),
+            r#"{"acpTestProjectMarker": true}"#,
+        )
+        .unwrap();
+
+        let original = std::env::var(SETTINGS_PATHS_ENV).ok();
+        // Safety: serial test; the original value is restored below
+        unsafe {
+            std::env::set_var(
+                SETTINGS_PATHS_ENV,
+                format!(
+                    "{},{},{}",
+                    first.display(),
+                    missing.display(),
+                    second.display()
+                ),
+            );
+        }
+        let manager = SettingsManager::new(temp_dir.path());
+        unsafe {
+            match original {
+                Some(value) => std::env::set_var(SETTINGS_PATHS_ENV, value),
+                None => std::env::remove_var(SETTINGS_PATHS_ENV),
+            }
+        }
+
+        let manager = manager.unwrap();
+        assert_eq!(manager.explicit_paths(), &[first, second]);
+        let extra = &manager.settings().extra;
+        assert_eq!(extra["acpTestMarker"], "second");
+        assert_eq!(extra["acpTestOnlyFirst"], true);
+        assert_eq!(extra["acpTestProjectMarker"], true);
+    }
+
     #[test]
     #[serial_test::serial]
     fn test_settings_deserialize_always_thinking_enabled() {
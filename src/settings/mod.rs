@@ -7,12 +7,26 @@
 //!
 //! Priority: Local > Project > User
 
+mod diff;
 mod manager;
+mod output_rule;
 mod permission_checker;
 mod rule;
+mod schema;
 mod watcher;
 
-pub use manager::{McpServerConfig, Settings, SettingsManager};
-pub use permission_checker::PermissionChecker;
-pub use rule::{ParsedRule, PermissionCheckResult, PermissionDecision, PermissionSettings};
+pub use diff::{RuleChanges, SettingsDiff};
+pub use manager::{
+    McpServerConfig, SETTINGS_PATHS_ENV, Settings, SettingsManager, parse_settings_paths,
+    persist_local_deny_rule, settings_paths_from_env,
+};
+pub use output_rule::{ToolOutputRule, ToolOutputViolation, check_tool_output};
+pub use permission_checker::{
+    DecisionMismatch, PermissionChecker, PermissionRuleEntry, SECRETS_DENY_RULES,
+};
+pub use rule::{
+    EmptyInputPolicy, ParsedRule, PermissionCheckResult, PermissionDecision, PermissionSettings,
+    RuleComponents, RuleMatcherKind, USE_LIMITED_SOURCE,
+};
+pub use schema::json_schema;
 pub use watcher::{SettingsChangeEvent, SettingsWatcher, WatcherError, WatcherHandle};
@@ -0,0 +1,227 @@
+//! Tool output rules evaluated by the PostToolUse hook
+//!
+//! By the time a result arrives the tool has already run, so these rules
+//! cannot block anything. They flag unexpectedly large or binary responses
+//! (runaway output, possible exfiltration) and can stop the current turn
+//! before the model acts on them.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::rule::ParsedRule;
+
+/// Fraction of control characters above which text is treated as binary
+const BINARY_CONTROL_CHAR_RATIO: f64 = 0.3;
+
+/// A rule inspecting the response of a completed tool call
+///
+/// ```json
+/// { "tool": "Bash", "maxBytes": 1048576, "flagBinary": true, "interrupt": true }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolOutputRule {
+    /// Tool name the rule applies to (tool groups like "Read" are honored).
+    /// Applies to every tool when omitted.
+    #[serde(default)]
+    pub tool: Option<String>,
+
+    /// Flag responses whose serialized size exceeds this many bytes
+    #[serde(default)]
+    pub max_bytes: Option<usize>,
+
+    /// Flag responses that look like binary data
+    #[serde(default)]
+    pub flag_binary: bool,
+
+    /// Stop the current turn instead of only emitting a warning
+    #[serde(default)]
+    pub interrupt: bool,
+}
+
+/// Why a tool response was flagged by a [`ToolOutputRule`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolOutputViolation {
+    /// Human-readable notice describing the violation
+    pub message: String,
+    /// Whether the matching rule asked to stop the turn
+    pub interrupt: bool,
+}
+
+impl ToolOutputRule {
+    /// Check if this rule applies to the given tool
+    fn applies_to(&self, tool_name: &str) -> bool {
+        self.tool.as_deref().is_none_or(|tool| {
+            ParsedRule::parse(tool).matches(tool_name, &Value::Null, Path::new(""))
+        })
+    }
+
+    /// Evaluate the rule against a tool response
+    fn check(&self, tool_name: &str, response: &Value) -> Option<ToolOutputViolation> {
+        if !self.applies_to(tool_name) {
+            return None;
+        }
+
+        if let Some(max_bytes) = self.max_bytes {
+            let size = response_size(response);
+            if size > max_bytes {
+                return Some(ToolOutputViolation {
+                    message: format!(
+                        "{} returned {} bytes, exceeding the configured limit of {} bytes",
+                        tool_name, size, max_bytes
+                    ),
+                    interrupt: self.interrupt,
+                });
+            }
+        }
+
+        if self.flag_binary && contains_binary(response) {
+            return Some(ToolOutputViolation {
+                message: format!("{} returned what looks like binary data", tool_name),
+                interrupt: self.interrupt,
+            });
+        }
+
+        None
+    }
+}
+
+/// Evaluate output rules against a tool response
+///
+/// An interrupting violation takes precedence over warnings so the turn is
+/// stopped whenever any matching rule asks for it.
+pub fn check_tool_output(
+    rules: &[ToolOutputRule],
+    tool_name: &str,
+    response: &Value,
+) -> Option<ToolOutputViolation> {
+    let mut warning = None;
+    for violation in rules
+        .iter()
+        .filter_map(|rule| rule.check(tool_name, response))
+    {
+        if violation.interrupt {
+            return Some(violation);
+        }
+        warning.get_or_insert(violation);
+    }
+    warning
+}
+
+/// Size of a response in bytes (raw length for plain strings)
+fn response_size(response: &Value) -> usize {
+    match response {
+        Value::String(text) => text.len(),
+        other => other.to_string().len(),
+    }
+}
+
+/// Check if any string in the response looks like binary data
+fn contains_binary(response: &Value) -> bool {
+    match response {
+        Value::String(text) => looks_binary(text),
+        Value::Array(items) => items.iter().any(contains_binary),
+        Value::Object(map) => map.values().any(contains_binary),
+        _ => false,
+    }
+}
+
+/// Heuristic: NUL bytes or a high share of non-whitespace control characters
+fn looks_binary(text: &str) -> bool {
+    if text.contains('\0') {
+        return true;
+    }
+    let total = text.chars().count();
+    if total == 0 {
+        return false;
+    }
+    let control = text
+        .chars()
+        .filter(|c| c.is_control() && !c.is_whitespace())
+        .count();
+    control as f64 / total as f64 > BINARY_CONTROL_CHAR_RATIO
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn size_rule(max_bytes: usize, interrupt: bool) -> ToolOutputRule {
+        ToolOutputRule {
+            tool: Some("Bash".to_string()),
+            max_bytes: Some(max_bytes),
+            interrupt,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_response_over_threshold_is_flagged() {
+        let rules = vec![size_rule(10, false)];
+        let violation = check_tool_output(&rules, "Bash", &json!("x".repeat(11))).unwrap();
+        assert!(!violation.interrupt);
+        assert!(violation.message.contains("11 bytes"));
+
+        assert!(check_tool_output(&rules, "Bash", &json!("x".repeat(10))).is_none());
+    }
+
+    #[test]
+    fn test_structured_response_size_uses_serialized_json() {
+        let rules = vec![size_rule(20, true)];
+        let response = json!({"stdout": "x".repeat(30), "stderr": ""});
+        let violation = check_tool_output(&rules, "mcp__acp__Bash", &response).unwrap();
+        assert!(violation.interrupt);
+    }
+
+    #[test]
+    fn test_rule_scoped_to_tool() {
+        let rules = vec![size_rule(1, true)];
+        assert!(check_tool_output(&rules, "Read", &json!("long output")).is_none());
+
+        let read_group = vec![ToolOutputRule {
+            tool: Some("Read".to_string()),
+            max_bytes: Some(1),
+            ..Default::default()
+        }];
+        assert!(check_tool_output(&read_group, "Grep", &json!("long output")).is_some());
+    }
+
+    #[test]
+    fn test_binary_detection() {
+        let rules = vec![ToolOutputRule {
+            flag_binary: true,
+            ..Default::default()
+        }];
+        assert!(check_tool_output(&rules, "Read", &json!("PNG\u{0}\u{1}\u{2}")).is_some());
+        assert!(
+            check_tool_output(&rules, "Read", &json!({"content": "\u{1}\u{2}\u{3}ab"})).is_some()
+        );
+        assert!(check_tool_output(&rules, "Read", &json!("plain\ttext\n")).is_none());
+    }
+
+    #[test]
+    fn test_interrupt_takes_precedence_over_warning() {
+        let rules = vec![size_rule(5, false), size_rule(8, true)];
+        let violation = check_tool_output(&rules, "Bash", &json!("x".repeat(9))).unwrap();
+        assert!(violation.interrupt);
+
+        let violation = check_tool_output(&rules, "Bash", &json!("x".repeat(6))).unwrap();
+        assert!(!violation.interrupt);
+    }
+
+    #[test]
+    fn test_deserialize_camel_case() {
+        let rule: ToolOutputRule = serde_json::from_value(json!({
+            "tool": "Bash",
+            "maxBytes": 1024,
+            "flagBinary": true,
+            "interrupt": true
+        }))
+        .unwrap();
+        assert_eq!(rule.max_bytes, Some(1024));
+        assert!(rule.flag_binary && rule.interrupt);
+    }
+}
@@ -2,70 +2,405 @@
 //!
 //! Checks tool permissions against settings rules.
 
-use std::path::{Path, PathBuf};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use serde::Serialize;
+use thiserror::Error;
 
 use super::manager::Settings;
-use super::rule::{ParsedRule, PermissionCheckResult};
-use crate::command_safety::extract_command_basename;
+use super::output_rule::ToolOutputRule;
+use super::rule::{
+    EmptyInputPolicy, MatchCache, ParsedRule, PermissionCheckResult, PermissionDecision,
+    USE_LIMITED_SOURCE, escape_glob, to_slash_path,
+};
+use crate::command_safety::{
+    extract_command_basename, resolve_command_alias, split_command_segments,
+};
+use crate::tools::{BashInput, FilePathInput, ToolInputExt};
+use crate::utils::{is_path_within_dir, resolve_dot_segments};
+
+/// Deny rules added when `protectSecrets` is enabled in settings
+///
+/// Environment files and private keys can't be read or edited, and git's
+/// internals can't be edited, in any directory.
+pub const SECRETS_DENY_RULES: &[&str] = &[
+    "Read(**/*.env)",
+    "Edit(**/*.env)",
+    "Read(**/.env.*)",
+    "Edit(**/.env.*)",
+    "Read(**/*.pem)",
+    "Edit(**/*.pem)",
+    "Read(**/*.key)",
+    "Edit(**/*.key)",
+    "Read(**/id_rsa*)",
+    "Edit(**/id_rsa*)",
+    "Read(**/id_ed25519*)",
+    "Edit(**/id_ed25519*)",
+    "Edit(**/.git/**)",
+];
+
+/// Mismatch between an expected and actual permission decision
+///
+/// Returned by [`PermissionChecker::assert_decision`] and
+/// [`PermissionChecker::evaluate_examples`] so integrators can write
+/// table-driven tests against their own settings files.
+#[derive(Debug, Clone, Error)]
+#[error(
+    "{tool_name} {tool_input}: expected {expected:?}, got {actual:?} (rule: {})",
+    .rule.as_deref().unwrap_or("<none>")
+)]
+pub struct DecisionMismatch {
+    /// Tool name that was checked
+    pub tool_name: String,
+    /// Tool input that was checked
+    pub tool_input: serde_json::Value,
+    /// Expected decision
+    pub expected: PermissionDecision,
+    /// Actual decision
+    pub actual: PermissionDecision,
+    /// Rule that produced the actual decision (if any)
+    pub rule: Option<String>,
+}
+
+/// A configured permission rule, as listed by [`PermissionChecker::list_permission_rules`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionRuleEntry {
+    /// The rule as written, e.g. `Bash(npm run:*)`
+    pub rule: String,
+    /// The list the rule is in: `"allow"`, `"deny"` or `"ask"`
+    pub source: &'static str,
+    /// Whether the rule was added in this session rather than by settings
+    pub runtime: bool,
+    /// Whether the rule takes part in matching (see [`PermissionChecker::set_rule_enabled`])
+    pub enabled: bool,
+}
+
+/// A runtime allow rule that lapses after a number of uses
+#[derive(Debug, Clone)]
+struct UseLimitedRule {
+    rule: String,
+    parsed: ParsedRule,
+    remaining: u32,
+}
+
+/// Use-limited allow rules, shared by concurrent permission checks
+///
+/// [`PermissionChecker::spend_use`] takes `&self`, so the counters sit
+/// behind their own lock; checking that a use is left and taking it happen
+/// under that one lock, so two calls can't spend the same use. Clones get
+/// their own copy of the counters.
+#[derive(Debug, Default)]
+struct UseLimitedRules(Mutex<Vec<UseLimitedRule>>);
+
+impl UseLimitedRules {
+    fn lock(&self) -> MutexGuard<'_, Vec<UseLimitedRule>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Clone for UseLimitedRules {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.lock().clone()))
+    }
+}
+
+/// Parsed rules from one settings source (user, project, local, ...)
+#[derive(Debug, Clone, Default)]
+struct RuleSet {
+    allow: Vec<(String, ParsedRule)>,
+    deny: Vec<(String, ParsedRule)>,
+    ask: Vec<(String, ParsedRule)>,
+}
+
+impl RuleSet {
+    fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty() && self.ask.is_empty()
+    }
+}
 
 /// Permission checker that evaluates tool permissions against settings rules
-#[derive(Debug)]
+///
+/// Cloning reuses the already parsed rules (no re-parsing or glob
+/// compilation), including rules added at runtime. Use
+/// [`PermissionChecker::without_runtime_rules`] to start a session from the
+/// same settings without another session's in-session approvals.
+#[derive(Debug, Clone)]
 pub struct PermissionChecker {
     /// Merged settings with permission rules
     settings: Settings,
     /// Working directory for path resolution
     cwd: PathBuf,
-    /// Parsed and cached allow rules
-    allow_rules: Vec<(String, ParsedRule)>,
-    /// Parsed and cached deny rules
-    deny_rules: Vec<(String, ParsedRule)>,
-    /// Parsed and cached ask rules
-    ask_rules: Vec<(String, ParsedRule)>,
+    /// Directory the agent's shell moved to with `cd` (None = still the cwd)
+    current_dir: Option<PathBuf>,
+    /// Project whose `.claude/` settings files hold the rules (the cwd unless set)
+    project_dir: PathBuf,
+    /// Parsed and cached settings rules, one set per settings source in
+    /// load order (the `protectSecrets` rules form a set of their own)
+    rule_sets: Vec<RuleSet>,
+    /// Allow rules added at runtime (e.g., "Always Allow"), kept across reloads
+    runtime_allow_rules: Vec<(String, ParsedRule)>,
+    /// Deny rules added at runtime, kept across reloads
+    runtime_deny_rules: Vec<(String, ParsedRule)>,
+    /// Which of the runtime deny rules came from "Reject for Session" and
+    /// are dropped on `/clear`
+    session_deny_rules: HashSet<String>,
+    /// Allow rules added at runtime for a number of uses, kept across reloads
+    use_limited_allow_rules: UseLimitedRules,
+    /// Advertised tool titles by tool name (without the `mcp__acp__` prefix),
+    /// matched by `ToolTitle(...)` rules
+    tool_titles: HashMap<String, String>,
+    /// Rules skipped during matching, kept across reloads
+    disabled_rules: HashSet<String>,
 }
 
 impl PermissionChecker {
     /// Create a new permission checker
+    ///
+    /// All of the settings' rules form one rule set; use
+    /// [`Self::from_layers`] to keep each settings source's rules apart.
     pub fn new(settings: Settings, cwd: impl AsRef<Path>) -> Self {
         let cwd = cwd.as_ref().to_path_buf();
+        let rule_sets = Self::parse_rule_sets(&settings, std::slice::from_ref(&settings), &cwd);
+        Self::with_rule_sets(settings, cwd, rule_sets)
+    }
 
-        // Pre-parse rules for efficiency
-        let allow_rules = Self::parse_rules(
-            settings.permissions.as_ref().and_then(|p| p.allow.as_ref()),
-            &cwd,
-        );
-        let deny_rules = Self::parse_rules(
-            settings.permissions.as_ref().and_then(|p| p.deny.as_ref()),
-            &cwd,
-        );
-        let ask_rules = Self::parse_rules(
-            settings.permissions.as_ref().and_then(|p| p.ask.as_ref()),
-            &cwd,
-        );
+    /// Create a permission checker from the settings of each source
+    ///
+    /// `layers` are the settings files in load order, as returned by
+    /// [`SettingsManager::layers`](super::SettingsManager::layers). Each
+    /// layer's rules are evaluated on their own and the results combined with
+    /// [`PermissionCheckResult::merge`]; everything else comes from the
+    /// merged settings.
+    pub fn from_layers(layers: &[Settings], cwd: impl AsRef<Path>) -> Self {
+        let cwd = cwd.as_ref().to_path_buf();
+        let settings = Settings::merged(layers);
+        let rule_sets = Self::parse_rule_sets(&settings, layers, &cwd);
+        Self::with_rule_sets(settings, cwd, rule_sets)
+    }
 
+    fn with_rule_sets(settings: Settings, cwd: PathBuf, rule_sets: Vec<RuleSet>) -> Self {
         Self {
             settings,
+            project_dir: cwd.clone(),
             cwd,
-            allow_rules,
-            deny_rules,
-            ask_rules,
+            current_dir: None,
+            rule_sets,
+            runtime_allow_rules: Vec::new(),
+            runtime_deny_rules: Vec::new(),
+            session_deny_rules: HashSet::new(),
+            use_limited_allow_rules: UseLimitedRules::default(),
+            tool_titles: HashMap::new(),
+            disabled_rules: HashSet::new(),
+        }
+    }
+
+    /// Copy this checker's settings and parsed rules, dropping runtime rules
+    ///
+    /// Cheaper than [`PermissionChecker::new`] with the same settings, since
+    /// rules are not parsed again. The copy keeps this checker's cwd.
+    pub fn without_runtime_rules(&self) -> Self {
+        Self {
+            settings: self.settings.clone(),
+            cwd: self.cwd.clone(),
+            current_dir: self.current_dir.clone(),
+            project_dir: self.project_dir.clone(),
+            rule_sets: self.rule_sets.clone(),
+            runtime_allow_rules: Vec::new(),
+            runtime_deny_rules: Vec::new(),
+            session_deny_rules: HashSet::new(),
+            use_limited_allow_rules: UseLimitedRules::default(),
+            tool_titles: self.tool_titles.clone(),
+            disabled_rules: self.disabled_rules.clone(),
+        }
+    }
+
+    /// Reload file-sourced rules from new settings
+    ///
+    /// Re-parses allow/deny/ask rules in place. Rules added at runtime via
+    /// `add_allow_rule`, `add_allow_rule_for_tool_call` or `add_deny_rule`
+    /// are preserved so in-session approvals survive a settings reload.
+    pub fn reload_from(&mut self, settings: Settings) {
+        let rule_sets =
+            Self::parse_rule_sets(&settings, std::slice::from_ref(&settings), &self.cwd);
+        self.reload_rule_sets(settings, rule_sets);
+    }
+
+    /// Reload file-sourced rules from the settings of each source
+    ///
+    /// Like [`Self::reload_from`], but keeps each layer's rules apart as
+    /// [`Self::from_layers`] does.
+    pub fn reload_from_layers(&mut self, layers: &[Settings]) {
+        let settings = Settings::merged(layers);
+        let rule_sets = Self::parse_rule_sets(&settings, layers, &self.cwd);
+        self.reload_rule_sets(settings, rule_sets);
+    }
+
+    fn reload_rule_sets(&mut self, settings: Settings, rule_sets: Vec<RuleSet>) {
+        let strict_read = Self::strict_read_in(&settings);
+        self.rule_sets = rule_sets;
+        for (_, rule) in self
+            .runtime_allow_rules
+            .iter_mut()
+            .chain(&mut self.runtime_deny_rules)
+        {
+            rule.set_strict_read(strict_read);
+        }
+        for limited in self.use_limited_allow_rules.lock().iter_mut() {
+            limited.parsed.set_strict_read(strict_read);
+        }
+        self.settings = settings;
+
+        tracing::info!(
+            rule_sets = self.rule_sets.len(),
+            allow_rules = self.settings_allow_rules().count(),
+            deny_rules = self.settings_deny_rules().count(),
+            ask_rules = self.settings_ask_rules().count(),
+            runtime_allow_rules = self.runtime_allow_rules.len(),
+            runtime_deny_rules = self.runtime_deny_rules.len(),
+            "Reloaded permission rules from settings"
+        );
+    }
+
+    /// Parse each layer's rules into a rule set, plus the secrets rules if
+    /// `protectSecrets` is enabled in the merged `settings`
+    ///
+    /// `strictReadRule` also comes from the merged settings.
+    fn parse_rule_sets(settings: &Settings, layers: &[Settings], cwd: &Path) -> Vec<RuleSet> {
+        let strict_read = Self::strict_read_in(settings);
+        let mut rule_sets: Vec<RuleSet> = layers
+            .iter()
+            .map(|layer| {
+                let permissions = layer.permissions.as_ref();
+                RuleSet {
+                    allow: Self::without_flag_rules(
+                        Self::parse_rules(
+                            permissions.and_then(|p| p.allow.as_ref()),
+                            cwd,
+                            strict_read,
+                        ),
+                        "allow",
+                    ),
+                    deny: Self::parse_rules(
+                        permissions.and_then(|p| p.deny.as_ref()),
+                        cwd,
+                        strict_read,
+                    ),
+                    ask: Self::without_flag_rules(
+                        Self::parse_rules(
+                            permissions.and_then(|p| p.ask.as_ref()),
+                            cwd,
+                            strict_read,
+                        ),
+                        "ask",
+                    ),
+                }
+            })
+            .collect();
+        if settings.protect_secrets.unwrap_or(false) {
+            rule_sets.push(RuleSet {
+                deny: SECRETS_DENY_RULES
+                    .iter()
+                    .map(|rule| (rule.to_string(), Self::parse_rule(rule, cwd, strict_read)))
+                    .collect(),
+                ..Default::default()
+            });
         }
+        rule_sets
+    }
+
+    /// Settings allow rules of every rule set, in load order
+    fn settings_allow_rules(&self) -> impl Iterator<Item = &(String, ParsedRule)> {
+        self.rule_sets.iter().flat_map(|set| &set.allow)
+    }
+
+    /// Settings deny rules of every rule set, in load order
+    fn settings_deny_rules(&self) -> impl Iterator<Item = &(String, ParsedRule)> {
+        self.rule_sets.iter().flat_map(|set| &set.deny)
+    }
+
+    /// Settings ask rules of every rule set, in load order
+    fn settings_ask_rules(&self) -> impl Iterator<Item = &(String, ParsedRule)> {
+        self.rule_sets.iter().flat_map(|set| &set.ask)
     }
 
     /// Parse a list of rule strings into ParsedRule objects
-    fn parse_rules(rules: Option<&Vec<String>>, cwd: &Path) -> Vec<(String, ParsedRule)> {
+    fn parse_rules(
+        rules: Option<&Vec<String>>,
+        cwd: &Path,
+        strict_read: bool,
+    ) -> Vec<(String, ParsedRule)> {
         rules
             .map(|rules| {
                 rules
                     .iter()
-                    .map(|rule| (rule.clone(), ParsedRule::parse_with_glob(rule, cwd)))
+                    .map(|rule| (rule.clone(), Self::parse_rule(rule, cwd, strict_read)))
                     .collect()
             })
             .unwrap_or_default()
     }
 
+    /// Drop `BashFlag` rules from an allow or ask list, warning about each
+    ///
+    /// A flag says nothing about the rest of the command, so flag rules are
+    /// only accepted as deny rules.
+    fn without_flag_rules(
+        mut rules: Vec<(String, ParsedRule)>,
+        list: &str,
+    ) -> Vec<(String, ParsedRule)> {
+        rules.retain(|(rule, parsed)| !Self::is_rejected_flag_rule(rule, parsed, list));
+        rules
+    }
+
+    /// Check if a rule outside the deny list is a `BashFlag` rule, warning if so
+    fn is_rejected_flag_rule(rule: &str, parsed: &ParsedRule, list: &str) -> bool {
+        if parsed.is_flag_rule() {
+            tracing::warn!(
+                rule = %rule,
+                list = %list,
+                "BashFlag rules are only supported as deny rules - ignoring"
+            );
+        }
+        parsed.is_flag_rule()
+    }
+
+    /// Parse a single rule string, honoring `strictReadRule`
+    fn parse_rule(rule: &str, cwd: &Path, strict_read: bool) -> ParsedRule {
+        let mut parsed = ParsedRule::parse_with_glob(rule, cwd);
+        parsed.set_strict_read(strict_read);
+        parsed
+    }
+
+    /// Whether `strictReadRule` is set in `settings`
+    fn strict_read_in(settings: &Settings) -> bool {
+        settings
+            .permissions
+            .as_ref()
+            .and_then(|p| p.strict_read_rule)
+            .unwrap_or(false)
+    }
+
     /// Check permission for a tool invocation
     ///
-    /// Priority: deny > allow > ask
+    /// Priority: deny > allow > ask. With `allowAlwaysOverridesDeny` set,
+    /// runtime allow rules are checked before deny rules from settings
+    /// files; runtime deny rules still win.
+    ///
+    /// `ToolTitle(...)` rules are only consulted when no rule matches the
+    /// tool by name, so a name rule takes priority over any title rule.
+    ///
+    /// Built-in tools are checked in the directory the agent's shell is in
+    /// (see [`Self::follow_shell_command`]); ACP tools always run in the cwd.
+    ///
+    /// Checking has no side effects: a matching use-limited allow rule is
+    /// reported (with source [`USE_LIMITED_SOURCE`]) but not spent, so
+    /// audits and repeated checks of the same call cost nothing. Spend it
+    /// with [`Self::spend_use`] once the call is allowed.
     ///
     /// Returns the permission decision and matching rule (if any).
     pub fn check_permission(
@@ -73,32 +408,107 @@ impl PermissionChecker {
         tool_name: &str,
         tool_input: &serde_json::Value,
     ) -> PermissionCheckResult {
-        // Check deny rules first (highest priority)
-        for (rule_str, parsed) in &self.deny_rules {
-            if parsed.matches(tool_name, tool_input, &self.cwd) {
-                tracing::debug!("Tool {} denied by rule: {}", tool_name, rule_str);
-                return PermissionCheckResult::deny(rule_str);
-            }
+        self.check_permission_in(tool_name, tool_input, self.cwd_for(tool_name))
+    }
+
+    /// Check whether a deny rule blocks a tool invocation
+    ///
+    /// Returns the [`check_permission`](Self::check_permission) result if it
+    /// is a denial, so callers can rule out denials before approving a call
+    /// on other grounds.
+    pub fn check_deny(
+        &self,
+        tool_name: &str,
+        tool_input: &serde_json::Value,
+    ) -> Option<PermissionCheckResult> {
+        let result = self.check_permission(tool_name, tool_input);
+        (result.decision == PermissionDecision::Deny).then_some(result)
+    }
+
+    /// Directory a tool's paths are resolved against
+    fn cwd_for(&self, tool_name: &str) -> &Path {
+        if tool_name.starts_with("mcp__acp__") {
+            &self.cwd
+        } else {
+            self.current_dir()
         }
+    }
+
+    /// Check permission for a tool invocation running in another directory
+    ///
+    /// Relative rule patterns (`Read(./src/**)`) and relative tool paths are
+    /// resolved against `cwd` instead of the checker's own cwd, e.g. after
+    /// the agent has `cd`-ed into a subdirectory. The parsed rules are
+    /// reused; only relative patterns are compiled again for `cwd`.
+    pub fn check_permission_in(
+        &self,
+        tool_name: &str,
+        tool_input: &serde_json::Value,
+        cwd: &Path,
+    ) -> PermissionCheckResult {
+        // Rules apply to the command a configured wrapper or alias runs
+        let resolved_input = self.resolve_command_aliases(tool_name, tool_input);
+        let tool_input = resolved_input.as_ref();
+
+        // The tool's path is normalized once for all the rules
+        let cache = MatchCache::default();
+        let matches = |rule_str: &str, parsed: &ParsedRule| {
+            !self.disabled_rules.contains(rule_str)
+                && parsed.matches_with_cache(tool_name, tool_input, cwd, &cache)
+        };
 
-        // Check allow rules
-        for (rule_str, parsed) in &self.allow_rules {
-            if parsed.matches(tool_name, tool_input, &self.cwd) {
-                tracing::debug!("Tool {} allowed by rule: {}", tool_name, rule_str);
-                return PermissionCheckResult::allow(rule_str);
+        // The user explicitly chose "Always Allow" for these in this session
+        if self.allow_always_overrides_deny()
+            && !self
+                .runtime_deny_rules
+                .iter()
+                .any(|(rule_str, parsed)| matches(rule_str, parsed))
+        {
+            for (rule_str, parsed) in &self.runtime_allow_rules {
+                if matches(rule_str, parsed) {
+                    tracing::debug!(
+                        "Tool {} allowed by runtime rule (overriding deny rules): {}",
+                        tool_name,
+                        rule_str
+                    );
+                    return PermissionCheckResult::allow(rule_str);
+                }
             }
         }
 
-        // Check ask rules
-        for (rule_str, parsed) in &self.ask_rules {
-            if parsed.matches(tool_name, tool_input, &self.cwd) {
-                tracing::debug!(
-                    "Tool {} requires permission (ask rule): {}",
-                    tool_name,
-                    rule_str
-                );
-                return PermissionCheckResult::ask_with_rule(rule_str);
-            }
+        // Each settings source's rules and the session's runtime rules are
+        // evaluated separately and combined by precedence (deny > allow > ask)
+        let runtime_result = Self::check_rule_set(
+            &self.runtime_deny_rules,
+            &self.runtime_allow_rules,
+            &[],
+            &matches,
+        );
+        let mut result = self
+            .rule_sets
+            .iter()
+            .map(|set| Self::check_rule_set(&set.deny, &set.allow, &set.ask, &matches))
+            .fold(PermissionCheckResult::ask(), PermissionCheckResult::merge);
+        result = PermissionCheckResult::merge(result, runtime_result);
+        // A use-limited rule only applies when no other rule decided
+        if result.decision == PermissionDecision::Ask
+            && let Some(rule_str) = self.matching_limited_rule(&matches)
+        {
+            result = PermissionCheckResult::allow_use_limited(rule_str);
+        }
+        if let Some(ref rule_str) = result.rule {
+            tracing::debug!(
+                "Tool {} matched {} rule: {}",
+                tool_name,
+                result.source.as_deref().unwrap_or("ask"),
+                rule_str
+            );
+            return result;
+        }
+
+        // No name rule matched: fall back to the tool's advertised title
+        if let Some(result) = self.check_title_rules(tool_name) {
+            return result;
         }
 
         // Default: ask
@@ -106,6 +516,200 @@ impl PermissionChecker {
         PermissionCheckResult::ask()
     }
 
+    /// Evaluate one set of rules against a tool call (deny > allow > ask)
+    fn check_rule_set(
+        deny_rules: &[(String, ParsedRule)],
+        allow_rules: &[(String, ParsedRule)],
+        ask_rules: &[(String, ParsedRule)],
+        matches: &impl Fn(&str, &ParsedRule) -> bool,
+    ) -> PermissionCheckResult {
+        let find = |rules: &[(String, ParsedRule)]| {
+            rules
+                .iter()
+                .find(|(rule_str, parsed)| matches(rule_str, parsed))
+                .map(|(rule_str, _)| rule_str.as_str())
+        };
+
+        if let Some(rule_str) = find(deny_rules) {
+            PermissionCheckResult::deny(rule_str)
+        } else if let Some(rule_str) = find(allow_rules) {
+            PermissionCheckResult::allow(rule_str)
+        } else if let Some(rule_str) = find(ask_rules) {
+            PermissionCheckResult::ask_with_rule(rule_str)
+        } else {
+            PermissionCheckResult::ask()
+        }
+    }
+
+    /// Find the first use-limited allow rule matching a tool call
+    fn matching_limited_rule(
+        &self,
+        matches: &impl Fn(&str, &ParsedRule) -> bool,
+    ) -> Option<String> {
+        self.use_limited_allow_rules
+            .lock()
+            .iter()
+            .find(|limited| matches(&limited.rule, &limited.parsed))
+            .map(|limited| limited.rule.clone())
+    }
+
+    /// Spend one use of the use-limited rule behind an allowed tool call
+    ///
+    /// Call this once per call that goes ahead on `result`, a
+    /// [`Self::check_permission`] result. Results from other rules spend
+    /// nothing and return `true`. The rule is removed once its last use is
+    /// taken; if another call took that use first, returns `false` and the
+    /// call is no longer allowed by the rule.
+    pub fn spend_use(&self, result: &PermissionCheckResult) -> bool {
+        let Some(rule) = result.rule.as_deref().filter(|_| result.is_use_limited()) else {
+            return true;
+        };
+        let mut rules = self.use_limited_allow_rules.lock();
+        let Some(index) = rules.iter().position(|limited| limited.rule == rule) else {
+            tracing::debug!(rule = %rule, "Use-limited allow rule has no uses left");
+            return false;
+        };
+        let limited = &mut rules[index];
+        limited.remaining -= 1;
+        tracing::debug!(rule = %rule, remaining = limited.remaining, "Used a use-limited allow rule");
+        if limited.remaining == 0 {
+            rules.remove(index);
+        }
+        true
+    }
+
+    /// Check `ToolTitle(...)` rules against the tool's title (deny > allow > ask)
+    fn check_title_rules(&self, tool_name: &str) -> Option<PermissionCheckResult> {
+        let title = self.tool_title(tool_name)?;
+        let enabled = |(rule_str, _): &&(String, ParsedRule)| self.is_rule_enabled(rule_str);
+
+        let deny_rules = self
+            .settings_deny_rules()
+            .chain(&self.runtime_deny_rules)
+            .filter(enabled);
+        if let Some(rule_str) = find_title_rule(deny_rules, title) {
+            tracing::debug!("Tool {} denied by title rule: {}", tool_name, rule_str);
+            return Some(PermissionCheckResult::deny(rule_str));
+        }
+
+        let allow_rules = self
+            .settings_allow_rules()
+            .chain(&self.runtime_allow_rules)
+            .filter(enabled);
+        if let Some(rule_str) = find_title_rule(allow_rules, title) {
+            tracing::debug!("Tool {} allowed by title rule: {}", tool_name, rule_str);
+            return Some(PermissionCheckResult::allow(rule_str));
+        }
+
+        find_title_rule(self.settings_ask_rules().filter(enabled), title).map(|rule_str| {
+            tracing::debug!(
+                "Tool {} requires permission (title ask rule): {}",
+                tool_name,
+                rule_str
+            );
+            PermissionCheckResult::ask_with_rule(rule_str)
+        })
+    }
+
+    /// Record the human-readable title a tool advertises
+    ///
+    /// Used by `ToolTitle(...)` rules, e.g. for MCP tools whose names are
+    /// generated but whose titles are stable.
+    pub fn set_tool_title(&mut self, tool_name: &str, title: impl Into<String>) {
+        let name = tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name);
+        self.tool_titles.insert(name.to_string(), title.into());
+    }
+
+    /// Get the title recorded for a tool, if any
+    pub fn tool_title(&self, tool_name: &str) -> Option<&str> {
+        let name = tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name);
+        self.tool_titles.get(name).map(String::as_str)
+    }
+
+    /// Assert that a tool invocation produces the expected decision
+    ///
+    /// Returns a descriptive [`DecisionMismatch`] if the actual decision differs.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// checker.assert_decision("Bash", &json!({"command": "npm run build"}), PermissionDecision::Allow)?;
+    /// ```
+    pub fn assert_decision(
+        &self,
+        tool_name: &str,
+        tool_input: &serde_json::Value,
+        expected: PermissionDecision,
+    ) -> Result<(), DecisionMismatch> {
+        let result = self.check_permission(tool_name, tool_input);
+        if result.decision == expected {
+            Ok(())
+        } else {
+            Err(DecisionMismatch {
+                tool_name: tool_name.to_string(),
+                tool_input: tool_input.clone(),
+                expected,
+                actual: result.decision,
+                rule: result.rule,
+            })
+        }
+    }
+
+    /// Evaluate a table of example invocations against their expected decisions
+    ///
+    /// Every example is checked; all mismatches are returned together.
+    pub fn evaluate_examples<'a>(
+        &self,
+        examples: impl IntoIterator<Item = (&'a str, &'a serde_json::Value, PermissionDecision)>,
+    ) -> Result<(), Vec<DecisionMismatch>> {
+        let mismatches: Vec<DecisionMismatch> = examples
+            .into_iter()
+            .filter_map(|(tool_name, tool_input, expected)| {
+                self.assert_decision(tool_name, tool_input, expected).err()
+            })
+            .collect();
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(mismatches)
+        }
+    }
+
+    /// Report the decision for each sample invocation as JSON, for auditing
+    ///
+    /// Each entry records the tool name and input with the `decision`
+    /// (`"allow"`, `"deny"` or `"ask"`), the matching `rule` and its `source`
+    /// list; the last two are `null` when no rule matched. Runtime rules
+    /// count, so the report reflects the checker's current configuration.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let report = checker.audit_report(&[("Bash".to_string(), json!({"command": "rm -rf /"}))]);
+    /// assert_eq!(report["samples"][0]["decision"], "deny");
+    /// ```
+    pub fn audit_report(&self, samples: &[(String, serde_json::Value)]) -> serde_json::Value {
+        let entries: Vec<serde_json::Value> = samples
+            .iter()
+            .map(|(tool_name, tool_input)| {
+                let result = self.check_permission(tool_name, tool_input);
+                serde_json::json!({
+                    "toolName": tool_name,
+                    "toolInput": tool_input,
+                    "decision": result.decision.as_str(),
+                    "rule": result.rule,
+                    "source": result.source,
+                })
+            })
+            .collect();
+
+        serde_json::json!({
+            "cwd": self.cwd.display().to_string(),
+            "samples": entries,
+        })
+    }
+
     /// Get the settings
     pub fn settings(&self) -> &Settings {
         &self.settings
@@ -116,15 +720,238 @@ impl PermissionChecker {
         &self.cwd
     }
 
+    /// Get the directory the agent's shell is in (the cwd unless it `cd`-ed away)
+    pub fn current_dir(&self) -> &Path {
+        self.current_dir.as_deref().unwrap_or(&self.cwd)
+    }
+
+    /// Follow a `cd` run by the agent's shell, so later checks resolve paths there
+    ///
+    /// Only a command that is just `cd DIR` is followed. Targets the shell
+    /// expands (`-`, `~`, variables) are ignored rather than guessed.
+    pub fn follow_shell_command(&mut self, command: &str) {
+        let Some(dir) = cd_target(command) else {
+            return;
+        };
+        let dir = resolve_dot_segments(&self.current_dir().join(dir));
+        self.current_dir = (dir != self.cwd).then_some(dir);
+    }
+
+    /// Use `project_dir` as the project whose settings files hold the rules
+    ///
+    /// Paths are still resolved against the working directory; rules saved
+    /// by the session (e.g. "Always Reject") go to `project_dir`'s local
+    /// settings, where the next reload picks them up.
+    pub fn with_project_dir(mut self, project_dir: impl AsRef<Path>) -> Self {
+        self.project_dir = project_dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Get the project directory whose settings files hold the rules
+    pub fn project_dir(&self) -> &Path {
+        &self.project_dir
+    }
+
     /// Check if there are any permission rules configured
     pub fn has_rules(&self) -> bool {
-        !self.allow_rules.is_empty() || !self.deny_rules.is_empty() || !self.ask_rules.is_empty()
+        !self.rule_sets.iter().all(RuleSet::is_empty)
+            || !self.runtime_allow_rules.is_empty()
+            || !self.runtime_deny_rules.is_empty()
+            || !self.use_limited_allow_rules.lock().is_empty()
+    }
+
+    /// List every configured rule with its list and whether it is enabled
+    ///
+    /// Settings rules come first, in the order deny, allow, ask, followed
+    /// by the rules added in this session. Disabled rules are included.
+    pub fn list_permission_rules(&self) -> Vec<PermissionRuleEntry> {
+        let lists: [(Vec<&(String, ParsedRule)>, &'static str, bool); 5] = [
+            (self.settings_deny_rules().collect(), "deny", false),
+            (self.settings_allow_rules().collect(), "allow", false),
+            (self.settings_ask_rules().collect(), "ask", false),
+            (self.runtime_deny_rules.iter().collect(), "deny", true),
+            (self.runtime_allow_rules.iter().collect(), "allow", true),
+        ];
+        let limited: Vec<String> = self
+            .use_limited_allow_rules
+            .lock()
+            .iter()
+            .map(|limited| limited.rule.clone())
+            .collect();
+        lists
+            .into_iter()
+            .flat_map(|(rules, source, runtime)| {
+                rules
+                    .into_iter()
+                    .map(move |(rule_str, _)| PermissionRuleEntry {
+                        rule: rule_str.clone(),
+                        source,
+                        runtime,
+                        enabled: self.is_rule_enabled(rule_str),
+                    })
+            })
+            .chain(limited.into_iter().map(|rule| PermissionRuleEntry {
+                enabled: self.is_rule_enabled(&rule),
+                rule,
+                source: "allow",
+                runtime: true,
+            }))
+            .collect()
+    }
+
+    /// Enable or disable a rule without removing it
+    ///
+    /// A disabled rule is skipped by [`Self::check_permission`] but still
+    /// listed by [`Self::list_permission_rules`], which helps find the rule
+    /// behind an unwanted decision. The rule is matched by its text in every
+    /// list, and stays disabled across settings reloads. Returns whether any
+    /// list holds the rule.
+    pub fn set_rule_enabled(&mut self, rule: &str, enabled: bool) -> bool {
+        if enabled {
+            self.disabled_rules.remove(rule);
+        } else {
+            self.disabled_rules.insert(rule.to_string());
+        }
+        tracing::info!(rule = %rule, enabled, "Permission rule toggled");
+        self.list_permission_rules()
+            .iter()
+            .any(|entry| entry.rule == rule)
+    }
+
+    /// Whether a rule takes part in matching
+    pub fn is_rule_enabled(&self, rule: &str) -> bool {
+        !self.disabled_rules.contains(rule)
+    }
+
+    /// The allow rules added in this session, in the order they were added
+    pub fn runtime_allow_rules(&self) -> Vec<String> {
+        self.runtime_allow_rules
+            .iter()
+            .map(|(rule_str, _)| rule_str.clone())
+            .collect()
+    }
+
+    /// The deny rules added in this session, in the order they were added
+    pub fn runtime_deny_rules(&self) -> Vec<String> {
+        self.runtime_deny_rules
+            .iter()
+            .map(|(rule_str, _)| rule_str.clone())
+            .collect()
+    }
+
+    /// Whether a runtime deny rule came from "Reject for Session"
+    pub fn is_session_deny_rule(&self, rule: &str) -> bool {
+        self.session_deny_rules.contains(rule)
+    }
+
+    /// The use-limited allow rules with the uses each has left
+    pub fn use_limited_allow_rules(&self) -> Vec<(String, u32)> {
+        self.use_limited_allow_rules
+            .lock()
+            .iter()
+            .map(|limited| (limited.rule.clone(), limited.remaining))
+            .collect()
     }
 
     /// Add a runtime allow rule (e.g., from user's "Always Allow" choice)
+    ///
+    /// `BashFlag` rules are ignored with a warning; they only work as deny rules.
     pub fn add_allow_rule(&mut self, rule: &str) {
-        let parsed = ParsedRule::parse_with_glob(rule, &self.cwd);
-        self.allow_rules.push((rule.to_string(), parsed));
+        let parsed = Self::parse_rule(rule, &self.cwd, self.strict_read_rule());
+        if Self::is_rejected_flag_rule(rule, &parsed, "allow") {
+            return;
+        }
+        self.runtime_allow_rules.push((rule.to_string(), parsed));
+    }
+
+    /// Add a runtime allow rule that approves at most `uses` tool calls
+    ///
+    /// Each call the rule approves uses it up a little (see
+    /// [`Self::spend_use`]); it is removed after the last use, so the next
+    /// call needs permission again. The rule only applies when no other rule
+    /// decides the call. `uses == 0` adds nothing.
+    pub fn add_allow_rule_with_uses(&mut self, rule: &str, uses: u32) {
+        if uses == 0 {
+            return;
+        }
+        let parsed = Self::parse_rule(rule, &self.cwd, self.strict_read_rule());
+        if Self::is_rejected_flag_rule(rule, &parsed, "allow") {
+            return;
+        }
+        self.use_limited_allow_rules.lock().push(UseLimitedRule {
+            rule: rule.to_string(),
+            parsed,
+            remaining: uses,
+        });
+    }
+
+    /// The rule [`Self::add_allow_rule_for_tool_call`] would add for this call
+    ///
+    /// Nothing is added, so clients can show the rule (and let the user edit
+    /// it) before it is saved.
+    pub fn suggested_allow_rule(&self, tool_name: &str, tool_input: &serde_json::Value) -> String {
+        // Strip mcp__acp__ prefix for consistent rule matching
+        let stripped = tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name);
+
+        match stripped {
+            "Bash" => self.generate_bash_rule(tool_input),
+            "Read" | "Grep" | "Glob" | "LS" => {
+                // For read operations, generate directory-based rule; a strict
+                // `Read` rule wouldn't cover the search tools
+                let rule_tool = if self.strict_read_rule() {
+                    stripped
+                } else {
+                    "Read"
+                };
+                Self::generate_file_rule(rule_tool, stripped, tool_input, &self.cwd)
+            }
+            "Edit" | "Write" => {
+                // For write operations, generate directory-based rule
+                Self::generate_file_rule(stripped, stripped, tool_input, &self.cwd)
+            }
+            _ => stripped.to_string(),
+        }
+    }
+
+    /// The "Always Allow" rules a prompt can offer for this call, narrowest first
+    ///
+    /// For Bash these are the exact command (`Bash(git push origin main)`),
+    /// the command with its subcommand (`Bash(git push:*)`) and every
+    /// invocation of the command (`Bash(git:*)`); other tools only get
+    /// [`Self::suggested_allow_rule`], which is always the last entry.
+    pub fn suggested_allow_rules(
+        &self,
+        tool_name: &str,
+        tool_input: &serde_json::Value,
+    ) -> Vec<String> {
+        let stripped = tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name);
+        let mut rules = Vec::new();
+        if stripped == "Bash"
+            && let Some(cmd) = BashInput::from_value(tool_input).command
+        {
+            let cmd = self.resolve_command_alias(&cmd);
+            // Rules are one line, and a trailing `:*` would make it a prefix rule
+            let single_line = !cmd.contains('\n');
+            if single_line && !cmd.trim().is_empty() && !cmd.ends_with(":*") {
+                rules.push(format!("Bash({})", cmd));
+            }
+            let cmd_name = Self::extract_command_name(&cmd);
+            if single_line
+                && let Some(subcommand) = cmd.split_whitespace().nth(1)
+                && !cmd_name.is_empty()
+                && !subcommand.starts_with('-')
+                && subcommand
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            {
+                rules.push(format!("Bash({} {}:*)", cmd_name, subcommand));
+            }
+        }
+
+        let broadest = self.suggested_allow_rule(tool_name, tool_input);
+        rules.retain(|rule| *rule != broadest);
+        rules.push(broadest);
+        rules
     }
 
     /// Add a runtime allow rule for "Always Allow" permission decision
@@ -144,86 +971,265 @@ impl PermissionChecker {
         tool_name: &str,
         tool_input: &serde_json::Value,
     ) {
-        // Strip mcp__acp__ prefix for consistent rule matching
+        let rule = self.suggested_allow_rule(tool_name, tool_input);
+
+        tracing::info!(
+            tool_name = %tool_name,
+            generated_rule = %rule,
+            "Adding allow rule for Always Allow"
+        );
+
+        let parsed = Self::parse_rule(&rule, &self.cwd, self.strict_read_rule());
+        self.runtime_allow_rules.push((rule, parsed));
+    }
+
+    /// The deny rule a "Reject for Session" or "Always Reject" decision adds
+    ///
+    /// For Bash tool: denies every invocation of the command, like the
+    /// allow counterpart (`rm -rf build` → `Bash(rm:*)`).
+    ///
+    /// For file and search tools: denies only the exact path that was
+    /// rejected, so rejecting one edit doesn't block the whole directory
+    /// (`Edit` on `./src/main.rs` → `Edit(./src/main.rs)`).
+    pub fn suggested_deny_rule(&self, tool_name: &str, tool_input: &serde_json::Value) -> String {
         let stripped = tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name);
 
-        let rule = match stripped {
-            "Bash" => {
-                // Extract command name (first word only) for Bash
-                if let Some(cmd) = tool_input.get("command").and_then(|v| v.as_str()) {
-                    let cmd_name = Self::extract_command_name(cmd);
-                    if cmd_name.is_empty() {
-                        stripped.to_string()
-                    } else {
-                        format!("Bash({}:*)", cmd_name) // e.g., "Bash(find:*)"
+        match stripped {
+            "Bash" => self.generate_bash_rule(tool_input),
+            "Read" | "Grep" | "Glob" | "LS" | "Edit" | "Write" => {
+                match FilePathInput::from_value(tool_input).path_for_tool(stripped) {
+                    Some(path) => {
+                        let path = Path::new(path);
+                        let path_str = match path.strip_prefix(&self.cwd) {
+                            Ok(relative) => format!(
+                                "./{}",
+                                escape_glob(&to_slash_path(&relative.to_string_lossy()))
+                            ),
+                            Err(_) => escape_glob(&to_slash_path(&path.to_string_lossy())),
+                        };
+                        format!("{}({})", stripped, path_str)
                     }
-                } else {
-                    stripped.to_string()
+                    None => stripped.to_string(),
                 }
             }
-            "Read" | "Grep" | "Glob" | "LS" => {
-                // For read operations, generate directory-based rule
-                Self::generate_file_rule("Read", tool_input, &self.cwd)
-            }
-            "Edit" | "Write" => {
-                // For write operations, generate directory-based rule
-                Self::generate_file_rule(stripped, tool_input, &self.cwd)
-            }
             _ => stripped.to_string(),
-        };
+        }
+    }
+
+    /// Add a runtime deny rule for a "Reject for Session" decision
+    ///
+    /// The rule is [`Self::suggested_deny_rule`]. It lasts until
+    /// [`Self::clear_session_deny_rules`] is called, typically on `/clear`.
+    pub fn add_deny_rule_for_tool_call(&mut self, tool_name: &str, tool_input: &serde_json::Value) {
+        let rule = self.suggested_deny_rule(tool_name, tool_input);
 
         tracing::info!(
             tool_name = %tool_name,
             generated_rule = %rule,
-            "Adding allow rule for Always Allow"
+            "Adding session deny rule for Reject for Session"
         );
 
-        let parsed = ParsedRule::parse_with_glob(&rule, &self.cwd);
-        self.allow_rules.push((rule, parsed));
+        self.add_session_deny_rule(&rule);
     }
 
-    /// Extract command name (basename only) from a shell command
+    /// Add a runtime deny rule that lasts until [`Self::clear_session_deny_rules`]
     ///
-    /// Supports both simple commands and full path commands:
-    /// - `find /path -name "*.rs"` → `find`
-    /// - `/usr/bin/find . -name "*.rs"` → `find`
-    /// - `ls -la /tmp` → `ls`
-    fn extract_command_name(cmd: &str) -> String {
-        extract_command_basename(cmd).to_string()
+    /// A rule that is already a standing runtime deny rule (see
+    /// [`Self::add_deny_rule`]) stays one.
+    pub fn add_session_deny_rule(&mut self, rule: &str) {
+        if self
+            .runtime_deny_rules
+            .iter()
+            .any(|(existing, _)| existing == rule)
+        {
+            return;
+        }
+        let parsed = Self::parse_rule(rule, &self.cwd, self.strict_read_rule());
+        self.runtime_deny_rules.push((rule.to_string(), parsed));
+        self.session_deny_rules.insert(rule.to_string());
     }
 
-    /// Generate a file-based permission rule
-    fn generate_file_rule(tool_name: &str, tool_input: &serde_json::Value, cwd: &Path) -> String {
-        if let Some(path) = tool_input.get("file_path").and_then(|v| v.as_str()) {
-            let path = Path::new(path);
-
-            // Try to get the directory
-            if let Some(dir) = path.parent() {
-                // Make path relative to cwd if possible
-                let dir_str = if let Ok(relative) = dir.strip_prefix(cwd) {
-                    format!("./{}", relative.display())
-                } else {
-                    dir.to_string_lossy().to_string()
-                };
-
-                // Generate glob rule for the directory
-                if dir_str.is_empty() || dir_str == "." {
-                    format!("{}(./*)", tool_name)
-                } else {
-                    format!("{}({}/**)", tool_name, dir_str)
-                }
-            } else {
-                tool_name.to_string()
-            }
-        } else {
-            tool_name.to_string()
+    /// Add a deny rule that has been written to a settings file
+    ///
+    /// The rule is treated like the other file-sourced deny rules: it isn't
+    /// removed by [`Self::clear_runtime_deny_rules`], and the next reload
+    /// reads it back from the file.
+    pub fn add_settings_deny_rule(&mut self, rule: &str) {
+        let parsed = Self::parse_rule(rule, &self.cwd, self.strict_read_rule());
+        if self.rule_sets.is_empty() {
+            self.rule_sets.push(RuleSet::default());
         }
+        // The rule was written to the local settings file, the last layer
+        let last = self.rule_sets.len() - 1;
+        self.rule_sets[last].deny.push((rule.to_string(), parsed));
+    }
+
+    /// Remove the deny rules added at runtime, leaving settings rules in place
+    pub fn clear_runtime_deny_rules(&mut self) {
+        tracing::debug!(
+            runtime_deny_rules = self.runtime_deny_rules.len(),
+            "Clearing runtime deny rules"
+        );
+        self.runtime_deny_rules.clear();
+        self.session_deny_rules.clear();
+    }
+
+    /// Remove the "Reject for Session" deny rules, keeping other runtime rules
+    pub fn clear_session_deny_rules(&mut self) {
+        tracing::debug!(
+            session_deny_rules = self.session_deny_rules.len(),
+            "Clearing session deny rules"
+        );
+        let session_rules = std::mem::take(&mut self.session_deny_rules);
+        self.runtime_deny_rules
+            .retain(|(rule_str, _)| !session_rules.contains(rule_str));
+    }
+
+    /// Generate a `Bash(<command>:*)` rule from the command's (alias-resolved) name
+    fn generate_bash_rule(&self, tool_input: &serde_json::Value) -> String {
+        let Some(cmd) = BashInput::from_value(tool_input).command else {
+            return "Bash".to_string();
+        };
+        let cmd_name = Self::extract_command_name(&self.resolve_command_alias(&cmd));
+        if cmd_name.is_empty() {
+            "Bash".to_string()
+        } else {
+            format!("Bash({}:*)", cmd_name) // e.g., "Bash(find:*)"
+        }
+    }
+
+    /// Resolve a configured command alias (`commandAliases`) at the start of a command
+    pub fn resolve_command_alias<'a>(&self, command: &'a str) -> Cow<'a, str> {
+        match &self.settings.command_aliases {
+            Some(aliases) => resolve_command_alias(command, aliases),
+            None => Cow::Borrowed(command),
+        }
+    }
+
+    /// Resolve command aliases in a Bash tool input
+    ///
+    /// Returns the input unchanged for other tools or when no alias applies.
+    pub fn resolve_command_aliases<'a>(
+        &self,
+        tool_name: &str,
+        tool_input: &'a serde_json::Value,
+    ) -> Cow<'a, serde_json::Value> {
+        let stripped = tool_name.strip_prefix("mcp__acp__").unwrap_or(tool_name);
+        if stripped != "Bash" {
+            return Cow::Borrowed(tool_input);
+        }
+        let Some(command) = BashInput::from_value(tool_input).command else {
+            return Cow::Borrowed(tool_input);
+        };
+
+        match self.resolve_command_alias(&command) {
+            Cow::Borrowed(_) => Cow::Borrowed(tool_input),
+            Cow::Owned(resolved) => {
+                let mut input = tool_input.clone();
+                input["command"] = serde_json::Value::String(resolved);
+                Cow::Owned(input)
+            }
+        }
+    }
+
+    /// Extract command name (basename only) from a shell command
+    ///
+    /// Supports both simple commands and full path commands:
+    /// - `find /path -name "*.rs"` → `find`
+    /// - `/usr/bin/find . -name "*.rs"` → `find`
+    /// - `ls -la /tmp` → `ls`
+    fn extract_command_name(cmd: &str) -> String {
+        extract_command_basename(cmd).to_string()
+    }
+
+    /// Generate a file-based permission rule
+    ///
+    /// `rule_tool` names the rule (e.g. "Read" for all read-only tools) while
+    /// `tool_name` selects which input fields hold the path.
+    fn generate_file_rule(
+        rule_tool: &str,
+        tool_name: &str,
+        tool_input: &serde_json::Value,
+        cwd: &Path,
+    ) -> String {
+        // Search tools are rooted at a directory; file tools target a file inside one
+        let input = FilePathInput::from_value(tool_input);
+        let search_tool = matches!(tool_name, "Grep" | "Glob" | "LS");
+        let path = if search_tool {
+            input.path.as_deref()
+        } else {
+            input.path_for_tool(tool_name)
+        };
+        let Some(path) = path.map(Path::new) else {
+            return rule_tool.to_string();
+        };
+
+        // Relative paths are relative to cwd (`src/main.rs` is `<cwd>/src/main.rs`),
+        // and `..` is resolved so it can't pass for a subdirectory of cwd
+        let target = resolve_dot_segments(&cwd.join(path));
+        let dir = if search_tool {
+            Some(target.as_path())
+        } else {
+            target.parent()
+        };
+        let Some(dir) = dir else {
+            return rule_tool.to_string();
+        };
+
+        // A directory rule for a path reached with `..` out of cwd, or for a
+        // parent of cwd, would grant access well beyond this call (`./../**`
+        // covers cwd's parent and everything in it). Only the path is allowed.
+        let escapes_cwd = !dir.starts_with(cwd)
+            && path
+                .components()
+                .any(|component| component == Component::ParentDir);
+        if escapes_cwd || (dir != cwd && cwd.starts_with(dir)) {
+            return format!(
+                "{}({})",
+                rule_tool,
+                escape_glob(&to_slash_path(&target.to_string_lossy()))
+            );
+        }
+
+        // Make path relative to cwd if possible, escaping glob metacharacters
+        // in directory names so the rule matches them literally
+        let dir_str = if let Ok(relative) = dir.strip_prefix(cwd) {
+            format!(
+                "./{}",
+                escape_glob(&to_slash_path(&relative.to_string_lossy()))
+            )
+        } else {
+            escape_glob(&to_slash_path(&dir.to_string_lossy()))
+        };
+
+        // Generate glob rule for the directory
+        if dir_str.is_empty() || dir_str == "." || dir_str == "./" {
+            format!("{}(./*)", rule_tool)
+        } else {
+            format!("{}({}/**)", rule_tool, dir_str.trim_end_matches('/'))
+        }
+    }
+
+    /// Remove the allow rules added at runtime, leaving settings rules in place
+    pub fn clear_runtime_allow_rules(&mut self) {
+        tracing::debug!(
+            runtime_allow_rules = self.runtime_allow_rules.len(),
+            "Clearing session allow rules"
+        );
+        self.runtime_allow_rules.clear();
+        self.use_limited_allow_rules.lock().clear();
     }
 
     /// Add a runtime deny rule
+    ///
+    /// Unlike a "Reject for Session" rule, it is kept on `/clear`.
     pub fn add_deny_rule(&mut self, rule: &str) {
-        let parsed = ParsedRule::parse_with_glob(rule, &self.cwd);
-        self.deny_rules.push((rule.to_string(), parsed));
+        // A session rule with the same text becomes a standing one
+        if self.session_deny_rules.remove(rule) {
+            return;
+        }
+        let parsed = Self::parse_rule(rule, &self.cwd, self.strict_read_rule());
+        self.runtime_deny_rules.push((rule.to_string(), parsed));
     }
 
     /// Get the default permission mode from settings
@@ -241,6 +1247,113 @@ impl PermissionChecker {
             .as_ref()
             .and_then(|p| p.additional_directories.as_ref())
     }
+
+    /// Check if a path is inside one of the configured additional directories
+    ///
+    /// Entries may be home-relative (`~/projects`) and may carry a trailing
+    /// glob (`~/projects/**`), which is treated as the directory itself.
+    /// Relative entries and paths are resolved against the working directory.
+    pub fn is_in_additional_directory(&self, path: &str) -> bool {
+        let Some(directories) = self.additional_directories() else {
+            return false;
+        };
+
+        let path = self.resolve_relative(path);
+        directories.iter().any(|entry| {
+            let dir = entry
+                .trim_end_matches("/**")
+                .trim_end_matches("/*")
+                .trim_end_matches('/');
+            let dir = if dir.is_empty() { "/" } else { dir };
+            is_path_within_dir(&path, Path::new(&self.resolve_relative(dir)))
+        })
+    }
+
+    /// Check if a path is inside the working directory or an additional directory
+    ///
+    /// These are the directories strict AcceptEdits mode edits without asking.
+    pub fn is_in_working_directory(&self, path: &str) -> bool {
+        is_path_within_dir(&self.resolve_relative(path), &self.cwd)
+            || self.is_in_additional_directory(path)
+    }
+
+    /// Resolve a relative path against the working directory, keeping `~` untouched
+    fn resolve_relative(&self, path: &str) -> String {
+        if path == "~" || path.starts_with("~/") || Path::new(path).is_absolute() {
+            path.to_string()
+        } else {
+            self.cwd.join(path).to_string_lossy().to_string()
+        }
+    }
+
+    /// Whether a `Read` rule matches only the Read tool, not Grep, Glob and LS
+    pub fn strict_read_rule(&self) -> bool {
+        Self::strict_read_in(&self.settings)
+    }
+
+    /// Whether runtime "Always Allow" rules take precedence over settings deny rules
+    pub fn allow_always_overrides_deny(&self) -> bool {
+        self.settings
+            .permissions
+            .as_ref()
+            .and_then(|p| p.allow_always_overrides_deny)
+            .unwrap_or(false)
+    }
+
+    /// Output rules applied to tool responses by the PostToolUse hook
+    pub fn output_rules(&self) -> &[ToolOutputRule] {
+        self.settings
+            .permissions
+            .as_ref()
+            .and_then(|p| p.output_rules.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// The fallback for tool calls with empty input that match no rule
+    pub fn empty_input_default(&self) -> EmptyInputPolicy {
+        self.settings
+            .permissions
+            .as_ref()
+            .and_then(|p| p.empty_input_default)
+            .unwrap_or_default()
+    }
+
+    /// Whether external MCP tools without a matching allow/ask rule should be blocked
+    pub fn deny_unknown_mcp_tools(&self) -> bool {
+        self.settings
+            .permissions
+            .as_ref()
+            .and_then(|p| p.deny_unknown_mcp_tools)
+            .unwrap_or(false)
+    }
+}
+
+/// Find the first title rule matching `title`
+/// Get the directory of a command that is just `cd DIR`
+fn cd_target(command: &str) -> Option<&str> {
+    let segments = split_command_segments(command);
+    let [segment] = segments.as_slice() else {
+        return None;
+    };
+    let mut tokens = segment.split_whitespace();
+    if tokens.next()? != "cd" {
+        return None;
+    }
+    let dir = tokens.next()?.trim_matches(|c| c == '\'' || c == '"');
+    if tokens.next().is_some() || dir == "-" || dir.starts_with(['~', '$']) {
+        return None;
+    }
+    Some(dir)
+}
+
+fn find_title_rule<'a>(
+    rules: impl IntoIterator<Item = &'a (String, ParsedRule)>,
+    title: &str,
+) -> Option<&'a str> {
+    rules
+        .into_iter()
+        .find(|(_, parsed)| parsed.matches_title(title))
+        .map(|(rule_str, _)| rule_str.as_str())
 }
 
 impl Default for PermissionChecker {
@@ -252,7 +1365,7 @@ impl Default for PermissionChecker {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::settings::{PermissionDecision, PermissionSettings};
+    use crate::settings::PermissionSettings;
     use serde_json::json;
 
     fn settings_with_permissions(permissions: PermissionSettings) -> Settings {
@@ -309,6 +1422,201 @@ mod tests {
         assert_eq!(result.decision, PermissionDecision::Deny);
     }
 
+    #[test]
+    fn test_check_deny_spends_no_uses() {
+        let permissions = PermissionSettings {
+            deny: Some(vec!["Edit(./secret/**)".to_string()]),
+            ..Default::default()
+        };
+        let mut checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
+        checker.add_allow_rule_with_uses("Edit", 1);
+
+        let secret = json!({"file_path": "/tmp/secret/key.pem"});
+        let result = checker.check_deny("Edit", &secret).expect("denied");
+        assert_eq!(result.rule, Some("Edit(./secret/**)".to_string()));
+
+        let other = json!({"file_path": "/tmp/src/lib.rs"});
+        assert!(checker.check_deny("Edit", &other).is_none());
+        assert_eq!(
+            checker.use_limited_allow_rules(),
+            vec![("Edit".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_bash_flag_rules_only_deny() {
+        let permissions = PermissionSettings {
+            allow: Some(vec!["BashFlag(--force)".to_string()]),
+            ask: Some(vec!["BashFlag(--dry-run)".to_string()]),
+            ..Default::default()
+        };
+        let mut checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
+        checker.add_allow_rule("BashFlag(--yes)");
+        checker.add_allow_rule_with_uses("BashFlag(--yes)", 3);
+
+        // A flag alone never approves (or specifically asks about) a command
+        for command in [
+            "rm -rf / --force",
+            "rm -rf / --yes",
+            "terraform apply --dry-run",
+        ] {
+            let result = checker.check_permission("Bash", &json!({ "command": command }));
+            assert_eq!(result.decision, PermissionDecision::Ask, "{command}");
+            assert_eq!(result.rule, None, "{command}");
+        }
+        assert!(checker.runtime_allow_rules().is_empty());
+        assert!(checker.use_limited_allow_rules().is_empty());
+    }
+
+    #[test]
+    fn test_bash_flag_deny_overrides_command_allow() {
+        let permissions = PermissionSettings {
+            allow: Some(vec!["Bash(git commit:*)".to_string()]),
+            deny: Some(vec!["BashFlag(--no-verify)".to_string()]),
+            ..Default::default()
+        };
+        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
+
+        let result =
+            checker.check_permission("Bash", &json!({"command": "git commit --no-verify -m x"}));
+        assert_eq!(result.decision, PermissionDecision::Deny);
+        assert_eq!(result.rule, Some("BashFlag(--no-verify)".to_string()));
+
+        let result = checker.check_permission("Bash", &json!({"command": "git commit -m x"}));
+        assert_eq!(result.decision, PermissionDecision::Allow);
+        assert_eq!(result.rule, Some("Bash(git commit:*)".to_string()));
+    }
+
+    #[test]
+    fn test_tool_title_rule() {
+        let settings = settings_with_permissions(PermissionSettings {
+            deny: Some(vec!["ToolTitle(Delete:*)".to_string()]),
+            ..Default::default()
+        });
+        let mut checker = PermissionChecker::new(settings, "/tmp");
+        let tool = "mcp__github-a1b2__delete_repo";
+
+        // Without a known title the rule has nothing to match
+        assert_eq!(
+            checker.check_permission(tool, &json!({})).decision,
+            PermissionDecision::Ask
+        );
+
+        checker.set_tool_title(tool, "Delete repository");
+        let result = checker.check_permission(tool, &json!({}));
+        assert_eq!(result.decision, PermissionDecision::Deny);
+        assert_eq!(result.rule, Some("ToolTitle(Delete:*)".to_string()));
+
+        checker.set_tool_title("mcp__github-a1b2__list_repos", "List repositories");
+        assert_eq!(
+            checker
+                .check_permission("mcp__github-a1b2__list_repos", &json!({}))
+                .decision,
+            PermissionDecision::Ask
+        );
+    }
+
+    #[test]
+    fn test_name_rule_takes_priority_over_title_rule() {
+        let tool = "mcp__github-a1b2__delete_repo";
+        let settings = settings_with_permissions(PermissionSettings {
+            allow: Some(vec![tool.to_string()]),
+            deny: Some(vec!["ToolTitle(Delete repository)".to_string()]),
+            ..Default::default()
+        });
+        let mut checker = PermissionChecker::new(settings, "/tmp");
+        checker.set_tool_title(tool, "Delete repository");
+
+        // The allow rule names the tool, so the title deny rule isn't consulted
+        let result = checker.check_permission(tool, &json!({}));
+        assert_eq!(result.decision, PermissionDecision::Allow);
+        assert_eq!(result.rule, Some(tool.to_string()));
+
+        // The title survives copying the checker for another session
+        assert_eq!(
+            checker.without_runtime_rules().tool_title(tool),
+            Some("Delete repository")
+        );
+    }
+
+    #[test]
+    fn test_protect_secrets() {
+        let permissions = PermissionSettings {
+            allow: Some(vec!["Edit".to_string()]),
+            ..Default::default()
+        };
+        let env = json!({"file_path": "config/.env"});
+        let source = json!({"file_path": "config/app.rs"});
+
+        // Off by default: the allow rule covers every edit
+        let checker =
+            PermissionChecker::new(settings_with_permissions(permissions.clone()), "/tmp");
+        assert_eq!(
+            checker.check_permission("Edit", &env).decision,
+            PermissionDecision::Allow
+        );
+
+        let mut settings = settings_with_permissions(permissions);
+        settings.protect_secrets = Some(true);
+        let mut checker = PermissionChecker::new(settings.clone(), "/tmp");
+        let result = checker.check_permission("Edit", &env);
+        assert_eq!(result.decision, PermissionDecision::Deny);
+        assert_eq!(result.rule, Some("Edit(**/*.env)".to_string()));
+        assert_eq!(
+            checker.check_permission("Edit", &source).decision,
+            PermissionDecision::Allow
+        );
+        assert_eq!(
+            checker
+                .check_permission("Read", &json!({"file_path": "/home/u/.ssh/id_ed25519"}))
+                .decision,
+            PermissionDecision::Deny
+        );
+
+        // The rules follow the setting across reloads
+        settings.protect_secrets = None;
+        checker.reload_from(settings);
+        assert_eq!(
+            checker.check_permission("Edit", &env).decision,
+            PermissionDecision::Allow
+        );
+    }
+
+    #[test]
+    fn test_audit_report() {
+        let permissions = PermissionSettings {
+            allow: Some(vec!["Read".to_string()]),
+            deny: Some(vec!["Bash(rm:*)".to_string()]),
+            ..Default::default()
+        };
+        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
+
+        let report = checker.audit_report(&[
+            ("Bash".to_string(), json!({"command": "rm -rf build"})),
+            ("Read".to_string(), json!({"file_path": "/tmp/a.txt"})),
+            ("Write".to_string(), json!({"file_path": "/tmp/a.txt"})),
+        ]);
+
+        assert_eq!(report["cwd"], "/tmp");
+        let samples = report["samples"].as_array().unwrap();
+        assert_eq!(samples.len(), 3);
+
+        assert_eq!(samples[0]["toolName"], "Bash");
+        assert_eq!(samples[0]["toolInput"]["command"], "rm -rf build");
+        assert_eq!(samples[0]["decision"], "deny");
+        assert_eq!(samples[0]["rule"], "Bash(rm:*)");
+        assert_eq!(samples[0]["source"], "deny");
+
+        assert_eq!(samples[1]["decision"], "allow");
+        assert_eq!(samples[1]["rule"], "Read");
+        assert_eq!(samples[1]["source"], "allow");
+
+        // No matching rule: ask, with null rule and source
+        assert_eq!(samples[2]["decision"], "ask");
+        assert!(samples[2]["rule"].is_null());
+        assert!(samples[2]["source"].is_null());
+    }
+
     #[test]
     fn test_allow_takes_priority_over_ask() {
         let permissions = PermissionSettings {
@@ -323,224 +1631,1072 @@ mod tests {
     }
 
     #[test]
-    fn test_bash_wildcard_rule() {
-        let permissions = PermissionSettings {
-            allow: Some(vec!["Bash(npm run:*)".to_string()]),
-            ..Default::default()
-        };
-        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
+    fn test_bash_wildcard_rule() {
+        let permissions = PermissionSettings {
+            allow: Some(vec!["Bash(npm run:*)".to_string()]),
+            ..Default::default()
+        };
+        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
+
+        // Should allow npm run commands
+        assert_eq!(
+            checker
+                .check_permission("Bash", &json!({"command": "npm run build"}))
+                .decision,
+            PermissionDecision::Allow
+        );
+
+        // Should not allow npm install
+        assert_eq!(
+            checker
+                .check_permission("Bash", &json!({"command": "npm install"}))
+                .decision,
+            PermissionDecision::Ask
+        );
+
+        // Should block command chaining
+        assert_eq!(
+            checker
+                .check_permission("Bash", &json!({"command": "npm run build && rm -rf /"}))
+                .decision,
+            PermissionDecision::Ask
+        );
+    }
+
+    #[test]
+    fn test_read_group_matching() {
+        let permissions = PermissionSettings {
+            allow: Some(vec!["Read".to_string()]),
+            ..Default::default()
+        };
+        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
+
+        // Read rule should allow Read, Grep, Glob, LS
+        assert_eq!(
+            checker.check_permission("Read", &json!({})).decision,
+            PermissionDecision::Allow
+        );
+        assert_eq!(
+            checker.check_permission("Grep", &json!({})).decision,
+            PermissionDecision::Allow
+        );
+        assert_eq!(
+            checker.check_permission("Glob", &json!({})).decision,
+            PermissionDecision::Allow
+        );
+        assert_eq!(
+            checker.check_permission("LS", &json!({})).decision,
+            PermissionDecision::Allow
+        );
+
+        // Should not allow Write
+        assert_eq!(
+            checker.check_permission("Write", &json!({})).decision,
+            PermissionDecision::Ask
+        );
+    }
+
+    #[test]
+    fn test_add_runtime_rule() {
+        let mut checker = PermissionChecker::default();
+
+        // Initially should ask
+        assert_eq!(
+            checker.check_permission("Read", &json!({})).decision,
+            PermissionDecision::Ask
+        );
+
+        // Add allow rule at runtime
+        checker.add_allow_rule("Read");
+
+        // Now should allow
+        assert_eq!(
+            checker.check_permission("Read", &json!({})).decision,
+            PermissionDecision::Allow
+        );
+    }
+
+    #[test]
+    fn test_acp_prefix_stripped() {
+        let permissions = PermissionSettings {
+            allow: Some(vec!["Read".to_string()]),
+            ..Default::default()
+        };
+        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
+
+        // Should work with or without ACP prefix
+        assert_eq!(
+            checker.check_permission("Read", &json!({})).decision,
+            PermissionDecision::Allow
+        );
+        assert_eq!(
+            checker
+                .check_permission("mcp__acp__Read", &json!({}))
+                .decision,
+            PermissionDecision::Allow
+        );
+    }
+
+    #[test]
+    fn test_has_rules() {
+        let checker = PermissionChecker::default();
+        assert!(!checker.has_rules());
+
+        let permissions = PermissionSettings {
+            allow: Some(vec!["Read".to_string()]),
+            ..Default::default()
+        };
+        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
+        assert!(checker.has_rules());
+    }
+
+    #[test]
+    fn test_add_allow_rule_for_bash_command() {
+        let mut checker = PermissionChecker::new(Settings::default(), "/tmp");
+
+        // Add rule for specific bash command (find)
+        checker
+            .add_allow_rule_for_tool_call("Bash", &json!({"command": "find /path1 -name '*.rs'"}));
+
+        // Should allow ANY find command (same command name)
+        assert_eq!(
+            checker
+                .check_permission("Bash", &json!({"command": "find /different/path -type f"}))
+                .decision,
+            PermissionDecision::Allow
+        );
+
+        // Should allow find with different arguments
+        assert_eq!(
+            checker
+                .check_permission("Bash", &json!({"command": "find . -name '*.txt' -delete"}))
+                .decision,
+            PermissionDecision::Allow
+        );
+
+        // Should NOT allow different commands (ls, rm, etc.)
+        assert_eq!(
+            checker
+                .check_permission("Bash", &json!({"command": "ls -la /tmp"}))
+                .decision,
+            PermissionDecision::Ask
+        );
+
+        assert_eq!(
+            checker
+                .check_permission("Bash", &json!({"command": "rm -rf /"}))
+                .decision,
+            PermissionDecision::Ask
+        );
+    }
+
+    #[test]
+    fn test_suggested_allow_rule() {
+        let checker = PermissionChecker::new(Settings::default(), "/tmp");
+        assert_eq!(
+            checker.suggested_allow_rule("Bash", &json!({"command": "cargo build --release"})),
+            "Bash(cargo:*)"
+        );
+        assert_eq!(
+            checker.suggested_allow_rule(
+                "mcp__acp__Edit",
+                &json!({"file_path": "/tmp/project/src/main.rs", "old_string": "a"})
+            ),
+            "Edit(./project/src/**)"
+        );
+        assert_eq!(
+            checker.suggested_allow_rule("Read", &json!({"file_path": "/etc/hosts"})),
+            "Read(/etc/**)"
+        );
+        assert_eq!(
+            checker.suggested_allow_rule("WebSearch", &json!({})),
+            "WebSearch"
+        );
+
+        // Previewing doesn't add the rule
+        assert!(!checker.has_rules());
+        assert_eq!(
+            checker
+                .check_permission("Bash", &json!({"command": "cargo build"}))
+                .decision,
+            PermissionDecision::Ask
+        );
+    }
+
+    #[test]
+    fn test_suggested_allow_rules_by_granularity() {
+        let checker = PermissionChecker::new(Settings::default(), "/tmp");
+        assert_eq!(
+            checker.suggested_allow_rules("Bash", &json!({"command": "git push origin main"})),
+            vec![
+                "Bash(git push origin main)",
+                "Bash(git push:*)",
+                "Bash(git:*)"
+            ]
+        );
+        // No subcommand for options or paths, nor a duplicate of the broadest rule
+        assert_eq!(
+            checker.suggested_allow_rules("Bash", &json!({"command": "ls -la /tmp"})),
+            vec!["Bash(ls -la /tmp)", "Bash(ls:*)"]
+        );
+        assert_eq!(
+            checker.suggested_allow_rules("Bash", &json!({"command": "make"})),
+            vec!["Bash(make)", "Bash(make:*)"]
+        );
+        assert_eq!(
+            checker.suggested_allow_rules("Bash", &json!({"command": "ls\nrm -rf x"})),
+            vec!["Bash(ls:*)"]
+        );
+        assert_eq!(
+            checker.suggested_allow_rules("WebSearch", &json!({})),
+            vec!["WebSearch"]
+        );
+
+        // Each rule covers what it says
+        let push = json!({"command": "git push origin main"});
+        let other_push = json!({"command": "git push upstream dev"});
+        let fetch = json!({"command": "git fetch"});
+        let decisions = |rule: &str| {
+            let mut checker = PermissionChecker::new(Settings::default(), "/tmp");
+            checker.add_allow_rule(rule);
+            [&push, &other_push, &fetch]
+                .map(|input| checker.check_permission("Bash", input).decision)
+        };
+        use PermissionDecision::{Allow, Ask};
+        assert_eq!(decisions("Bash(git push origin main)"), [Allow, Ask, Ask]);
+        assert_eq!(decisions("Bash(git push:*)"), [Allow, Allow, Ask]);
+        assert_eq!(decisions("Bash(git:*)"), [Allow, Allow, Allow]);
+
+        // A prefix rule stops at a word boundary
+        let mut checker = PermissionChecker::new(Settings::default(), "/tmp");
+        checker.add_allow_rule("Bash(git push:*)");
+        for command in ["git pushx", "git push-foo"] {
+            assert_eq!(
+                checker
+                    .check_permission("Bash", &json!({"command": command}))
+                    .decision,
+                Ask,
+                "{command}"
+            );
+        }
+
+        // An exact rule survives a command with parentheses
+        let echo = json!({"command": "echo )"});
+        let rules = checker.suggested_allow_rules("Bash", &echo);
+        assert_eq!(rules, vec!["Bash(echo ))", "Bash(echo:*)"]);
+        let mut checker = PermissionChecker::new(Settings::default(), "/tmp");
+        checker.add_allow_rule(&rules[0]);
+        assert_eq!(checker.check_permission("Bash", &echo).decision, Allow);
+        assert_eq!(
+            checker
+                .check_permission("Bash", &json!({"command": "echo x"}))
+                .decision,
+            Ask
+        );
+    }
+
+    #[test]
+    fn test_strict_read_rule() {
+        let grep = json!({"pattern": "TODO", "path": "/tmp/src"});
+        let permissions = |strict| PermissionSettings {
+            allow: Some(vec!["Read(/tmp/src/**)".to_string()]),
+            strict_read_rule: strict,
+            ..Default::default()
+        };
+
+        // Grouped by default: the Read rule covers Grep
+        let grouped = PermissionChecker::new(settings_with_permissions(permissions(None)), "/tmp");
+        assert!(!grouped.strict_read_rule());
+        assert_eq!(
+            grouped.check_permission("Grep", &grep).decision,
+            PermissionDecision::Allow
+        );
+
+        let mut strict =
+            PermissionChecker::new(settings_with_permissions(permissions(Some(true))), "/tmp");
+        assert_eq!(
+            strict.check_permission("Grep", &grep).decision,
+            PermissionDecision::Ask
+        );
+        assert_eq!(
+            strict
+                .check_permission("Read", &json!({"file_path": "/tmp/src/a.rs"}))
+                .decision,
+            PermissionDecision::Allow
+        );
+
+        // Always Allow on a search tool names the tool itself
+        assert_eq!(strict.suggested_allow_rule("Grep", &grep), "Grep(./src/**)");
+        strict.add_allow_rule_for_tool_call("Grep", &grep);
+        assert_eq!(
+            strict.check_permission("Grep", &grep).decision,
+            PermissionDecision::Allow
+        );
+    }
+
+    #[test]
+    fn test_add_allow_rule_for_search_directory() {
+        let mut checker = PermissionChecker::new(Settings::default(), "/tmp");
+
+        // Always Allow on a Grep rooted at a directory covers that directory
+        checker.add_allow_rule_for_tool_call(
+            "Grep",
+            &json!({"pattern": "TODO", "path": "/tmp/project/src"}),
+        );
+
+        assert_eq!(
+            checker
+                .check_permission(
+                    "Glob",
+                    &json!({"pattern": "*.rs", "path": "/tmp/project/src"})
+                )
+                .decision,
+            PermissionDecision::Allow
+        );
+        assert_eq!(
+            checker
+                .check_permission("Read", &json!({"file_path": "/tmp/project/src/lib.rs"}))
+                .decision,
+            PermissionDecision::Allow
+        );
+        assert_eq!(
+            checker
+                .check_permission("Read", &json!({"file_path": "/tmp/other/lib.rs"}))
+                .decision,
+            PermissionDecision::Ask
+        );
+    }
+
+    #[test]
+    fn test_add_allow_rule_for_directory_with_spaces_and_parens() {
+        let mut checker = PermissionChecker::new(Settings::default(), "/tmp");
+
+        checker.add_allow_rule_for_tool_call("Edit", &json!({"file_path": "/tmp/my project/a.rs"}));
+        checker.add_allow_rule_for_tool_call("Edit", &json!({"file_path": "/tmp/src (old)/a.rs"}));
+
+        let result =
+            checker.check_permission("Edit", &json!({"file_path": "/tmp/my project/b.rs"}));
+        assert_eq!(result.decision, PermissionDecision::Allow);
+        assert_eq!(result.rule.as_deref(), Some("Edit(./my project/**)"));
+
+        let result =
+            checker.check_permission("Edit", &json!({"file_path": "/tmp/src (old)/lib/b.rs"}));
+        assert_eq!(result.decision, PermissionDecision::Allow);
+        assert_eq!(result.rule.as_deref(), Some("Edit(./src (old)/**)"));
+
+        assert_eq!(
+            checker
+                .check_permission("Edit", &json!({"file_path": "/tmp/my/b.rs"}))
+                .decision,
+            PermissionDecision::Ask
+        );
+    }
+
+    #[test]
+    fn test_add_allow_rule_escapes_glob_metacharacters() {
+        let mut checker = PermissionChecker::new(Settings::default(), "/tmp");
+
+        checker.add_allow_rule_for_tool_call("Edit", &json!({"file_path": "/tmp/[draft]/a.rs"}));
+        checker
+            .add_allow_rule_for_tool_call("Grep", &json!({"pattern": "x", "path": "/opt/{a,b}"}));
+
+        let result = checker.check_permission("Edit", &json!({"file_path": "/tmp/[draft]/b.rs"}));
+        assert_eq!(result.decision, PermissionDecision::Allow);
+        // `[draft]` is not treated as a character class
+        assert_eq!(
+            checker
+                .check_permission("Edit", &json!({"file_path": "/tmp/d/b.rs"}))
+                .decision,
+            PermissionDecision::Ask
+        );
+
+        // The search root itself and files below it match; brace alternatives don't
+        for path in ["/opt/{a,b}", "/opt/{a,b}/x.rs"] {
+            assert_eq!(
+                checker
+                    .check_permission("Read", &json!({"file_path": path}))
+                    .decision,
+                PermissionDecision::Allow,
+                "{path}"
+            );
+        }
+        assert_eq!(
+            checker
+                .check_permission("Read", &json!({"file_path": "/opt/a/x.rs"}))
+                .decision,
+            PermissionDecision::Ask
+        );
+    }
+
+    #[test]
+    fn test_add_allow_rule_for_file_operation() {
+        let mut checker = PermissionChecker::new(Settings::default(), "/tmp");
+
+        // Add rule for specific file
+        checker.add_allow_rule_for_tool_call(
+            "Read",
+            &json!({"file_path": "/tmp/project/src/main.rs"}),
+        );
+
+        // Should allow files in the same directory
+        assert_eq!(
+            checker
+                .check_permission("Read", &json!({"file_path": "/tmp/project/src/lib.rs"}))
+                .decision,
+            PermissionDecision::Allow
+        );
+
+        // Should allow subdirectories (glob **)
+        assert_eq!(
+            checker
+                .check_permission(
+                    "Read",
+                    &json!({"file_path": "/tmp/project/src/utils/helper.rs"})
+                )
+                .decision,
+            PermissionDecision::Allow
+        );
+
+        // Should NOT allow different directories
+        assert_eq!(
+            checker
+                .check_permission("Read", &json!({"file_path": "/etc/passwd"}))
+                .decision,
+            PermissionDecision::Ask
+        );
+    }
+
+    #[test]
+    fn test_deny_wins_across_rule_sets() {
+        let settings = |allow: &[&str], deny: &[&str]| {
+            let to_vec = |rules: &[&str]| Some(rules.iter().map(|r| r.to_string()).collect());
+            Settings {
+                permissions: Some(PermissionSettings {
+                    allow: to_vec(allow),
+                    deny: to_vec(deny),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }
+        };
+        let rm = json!({"command": "rm -rf build"});
+
+        // A user deny beats a project allow, and a project deny a user allow
+        for layers in [
+            [settings(&[], &["Bash(rm:*)"]), settings(&["Bash"], &[])],
+            [settings(&["Bash"], &[]), settings(&[], &["Bash(rm:*)"])],
+        ] {
+            let mut checker = PermissionChecker::from_layers(&layers, "/tmp");
+            let result = checker.check_permission("Bash", &rm);
+            assert_eq!(result.decision, PermissionDecision::Deny);
+            assert_eq!(result.rule.as_deref(), Some("Bash(rm:*)"));
+            assert_eq!(result.source.as_deref(), Some("deny"));
+            // Each layer still decides the calls only it has a rule for
+            assert_eq!(
+                checker
+                    .check_permission("Bash", &json!({"command": "ls"}))
+                    .decision,
+                PermissionDecision::Allow
+            );
+
+            // Reloading keeps the layers apart
+            checker.reload_from_layers(&layers);
+            assert_eq!(
+                checker.check_permission("Bash", &rm).decision,
+                PermissionDecision::Deny
+            );
+            assert_eq!(checker.list_permission_rules().len(), 2);
+        }
+
+        // Settings and session rules combine the same way
+        let mut checker = PermissionChecker::new(settings(&[], &["Bash(rm:*)"]), "/tmp");
+        checker.add_allow_rule("Bash");
+        assert_eq!(
+            checker.check_permission("Bash", &rm).decision,
+            PermissionDecision::Deny
+        );
+        let mut checker = PermissionChecker::new(settings(&["Bash"], &[]), "/tmp");
+        checker.add_deny_rule("Bash(rm:*)");
+        assert_eq!(
+            checker.check_permission("Bash", &rm).decision,
+            PermissionDecision::Deny
+        );
+        assert_eq!(
+            checker
+                .check_permission("Bash", &json!({"command": "ls"}))
+                .decision,
+            PermissionDecision::Allow
+        );
+    }
+
+    #[test]
+    fn test_disabled_rules_are_skipped_but_listed() {
+        let settings = Settings {
+            permissions: Some(PermissionSettings {
+                allow: Some(vec!["Bash".to_string()]),
+                deny: Some(vec!["Bash(rm:*)".to_string()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut checker = PermissionChecker::new(settings, "/tmp");
+        let rm = json!({"command": "rm -rf build"});
+        assert_eq!(
+            checker.check_permission("Bash", &rm).decision,
+            PermissionDecision::Deny
+        );
+
+        // Disabling the deny rule lets the allow rule decide
+        assert!(checker.set_rule_enabled("Bash(rm:*)", false));
+        let result = checker.check_permission("Bash", &rm);
+        assert_eq!(result.decision, PermissionDecision::Allow);
+        assert_eq!(result.rule.as_deref(), Some("Bash"));
+        assert!(
+            checker
+                .list_permission_rules()
+                .contains(&PermissionRuleEntry {
+                    rule: "Bash(rm:*)".to_string(),
+                    source: "deny",
+                    runtime: false,
+                    enabled: false,
+                })
+        );
+
+        // Reloading keeps it disabled; re-enabling restores the denial
+        checker.reload_from(checker.settings().clone());
+        assert_eq!(
+            checker.check_permission("Bash", &rm).decision,
+            PermissionDecision::Allow
+        );
+        assert!(checker.set_rule_enabled("Bash(rm:*)", true));
+        assert_eq!(
+            checker.check_permission("Bash", &rm).decision,
+            PermissionDecision::Deny
+        );
+        assert!(
+            checker
+                .list_permission_rules()
+                .iter()
+                .all(|entry| entry.enabled)
+        );
+
+        assert!(!checker.set_rule_enabled("Bash(unknown:*)", false));
+    }
+
+    #[test]
+    fn test_use_limited_allow_rule_lapses() {
+        let mut checker = PermissionChecker::new(Settings::default(), "/tmp");
+        checker.add_allow_rule_with_uses("Bash(rm:*)", 5);
+        let rm = json!({"command": "rm build.log"});
+
+        // Calls the rule doesn't match don't use it up
+        assert_eq!(
+            checker
+                .check_permission("Bash", &json!({"command": "ls"}))
+                .decision,
+            PermissionDecision::Ask
+        );
+        for _ in 0..5 {
+            let result = checker.check_permission("Bash", &rm);
+            assert_eq!(result.decision, PermissionDecision::Allow);
+            assert_eq!(result.rule.as_deref(), Some("Bash(rm:*)"));
+            assert!(checker.spend_use(&result));
+        }
+        assert_eq!(
+            checker.check_permission("Bash", &rm).decision,
+            PermissionDecision::Ask
+        );
+        assert!(checker.list_permission_rules().is_empty());
+    }
+
+    #[test]
+    fn test_checking_spends_no_uses() {
+        let mut checker = PermissionChecker::new(Settings::default(), "/tmp");
+        checker.add_allow_rule("Bash(ls:*)");
+        checker.add_allow_rule_with_uses("Bash(rm:*)", 1);
+        let rm = json!({"command": "rm build.log"});
+
+        // Audits and repeated checks of the same call leave the use in place
+        for _ in 0..3 {
+            let result = checker.check_permission("Bash", &rm);
+            assert_eq!(result.decision, PermissionDecision::Allow);
+            assert_eq!(result.source.as_deref(), Some(USE_LIMITED_SOURCE));
+        }
+        assert!(checker.check_deny("Bash", &rm).is_none());
+        assert_eq!(
+            checker.use_limited_allow_rules(),
+            vec![("Bash(rm:*)".to_string(), 1)]
+        );
+
+        // The last use can only be spent once
+        let result = checker.check_permission("Bash", &rm);
+        assert!(checker.spend_use(&result));
+        assert!(!checker.spend_use(&result));
+
+        // Other rules have nothing to spend
+        let ls = checker.check_permission("Bash", &json!({"command": "ls"}));
+        assert!(checker.spend_use(&ls));
+        assert!(checker.spend_use(&PermissionCheckResult::ask()));
+    }
+
+    #[test]
+    fn test_use_limited_allow_rule_is_spent_once_per_use() {
+        let mut checker = PermissionChecker::new(Settings::default(), "/tmp");
+        checker.add_allow_rule_with_uses("Bash(rm:*)", 5);
+        let rm = json!({"command": "rm build.log"});
+
+        let allowed = std::sync::atomic::AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..4 {
+                        let result = checker.check_permission("Bash", &rm);
+                        if result.decision == PermissionDecision::Allow
+                            && checker.spend_use(&result)
+                        {
+                            allowed.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(allowed.into_inner(), 5);
+    }
+
+    #[test]
+    fn test_generate_file_rule_resolves_relative_paths() {
+        let cwd = Path::new("/tmp/project");
+        let rule = |input| PermissionChecker::generate_file_rule("Read", "Read", &input, cwd);
+
+        assert_eq!(
+            rule(json!({"file_path": "/tmp/project/src/main.rs"})),
+            "Read(./src/**)"
+        );
+        assert_eq!(rule(json!({"file_path": "src/main.rs"})), "Read(./src/**)");
+        assert_eq!(
+            rule(json!({"file_path": "./src/main.rs"})),
+            "Read(./src/**)"
+        );
+        assert_eq!(rule(json!({"file_path": "main.rs"})), "Read(./*)");
+    }
+
+    #[test]
+    fn test_generate_file_rule_does_not_broaden_with_dot_dot() {
+        let cwd = Path::new("/tmp/project");
+        let rule = |input| PermissionChecker::generate_file_rule("Edit", "Edit", &input, cwd);
+
+        // `..` that stays inside cwd is resolved away
+        assert_eq!(
+            rule(json!({"file_path": "src/../lib/mod.rs"})),
+            "Edit(./lib/**)"
+        );
+        assert_eq!(
+            rule(json!({"file_path": "/tmp/project/src/../main.rs"})),
+            "Edit(./*)"
+        );
+
+        // Leaving cwd allows only the file, never `./../**`
+        assert_eq!(
+            rule(json!({"file_path": "../secrets.txt"})),
+            "Edit(/tmp/secrets.txt)"
+        );
+        assert_eq!(
+            rule(json!({"file_path": "./../other/src/lib.rs"})),
+            "Edit(/tmp/other/src/lib.rs)"
+        );
+        assert_eq!(
+            rule(json!({"file_path": "/tmp/project/../../etc/passwd"})),
+            "Edit(/etc/passwd)"
+        );
+        assert_eq!(
+            PermissionChecker::generate_file_rule("Grep", "Grep", &json!({"path": ".."}), cwd),
+            "Grep(/tmp)"
+        );
+
+        // The generated rule doesn't cover the rest of cwd's parent
+        let mut checker = PermissionChecker::new(Settings::default(), "/tmp/project");
+        checker.add_allow_rule_for_tool_call("Edit", &json!({"file_path": "../secrets.txt"}));
+        assert_eq!(
+            checker
+                .check_permission("Edit", &json!({"file_path": "/tmp/other.txt"}))
+                .decision,
+            PermissionDecision::Ask
+        );
+        assert_eq!(
+            checker
+                .check_permission("Edit", &json!({"file_path": "/tmp/project/src/main.rs"}))
+                .decision,
+            PermissionDecision::Ask
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_generate_file_rule_for_backslash_path() {
+        let cwd = Path::new(r"C:\proj");
+        let rule = |input| PermissionChecker::generate_file_rule("Edit", "Edit", &input, cwd);
+
+        assert_eq!(
+            rule(json!({"file_path": r"C:\proj\src\main.rs"})),
+            "Edit(./src/**)"
+        );
+        assert_eq!(
+            rule(json!({"file_path": r"src\nested\lib.rs"})),
+            "Edit(./src/nested/**)"
+        );
+
+        let mut checker = PermissionChecker::new(Settings::default(), r"C:\proj");
+        checker.add_allow_rule_for_tool_call("Edit", &json!({"file_path": r"C:\proj\src\main.rs"}));
+        for file_path in ["C:/proj/src/lib.rs", r"C:\proj\src\lib.rs", "src/lib.rs"] {
+            assert_eq!(
+                checker
+                    .check_permission("Edit", &json!({"file_path": file_path}))
+                    .decision,
+                PermissionDecision::Allow,
+                "{file_path}"
+            );
+        }
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_backslash_is_a_file_name_character_off_windows() {
+        let cwd = Path::new("/tmp/project");
+        assert_eq!(
+            PermissionChecker::generate_file_rule(
+                "Edit",
+                "Edit",
+                &json!({"file_path": r"/tmp/project/a\b/main.rs"}),
+                cwd
+            ),
+            r"Edit(./a\b/**)"
+        );
+    }
+
+    #[test]
+    fn test_add_allow_rule_for_mcp_prefixed_tool() {
+        let mut checker = PermissionChecker::new(Settings::default(), "/tmp");
 
-        // Should allow npm run commands
-        assert_eq!(
-            checker
-                .check_permission("Bash", &json!({"command": "npm run build"}))
-                .decision,
-            PermissionDecision::Allow
-        );
+        // Add rule with MCP prefix
+        checker
+            .add_allow_rule_for_tool_call("mcp__acp__Bash", &json!({"command": "npm run build"}));
 
-        // Should not allow npm install
+        // Should work for both prefixed and non-prefixed tool names
         assert_eq!(
             checker
-                .check_permission("Bash", &json!({"command": "npm install"}))
+                .check_permission("Bash", &json!({"command": "npm run test"}))
                 .decision,
-            PermissionDecision::Ask
+            PermissionDecision::Allow
         );
-
-        // Should block command chaining
         assert_eq!(
             checker
-                .check_permission("Bash", &json!({"command": "npm run build && rm -rf /"}))
+                .check_permission("mcp__acp__Bash", &json!({"command": "npm run lint"}))
                 .decision,
-            PermissionDecision::Ask
+            PermissionDecision::Allow
         );
     }
 
     #[test]
-    fn test_read_group_matching() {
+    fn test_reload_replaces_file_rules_keeps_runtime_rules() {
         let permissions = PermissionSettings {
             allow: Some(vec!["Read".to_string()]),
             ..Default::default()
         };
-        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
+        let mut checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
+        checker.add_allow_rule_for_tool_call("Bash", &json!({"command": "cargo build"}));
 
-        // Read rule should allow Read, Grep, Glob, LS
+        // Reload with different file rules
+        let permissions = PermissionSettings {
+            allow: Some(vec!["Write".to_string()]),
+            ..Default::default()
+        };
+        checker.reload_from(settings_with_permissions(permissions));
+
+        // Old file rule is gone, new file rule applies
         assert_eq!(
             checker.check_permission("Read", &json!({})).decision,
-            PermissionDecision::Allow
+            PermissionDecision::Ask
         );
         assert_eq!(
-            checker.check_permission("Grep", &json!({})).decision,
+            checker.check_permission("Write", &json!({})).decision,
             PermissionDecision::Allow
         );
+
+        // Runtime-added rule survives the reload
+        let result = checker.check_permission("Bash", &json!({"command": "cargo test"}));
+        assert_eq!(result.decision, PermissionDecision::Allow);
+        assert_eq!(result.rule, Some("Bash(cargo:*)".to_string()));
+    }
+
+    #[test]
+    fn test_check_permission_in_effective_cwd() {
+        let permissions = PermissionSettings {
+            allow: Some(vec!["Read(./src/**)".to_string()]),
+            ..Default::default()
+        };
+        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/work");
+        let relative = json!({"file_path": "src/lib.rs"});
+
+        // At the checker's cwd, ./src is /work/src
         assert_eq!(
-            checker.check_permission("Glob", &json!({})).decision,
+            checker.check_permission("Read", &relative).decision,
             PermissionDecision::Allow
         );
         assert_eq!(
-            checker.check_permission("LS", &json!({})).decision,
+            checker
+                .check_permission_in("Read", &relative, Path::new("/work"))
+                .decision,
             PermissionDecision::Allow
         );
 
-        // Should not allow Write
+        // After `cd crates/core`, ./src is /work/crates/core/src
+        let core = Path::new("/work/crates/core");
         assert_eq!(
-            checker.check_permission("Write", &json!({})).decision,
-            PermissionDecision::Ask
+            checker
+                .check_permission_in("Read", &relative, core)
+                .decision,
+            PermissionDecision::Allow
         );
-    }
-
-    #[test]
-    fn test_add_runtime_rule() {
-        let mut checker = PermissionChecker::default();
-
-        // Initially should ask
         assert_eq!(
-            checker.check_permission("Read", &json!({})).decision,
+            checker
+                .check_permission_in("Read", &json!({"file_path": "/work/src/lib.rs"}), core)
+                .decision,
             PermissionDecision::Ask
         );
-
-        // Add allow rule at runtime
-        checker.add_allow_rule("Read");
-
-        // Now should allow
         assert_eq!(
-            checker.check_permission("Read", &json!({})).decision,
+            checker
+                .check_permission_in(
+                    "Read",
+                    &json!({"file_path": "/work/crates/core/src/lib.rs"}),
+                    core
+                )
+                .decision,
             PermissionDecision::Allow
         );
     }
 
     #[test]
-    fn test_acp_prefix_stripped() {
+    fn test_checks_follow_shell_cd() {
         let permissions = PermissionSettings {
-            allow: Some(vec!["Read".to_string()]),
+            allow: Some(vec!["Read(./src/**)".to_string()]),
             ..Default::default()
         };
-        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
+        let mut checker = PermissionChecker::new(settings_with_permissions(permissions), "/work");
+        let relative = json!({"file_path": "src/lib.rs"});
 
-        // Should work with or without ACP prefix
+        checker.follow_shell_command("cd crates/core");
+        assert_eq!(checker.current_dir(), Path::new("/work/crates/core"));
         assert_eq!(
-            checker.check_permission("Read", &json!({})).decision,
+            checker
+                .check_permission(
+                    "Read",
+                    &json!({"file_path": "/work/crates/core/src/lib.rs"})
+                )
+                .decision,
             PermissionDecision::Allow
         );
         assert_eq!(
             checker
-                .check_permission("mcp__acp__Read", &json!({}))
+                .check_permission("Read", &json!({"file_path": "/work/src/lib.rs"}))
+                .decision,
+            PermissionDecision::Ask
+        );
+        // ACP tools don't run in the agent's shell
+        assert_eq!(
+            checker
+                .check_permission("mcp__acp__Read", &json!({"file_path": "/work/src/lib.rs"}))
                 .decision,
             PermissionDecision::Allow
         );
+
+        // Chained or shell-expanded targets are not followed
+        for command in ["cd /etc && ls", "cd -", "cd ~", "cd $HOME"] {
+            checker.follow_shell_command(command);
+            assert_eq!(
+                checker.current_dir(),
+                Path::new("/work/crates/core"),
+                "{command}"
+            );
+        }
+
+        checker.follow_shell_command("cd ../..");
+        assert_eq!(checker.current_dir(), Path::new("/work"));
+        assert_eq!(
+            checker.check_permission("Read", &relative).decision,
+            PermissionDecision::Allow
+        );
     }
 
     #[test]
-    fn test_has_rules() {
-        let checker = PermissionChecker::default();
-        assert!(!checker.has_rules());
-
+    fn test_cloned_checker_reuses_parsed_rules() {
         let permissions = PermissionSettings {
-            allow: Some(vec!["Read".to_string()]),
+            allow: Some(vec!["Read".to_string(), "Bash(npm run:*)".to_string()]),
+            deny: Some(vec!["Read(./.env)".to_string()]),
+            ask: Some(vec!["Bash(git push:*)".to_string()]),
             ..Default::default()
         };
-        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
-        assert!(checker.has_rules());
-    }
-
-    #[test]
-    fn test_add_allow_rule_for_bash_command() {
-        let mut checker = PermissionChecker::new(Settings::default(), "/tmp");
-
-        // Add rule for specific bash command (find)
-        checker
-            .add_allow_rule_for_tool_call("Bash", &json!({"command": "find /path1 -name '*.rs'"}));
+        let mut checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
+        checker.add_allow_rule_for_tool_call("Bash", &json!({"command": "cargo build"}));
+
+        let cloned = checker.clone();
+        let fresh = checker.without_runtime_rules();
+        let cases = [
+            ("Read", json!({"file_path": "/tmp/src/main.rs"})),
+            ("Read", json!({"file_path": "/tmp/.env"})),
+            ("Bash", json!({"command": "npm run build"})),
+            ("Bash", json!({"command": "git push origin main"})),
+            ("Write", json!({"file_path": "/tmp/out.txt"})),
+        ];
+        for (tool_name, input) in &cases {
+            let expected = checker.check_permission(tool_name, input);
+            for copy in [&cloned, &fresh] {
+                let actual = copy.check_permission(tool_name, input);
+                assert_eq!(
+                    actual.decision, expected.decision,
+                    "{} {}",
+                    tool_name, input
+                );
+                assert_eq!(actual.rule, expected.rule, "{} {}", tool_name, input);
+            }
+        }
 
-        // Should allow ANY find command (same command name)
+        // Runtime rules are copied by clone but not by without_runtime_rules
+        let cargo = json!({"command": "cargo test"});
         assert_eq!(
-            checker
-                .check_permission("Bash", &json!({"command": "find /different/path -type f"}))
-                .decision,
+            cloned.check_permission("Bash", &cargo).decision,
             PermissionDecision::Allow
         );
-
-        // Should allow find with different arguments
         assert_eq!(
-            checker
-                .check_permission("Bash", &json!({"command": "find . -name '*.txt' -delete"}))
-                .decision,
-            PermissionDecision::Allow
+            fresh.check_permission("Bash", &cargo).decision,
+            PermissionDecision::Ask
         );
 
-        // Should NOT allow different commands (ls, rm, etc.)
+        // Copies are independent afterwards
+        let mut cloned = cloned;
+        cloned.add_deny_rule("Write");
         assert_eq!(
             checker
-                .check_permission("Bash", &json!({"command": "ls -la /tmp"}))
+                .check_permission("Write", &json!({"file_path": "/tmp/out.txt"}))
                 .decision,
             PermissionDecision::Ask
         );
+    }
 
+    #[test]
+    fn test_add_deny_rule_for_tool_call() {
+        let mut checker = PermissionChecker::new(Settings::default(), "/project");
+
+        checker.add_deny_rule_for_tool_call("Bash", &json!({"command": "rm -rf build"}));
+        let result = checker.check_permission("Bash", &json!({"command": "rm -rf build"}));
+        assert_eq!(result.decision, PermissionDecision::Deny);
+        assert_eq!(result.rule.as_deref(), Some("Bash(rm:*)"));
+
+        // File denies cover only the rejected file, not its directory
+        let main_rs =
+            json!({"file_path": "/project/src/main.rs", "old_string": "a", "new_string": "b"});
+        checker.add_deny_rule_for_tool_call("mcp__acp__Edit", &main_rs);
+        assert_eq!(
+            checker.check_permission("Edit", &main_rs).decision,
+            PermissionDecision::Deny
+        );
         assert_eq!(
             checker
-                .check_permission("Bash", &json!({"command": "rm -rf /"}))
+                .check_permission("Edit", &json!({"file_path": "/project/src/lib.rs"}))
                 .decision,
             PermissionDecision::Ask
         );
     }
 
     #[test]
-    fn test_add_allow_rule_for_file_operation() {
+    fn test_reload_file_deny_overrides_runtime_allow() {
         let mut checker = PermissionChecker::new(Settings::default(), "/tmp");
+        checker.add_allow_rule("Bash");
 
-        // Add rule for specific file
-        checker.add_allow_rule_for_tool_call(
-            "Read",
-            &json!({"file_path": "/tmp/project/src/main.rs"}),
-        );
+        let permissions = PermissionSettings {
+            deny: Some(vec!["Bash".to_string()]),
+            ..Default::default()
+        };
+        checker.reload_from(settings_with_permissions(permissions));
 
-        // Should allow files in the same directory
+        // Deny still takes priority over allow after reload
         assert_eq!(
             checker
-                .check_permission("Read", &json!({"file_path": "/tmp/project/src/lib.rs"}))
+                .check_permission("Bash", &json!({"command": "ls"}))
                 .decision,
-            PermissionDecision::Allow
+            PermissionDecision::Deny
         );
+    }
 
-        // Should allow subdirectories (glob **)
-        assert_eq!(
+    #[test]
+    fn test_assert_decision() {
+        let permissions = PermissionSettings {
+            allow: Some(vec!["Bash(npm run:*)".to_string()]),
+            deny: Some(vec!["Read(./.env)".to_string()]),
+            ..Default::default()
+        };
+        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
+
+        // Correct expectation passes
+        assert!(
             checker
-                .check_permission(
-                    "Read",
-                    &json!({"file_path": "/tmp/project/src/utils/helper.rs"})
+                .assert_decision(
+                    "Bash",
+                    &json!({"command": "npm run build"}),
+                    PermissionDecision::Allow
                 )
-                .decision,
-            PermissionDecision::Allow
+                .is_ok()
         );
 
-        // Should NOT allow different directories
-        assert_eq!(
-            checker
-                .check_permission("Read", &json!({"file_path": "/etc/passwd"}))
-                .decision,
-            PermissionDecision::Ask
-        );
+        // Wrong expectation is reported with details
+        let err = checker
+            .assert_decision(
+                "Bash",
+                &json!({"command": "npm run build"}),
+                PermissionDecision::Deny,
+            )
+            .unwrap_err();
+        assert_eq!(err.expected, PermissionDecision::Deny);
+        assert_eq!(err.actual, PermissionDecision::Allow);
+        assert_eq!(err.rule, Some("Bash(npm run:*)".to_string()));
+        let message = err.to_string();
+        assert!(message.contains("expected Deny, got Allow"));
+        assert!(message.contains("Bash(npm run:*)"));
     }
 
     #[test]
-    fn test_add_allow_rule_for_mcp_prefixed_tool() {
-        let mut checker = PermissionChecker::new(Settings::default(), "/tmp");
+    fn test_evaluate_examples() {
+        let permissions = PermissionSettings {
+            allow: Some(vec!["Read".to_string()]),
+            deny: Some(vec!["Bash(rm:*)".to_string()]),
+            ..Default::default()
+        };
+        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
 
-        // Add rule with MCP prefix
-        checker
-            .add_allow_rule_for_tool_call("mcp__acp__Bash", &json!({"command": "npm run build"}));
+        let read = json!({"file_path": "/tmp/a.txt"});
+        let rm = json!({"command": "rm -rf build"});
+        let write = json!({"file_path": "/tmp/a.txt"});
 
-        // Should work for both prefixed and non-prefixed tool names
-        assert_eq!(
-            checker
-                .check_permission("Bash", &json!({"command": "npm run test"}))
-                .decision,
-            PermissionDecision::Allow
-        );
-        assert_eq!(
+        assert!(
             checker
-                .check_permission("mcp__acp__Bash", &json!({"command": "npm run lint"}))
-                .decision,
-            PermissionDecision::Allow
+                .evaluate_examples([
+                    ("Read", &read, PermissionDecision::Allow),
+                    ("Bash", &rm, PermissionDecision::Deny),
+                    ("Write", &write, PermissionDecision::Ask),
+                ])
+                .is_ok()
         );
+
+        // Only the mismatching example is reported
+        let mismatches = checker
+            .evaluate_examples([
+                ("Read", &read, PermissionDecision::Allow),
+                ("Write", &write, PermissionDecision::Allow),
+            ])
+            .unwrap_err();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].tool_name, "Write");
+        assert_eq!(mismatches[0].actual, PermissionDecision::Ask);
+        assert!(mismatches[0].rule.is_none());
     }
 
     #[test]
@@ -567,4 +2723,147 @@ mod tests {
             "cargo"
         );
     }
+
+    #[test]
+    fn test_additional_directories_expand_home() {
+        let permissions = PermissionSettings {
+            additional_directories: Some(vec![
+                "~/projects/**".to_string(),
+                "vendor/shared".to_string(),
+            ]),
+            ..Default::default()
+        };
+        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/work/app");
+        let home = dirs::home_dir().unwrap();
+
+        assert!(checker.is_in_additional_directory("~/projects/foo/main.rs"));
+        let absolute = home.join("projects").join("foo").join("main.rs");
+        assert!(checker.is_in_additional_directory(absolute.to_str().unwrap()));
+        assert!(checker.is_in_additional_directory("~/projects"));
+        assert!(!checker.is_in_additional_directory("~/other/foo"));
+
+        // Relative entries resolve against the working directory
+        assert!(checker.is_in_additional_directory("/work/app/vendor/shared/lib.rs"));
+        assert!(checker.is_in_additional_directory("vendor/shared/lib.rs"));
+        assert!(!checker.is_in_additional_directory("/work/app/src/main.rs"));
+    }
+
+    #[test]
+    fn test_no_additional_directories() {
+        let checker = PermissionChecker::default();
+        assert!(!checker.is_in_additional_directory("~/projects/foo"));
+    }
+
+    #[test]
+    fn test_project_dir_defaults_to_cwd() {
+        let permissions = PermissionSettings {
+            deny: Some(vec!["Read(./secrets/**)".to_string()]),
+            ..Default::default()
+        };
+        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/work/app");
+        assert_eq!(checker.project_dir(), Path::new("/work/app"));
+
+        // A separate project directory doesn't move path resolution
+        let checker = checker.with_project_dir("/work");
+        assert_eq!(checker.project_dir(), Path::new("/work"));
+        assert_eq!(checker.cwd(), Path::new("/work/app"));
+        let read = |path: &str| {
+            checker
+                .check_permission("Read", &json!({"file_path": path}))
+                .decision
+        };
+        assert_eq!(read("/work/app/secrets/key"), PermissionDecision::Deny);
+        assert_eq!(read("/work/secrets/key"), PermissionDecision::Ask);
+    }
+
+    #[test]
+    fn test_is_in_working_directory() {
+        let permissions = PermissionSettings {
+            additional_directories: Some(vec!["/work/shared".to_string()]),
+            ..Default::default()
+        };
+        let checker = PermissionChecker::new(settings_with_permissions(permissions), "/work/app");
+
+        assert!(checker.is_in_working_directory("/work/app/src/main.rs"));
+        assert!(checker.is_in_working_directory("src/main.rs"));
+        assert!(checker.is_in_working_directory("/work/shared/lib.rs"));
+        assert!(!checker.is_in_working_directory("/work/other/lib.rs"));
+        assert!(!checker.is_in_working_directory("/etc/passwd"));
+    }
+
+    fn checker_with_deny_bash(allow_always_overrides_deny: Option<bool>) -> PermissionChecker {
+        let permissions = PermissionSettings {
+            deny: Some(vec!["Bash".to_string()]),
+            allow_always_overrides_deny,
+            ..Default::default()
+        };
+        let mut checker = PermissionChecker::new(settings_with_permissions(permissions), "/tmp");
+        checker.add_allow_rule_for_tool_call("Bash", &json!({"command": "ls -la"}));
+        checker
+    }
+
+    #[test]
+    fn test_deny_wins_over_always_allow_by_default() {
+        let checker = checker_with_deny_bash(None);
+        assert!(!checker.allow_always_overrides_deny());
+
+        let result = checker.check_permission("Bash", &json!({"command": "ls /tmp"}));
+        assert_eq!(result.decision, PermissionDecision::Deny);
+        assert_eq!(result.rule.as_deref(), Some("Bash"));
+    }
+
+    #[test]
+    fn test_always_allow_overrides_deny_when_enabled() {
+        let mut checker = checker_with_deny_bash(Some(true));
+
+        let result = checker.check_permission("Bash", &json!({"command": "ls /tmp"}));
+        assert_eq!(result.decision, PermissionDecision::Allow);
+        assert_eq!(result.rule.as_deref(), Some("Bash(ls:*)"));
+
+        // Commands the user did not allow are still denied
+        let result = checker.check_permission("Bash", &json!({"command": "rm -rf /tmp/x"}));
+        assert_eq!(result.decision, PermissionDecision::Deny);
+
+        // Runtime deny rules keep precedence
+        checker.add_deny_rule("Bash(ls:*)");
+        let result = checker.check_permission("Bash", &json!({"command": "ls /tmp"}));
+        assert_eq!(result.decision, PermissionDecision::Deny);
+    }
+
+    #[test]
+    fn test_command_aliases_apply_to_rules() {
+        let settings = Settings {
+            permissions: Some(PermissionSettings {
+                allow: Some(vec!["Bash(git status:*)".to_string()]),
+                deny: Some(vec!["Bash(git push:*)".to_string()]),
+                ..Default::default()
+            }),
+            command_aliases: Some(
+                [("gitw".to_string(), "git".to_string())]
+                    .into_iter()
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        let mut checker = PermissionChecker::new(settings, "/tmp");
+
+        let result = checker.check_permission("Bash", &json!({"command": "./scripts/gitw status"}));
+        assert_eq!(result.decision, PermissionDecision::Allow);
+        let result = checker.check_permission("Bash", &json!({"command": "gitw push origin"}));
+        assert_eq!(result.decision, PermissionDecision::Deny);
+
+        // Unconfigured wrappers pass through unchanged
+        let result = checker.check_permission("Bash", &json!({"command": "hgw status"}));
+        assert_eq!(result.decision, PermissionDecision::Ask);
+        let input = json!({"command": "hgw status"});
+        assert!(matches!(
+            checker.resolve_command_aliases("Bash", &input),
+            Cow::Borrowed(_)
+        ));
+
+        // Always Allow on the wrapper records the canonical command
+        checker.add_allow_rule_for_tool_call("Bash", &json!({"command": "gitw log -5"}));
+        let result = checker.check_permission("Bash", &json!({"command": "git log"}));
+        assert_eq!(result.rule.as_deref(), Some("Bash(git:*)"));
+    }
 }
@@ -2,26 +2,41 @@
 //!
 //! Implements rule parsing for allow/deny/ask permission rules with glob pattern support.
 
-use std::path::Path;
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::path::{Path, PathBuf};
 
 use globset::{Glob, GlobMatcher};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use super::output_rule::ToolOutputRule;
+
 use crate::mcp::ExternalMcpManager;
 use crate::mcp::tools::bash::contains_shell_operator;
+use crate::tools::{BashInput, FilePathInput, ToolInputExt};
+use crate::utils::{expand_home, resolve_dot_segments};
 
 /// Cached regex for parsing permission rules
 /// Pattern: ToolName or ToolName(argument)
 /// Compiled once and reused for better performance
 static RULE_REGEX: std::sync::LazyLock<Regex> = std::sync::LazyLock::new(|| {
     // This regex is statically known and will always compile correctly
-    Regex::new(r"^(\w+)(?:\((.+)\))?$").expect("Invalid hardcoded regex pattern")
+    Regex::new(r"^(\w+(?:\|\w+)*)(?:\((.+)\))?$").expect("Invalid hardcoded regex pattern")
 });
 
 /// ACP tool name prefix
 const ACP_TOOL_PREFIX: &str = "mcp__acp__";
 
+/// Rule name for flag rules such as `BashFlag(--privileged)`, which match a
+/// Bash command if the flag appears in any of its simple commands. They are
+/// only accepted as deny rules.
+const BASH_FLAG_RULE: &str = "BashFlag";
+
+/// Rule name for title rules such as `ToolTitle(Create issue)`, which match
+/// a tool by its advertised title rather than its name
+const TOOL_TITLE_RULE: &str = "ToolTitle";
+
 /// Permission decision result
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PermissionDecision {
@@ -33,6 +48,17 @@ pub enum PermissionDecision {
     Ask,
 }
 
+impl PermissionDecision {
+    /// Lowercase name, matching the settings list the decision comes from
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+            Self::Ask => "ask",
+        }
+    }
+}
+
 /// Result of a permission check
 #[derive(Debug, Clone)]
 pub struct PermissionCheckResult {
@@ -40,10 +66,14 @@ pub struct PermissionCheckResult {
     pub decision: PermissionDecision,
     /// The rule that matched (if any)
     pub rule: Option<String>,
-    /// The source of the rule (allow, deny, ask)
+    /// The source of the rule (allow, deny, ask, or [`USE_LIMITED_SOURCE`])
     pub source: Option<String>,
 }
 
+/// Source of a result allowed by a use-limited rule, which is spent with
+/// [`PermissionChecker::spend_use`](super::PermissionChecker::spend_use)
+pub const USE_LIMITED_SOURCE: &str = "useLimited";
+
 impl PermissionCheckResult {
     /// Create a new allow result
     pub fn allow(rule: impl Into<String>) -> Self {
@@ -54,6 +84,20 @@ impl PermissionCheckResult {
         }
     }
 
+    /// Create an allow result for a use-limited rule
+    pub fn allow_use_limited(rule: impl Into<String>) -> Self {
+        Self {
+            decision: PermissionDecision::Allow,
+            rule: Some(rule.into()),
+            source: Some(USE_LIMITED_SOURCE.to_string()),
+        }
+    }
+
+    /// Whether the result comes from a use-limited rule
+    pub fn is_use_limited(&self) -> bool {
+        self.source.as_deref() == Some(USE_LIMITED_SOURCE)
+    }
+
     /// Create a new deny result
     pub fn deny(rule: impl Into<String>) -> Self {
         Self {
@@ -80,6 +124,31 @@ impl PermissionCheckResult {
             source: None,
         }
     }
+
+    /// Combine the results of evaluating two rule sets
+    ///
+    /// Uses the precedence of [`PermissionChecker::check_permission`]: deny
+    /// beats allow, allow beats an ask rule, and any matching rule beats no
+    /// match. The winner keeps its rule and source; on a tie, `a` wins.
+    ///
+    /// [`PermissionChecker::check_permission`]: super::PermissionChecker::check_permission
+    pub fn merge(a: Self, b: Self) -> Self {
+        if b.precedence() > a.precedence() {
+            b
+        } else {
+            a
+        }
+    }
+
+    /// Rank used by [`Self::merge`]; higher wins
+    fn precedence(&self) -> u8 {
+        match self.decision {
+            PermissionDecision::Deny => 3,
+            PermissionDecision::Allow => 2,
+            PermissionDecision::Ask if self.rule.is_some() => 1,
+            PermissionDecision::Ask => 0,
+        }
+    }
 }
 
 /// Permission settings from settings.json
@@ -105,6 +174,68 @@ pub struct PermissionSettings {
     /// Default permission mode
     #[serde(default)]
     pub default_mode: Option<String>,
+
+    /// Block external MCP tools (`mcp__<server>__*`) that match no allow or ask rule
+    /// instead of prompting. Built-in tools are unaffected.
+    #[serde(default)]
+    pub deny_unknown_mcp_tools: Option<bool>,
+
+    /// Let in-session "Always Allow" rules take precedence over deny rules
+    /// from settings files. Off by default, so deny always wins.
+    #[serde(default)]
+    pub allow_always_overrides_deny: Option<bool>,
+
+    /// Make `Read` rules (allow, ask and deny alike) match only the Read
+    /// tool. Off by default, so a `Read` rule also covers Grep, Glob and LS.
+    #[serde(default)]
+    pub strict_read_rule: Option<bool>,
+
+    /// Rules flagging oversized or binary tool responses after execution
+    #[serde(default)]
+    pub output_rules: Option<Vec<ToolOutputRule>>,
+
+    /// What happens to a tool called with empty input (`{}`) that no rule
+    /// matches. Only name rules (`mcp__server__tool`) can match such calls.
+    #[serde(default)]
+    pub empty_input_default: Option<EmptyInputPolicy>,
+}
+
+/// Fallback decision for tool calls with empty input and no matching rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptyInputPolicy {
+    /// Prompt, as for any other tool call without a rule
+    #[default]
+    Ask,
+    /// Block the call; approve such tools with a name rule instead
+    Deny,
+}
+
+/// How a rule's pattern is matched against a tool call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleMatcherKind {
+    /// No pattern: every call of the tool matches
+    Any,
+    /// The argument must equal the pattern
+    Exact,
+    /// The argument must start with the pattern (`:*` rules)
+    Prefix,
+    /// The path must match the pattern as a glob (file tools)
+    Glob,
+    /// The Bash command must carry the pattern as a flag (`BashFlag` rules)
+    Flag,
+}
+
+/// The parsed structure of a rule, for display and editing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuleComponents {
+    /// Tool (or group) names the rule applies to; several for `A|B(...)` rules
+    pub tool_names: Vec<String>,
+    /// How `pattern` is matched
+    pub kind: RuleMatcherKind,
+    /// The pattern, without any `:*` suffix
+    pub pattern: Option<String>,
 }
 
 /// A parsed permission rule
@@ -118,10 +249,26 @@ pub struct ParsedRule {
     pub is_wildcard: bool,
     /// Compiled glob matcher for file paths
     glob_matcher: Option<GlobMatcher>,
+    /// Compiled matcher for the directory of a `dir/**` pattern, which the rule also covers
+    dir_matcher: Option<GlobMatcher>,
+    /// The file pattern normalized when the matchers were compiled, compared
+    /// as a string if it isn't a valid glob
+    normalized_pattern: Option<String>,
+    /// Cwd a relative pattern's matchers were compiled against (None when
+    /// the pattern doesn't depend on the cwd)
+    glob_cwd: Option<PathBuf>,
+    /// One rule per tool of a `A|B(arg)` tool set (empty for a single tool)
+    alternatives: Vec<ParsedRule>,
+    /// Whether a `Read` rule matches only the Read tool (`strictReadRule`)
+    strict_read: bool,
 }
 
 impl ParsedRule {
-    /// Parse a rule string like "Read", "Read(./.env)", "Bash(npm run:*)"
+    /// Parse a rule string like "Read", "Read(./.env)", "Bash(npm run:*)",
+    /// "BashFlag(--privileged)", "ToolTitle(Delete:*)"
+    ///
+    /// Several tools can share one argument: `Edit|Write(./src/**)` is
+    /// equivalent to `Edit(./src/**)` and `Write(./src/**)`.
     pub fn parse(rule: &str) -> Self {
         // Use cached regex (compiled once at first use)
         // The regex is statically known and guaranteed to compile correctly
@@ -141,11 +288,36 @@ impl ParsedRule {
                 argument
             };
 
+            // Expand a tool set into one rule per tool
+            let alternatives = if tool_name.contains('|') {
+                tool_name
+                    .split('|')
+                    .map(|name| Self {
+                        tool_name: name.to_string(),
+                        argument: argument.clone(),
+                        is_wildcard,
+                        glob_matcher: None,
+                        dir_matcher: None,
+                        normalized_pattern: None,
+                        glob_cwd: None,
+                        alternatives: Vec::new(),
+                        strict_read: false,
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
             Self {
                 tool_name,
                 argument,
                 is_wildcard,
                 glob_matcher: None,
+                dir_matcher: None,
+                normalized_pattern: None,
+                glob_cwd: None,
+                alternatives,
+                strict_read: false,
             }
         } else {
             // Fallback: treat entire string as tool name
@@ -154,30 +326,206 @@ impl ParsedRule {
                 argument: None,
                 is_wildcard: false,
                 glob_matcher: None,
+                dir_matcher: None,
+                normalized_pattern: None,
+                glob_cwd: None,
+                alternatives: Vec::new(),
+                strict_read: false,
             }
         }
     }
 
     /// Parse with glob compilation for file path rules
+    ///
+    /// `**` may appear anywhere in the pattern and matches across directory
+    /// boundaries, so `Edit(./**/migrations/**)` covers migrations at any
+    /// depth below the cwd. Brace groups expand into one alternative per
+    /// choice, so `Edit(./src/{a,b}/**)` covers `./src/a` and `./src/b`.
     pub fn parse_with_glob(rule: &str, cwd: &Path) -> Self {
         let mut parsed = Self::parse(rule);
+        parsed.expand_brace_alternatives();
+        parsed.compile_glob(cwd);
+        for alternative in &mut parsed.alternatives {
+            alternative.compile_glob(cwd);
+        }
+        parsed
+    }
+
+    /// Stop a `Read` rule from also matching Grep, Glob and LS
+    pub fn set_strict_read(&mut self, strict: bool) {
+        self.strict_read = strict;
+        for alternative in &mut self.alternatives {
+            alternative.strict_read = strict;
+        }
+    }
+
+    /// Expand the `{a,b}` groups of a file rule's pattern into alternatives
+    ///
+    /// globset rejects nested groups, so the pattern is expanded up front
+    /// (see [`expand_braces`]) and each file tool of the rule gets one
+    /// alternative per expanded pattern. Other tools of a tool set keep the
+    /// pattern as written.
+    fn expand_brace_alternatives(&mut self) {
+        let Some(ref argument) = self.argument else {
+            return;
+        };
+        let patterns = expand_braces(argument);
+        if patterns.len() == 1 && patterns[0] == *argument {
+            return;
+        }
+
+        let tool_names: Vec<String> = if self.alternatives.is_empty() {
+            vec![self.tool_name.clone()]
+        } else {
+            self.alternatives
+                .iter()
+                .map(|rule| rule.tool_name.clone())
+                .collect()
+        };
+        if !tool_names.iter().any(|name| is_file_tool(name)) {
+            return;
+        }
 
-        // Compile glob for file-related tools
-        if let Some(ref arg) = parsed.argument
-            && is_file_tool(&parsed.tool_name)
-            && !parsed.is_wildcard
+        let mut alternatives = Vec::new();
+        for tool_name in tool_names {
+            let tool_patterns = if is_file_tool(&tool_name) {
+                patterns.clone()
+            } else {
+                vec![argument.clone()]
+            };
+            for pattern in tool_patterns {
+                alternatives.push(Self {
+                    tool_name: tool_name.clone(),
+                    argument: Some(pattern),
+                    is_wildcard: self.is_wildcard,
+                    glob_matcher: None,
+                    dir_matcher: None,
+                    normalized_pattern: None,
+                    glob_cwd: None,
+                    alternatives: Vec::new(),
+                    strict_read: self.strict_read,
+                });
+            }
+        }
+        self.alternatives = alternatives;
+    }
+
+    /// Compile the glob matcher for file-related tools
+    fn compile_glob(&mut self, cwd: &Path) {
+        if let Some(ref arg) = self.argument
+            && is_file_tool(&self.tool_name)
+            && !self.is_wildcard
         {
-            let normalized = normalize_path(arg, cwd);
+            let normalized = normalize_pattern(arg, cwd);
             if let Ok(glob) = Glob::new(&normalized) {
-                parsed.glob_matcher = Some(glob.compile_matcher());
+                self.glob_matcher = Some(glob.compile_matcher());
+            }
+            self.normalized_pattern = Some(normalized);
+            if let Some(dir) = arg.strip_suffix("/**")
+                && let Ok(glob) = Glob::new(&normalize_pattern(dir, cwd))
+            {
+                self.dir_matcher = Some(glob.compile_matcher());
+            }
+            if is_cwd_relative(arg) {
+                self.glob_cwd = Some(cwd.to_path_buf());
             }
         }
+    }
 
-        parsed
+    /// The rule's tool names, matcher kind and pattern
+    ///
+    /// For a tool set spanning different kinds of tools, the kind is that
+    /// of the first tool.
+    pub fn components(&self) -> RuleComponents {
+        let mut tool_names = vec![];
+        if self.alternatives.is_empty() {
+            tool_names.push(self.tool_name.clone());
+        }
+        // A brace pattern gives a tool several alternatives; list it once
+        for rule in &self.alternatives {
+            if !tool_names.contains(&rule.tool_name) {
+                tool_names.push(rule.tool_name.clone());
+            }
+        }
+        let tool = self.alternatives.first().unwrap_or(self);
+
+        let kind = if self.argument.is_none() {
+            RuleMatcherKind::Any
+        } else if tool.tool_name == BASH_FLAG_RULE {
+            RuleMatcherKind::Flag
+        } else if is_file_tool(&tool.tool_name) {
+            RuleMatcherKind::Glob
+        } else if self.is_wildcard {
+            RuleMatcherKind::Prefix
+        } else {
+            RuleMatcherKind::Exact
+        };
+
+        RuleComponents {
+            tool_names,
+            kind,
+            pattern: self.argument.clone(),
+        }
+    }
+
+    /// Whether this is a `ToolTitle(...)` rule, matched with [`Self::matches_title`]
+    pub fn is_title_rule(&self) -> bool {
+        self.tool_name == TOOL_TITLE_RULE
+    }
+
+    /// Check if this is a `BashFlag(...)` rule, which only works as a deny rule
+    pub fn is_flag_rule(&self) -> bool {
+        self.tool_name == BASH_FLAG_RULE
+    }
+
+    /// Check if this title rule matches a tool's advertised title
+    ///
+    /// Titles compare case-insensitively, exactly or by prefix for `:*`
+    /// rules. Always false for rules that aren't title rules.
+    pub fn matches_title(&self, title: &str) -> bool {
+        if !self.is_title_rule() {
+            return false;
+        }
+        let Some(ref pattern) = self.argument else {
+            return false;
+        };
+        let title = title.trim().to_lowercase();
+        let pattern = pattern.trim().to_lowercase();
+        if self.is_wildcard {
+            title.starts_with(&pattern)
+        } else {
+            title == pattern
+        }
     }
 
     /// Check if this rule matches a tool invocation
+    ///
+    /// Title rules never match here; see [`Self::matches_title`].
     pub fn matches(&self, tool_name: &str, tool_input: &serde_json::Value, cwd: &Path) -> bool {
+        self.matches_with_cache(tool_name, tool_input, cwd, &MatchCache::default())
+    }
+
+    /// [`Self::matches`], reusing the paths normalized by earlier rules
+    ///
+    /// `cache` must only be shared by checks of the same tool call in the
+    /// same `cwd`.
+    pub(crate) fn matches_with_cache(
+        &self,
+        tool_name: &str,
+        tool_input: &serde_json::Value,
+        cwd: &Path,
+        cache: &MatchCache,
+    ) -> bool {
+        if !self.alternatives.is_empty() {
+            return self
+                .alternatives
+                .iter()
+                .any(|rule| rule.matches_with_cache(tool_name, tool_input, cwd, cache));
+        }
+        if self.is_title_rule() {
+            return false;
+        }
+
         // Strip ACP prefix if present
         let stripped_name = tool_name.strip_prefix(ACP_TOOL_PREFIX).unwrap_or(tool_name);
 
@@ -198,10 +546,12 @@ impl ParsedRule {
         };
 
         // Match based on tool type
-        if is_bash_tool(stripped_name) {
+        if self.tool_name == BASH_FLAG_RULE {
+            command_has_flag(&actual_arg, pattern)
+        } else if is_bash_tool(stripped_name) {
             self.matches_bash_command(pattern, &actual_arg)
         } else if is_file_tool(stripped_name) {
-            self.matches_file_path(pattern, &actual_arg, cwd)
+            self.matches_file_path(pattern, &actual_arg, cwd, cache)
         } else {
             // Exact match for other tools
             pattern == &actual_arg
@@ -225,14 +575,16 @@ impl ParsedRule {
 
         // Tool group matching
         match self.tool_name.as_str() {
-            // Read rule matches Read, Grep, Glob, LS
-            "Read" => matches!(tool_name, "Read" | "Grep" | "Glob" | "LS"),
+            // Read rule matches Read, Grep, Glob, LS (unless strict)
+            "Read" => !self.strict_read && matches!(tool_name, "Read" | "Grep" | "Glob" | "LS"),
             // Edit rule matches Edit, Write
             "Edit" => matches!(tool_name, "Edit" | "Write"),
             // Task rule matches Task, TaskOutput
             "Task" => matches!(tool_name, "Task" | "TaskOutput"),
             // Web rule matches WebSearch, WebFetch
             "Web" => matches!(tool_name, "WebSearch" | "WebFetch"),
+            // BashFlag rule matches Bash commands carrying the flag
+            BASH_FLAG_RULE => tool_name == "Bash",
             _ => false,
         }
     }
@@ -242,11 +594,17 @@ impl ParsedRule {
         if self.is_wildcard {
             // Prefix match with wildcard
             if let Some(remainder) = command.strip_prefix(pattern) {
+                // The prefix must end at a word boundary, so `git push` doesn't
+                // cover `git pushx` or `git push-foo`; a prefix already ending
+                // in whitespace or punctuation such as `/` is a boundary itself
+                let at_boundary = remainder.is_empty()
+                    || remainder.starts_with(char::is_whitespace)
+                    || !pattern
+                        .chars()
+                        .next_back()
+                        .is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '-'));
                 // Check remainder for shell operators (security)
-                if contains_shell_operator(remainder) {
-                    return false;
-                }
-                return true;
+                return at_boundary && !contains_shell_operator(remainder);
             }
             false
         } else {
@@ -256,48 +614,356 @@ impl ParsedRule {
     }
 
     /// Match file path with glob pattern
-    fn matches_file_path(&self, pattern: &str, file_path: &str, cwd: &Path) -> bool {
-        // Use pre-compiled glob if available
-        if let Some(ref matcher) = self.glob_matcher {
-            let normalized_path = normalize_path(file_path, cwd);
-            return matcher.is_match(&normalized_path);
+    fn matches_file_path(
+        &self,
+        pattern: &str,
+        file_path: &str,
+        cwd: &Path,
+        cache: &MatchCache,
+    ) -> bool {
+        let normalized_path = cache.normalized_path(file_path, cwd);
+        let normalized_path = normalized_path.as_ref();
+
+        // Patterns compiled at parse time match without touching the disk. A
+        // relative pattern's matchers only apply in the cwd they were
+        // compiled against
+        if let Some(ref normalized_pattern) = self.normalized_pattern
+            && self
+                .glob_cwd
+                .as_deref()
+                .is_none_or(|glob_cwd| glob_cwd == cwd)
+        {
+            // A `dir/**` rule also covers `dir` itself, e.g. a Grep or Glob rooted there
+            if self
+                .dir_matcher
+                .as_ref()
+                .is_some_and(|matcher| matcher.is_match(normalized_path))
+            {
+                return true;
+            }
+            return match self.glob_matcher {
+                Some(ref matcher) => matcher.is_match(normalized_path),
+                None => normalized_pattern == normalized_path,
+            };
         }
 
-        // Fallback: compile glob on demand
-        let normalized_pattern = normalize_path(pattern, cwd);
-        let normalized_path = normalize_path(file_path, cwd);
+        // In another cwd a relative pattern is resolved against that cwd,
+        // canonicalized once per check; other uncompiled patterns are
+        // normalized here
+        let normalize = |pattern: &str| match self.glob_cwd {
+            Some(_) => resolve_relative_pattern(pattern, cache.canonical_cwd(cwd)),
+            None => normalize_pattern(pattern, cwd),
+        };
 
-        if let Ok(glob) = Glob::new(&normalized_pattern) {
-            let matcher = glob.compile_matcher();
-            return matcher.is_match(&normalized_path);
+        // The directory part may contain escaped glob metacharacters, so it is
+        // matched as a glob rather than compared as a string
+        if let Some(dir) = pattern.strip_suffix("/**")
+            && Glob::new(&normalize(dir))
+                .is_ok_and(|glob| glob.compile_matcher().is_match(normalized_path))
+        {
+            return true;
         }
 
-        // Last resort: exact match
-        normalized_pattern == normalized_path
+        let normalized_pattern = normalize(pattern);
+        match Glob::new(&normalized_pattern) {
+            Ok(glob) => glob.compile_matcher().is_match(normalized_path),
+            // Last resort: exact match
+            Err(_) => normalized_pattern == normalized_path,
+        }
     }
 }
 
-/// Normalize a file path, expanding ~ and resolving relative paths
-fn normalize_path(path: &str, cwd: &Path) -> String {
-    let path = if let Some(rest) = path.strip_prefix("~/") {
-        if let Some(home) = dirs::home_dir() {
-            home.join(rest).to_string_lossy().to_string()
+/// Paths normalized while checking the rules against one tool call
+///
+/// Normalizing canonicalizes a path on disk, so the tool's path and the
+/// cwd are each normalized once per check, by the first rule that needs
+/// them, instead of once per rule.
+#[derive(Debug, Default)]
+pub(crate) struct MatchCache {
+    /// The tool's file path as given and normalized
+    file_path: OnceCell<(String, String)>,
+    /// The canonical cwd
+    canonical_cwd: OnceCell<PathBuf>,
+}
+
+impl MatchCache {
+    /// Normalize the tool's file path, reusing the result of an earlier rule
+    fn normalized_path(&self, file_path: &str, cwd: &Path) -> Cow<'_, str> {
+        let (cached_path, normalized) = self
+            .file_path
+            .get_or_init(|| (file_path.to_string(), normalize_path(file_path, cwd)));
+        if cached_path == file_path {
+            Cow::Borrowed(normalized)
         } else {
-            path.to_string()
+            Cow::Owned(normalize_path(file_path, cwd))
+        }
+    }
+
+    /// Canonicalize the cwd, reusing the result of an earlier rule
+    fn canonical_cwd(&self, cwd: &Path) -> &Path {
+        self.canonical_cwd
+            .get_or_init(|| canonicalize_existing_prefix(cwd))
+    }
+}
+
+/// Escape glob metacharacters so a literal path can be embedded in a rule
+///
+/// Each metacharacter is wrapped in a single-character class (`[` → `[[]`),
+/// which globset matches literally.
+pub(super) fn escape_glob(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '{' | '}') {
+            escaped.push('[');
+            escaped.push(c);
+            escaped.push(']');
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+/// Most patterns one brace pattern may expand to
+const MAX_BRACE_EXPANSIONS: usize = 256;
+
+/// Expand the `{a,b}` groups of a glob pattern into one pattern per choice
+///
+/// Groups may be nested (`{a,b{c,d}}`) and a pattern may hold several.
+/// Braces inside a character class (`[{]`) or after a backslash are
+/// literal, and so are groups without a comma (`{}`, `{a}`), which are
+/// escaped to match as written. A pattern with unbalanced braces, or one
+/// that would expand to more than 256 patterns, is returned unexpanded.
+///
+/// # Examples
+/// ```ignore
+/// assert_eq!(expand_braces("src/{a,b}/**"), vec!["src/a/**", "src/b/**"]);
+/// assert_eq!(expand_braces("{x,y{1,2}}"), vec!["x", "y1", "y2"]);
+/// ```
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let mut expanded = Vec::new();
+    let mut pending = vec![pattern.to_string()];
+    while let Some(current) = pending.pop() {
+        match split_brace_group(&current) {
+            Some((prefix, choices, suffix)) => {
+                // Pushed in reverse so choices come out in the order written
+                for choice in choices.iter().rev() {
+                    pending.push(format!("{prefix}{choice}{suffix}"));
+                }
+            }
+            None => expanded.push(current),
+        }
+        if expanded.len() + pending.len() > MAX_BRACE_EXPANSIONS {
+            return vec![pattern.to_string()];
+        }
+    }
+    expanded
+}
+
+/// Split a pattern around its first brace group
+///
+/// Returns the text before the group, its choices and the text after it. A
+/// group without a comma yields its text with the braces escaped. Returns
+/// `None` if the pattern has no group or its braces don't balance.
+fn split_brace_group(pattern: &str) -> Option<(String, Vec<String>, String)> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '[' => i = class_end(&chars, i).map_or(i + 1, |end| end + 1),
+            '{' => {
+                let (end, commas) = brace_group_end(&chars, i)?;
+                let prefix: String = chars[..i].iter().collect();
+                let suffix: String = chars[end + 1..].iter().collect();
+                if commas.is_empty() {
+                    let inner: String = chars[i + 1..end].iter().collect();
+                    return Some((prefix, vec![format!("[{{]{inner}[}}]")], suffix));
+                }
+                let mut choices: Vec<String> = Vec::new();
+                let mut start = i + 1;
+                for comma in commas.into_iter().chain([end]) {
+                    choices.push(chars[start..comma].iter().collect());
+                    start = comma + 1;
+                }
+                return Some((prefix, choices, suffix));
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Find the `}` closing the group opened at `start`, and the group's top-level commas
+fn brace_group_end(chars: &[char], start: usize) -> Option<(usize, Vec<usize>)> {
+    let mut depth = 0;
+    let mut commas = Vec::new();
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '[' => i = class_end(chars, i).unwrap_or(i),
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((i, commas));
+                }
+            }
+            ',' if depth == 1 => commas.push(i),
+            _ => {}
         }
+        i += 1;
+    }
+    None
+}
+
+/// Find the `]` closing the character class opened at `start`
+///
+/// A `]` right after the opening `[` (or `[!`) is part of the class.
+fn class_end(chars: &[char], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    if matches!(chars.get(i), Some('!' | '^')) {
+        i += 1;
+    }
+    if chars.get(i) == Some(&']') {
+        i += 1;
+    }
+    (i..chars.len()).find(|&i| chars[i] == ']')
+}
+
+/// Write a path with forward slashes, as rules and glob patterns expect
+///
+/// On Windows, tools may report `C:\proj\src\main.rs`; the separators are
+/// converted (and the `\\?\` prefix `canonicalize` adds is dropped) so the
+/// path matches a rule written as `C:/proj/src/**`. Elsewhere a backslash is
+/// an ordinary file name character and the path is returned unchanged.
+pub(super) fn to_slash_path(path: &str) -> String {
+    if cfg!(windows) {
+        path.strip_prefix(r"\\?\")
+            .unwrap_or(path)
+            .replace('\\', "/")
+    } else {
+        path.to_string()
+    }
+}
+
+/// Whether a path pattern is resolved against the cwd
+///
+/// Absolute, `~` and `**/` patterns mean the same wherever they are matched.
+fn is_cwd_relative(pattern: &str) -> bool {
+    let pattern = to_slash_path(pattern);
+    !(pattern.starts_with("**/")
+        || pattern == "~"
+        || pattern.starts_with("~/")
+        || Path::new(&pattern).is_absolute())
+}
+
+/// Make a path absolute, expanding ~ and resolving it against the cwd
+fn absolute_path(path: &str, cwd: &Path) -> String {
+    if path == "~" || path.starts_with("~/") {
+        expand_home(path)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string())
     } else if let Some(rest) = path.strip_prefix("./") {
         cwd.join(rest).to_string_lossy().to_string()
     } else if !Path::new(path).is_absolute() {
         cwd.join(path).to_string_lossy().to_string()
     } else {
         path.to_string()
+    }
+}
+
+/// Canonicalize as much of a path as exists
+///
+/// The deepest existing ancestor is canonicalized, which resolves symlinks
+/// such as macOS's `/tmp`, and the rest is appended with `.` and `..`
+/// resolved lexically. A file about to be created thereby normalizes the
+/// same way its existing siblings do.
+fn canonicalize_existing_prefix(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    let resolved = resolve_dot_segments(path);
+    for ancestor in resolved.ancestors().skip(1) {
+        if let Ok(canonical) = ancestor.canonicalize()
+            && let Ok(rest) = resolved.strip_prefix(ancestor)
+        {
+            return canonical.join(rest);
+        }
+    }
+    resolved
+}
+
+/// Normalize a file path, expanding ~ and resolving relative paths
+///
+/// Paths starting with `**/` are left as they are, so `Edit(**/*.env)`
+/// matches at any depth, inside the cwd or not.
+fn normalize_path(path: &str, cwd: &Path) -> String {
+    let path = &to_slash_path(path);
+    if path.starts_with("**/") {
+        return path.to_string();
+    }
+
+    let path = absolute_path(path, cwd);
+    to_slash_path(&canonicalize_existing_prefix(Path::new(&path)).to_string_lossy())
+}
+
+/// Normalize a rule's path pattern to match paths from [`normalize_path`]
+///
+/// The literal directories before the first glob metacharacter are resolved
+/// the way a path is, so `Edit(./src/**)` and an absolute path under the cwd
+/// agree even when the cwd is reached through a symlink. The glob part is
+/// kept as written.
+fn normalize_pattern(pattern: &str, cwd: &Path) -> String {
+    let pattern = &to_slash_path(pattern);
+    if pattern.starts_with("**/") {
+        return pattern.to_string();
+    }
+
+    let pattern = absolute_path(pattern, cwd);
+    let Some(glob_start) = pattern.find(['*', '?', '[', '{']) else {
+        return normalize_path(&pattern, cwd);
+    };
+    let Some(base_end) = pattern[..glob_start].rfind('/') else {
+        return pattern;
     };
+    let base = if base_end == 0 {
+        "/"
+    } else {
+        &pattern[..base_end]
+    };
+    let base = to_slash_path(&canonicalize_existing_prefix(Path::new(base)).to_string_lossy());
+    format!("{}{}", base.trim_end_matches('/'), &pattern[base_end..])
+}
 
-    // Normalize path separators and resolve ..
-    Path::new(&path)
-        .canonicalize()
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or(path)
+/// Resolve a cwd-relative pattern against an already canonical cwd
+///
+/// Unlike [`normalize_pattern`], nothing is looked up on disk: the pattern
+/// is joined to `canonical_cwd` and `.` and `..` are resolved lexically.
+fn resolve_relative_pattern(pattern: &str, canonical_cwd: &Path) -> String {
+    let pattern = to_slash_path(pattern);
+    let relative = pattern.strip_prefix("./").unwrap_or(&pattern);
+    to_slash_path(&resolve_dot_segments(&canonical_cwd.join(relative)).to_string_lossy())
+}
+
+/// Check whether a flag appears as an argument anywhere in a Bash command
+///
+/// The command is split into its simple commands and tokenized on
+/// whitespace, with surrounding quotes removed. A long flag also matches
+/// its `--flag=value` form, so `--privileged` catches `--privileged=true`.
+fn command_has_flag(command: &str, flag: &str) -> bool {
+    crate::command_safety::split_command_segments(command)
+        .into_iter()
+        .flat_map(|segment| segment.split_whitespace().skip(1))
+        .map(|token| token.trim_matches(|c| c == '\'' || c == '"'))
+        .any(|token| {
+            token == flag
+                || (flag.starts_with("--")
+                    && token
+                        .strip_prefix(flag)
+                        .is_some_and(|rest| rest.starts_with('=')))
+        })
 }
 
 /// Check if tool is bash-like (command execution)
@@ -309,30 +975,33 @@ fn is_bash_tool(tool_name: &str) -> bool {
 fn is_file_tool(tool_name: &str) -> bool {
     matches!(
         tool_name,
-        "Read" | "Write" | "Edit" | "Grep" | "Glob" | "LS" | "NotebookRead" | "NotebookEdit"
+        "Read"
+            | "Write"
+            | "Edit"
+            | "MultiEdit"
+            | "Grep"
+            | "Glob"
+            | "LS"
+            | "NotebookRead"
+            | "NotebookEdit"
     )
 }
 
+/// Extract the file path a tool operates on, checking each known path field
+pub(super) fn extract_file_path(tool_name: &str, input: &serde_json::Value) -> Option<String> {
+    FilePathInput::from_value(input)
+        .path_for_tool(tool_name)
+        .map(String::from)
+}
+
 /// Extract the relevant argument from tool input for permission matching
 fn extract_tool_argument(tool_name: &str, input: &serde_json::Value) -> Option<String> {
     match tool_name {
         // Bash tools use "command"
-        "Bash" | "BashOutput" | "KillShell" => input
-            .get("command")
-            .and_then(|v| v.as_str())
-            .map(String::from),
-        // File tools use "file_path" or "path"
-        "Read" | "Write" | "Edit" | "NotebookRead" | "NotebookEdit" => input
-            .get("file_path")
-            .or_else(|| input.get("path"))
-            .and_then(|v| v.as_str())
-            .map(String::from),
-        // Search tools use "path" or "pattern"
-        "Grep" | "Glob" | "LS" => input
-            .get("path")
-            .or_else(|| input.get("pattern"))
-            .and_then(|v| v.as_str())
-            .map(String::from),
+        "Bash" | "BashOutput" | "KillShell" => BashInput::from_value(input).command,
+        // File and search tools use their path-bearing fields
+        "Read" | "Write" | "Edit" | "MultiEdit" | "NotebookRead" | "NotebookEdit" | "Grep"
+        | "Glob" | "LS" => extract_file_path(tool_name, input),
         // Task tool: extract subagent_type for permission control
         "Task" => input
             .get("subagent_type")
@@ -369,7 +1038,6 @@ mod tests {
     use super::*;
     use crate::settings::{manager::Settings, permission_checker::PermissionChecker};
     use serde_json::json;
-    use std::path::PathBuf;
 
     fn settings_with_permissions(permissions: PermissionSettings) -> Settings {
         Settings {
@@ -378,6 +1046,62 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_relative_rules_match_absolute_paths_and_back() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cwd = dir.path().join("project");
+        std::fs::create_dir_all(cwd.join("src")).unwrap();
+        std::fs::write(cwd.join("src/x.rs"), "").unwrap();
+        let absolute = |path: &str| cwd.join(path).to_string_lossy().to_string();
+
+        // Relative rule, absolute path (existing and not yet created)
+        let relative = ParsedRule::parse_with_glob("Edit(./src/**)", &cwd);
+        for path in [absolute("src/x.rs"), absolute("src/new/y.rs")] {
+            assert!(relative.matches("Edit", &json!({"file_path": path}), &cwd));
+        }
+        let outside = absolute("tests/x.rs");
+        assert!(!relative.matches("Edit", &json!({"file_path": outside}), &cwd));
+
+        // Absolute rule, relative path
+        let absolute_rule =
+            ParsedRule::parse_with_glob(&format!("Edit({})", absolute("src/**")), &cwd);
+        for path in ["src/x.rs", "./src/new.rs", "src/../src/x.rs"] {
+            assert!(
+                absolute_rule.matches("Edit", &json!({"file_path": path}), &cwd),
+                "{path}"
+            );
+        }
+        assert!(!absolute_rule.matches("Edit", &json!({"file_path": "tests/x.rs"}), &cwd));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rules_match_through_symlinked_cwd() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let real = dir.path().join("real");
+        std::fs::create_dir_all(real.join("src")).unwrap();
+        std::fs::write(real.join("src/x.rs"), "").unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        // The session's cwd is the symlink; tools report resolved paths or not
+        let rule = ParsedRule::parse_with_glob("Edit(./src/**)", &link);
+        for path in [
+            real.join("src/x.rs"),
+            link.join("src/x.rs"),
+            link.join("src/new.rs"),
+            real.join("src/new/y.rs"),
+        ] {
+            let path = path.to_string_lossy().to_string();
+            assert!(
+                rule.matches("Edit", &json!({"file_path": path}), &link),
+                "{path}"
+            );
+        }
+        let outside = real.join("tests/x.rs").to_string_lossy().to_string();
+        assert!(!rule.matches("Edit", &json!({"file_path": outside}), &link));
+    }
+
     #[test]
     fn test_parse_simple_rule() {
         let rule = ParsedRule::parse("Read");
@@ -410,6 +1134,69 @@ mod tests {
         assert!(!rule.is_wildcard);
     }
 
+    #[test]
+    fn test_matches_mid_path_double_star() {
+        let cwd = PathBuf::from("/tmp");
+        let rule = ParsedRule::parse_with_glob("Edit(./**/migrations/**)", &cwd);
+
+        assert!(rule.matches(
+            "Edit",
+            &json!({"file_path": "./db/2024/migrations/001.sql"}),
+            &cwd
+        ));
+        assert!(rule.matches(
+            "Write",
+            &json!({"file_path": "/tmp/migrations/002.sql"}),
+            &cwd
+        ));
+        assert!(!rule.matches("Edit", &json!({"file_path": "./db/models.rs"}), &cwd));
+        assert!(!rule.matches(
+            "Edit",
+            &json!({"file_path": "./db/migrations_old.rs"}),
+            &cwd
+        ));
+
+        // In another cwd, the relative pattern is anchored there instead
+        let other = PathBuf::from("/srv");
+        assert!(rule.matches(
+            "Edit",
+            &json!({"file_path": "/srv/db/migrations/001.sql"}),
+            &other
+        ));
+        assert!(!rule.matches(
+            "Edit",
+            &json!({"file_path": "/tmp/db/migrations/001.sql"}),
+            &other
+        ));
+
+        // Without pre-compilation too
+        let uncompiled = ParsedRule::parse("Edit(./**/migrations/**)");
+        assert!(uncompiled.matches(
+            "Edit",
+            &json!({"file_path": "./db/2024/migrations/001.sql"}),
+            &cwd
+        ));
+        assert!(!uncompiled.matches("Edit", &json!({"file_path": "./db/models.rs"}), &cwd));
+    }
+
+    #[test]
+    fn test_matches_filename_globs_at_any_depth() {
+        let cwd = PathBuf::from("/tmp");
+        let env = ParsedRule::parse_with_glob("Edit(**/*.env)", &cwd);
+        assert!(env.matches("Edit", &json!({"file_path": "config/.env"}), &cwd));
+        assert!(env.matches("Write", &json!({"file_path": "/srv/app/prod.env"}), &cwd));
+        assert!(!env.matches("Edit", &json!({"file_path": "config/app.rs"}), &cwd));
+
+        let key = ParsedRule::parse_with_glob("Read(**/id_rsa*)", &cwd);
+        assert!(key.matches("Read", &json!({"file_path": "/home/u/.ssh/id_rsa"}), &cwd));
+        assert!(key.matches("Read", &json!({"file_path": "./keys/id_rsa.pub"}), &cwd));
+        assert!(!key.matches("Read", &json!({"file_path": "./src/rsa.rs"}), &cwd));
+
+        let git = ParsedRule::parse_with_glob("Edit(**/.git/**)", &cwd);
+        assert!(git.matches("Edit", &json!({"file_path": "/repo/.git/config"}), &cwd));
+        assert!(!git.matches("Edit", &json!({"file_path": "/repo/.gitignore"}), &cwd));
+    }
+
     #[test]
     fn test_matches_simple_tool() {
         let rule = ParsedRule::parse("Read");
@@ -433,6 +1220,176 @@ mod tests {
         assert!(!rule.matches("Write", &json!({}), &cwd));
     }
 
+    #[test]
+    fn test_strict_read_rule_matches_only_read() {
+        let cwd = PathBuf::from("/tmp");
+        let grep = json!({"pattern": "TODO", "path": "/tmp/src"});
+
+        let grouped = ParsedRule::parse_with_glob("Read(/tmp/src/**)", &cwd);
+        assert!(grouped.matches("Grep", &grep, &cwd));
+
+        let mut strict = ParsedRule::parse_with_glob("Read(/tmp/src/**)", &cwd);
+        strict.set_strict_read(true);
+        assert!(!strict.matches("Grep", &grep, &cwd));
+        assert!(!strict.matches("LS", &json!({"path": "/tmp/src"}), &cwd));
+        assert!(strict.matches("Read", &json!({"file_path": "/tmp/src/a.rs"}), &cwd));
+
+        // Naming the search tools still works
+        let mut set = ParsedRule::parse_with_glob("Read|Grep(/tmp/src/**)", &cwd);
+        set.set_strict_read(true);
+        assert!(set.matches("Grep", &grep, &cwd));
+    }
+
+    #[test]
+    fn test_read_rule_matches_search_tool_paths() {
+        let cwd = PathBuf::from("/nonexistent/project");
+        let rule = ParsedRule::parse_with_glob("Read(./src/**)", &cwd);
+
+        // Grep over ./src (Grep's pattern is a regex, not a path)
+        assert!(rule.matches("Grep", &json!({"pattern": "TODO", "path": "./src"}), &cwd));
+        assert!(!rule.matches("Grep", &json!({"pattern": "TODO", "path": "./tests"}), &cwd));
+        assert!(!rule.matches("Grep", &json!({"pattern": "./src/lib.rs"}), &cwd));
+
+        // Glob rooted at ./src, or with a path-like pattern and no root
+        assert!(rule.matches(
+            "Glob",
+            &json!({"pattern": "**/*.rs", "path": "./src"}),
+            &cwd
+        ));
+        assert!(rule.matches("Glob", &json!({"pattern": "./src/**/*.rs"}), &cwd));
+        assert!(rule.matches("LS", &json!({"path": "./src/utils"}), &cwd));
+
+        // Read of a notebook under ./src
+        assert!(rule.matches("Read", &json!({"notebook_path": "./src/a.ipynb"}), &cwd));
+    }
+
+    #[test]
+    fn test_dir_rule_matcher_compiled_at_parse_time() {
+        let cwd = PathBuf::from("/nonexistent/project");
+        let rule = ParsedRule::parse_with_glob("Read(./src/**)", &cwd);
+        assert!(rule.dir_matcher.is_some());
+        assert!(rule.matches("LS", &json!({"path": "./src"}), &cwd));
+        assert!(!rule.matches("LS", &json!({"path": "./srcx"}), &cwd));
+
+        // Rules parsed without a working directory still cover the directory
+        let unparsed = ParsedRule::parse("Read(/tmp/src/**)");
+        assert!(unparsed.dir_matcher.is_none());
+        assert!(unparsed.matches("LS", &json!({"path": "/tmp/src"}), &cwd));
+        assert!(
+            ParsedRule::parse_with_glob("Read(./src/*)", &cwd)
+                .dir_matcher
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_only_relative_rules_depend_on_cwd() {
+        let cwd = PathBuf::from("/nonexistent/project");
+        let relative = ParsedRule::parse_with_glob("Read(./src/**)", &cwd);
+        assert_eq!(relative.glob_cwd.as_deref(), Some(cwd.as_path()));
+        for rule in ["Read(/etc/**)", "Read(~/.ssh/**)", "Read(**/*.env)"] {
+            assert!(
+                ParsedRule::parse_with_glob(rule, &cwd).glob_cwd.is_none(),
+                "{rule}"
+            );
+        }
+
+        // Compiled matchers of absolute patterns are reused in any cwd
+        let absolute = ParsedRule::parse_with_glob("Read(/etc/**)", &cwd);
+        let other = Path::new("/nonexistent/other");
+        assert!(absolute.matches("Read", &json!({"file_path": "/etc/passwd"}), other));
+    }
+
+    #[test]
+    fn test_patterns_normalized_at_parse_time() {
+        let cwd = PathBuf::from("/nonexistent/project");
+        let rule = ParsedRule::parse_with_glob("Edit(./src/../docs/**)", &cwd);
+        assert_eq!(
+            rule.normalized_pattern.as_deref(),
+            Some("/nonexistent/project/docs/**")
+        );
+        assert!(
+            ParsedRule::parse("Edit(./docs/**)")
+                .normalized_pattern
+                .is_none()
+        );
+        assert!(
+            ParsedRule::parse_with_glob("Bash(ls:*)", &cwd)
+                .normalized_pattern
+                .is_none()
+        );
+
+        // In another cwd a relative pattern is resolved against that cwd
+        let other = Path::new("/nonexistent/other");
+        assert!(rule.matches("Edit", &json!({"file_path": "docs/a.md"}), other));
+        assert!(!rule.matches(
+            "Edit",
+            &json!({"file_path": "/nonexistent/project/docs/a.md"}),
+            other
+        ));
+        assert!(rule.matches("LS", &json!({"path": "/nonexistent/other/docs"}), other));
+    }
+
+    #[test]
+    fn test_match_cache_shared_across_rules() {
+        let cwd = PathBuf::from("/nonexistent/project");
+        let rules = [
+            ParsedRule::parse_with_glob("Edit(./src/**)", &cwd),
+            ParsedRule::parse_with_glob("Edit(/etc/**)", &cwd),
+            ParsedRule::parse_with_glob("Edit(**/*.rs)", &cwd),
+        ];
+        for input in [
+            json!({"file_path": "src/main.rs"}),
+            json!({"file_path": "/etc/hosts"}),
+            json!({"file_path": "/tmp/notes.txt"}),
+        ] {
+            let cache = MatchCache::default();
+            for rule in &rules {
+                assert_eq!(
+                    rule.matches_with_cache("Edit", &input, &cwd, &cache),
+                    rule.matches("Edit", &input, &cwd),
+                    "{input}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_escape_glob() {
+        assert_eq!(escape_glob("./my project (old)"), "./my project (old)");
+        assert_eq!(escape_glob("/tmp/[draft]"), "/tmp/[[]draft[]]");
+        assert_eq!(escape_glob("a{b,c}*?"), "a[{]b,c[}][*][?]");
+
+        let pattern = format!("{}/**", escape_glob("/tmp/[draft]"));
+        let rule = ParsedRule::parse_with_glob(&format!("Edit({pattern})"), Path::new("/tmp"));
+        assert!(rule.matches(
+            "Edit",
+            &json!({"file_path": "/tmp/[draft]/a.rs"}),
+            Path::new("/tmp")
+        ));
+        assert!(!rule.matches(
+            "Edit",
+            &json!({"file_path": "/tmp/d/a.rs"}),
+            Path::new("/tmp")
+        ));
+    }
+
+    #[test]
+    fn test_extract_file_path_fields() {
+        let input = json!({"notebook_path": "/a.ipynb", "path": "/dir"});
+        assert_eq!(
+            extract_file_path("NotebookEdit", &input).as_deref(),
+            Some("/a.ipynb")
+        );
+        assert_eq!(
+            extract_file_path("Read", &input).as_deref(),
+            Some("/a.ipynb")
+        );
+        assert_eq!(extract_file_path("LS", &input).as_deref(), Some("/dir"));
+        assert_eq!(extract_file_path("Grep", &json!({"pattern": "x"})), None);
+        assert_eq!(extract_file_path("Bash", &input), None);
+    }
+
     #[test]
     fn test_matches_tool_group_edit() {
         let rule = ParsedRule::parse("Edit");
@@ -444,6 +1401,111 @@ mod tests {
         assert!(!rule.matches("Read", &json!({}), &cwd));
     }
 
+    #[test]
+    fn test_matches_tool_set() {
+        let cwd = PathBuf::from("/nonexistent/project");
+        let rule = ParsedRule::parse_with_glob("Edit|Write|MultiEdit(./src/**)", &cwd);
+        assert_eq!(rule.argument.as_deref(), Some("./src/**"));
+
+        for tool in ["Edit", "Write", "MultiEdit", "mcp__acp__Edit"] {
+            assert!(
+                rule.matches(tool, &json!({"file_path": "./src/lib.rs"}), &cwd),
+                "{tool}"
+            );
+            assert!(
+                !rule.matches(tool, &json!({"file_path": "./tests/a.rs"}), &cwd),
+                "{tool}"
+            );
+        }
+        assert!(!rule.matches("Read", &json!({"file_path": "./src/lib.rs"}), &cwd));
+
+        // Group names keep their meaning inside a set
+        let rule = ParsedRule::parse("Read|Bash");
+        assert!(rule.matches("Grep", &json!({}), &cwd));
+        assert!(rule.matches("Bash", &json!({"command": "ls"}), &cwd));
+        assert!(!rule.matches("Edit", &json!({}), &cwd));
+
+        let rule = ParsedRule::parse("Bash|BashOutput(npm run:*)");
+        assert!(rule.matches("Bash", &json!({"command": "npm run build"}), &cwd));
+        assert!(!rule.matches("Bash", &json!({"command": "rm -rf /"}), &cwd));
+    }
+
+    #[test]
+    fn test_expand_braces() {
+        assert_eq!(
+            expand_braces("./src/{a,b}/**"),
+            ["./src/a/**", "./src/b/**"]
+        );
+        assert_eq!(expand_braces("{x,y{1,2}}.rs"), ["x.rs", "y1.rs", "y2.rs"]);
+        assert_eq!(expand_braces("{a,b}/{c,d}"), ["a/c", "a/d", "b/c", "b/d"]);
+        assert_eq!(expand_braces("tests{,_old}"), ["tests", "tests_old"]);
+
+        // Braces that aren't groups match literally
+        assert_eq!(expand_braces("./{}/x"), ["./[{][}]/x"]);
+        assert_eq!(expand_braces("./[{]a,b[}]"), ["./[{]a,b[}]"]);
+        assert_eq!(expand_braces("./src/{a,b"), ["./src/{a,b"]);
+        assert_eq!(expand_braces("./src/**"), ["./src/**"]);
+    }
+
+    #[test]
+    fn test_matches_brace_pattern() {
+        let cwd = PathBuf::from("/nonexistent/project");
+        let rule = ParsedRule::parse_with_glob("Edit(./src/{a,b}/**)", &cwd);
+
+        assert!(rule.matches("Edit", &json!({"file_path": "./src/a/x.rs"}), &cwd));
+        assert!(rule.matches("Edit", &json!({"file_path": "./src/b/y.rs"}), &cwd));
+        assert!(!rule.matches("Edit", &json!({"file_path": "./src/c/z.rs"}), &cwd));
+        assert_eq!(rule.components().tool_names, ["Edit"]);
+        assert_eq!(rule.argument.as_deref(), Some("./src/{a,b}/**"));
+
+        // Nested groups, which globset alone rejects
+        let rule = ParsedRule::parse_with_glob("Read|Edit(./{docs,src/{a,b}}/**)", &cwd);
+        for path in ["./docs/x.md", "./src/a/x.rs", "./src/b/x.rs"] {
+            assert!(
+                rule.matches("Edit", &json!({"file_path": path}), &cwd),
+                "{path}"
+            );
+        }
+        assert!(!rule.matches("Edit", &json!({"file_path": "./src/c/x.rs"}), &cwd));
+        assert_eq!(rule.components().tool_names, ["Read", "Edit"]);
+
+        // An empty group is a literal directory name
+        let rule = ParsedRule::parse_with_glob("Edit(./{}/**)", &cwd);
+        assert!(rule.matches("Edit", &json!({"file_path": "./{}/x.rs"}), &cwd));
+        assert!(!rule.matches("Edit", &json!({"file_path": "./x.rs"}), &cwd));
+    }
+
+    #[test]
+    fn test_rule_components() {
+        assert_eq!(
+            ParsedRule::parse("Bash(npm run:*)").components(),
+            RuleComponents {
+                tool_names: vec!["Bash".to_string()],
+                kind: RuleMatcherKind::Prefix,
+                pattern: Some("npm run".to_string()),
+            }
+        );
+
+        let components = ParsedRule::parse("Edit|Write(./src/**)").components();
+        assert_eq!(components.tool_names, ["Edit", "Write"]);
+        assert_eq!(components.kind, RuleMatcherKind::Glob);
+        assert_eq!(components.pattern.as_deref(), Some("./src/**"));
+
+        assert_eq!(
+            ParsedRule::parse("Bash(npm test)").components().kind,
+            RuleMatcherKind::Exact
+        );
+        assert_eq!(
+            ParsedRule::parse("BashFlag(--privileged)")
+                .components()
+                .kind,
+            RuleMatcherKind::Flag
+        );
+        let any = ParsedRule::parse("WebSearch").components();
+        assert_eq!(any.kind, RuleMatcherKind::Any);
+        assert_eq!(any.pattern, None);
+    }
+
     #[test]
     fn test_matches_bash_exact() {
         let rule = ParsedRule::parse("Bash(npm run lint)");
@@ -465,6 +1527,25 @@ mod tests {
         assert!(!rule.matches("Bash", &json!({"command": "npm install"}), &cwd));
     }
 
+    #[test]
+    fn test_matches_bash_wildcard_at_word_boundary() {
+        let rule = ParsedRule::parse("Bash(git push:*)");
+        let cwd = PathBuf::from("/tmp");
+
+        assert!(rule.matches("Bash", &json!({"command": "git push"}), &cwd));
+        assert!(rule.matches("Bash", &json!({"command": "git push\torigin"}), &cwd));
+        assert!(!rule.matches("Bash", &json!({"command": "git pushx"}), &cwd));
+        assert!(!rule.matches("Bash", &json!({"command": "git push-foo"}), &cwd));
+        assert!(!rule.matches("Bash", &json!({"command": "git push_all"}), &cwd));
+
+        // A prefix ending in whitespace or punctuation needs no further boundary
+        let scripts = ParsedRule::parse("Bash(./scripts/:*)");
+        assert!(scripts.matches("Bash", &json!({"command": "./scripts/build.sh"}), &cwd));
+        let spaced = ParsedRule::parse("Bash(npm run :*)");
+        assert!(spaced.matches("Bash", &json!({"command": "npm run build"}), &cwd));
+        assert!(!spaced.matches("Bash", &json!({"command": "npm runx"}), &cwd));
+    }
+
     #[test]
     fn test_matches_bash_wildcard_blocks_shell_operators() {
         let rule = ParsedRule::parse("Bash(npm run:*)");
@@ -484,6 +1565,69 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_matches_bash_flag() {
+        let rule = ParsedRule::parse("BashFlag(--privileged)");
+        let cwd = PathBuf::from("/tmp");
+
+        assert!(rule.matches(
+            "Bash",
+            &json!({"command": "docker run --privileged ubuntu"}),
+            &cwd
+        ));
+        assert!(rule.matches(
+            "Bash",
+            &json!({"command": "cd app && docker run --privileged=true ubuntu"}),
+            &cwd
+        ));
+        assert!(rule.matches(
+            "Bash",
+            &json!({"command": "(cd app; docker run --privileged)"}),
+            &cwd
+        ));
+        assert!(rule.matches(
+            "mcp__acp__Bash",
+            &json!({"command": "podman run \"--privileged\" img"}),
+            &cwd
+        ));
+
+        // Without the flag, or with it only as part of another word, no match
+        assert!(!rule.matches("Bash", &json!({"command": "docker run ubuntu"}), &cwd));
+        assert!(!rule.matches(
+            "Bash",
+            &json!({"command": "docker run --privileged-mode ubuntu"}),
+            &cwd
+        ));
+        assert!(!rule.matches("Bash", &json!({"command": "--privileged"}), &cwd));
+        assert!(!rule.matches("Read", &json!({"file_path": "--privileged"}), &cwd));
+    }
+
+    #[test]
+    fn test_matches_bash_short_flag() {
+        let rule = ParsedRule::parse("BashFlag(-x)");
+        let cwd = PathBuf::from("/tmp");
+
+        assert!(rule.matches("Bash", &json!({"command": "bash -x script.sh"}), &cwd));
+        assert!(!rule.matches("Bash", &json!({"command": "tar -xf a.tar"}), &cwd));
+        assert!(!rule.matches("Bash", &json!({"command": "ls -x=1"}), &cwd));
+    }
+
+    #[test]
+    fn test_matches_tool_title() {
+        let rule = ParsedRule::parse("ToolTitle(Delete repository)");
+        assert!(rule.is_title_rule());
+        assert!(rule.matches_title("Delete Repository"));
+        assert!(!rule.matches_title("Delete repository branch"));
+        // Title rules don't match on the tool name
+        assert!(!rule.matches("ToolTitle", &json!({}), &PathBuf::from("/tmp")));
+
+        let prefix = ParsedRule::parse("ToolTitle(Delete:*)");
+        assert!(prefix.matches_title("Delete issue"));
+        assert!(!prefix.matches_title("Create issue"));
+
+        assert!(!ParsedRule::parse("Bash").matches_title("Bash"));
+    }
+
     #[test]
     fn test_permission_check_result() {
         let allow = PermissionCheckResult::allow("Read");
@@ -499,6 +1643,40 @@ mod tests {
         assert!(ask.rule.is_none());
     }
 
+    #[test]
+    fn test_merge_permission_check_results() {
+        let merge = |a, b| {
+            let merged = PermissionCheckResult::merge(a, b);
+            (merged.decision, merged.rule)
+        };
+        let deny = || PermissionCheckResult::deny("Bash(rm:*)");
+        let allow = || PermissionCheckResult::allow("Bash");
+        let ask = || PermissionCheckResult::ask_with_rule("Bash(git push:*)");
+
+        // Deny wins whichever rule set it comes from
+        let denied = (PermissionDecision::Deny, Some("Bash(rm:*)".to_string()));
+        assert_eq!(merge(deny(), allow()), denied);
+        assert_eq!(merge(allow(), deny()), denied);
+
+        // Allow beats an ask rule, and any rule beats no match
+        assert_eq!(merge(ask(), allow()).0, PermissionDecision::Allow);
+        assert_eq!(merge(PermissionCheckResult::ask(), ask()).1, ask().rule);
+        assert_eq!(
+            merge(PermissionCheckResult::ask(), allow()).0,
+            PermissionDecision::Allow
+        );
+
+        // On a tie the first result is kept
+        let other = PermissionCheckResult::allow("Bash(ls:*)");
+        assert_eq!(merge(other, allow()).1.as_deref(), Some("Bash(ls:*)"));
+        assert_eq!(
+            PermissionCheckResult::merge(deny(), PermissionCheckResult::ask())
+                .source
+                .as_deref(),
+            Some("deny")
+        );
+    }
+
     #[test]
     fn test_mcp_tool_web_fetch_matching() {
         // Test that "WebFetch" rule matches "mcp__web-fetch__webReader"
@@ -0,0 +1,186 @@
+//! JSON Schema for settings files
+//!
+//! Hand-written to match the serde representation of [`Settings`] and
+//! [`PermissionSettings`], so clients can validate `.claude/settings.json`
+//! before sending it.
+//!
+//! [`Settings`]: super::Settings
+//! [`PermissionSettings`]: super::PermissionSettings
+
+use serde_json::{Value, json};
+
+use crate::session::PermissionMode;
+
+/// Permission modes accepted in `permissions.defaultMode` and `permissionMode`
+const PERMISSION_MODES: [PermissionMode; 6] = [
+    PermissionMode::Default,
+    PermissionMode::AcceptEdits,
+    PermissionMode::Plan,
+    PermissionMode::DontAsk,
+    PermissionMode::Interactive,
+    PermissionMode::BypassPermissions,
+];
+
+/// Build a JSON Schema (draft 2020-12) describing the settings file format
+///
+/// Unknown top-level keys are allowed because [`Settings`](super::Settings)
+/// keeps them in `extra`; unknown keys inside `permissions` are ignored by
+/// serde and are allowed here too.
+pub fn json_schema() -> Value {
+    let modes: Vec<&str> = PERMISSION_MODES
+        .iter()
+        .map(PermissionMode::as_str)
+        .collect();
+    let string_array = json!({ "type": "array", "items": { "type": "string" } });
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Claude Code settings",
+        "type": "object",
+        "properties": {
+            "systemPrompt": { "type": "string" },
+            "permissionMode": { "enum": modes },
+            "model": { "type": "string" },
+            "smallFastModel": { "type": "string" },
+            "apiBaseUrl": { "type": "string" },
+            "alwaysThinkingEnabled": { "type": "boolean" },
+            "allowedTools": string_array,
+            "deniedTools": string_array,
+            "permissions": {
+                "type": "object",
+                "properties": {
+                    "allow": string_array,
+                    "deny": string_array,
+                    "ask": string_array,
+                    "additionalDirectories": string_array,
+                    "defaultMode": { "enum": modes },
+                    "denyUnknownMcpTools": { "type": "boolean" },
+                    "allowAlwaysOverridesDeny": { "type": "boolean" },
+                    "strictReadRule": { "type": "boolean" },
+                    "emptyInputDefault": { "enum": ["ask", "deny"] },
+                    "outputRules": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "tool": { "type": "string" },
+                                "maxBytes": { "type": "integer", "minimum": 0 },
+                                "flagBinary": { "type": "boolean" },
+                                "interrupt": { "type": "boolean" }
+                            }
+                        }
+                    }
+                }
+            },
+            "mcpServers": {
+                "type": "object",
+                "additionalProperties": {
+                    "type": "object",
+                    "required": ["command"],
+                    "properties": {
+                        "command": { "type": "string" },
+                        "args": string_array,
+                        "env": {
+                            "type": "object",
+                            "additionalProperties": { "type": "string" }
+                        },
+                        "disabled": { "type": "boolean" }
+                    }
+                }
+            },
+            "env": {
+                "type": "object",
+                "additionalProperties": { "type": "string" }
+            },
+            "plansDirectory": { "type": "string" },
+            "disableSafeCommands": string_array,
+            "extraDangerousCommands": string_array,
+            "promptForNetworkCommands": { "type": "boolean" },
+            "strictAcceptEdits": { "type": "boolean" },
+            "maxAutoApproveCommandLen": { "type": "integer", "minimum": 0 },
+            "allowRuleIdleTimeoutSecs": { "type": "integer", "minimum": 0 },
+            "protectSecrets": { "type": "boolean" },
+            "dontAskDenyMessage": { "type": "string" },
+            "denialEscalationThreshold": { "type": "integer", "minimum": 0 },
+            "maxConcurrentPrompts": { "type": "integer", "minimum": 0 },
+            "promptRateLimitSecs": { "type": "integer", "minimum": 0 },
+            "confirmEmptyPlan": { "type": "boolean" },
+            "commandAliases": {
+                "type": "object",
+                "additionalProperties": { "type": "string" }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{PermissionSettings, Settings};
+
+    /// Property names of a schema object, sorted
+    fn property_names(schema: &Value) -> Vec<String> {
+        let mut names: Vec<String> = schema["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Field names as serialized, sorted (every field is written, even when unset)
+    fn field_names(value: impl serde::Serialize) -> Vec<String> {
+        let mut names: Vec<String> = serde_json::to_value(value)
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_schema_accepts_valid_settings() {
+        let settings = json!({
+            "model": "claude-sonnet",
+            "permissions": {
+                "allow": ["Read", "Bash(npm run:*)"],
+                "deny": ["Read(./.env)"],
+                "ask": [],
+                "additionalDirectories": ["~/projects"],
+                "defaultMode": "acceptEdits"
+            },
+            "mcpServers": {
+                "files": { "command": "mcp-files", "args": ["--root", "."] }
+            },
+            "somethingNew": 1
+        });
+
+        assert!(jsonschema::is_valid(&json_schema(), &settings));
+        // The document is also accepted by the real deserializer
+        assert!(serde_json::from_value::<Settings>(settings).is_ok());
+    }
+
+    #[test]
+    fn test_schema_covers_every_setting() {
+        let schema = json_schema();
+        assert_eq!(property_names(&schema), field_names(Settings::default()));
+        assert_eq!(
+            property_names(&schema["properties"]["permissions"]),
+            field_names(PermissionSettings::default())
+        );
+    }
+
+    #[test]
+    fn test_schema_rejects_invalid_default_mode() {
+        let settings = json!({ "permissions": { "defaultMode": "yolo" } });
+        assert!(!jsonschema::is_valid(&json_schema(), &settings));
+
+        let settings = json!({ "permissions": { "allow": "Read" } });
+        assert!(!jsonschema::is_valid(&json_schema(), &settings));
+    }
+}
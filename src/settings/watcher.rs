@@ -31,11 +31,13 @@ pub struct SettingsChangeEvent {
 impl SettingsWatcher {
     /// Create a new settings watcher
     ///
+    /// Files listed in [`SETTINGS_PATHS_ENV`](super::SETTINGS_PATHS_ENV) are
+    /// watched too.
+    ///
     /// # Arguments
     ///
     /// * `project_dir` - The project working directory
     /// * `debounce_ms` - Debounce duration in milliseconds (default 100)
-    /// * `on_change` - Callback when settings change
     ///
     /// # Returns
     ///
@@ -43,6 +45,19 @@ impl SettingsWatcher {
     pub fn new(
         project_dir: impl AsRef<Path>,
         debounce_ms: u64,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<SettingsChangeEvent>), WatcherError> {
+        Self::with_explicit_paths(project_dir, &super::settings_paths_from_env(), debounce_ms)
+    }
+
+    /// Create a settings watcher that also watches the given settings files
+    ///
+    /// The files' directories are watched, so a file replaced by an editor
+    /// is still noticed, but only changes to the files themselves (and to
+    /// the usual settings files) are reported.
+    pub fn with_explicit_paths(
+        project_dir: impl AsRef<Path>,
+        explicit_paths: &[PathBuf],
+        debounce_ms: u64,
     ) -> Result<(Self, mpsc::UnboundedReceiver<SettingsChangeEvent>), WatcherError> {
         let project_dir = project_dir.as_ref();
         let (tx, rx) = mpsc::unbounded_channel();
@@ -64,6 +79,18 @@ impl SettingsWatcher {
             watched_paths.push(project_settings_dir);
         }
 
+        // Directories of the explicit files; events are reported with the
+        // watched directory's path, so match against canonical paths
+        let explicit_files: Vec<PathBuf> = explicit_paths
+            .iter()
+            .filter_map(|path| path.canonicalize().ok())
+            .collect();
+        for dir in explicit_files.iter().filter_map(|file| file.parent()) {
+            if !watched_paths.iter().any(|watched| watched == dir) {
+                watched_paths.push(dir.to_path_buf());
+            }
+        }
+
         // Create debounced watcher
         let tx_clone = tx.clone();
         let watched_clone = watched_paths.clone();
@@ -77,7 +104,7 @@ impl SettingsWatcher {
                             .into_iter()
                             .filter(|e| matches!(e.kind, DebouncedEventKind::Any))
                             .map(|e| e.path)
-                            .filter(|p| is_settings_file(p))
+                            .filter(|p| is_settings_file(p) || explicit_files.contains(p))
                             .collect();
 
                         if !changed_paths.is_empty() {
@@ -124,7 +151,14 @@ impl SettingsWatcher {
         settings_manager: Arc<tokio::sync::RwLock<super::SettingsManager>>,
         debounce_ms: u64,
     ) -> Result<WatcherHandle, WatcherError> {
-        let (watcher, mut rx) = Self::new(project_dir, debounce_ms)?;
+        // Watch the files the manager merges; if it is busy reloading, the
+        // same files come from the environment
+        let explicit_paths = settings_manager
+            .try_read()
+            .map(|manager| manager.explicit_paths().to_vec())
+            .unwrap_or_else(|_| super::settings_paths_from_env());
+        let (watcher, mut rx) =
+            Self::with_explicit_paths(project_dir, &explicit_paths, debounce_ms)?;
 
         let handle = tokio::spawn(async move {
             while let Some(event) = rx.recv().await {
@@ -211,6 +245,20 @@ mod tests {
         assert!(!watcher.watched_paths().is_empty());
     }
 
+    #[tokio::test]
+    async fn test_watcher_watches_explicit_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let ci_dir = temp_dir.path().join("ci");
+        fs::create_dir_all(&ci_dir).unwrap();
+        let ci_settings = ci_dir.join("ci.json");
+        fs::write(&ci_settings, "{}").unwrap();
+
+        let (watcher, _rx) =
+            SettingsWatcher::with_explicit_paths(temp_dir.path(), &[ci_settings], 100).unwrap();
+        let ci_dir = ci_dir.canonicalize().unwrap();
+        assert!(watcher.watched_paths().contains(&ci_dir));
+    }
+
     #[tokio::test]
     async fn test_watcher_detects_changes() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,181 @@
+//! Typed tool input structs
+//!
+//! All fields are optional and unknown fields are ignored, so a malformed or
+//! unexpected input shape degrades to "field not present" rather than an
+//! error - the same behavior as the `get(..).and_then(as_str)` chains these
+//! types replace.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// Deserialize typed tool input with a fallback for unknown shapes
+pub trait ToolInputExt: DeserializeOwned + Default {
+    /// Parse from raw tool input, falling back to `Default` if the shape doesn't fit
+    ///
+    /// A non-object input, or a field with an unexpected type, yields the
+    /// default (all fields `None`).
+    fn from_value(value: &Value) -> Self {
+        Self::deserialize(value).unwrap_or_default()
+    }
+}
+
+impl<T: DeserializeOwned + Default> ToolInputExt for T {}
+
+/// Deserialize an optional field, treating a value of the wrong type as absent
+fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(T::deserialize(value).ok())
+}
+
+/// Input for the Bash tool
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct BashInput {
+    /// Shell command to execute
+    #[serde(default, deserialize_with = "lenient")]
+    pub command: Option<String>,
+    /// Short description of what the command does
+    #[serde(default, deserialize_with = "lenient")]
+    pub description: Option<String>,
+    /// Whether to run the command in the background
+    #[serde(default, deserialize_with = "lenient")]
+    pub run_in_background: Option<bool>,
+}
+
+/// Input for the Edit tool
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct FileEditInput {
+    /// File to edit
+    #[serde(default, deserialize_with = "lenient")]
+    pub file_path: Option<String>,
+    /// Text to replace
+    #[serde(default, deserialize_with = "lenient")]
+    pub old_string: Option<String>,
+    /// Replacement text
+    #[serde(default, deserialize_with = "lenient")]
+    pub new_string: Option<String>,
+    /// Replace every occurrence instead of requiring a unique match
+    #[serde(default, deserialize_with = "lenient")]
+    pub replace_all: Option<bool>,
+}
+
+/// Path-bearing fields shared by file and search tools
+///
+/// Different tools name their path differently (`file_path` for Read/Edit,
+/// `notebook_path` for notebooks, `path` for Grep/Glob/LS).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct FilePathInput {
+    /// File path (Read, Write, Edit)
+    #[serde(default, deserialize_with = "lenient")]
+    pub file_path: Option<String>,
+    /// Notebook path (NotebookRead, NotebookEdit)
+    #[serde(default, deserialize_with = "lenient")]
+    pub notebook_path: Option<String>,
+    /// Directory or file path (Grep, Glob, LS)
+    #[serde(default, deserialize_with = "lenient")]
+    pub path: Option<String>,
+    /// Search pattern (a path glob for Glob, a regex for Grep)
+    #[serde(default, deserialize_with = "lenient")]
+    pub pattern: Option<String>,
+}
+
+impl FilePathInput {
+    /// First path present, checking `file_path`, then `path`, then `notebook_path`
+    pub fn any_path(&self) -> Option<&str> {
+        self.file_path
+            .as_deref()
+            .or(self.path.as_deref())
+            .or(self.notebook_path.as_deref())
+    }
+
+    /// Path a specific tool operates on, using that tool's field order
+    ///
+    /// Grep's `pattern` is a regex rather than a path, so only its `path`
+    /// counts; Glob's `pattern` is a path glob and is used when no `path` is
+    /// given. Returns `None` for tools that don't operate on paths.
+    pub fn path_for_tool(&self, tool_name: &str) -> Option<&str> {
+        match tool_name {
+            "Read" | "Write" | "Edit" | "MultiEdit" => self
+                .file_path
+                .as_deref()
+                .or(self.notebook_path.as_deref())
+                .or(self.path.as_deref()),
+            "NotebookRead" | "NotebookEdit" => self
+                .notebook_path
+                .as_deref()
+                .or(self.file_path.as_deref())
+                .or(self.path.as_deref()),
+            "Glob" => self.path.as_deref().or(self.pattern.as_deref()),
+            "Grep" | "LS" => self.path.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_bash_input_tolerates_extra_and_missing_fields() {
+        let input = BashInput::from_value(&json!({
+            "command": "ls -la",
+            "timeout": 5000,
+            "unknown": {"nested": true}
+        }));
+        assert_eq!(input.command.as_deref(), Some("ls -la"));
+        assert_eq!(input.description, None);
+
+        assert_eq!(BashInput::from_value(&json!({})), BashInput::default());
+    }
+
+    #[test]
+    fn test_unknown_shapes_fall_back_to_default() {
+        assert_eq!(BashInput::from_value(&json!(null)), BashInput::default());
+        assert_eq!(BashInput::from_value(&json!("ls")), BashInput::default());
+        // A wrongly typed field behaves like a missing one
+        let input = BashInput::from_value(&json!({"command": 42, "description": "list"}));
+        assert_eq!(input.command, None);
+        assert_eq!(input.description.as_deref(), Some("list"));
+    }
+
+    #[test]
+    fn test_file_edit_input() {
+        let input = FileEditInput::from_value(&json!({
+            "file_path": "/tmp/a.rs",
+            "old_string": "foo",
+            "new_string": "bar"
+        }));
+        assert_eq!(input.file_path.as_deref(), Some("/tmp/a.rs"));
+        assert_eq!(input.old_string.as_deref(), Some("foo"));
+        assert_eq!(input.new_string.as_deref(), Some("bar"));
+        assert_eq!(input.replace_all, None);
+    }
+
+    #[test]
+    fn test_file_path_input_field_order() {
+        let input = FilePathInput::from_value(&json!({
+            "notebook_path": "/a.ipynb",
+            "path": "/dir",
+            "pattern": "TODO"
+        }));
+        assert_eq!(input.any_path(), Some("/dir"));
+        assert_eq!(input.path_for_tool("Read"), Some("/a.ipynb"));
+        assert_eq!(input.path_for_tool("NotebookEdit"), Some("/a.ipynb"));
+        assert_eq!(input.path_for_tool("LS"), Some("/dir"));
+        assert_eq!(input.path_for_tool("Bash"), None);
+
+        let mixed =
+            FilePathInput::from_value(&json!({"file_path": 1, "notebook_path": "/b.ipynb"}));
+        assert_eq!(mixed.path_for_tool("Read"), Some("/b.ipynb"));
+
+        let grep = FilePathInput::from_value(&json!({"pattern": "TODO"}));
+        assert_eq!(grep.path_for_tool("Grep"), None);
+        assert_eq!(grep.path_for_tool("Glob"), Some("TODO"));
+    }
+}
@@ -0,0 +1,9 @@
+//! Typed views of tool input
+//!
+//! Tool input arrives as raw JSON. These types replace scattered
+//! `tool_input.get("...").and_then(|v| v.as_str())` chains with a single,
+//! tolerant deserialization step.
+
+mod input;
+
+pub use input::{BashInput, FileEditInput, FilePathInput, ToolInputExt};
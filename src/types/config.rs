@@ -49,6 +49,22 @@ pub struct AgentConfig {
     /// When `alwaysThinkingEnabled` is true in settings, this defaults to 20000.
     /// Typical values: 4096, 8000, 16000, 20000
     pub max_thinking_tokens: Option<u32>,
+
+    /// File remembering the permission mode the user last switched to
+    ///
+    /// New sessions start in that mode unless the client requests one or
+    /// settings set `permissions.defaultMode`.
+    /// `None` (the default for [`AgentConfig::new`]) disables it; the
+    /// loaders use `~/.claude/acp-permission-mode.json`.
+    pub permission_mode_state_file: Option<std::path::PathBuf>,
+
+    /// Directory sessions save their permission state to, keyed by session id
+    ///
+    /// A session created with the id of a saved one gets its mode and
+    /// in-session approvals back, e.g. after the agent restarted. `None`
+    /// (the default for [`AgentConfig::new`]) disables it; the loaders use
+    /// `~/.claude/acp-permission-state/`.
+    pub permission_state_dir: Option<std::path::PathBuf>,
 }
 
 impl AgentConfig {
@@ -83,6 +99,8 @@ impl AgentConfig {
             model: std::env::var("ANTHROPIC_MODEL").ok(),
             small_fast_model: std::env::var("ANTHROPIC_SMALL_FAST_MODEL").ok(),
             max_thinking_tokens,
+            permission_mode_state_file: crate::session::default_mode_state_path(),
+            permission_state_dir: crate::session::default_permission_state_dir(),
         }
     }
 
@@ -250,6 +268,8 @@ impl AgentConfig {
             model,
             small_fast_model,
             max_thinking_tokens,
+            permission_mode_state_file: crate::session::default_mode_state_path(),
+            permission_state_dir: crate::session::default_permission_state_dir(),
         };
 
         // Log configuration sources
@@ -427,6 +447,7 @@ mod tests {
             model: Some("claude-3".to_string()),
             small_fast_model: None,
             max_thinking_tokens: None,
+            permission_mode_state_file: None,
         };
 
         let env = config.to_env_vars();
@@ -508,6 +529,7 @@ mod tests {
             model: None,
             small_fast_model: None,
             max_thinking_tokens: Some(4096),
+            permission_mode_state_file: None,
         };
 
         assert!(config.is_configured());
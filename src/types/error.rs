@@ -127,6 +127,19 @@ pub enum AgentError {
     #[error("Tool permission denied: {0}")]
     ToolPermissionDenied(String),
 
+    // === Permission request errors ===
+    /// The client did not answer a permission request in time
+    #[error("Permission request timed out after {0}ms")]
+    PermissionTimeout(u64),
+
+    /// The client connection closed before a request was answered
+    #[error("Connection closed: {0}")]
+    ConnectionClosed(String),
+
+    /// The client answered a request with a JSON-RPC error
+    #[error("Protocol error: {0}")]
+    ProtocolError(String),
+
     // === Configuration errors ===
     /// Configuration error
     #[error("Configuration error: {0}")]
@@ -183,6 +196,9 @@ impl AgentError {
             AgentError::ToolExecutionFailed(_) => ErrorCode::ToolFailed,
             AgentError::ToolNotFound(_) => ErrorCode::ToolFailed,
             AgentError::ToolPermissionDenied(_) => ErrorCode::ToolFailed,
+            AgentError::PermissionTimeout(_) => ErrorCode::ConnectionFailed,
+            AgentError::ConnectionClosed(_) => ErrorCode::ConnectionFailed,
+            AgentError::ProtocolError(_) => ErrorCode::InternalError,
             AgentError::ConfigError(_) => ErrorCode::ConfigError,
             AgentError::MissingConfig(_) => ErrorCode::ConfigError,
             AgentError::ClaudeSdk(_) => ErrorCode::InternalError,
@@ -199,6 +215,8 @@ impl AgentError {
             self,
             AgentError::ConnectionFailed(_)
                 | AgentError::ConnectionTimeout(_)
+                | AgentError::ConnectionClosed(_)
+                | AgentError::PermissionTimeout(_)
                 | AgentError::StreamingError(_)
                 | AgentError::NotificationFailed(_)
         )
@@ -302,6 +320,9 @@ mod tests {
         assert!(AgentError::streaming_error("lost").is_retryable());
         assert!(!AgentError::session_not_found("x").is_retryable());
         assert!(!AgentError::Cancelled.is_retryable());
+        assert!(AgentError::ConnectionClosed("eof".to_string()).is_retryable());
+        assert!(AgentError::PermissionTimeout(1000).is_retryable());
+        assert!(!AgentError::ProtocolError("bad".to_string()).is_retryable());
     }
 
     #[test]
@@ -4,6 +4,7 @@
 //! for passing additional configuration.
 
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 /// System prompt configuration from meta field
 ///
@@ -70,6 +71,10 @@ pub struct ClaudeCodeOptions {
     /// Typical values: 4096, 8000, 16000
     #[serde(skip_serializing_if = "Option::is_none", rename = "maxThinkingTokens")]
     pub max_thinking_tokens: Option<u32>,
+
+    /// Initial permission mode for the session (e.g. "plan", "acceptEdits")
+    #[serde(skip_serializing_if = "Option::is_none", rename = "permissionMode")]
+    pub permission_mode: Option<String>,
 }
 
 /// Claude Code meta configuration
@@ -96,6 +101,11 @@ impl ClaudeCodeMeta {
     pub fn get_max_thinking_tokens(&self) -> Option<u32> {
         self.options.as_ref()?.max_thinking_tokens
     }
+
+    /// Get the requested initial permission mode, if any
+    pub fn get_permission_mode(&self) -> Option<&str> {
+        self.options.as_ref()?.permission_mode.as_deref()
+    }
 }
 
 /// Combined meta configuration for new session requests
@@ -111,6 +121,10 @@ pub struct NewSessionMeta {
 
     /// Whether to disable built-in tools
     pub disable_built_in_tools: bool,
+
+    /// Workspace whose settings apply to the session (`workspaceRoot`),
+    /// when it differs from the session's cwd
+    pub workspace_root: Option<PathBuf>,
 }
 
 impl NewSessionMeta {
@@ -125,9 +139,11 @@ impl NewSessionMeta {
                 options: Some(ClaudeCodeOptions {
                     resume: Some(session_id.to_string()),
                     max_thinking_tokens: None,
+                    permission_mode: None,
                 }),
             }),
             disable_built_in_tools: false,
+            workspace_root: None,
         }
     }
 
@@ -152,6 +168,10 @@ impl NewSessionMeta {
                 .get("disableBuiltInTools")
                 .and_then(|v| v.as_bool())
                 .unwrap_or(false),
+            workspace_root: meta
+                .get("workspaceRoot")
+                .and_then(|v| v.as_str())
+                .map(PathBuf::from),
         }
     }
 
@@ -175,6 +195,16 @@ impl NewSessionMeta {
         self.claude_code.as_ref()?.get_max_thinking_tokens()
     }
 
+    /// Get the requested initial permission mode, if any
+    pub fn get_permission_mode(&self) -> Option<&str> {
+        self.claude_code.as_ref()?.get_permission_mode()
+    }
+
+    /// Get the workspace root whose settings apply, if one was given
+    pub fn get_workspace_root(&self) -> Option<&Path> {
+        self.workspace_root.as_deref()
+    }
+
     /// Check if this session should resume from a previous session
     pub fn should_resume(&self) -> bool {
         self.get_resume_session_id().is_some()
@@ -253,15 +283,42 @@ mod tests {
         assert!(parsed.should_resume());
     }
 
+    #[test]
+    fn test_new_session_meta_permission_mode() {
+        let meta = json!({
+            "claudeCode": {
+                "options": {
+                    "permissionMode": "plan"
+                }
+            }
+        });
+
+        let parsed = NewSessionMeta::from_request_meta(Some(&meta));
+        assert_eq!(parsed.get_permission_mode(), Some("plan"));
+        assert_eq!(
+            NewSessionMeta::with_resume("abc").get_permission_mode(),
+            None
+        );
+    }
+
     #[test]
     fn test_new_session_meta_empty() {
         let parsed = NewSessionMeta::from_request_meta(None);
         assert!(parsed.system_prompt.is_none());
         assert!(parsed.claude_code.is_none());
         assert!(!parsed.disable_built_in_tools);
+        assert!(parsed.get_workspace_root().is_none());
         assert!(!parsed.should_resume());
     }
 
+    #[test]
+    fn test_new_session_meta_workspace_root() {
+        let meta = json!({ "workspaceRoot": "/work/api" });
+
+        let parsed = NewSessionMeta::from_request_meta(Some(&meta));
+        assert_eq!(parsed.get_workspace_root(), Some(Path::new("/work/api")));
+    }
+
     #[test]
     fn test_new_session_meta_partial() {
         let meta = json!({
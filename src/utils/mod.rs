@@ -1,5 +1,13 @@
 //! Shared utility functions
 
 mod paths;
+mod redact;
 
-pub use paths::is_plans_directory_path;
+pub use paths::{
+    default_plans_dir, expand_home, is_path_in_any_plans_dir, is_path_in_plans_dir,
+    is_path_within_dir, is_plans_directory_path, plans_dirs, project_plans_dir,
+    resolve_dot_segments,
+};
+pub use redact::{
+    DEFAULT_SENSITIVE_KEYS, REDACTED, redact_tool_input, redact_tool_input_with, tool_input_preview,
+};
@@ -1,6 +1,40 @@
 //! Path utility functions
 
-use std::path::{Component, Path};
+use std::path::{Component, Path, PathBuf};
+
+/// Get the default Claude plans directory (~/.claude/plans/)
+///
+/// Returns `None` if the home directory cannot be determined (e.g. a sandbox
+/// without `HOME`). Callers should allow an explicit override in that case.
+pub fn default_plans_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".claude").join("plans"))
+}
+
+/// Get the project-local plans directory (`<cwd>/.claude/plans/`)
+pub fn project_plans_dir(cwd: &Path) -> PathBuf {
+    cwd.join(".claude").join("plans")
+}
+
+/// Resolve every plans directory in effect
+///
+/// The primary directory is `plans_dir` if overridden, otherwise
+/// `~/.claude/plans/` (omitted when the home directory is unknown). The
+/// project-local directory, if any, is appended after it.
+pub fn plans_dirs(plans_dir: Option<&Path>, project_plans_dir: Option<&Path>) -> Vec<PathBuf> {
+    plans_dir
+        .map(Path::to_path_buf)
+        .or_else(default_plans_dir)
+        .into_iter()
+        .chain(project_plans_dir.map(Path::to_path_buf))
+        .collect()
+}
+
+/// Check if a file path is within any of the given plans directories
+pub fn is_path_in_any_plans_dir(path_str: &str, plans_dirs: &[PathBuf]) -> bool {
+    plans_dirs
+        .iter()
+        .any(|dir| is_path_in_plans_dir(path_str, dir))
+}
 
 /// Check if a file path is within the Claude plans directory (~/.claude/plans/)
 ///
@@ -18,24 +52,61 @@ use std::path::{Component, Path};
 /// * `true` if the path is within ~/.claude/plans/
 /// * `false` otherwise
 pub fn is_plans_directory_path(path_str: &str) -> bool {
-    let Some(home) = dirs::home_dir() else {
+    let Some(plans_dir) = default_plans_dir() else {
         tracing::warn!("Could not determine home directory for plans path check");
         return false;
     };
 
-    let plans_dir = home.join(".claude").join("plans");
+    is_path_in_plans_dir(path_str, &plans_dir)
+}
 
-    let normalized_input = if let Some(rest) = path_str.strip_prefix("~/") {
-        home.join(rest)
-    } else if Path::new(path_str).is_absolute() {
-        Path::new(path_str).to_path_buf()
+/// Expand a leading `~` or `~/` to the home directory
+///
+/// Paths without a tilde prefix are returned unchanged. Returns `None` only
+/// when the path needs expanding and the home directory is unknown.
+/// `~user/` forms are not expanded.
+pub fn expand_home(path_str: &str) -> Option<PathBuf> {
+    if path_str == "~" {
+        dirs::home_dir()
+    } else if let Some(rest) = path_str.strip_prefix("~/") {
+        dirs::home_dir().map(|home| home.join(rest))
     } else {
+        Some(PathBuf::from(path_str))
+    }
+}
+
+/// Check if a file path is within the given plans directory
+///
+/// Same semantics as [`is_plans_directory_path`], but against an explicit
+/// directory instead of `~/.claude/plans/`. Home-relative input paths
+/// (`~/...`) are still expanded when the home directory is known.
+pub fn is_path_in_plans_dir(path_str: &str, plans_dir: &Path) -> bool {
+    is_path_within_dir(path_str, plans_dir)
+}
+
+/// Check if a path is inside (or equal to) a directory
+///
+/// Both the path and the directory may be home-relative (`~/...`); relative
+/// paths are never considered inside. Symlinks are resolved for paths that
+/// exist, otherwise components are compared lexically after resolving `.`
+/// and `..`, so `plans/../../etc/passwd` is not inside `plans`.
+pub fn is_path_within_dir(path_str: &str, dir: &Path) -> bool {
+    let Some(normalized_input) = expand_home(path_str) else {
         return false;
     };
+    if !normalized_input.is_absolute() {
+        return false;
+    }
+    let normalized_input = resolve_dot_segments(&normalized_input);
 
-    let plans_canonical = match plans_dir.canonicalize() {
+    let Some(dir) = expand_home(&dir.to_string_lossy()) else {
+        return false;
+    };
+    let dir = resolve_dot_segments(&dir);
+
+    let dir_canonical = match dir.canonicalize() {
         Ok(p) => p,
-        Err(_) => plans_dir,
+        Err(_) => dir.clone(),
     };
 
     if normalized_input.exists() {
@@ -51,23 +122,23 @@ pub fn is_plans_directory_path(path_str: &str) -> bool {
                 }
             }
         };
-        return input_canonical.starts_with(&plans_canonical);
+        return input_canonical.starts_with(&dir_canonical);
     }
 
-    if normalized_input.starts_with(&plans_canonical) {
+    if normalized_input.starts_with(&dir_canonical) {
         return true;
     }
 
     let input_components = normalize_path_components(&normalized_input);
-    let plans_components = normalize_path_components(&plans_canonical);
+    let dir_components = normalize_path_components(&dir_canonical);
 
-    if input_components.len() >= plans_components.len() {
+    if input_components.len() >= dir_components.len() {
         for (i, input_comp) in input_components
             .iter()
             .enumerate()
-            .take(plans_components.len())
+            .take(dir_components.len())
         {
-            if input_comp != &plans_components[i] {
+            if input_comp != &dir_components[i] {
                 return false;
             }
         }
@@ -77,6 +148,30 @@ pub fn is_plans_directory_path(path_str: &str) -> bool {
     false
 }
 
+/// Resolve `.` and `..` components without touching the file system
+///
+/// `..` removes the component before it (never the root), so
+/// `/proj/src/../lib` becomes `/proj/lib`. Symlinks are not followed, which
+/// makes this a fallback for paths that `canonicalize` can't resolve.
+pub fn resolve_dot_segments(path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match resolved.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    resolved.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                // Leading `..` of a relative path stay
+                _ => resolved.push(component),
+            },
+            _ => resolved.push(component),
+        }
+    }
+    resolved
+}
+
 /// Normalize path components for cross-platform comparison
 ///
 /// This function decomposes a path into its components and returns
@@ -131,6 +226,113 @@ mod tests {
         assert!(!is_plans_directory_path("~/../.claude/plans/plan.md"));
     }
 
+    #[test]
+    fn test_is_path_in_custom_plans_dir() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let plans_dir = temp_dir.path().join("plans");
+        std::fs::create_dir_all(&plans_dir).unwrap();
+
+        let plan_file = plans_dir.join("plan.md");
+        assert!(is_path_in_plans_dir(
+            plan_file.to_str().unwrap(),
+            &plans_dir
+        ));
+
+        // Paths outside the custom directory are rejected
+        let other_file = temp_dir.path().join("other.md");
+        assert!(!is_path_in_plans_dir(
+            other_file.to_str().unwrap(),
+            &plans_dir
+        ));
+
+        // Relative paths are never considered plan files
+        assert!(!is_path_in_plans_dir("plans/plan.md", &plans_dir));
+    }
+
+    #[test]
+    fn test_project_local_plans_dir() {
+        let project = tempfile::TempDir::new().unwrap();
+        let local = project_plans_dir(project.path());
+        assert_eq!(local, project.path().join(".claude").join("plans"));
+
+        let dirs = plans_dirs(None, Some(&local));
+        assert_eq!(dirs.last(), Some(&local));
+        assert!(dirs.contains(&default_plans_dir().unwrap()));
+
+        let local_plan = local.join("plan.md");
+        assert!(is_path_in_any_plans_dir(
+            local_plan.to_str().unwrap(),
+            &dirs
+        ));
+        assert!(is_path_in_any_plans_dir("~/.claude/plans/plan.md", &dirs));
+        let source = project.path().join("src").join("main.rs");
+        assert!(!is_path_in_any_plans_dir(source.to_str().unwrap(), &dirs));
+
+        // An override replaces the home directory but keeps the project one
+        let custom = project.path().join("custom");
+        assert_eq!(
+            plans_dirs(Some(&custom), Some(&local)),
+            vec![custom.clone(), local.clone()]
+        );
+        assert_eq!(plans_dirs(Some(&custom), None), vec![custom]);
+    }
+
+    #[test]
+    fn test_expand_home() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_home("~"), Some(home.clone()));
+        assert_eq!(expand_home("~/projects"), Some(home.join("projects")));
+        assert_eq!(expand_home("/tmp/x"), Some(PathBuf::from("/tmp/x")));
+        assert_eq!(expand_home("~other/x"), Some(PathBuf::from("~other/x")));
+    }
+
+    #[test]
+    fn test_is_path_within_home_relative_dir() {
+        let home = dirs::home_dir().unwrap();
+        let projects = Path::new("~/projects");
+
+        assert!(is_path_within_dir("~/projects/foo/main.rs", projects));
+        let absolute = home.join("projects").join("foo");
+        assert!(is_path_within_dir(absolute.to_str().unwrap(), projects));
+
+        assert!(!is_path_within_dir("~/other/foo", projects));
+        assert!(!is_path_within_dir("/tmp/projects/foo", projects));
+    }
+
+    #[test]
+    fn test_is_path_within_dir_resolves_parent_segments() {
+        let dir = Path::new("/nonexistent/plans");
+        assert!(!is_path_within_dir(
+            "/nonexistent/plans/../../etc/passwd",
+            dir
+        ));
+        assert!(!is_path_within_dir("/nonexistent/plans/../secrets.md", dir));
+        assert!(is_path_within_dir(
+            "/nonexistent/plans/drafts/../plan.md",
+            dir
+        ));
+        assert!(is_path_within_dir(
+            "/nonexistent/other/../plans/plan.md",
+            dir
+        ));
+        assert!(is_path_within_dir(
+            "/nonexistent/plans/a.md",
+            Path::new("/nonexistent/x/../plans")
+        ));
+    }
+
+    #[test]
+    fn test_resolve_dot_segments() {
+        let resolve = |path: &str| resolve_dot_segments(Path::new(path));
+        assert_eq!(
+            resolve("/proj/src/../lib/./a.rs"),
+            Path::new("/proj/lib/a.rs")
+        );
+        assert_eq!(resolve("/proj/../../etc"), Path::new("/etc"));
+        assert_eq!(resolve("../../x/../y"), Path::new("../../y"));
+        assert_eq!(resolve("src/.."), Path::new(""));
+    }
+
     #[test]
     fn test_normalize_path_components() {
         use std::path::Path;
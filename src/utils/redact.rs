@@ -0,0 +1,146 @@
+//! Tool input redaction for logs
+//!
+//! Tool inputs can carry credentials (an MCP tool's `api_key`, a `token`
+//! header for WebFetch), so they are redacted before being rendered into
+//! log lines or tracing fields.
+
+use serde_json::Value;
+
+/// Placeholder written in place of a sensitive value
+pub const REDACTED: &str = "***";
+
+/// Key fragments treated as sensitive by [`redact_tool_input`]
+pub const DEFAULT_SENSITIVE_KEYS: &[&str] = &[
+    "token",
+    "password",
+    "passwd",
+    "secret",
+    "api_key",
+    "apikey",
+    "authorization",
+    "credential",
+    "private_key",
+];
+
+/// Redact a tool input for logging using [`DEFAULT_SENSITIVE_KEYS`]
+///
+/// # Examples
+/// ```ignore
+/// let input = json!({"url": "https://example.com", "api_key": "sk-123"});
+/// assert_eq!(redact_tool_input(&input), json!({"url": "https://example.com", "api_key": "***"}));
+/// ```
+pub fn redact_tool_input(input: &Value) -> Value {
+    redact_tool_input_with(input, DEFAULT_SENSITIVE_KEYS)
+}
+
+/// Redact a tool input for logging, masking values of sensitive keys
+///
+/// A key is sensitive if, lowercased with `-` read as `_`, it contains one
+/// of `sensitive_keys` (so `GITHUB_TOKEN` and `X-Api-Key` both match).
+/// Nested objects and arrays are redacted too; the whole value of a
+/// sensitive key is masked, whatever its type.
+pub fn redact_tool_input_with(input: &Value, sensitive_keys: &[&str]) -> Value {
+    match input {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let value = if is_sensitive_key(key, sensitive_keys) {
+                        Value::String(REDACTED.to_string())
+                    } else {
+                        redact_tool_input_with(value, sensitive_keys)
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| redact_tool_input_with(item, sensitive_keys))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Render a short, redacted preview of a tool input for a log line
+///
+/// The redacted input is serialized and cut to `max_chars` characters, so
+/// large inputs (a file being written, a long script) stay out of the logs.
+///
+/// # Examples
+/// ```ignore
+/// let input = json!({"content": "x".repeat(500), "token": "t"});
+/// assert!(tool_input_preview(&input, 20).chars().count() <= 21);
+/// ```
+pub fn tool_input_preview(input: &Value, max_chars: usize) -> String {
+    let rendered = redact_tool_input(input).to_string();
+    if rendered.chars().count() <= max_chars {
+        return rendered;
+    }
+    let mut preview: String = rendered.chars().take(max_chars).collect();
+    preview.push('…');
+    preview
+}
+
+fn is_sensitive_key(key: &str, sensitive_keys: &[&str]) -> bool {
+    let normalized = key.to_lowercase().replace('-', "_");
+    sensitive_keys
+        .iter()
+        .any(|sensitive| normalized.contains(&sensitive.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sensitive_keys_are_masked() {
+        let input = json!({
+            "url": "https://example.com",
+            "api_key": "sk-123",
+            "headers": {"Authorization": "Bearer abc", "Accept": "text/html"},
+            "env": [{"GITHUB_TOKEN": "ghp_x"}, {"X-Api-Key": 42}],
+        });
+
+        assert_eq!(
+            redact_tool_input(&input),
+            json!({
+                "url": "https://example.com",
+                "api_key": "***",
+                "headers": {"Authorization": "***", "Accept": "text/html"},
+                "env": [{"GITHUB_TOKEN": "***"}, {"X-Api-Key": "***"}],
+            })
+        );
+    }
+
+    #[test]
+    fn test_other_values_are_preserved() {
+        let input = json!({"command": "ls -la", "timeout": 1000, "run_in_background": false});
+        assert_eq!(redact_tool_input(&input), input);
+        assert_eq!(redact_tool_input(&json!("plain")), json!("plain"));
+    }
+
+    #[test]
+    fn test_tool_input_preview() {
+        let input = json!({"token": "ghp_secret", "content": "x".repeat(500)});
+        let preview = tool_input_preview(&input, 40);
+        assert_eq!(preview.chars().count(), 41);
+        assert!(preview.ends_with('…'));
+        assert!(!preview.contains("ghp_secret"));
+
+        // Short inputs are shown whole
+        let input = json!({"command": "ls"});
+        assert_eq!(tool_input_preview(&input, 40), r#"{"command":"ls"}"#);
+    }
+
+    #[test]
+    fn test_custom_sensitive_keys() {
+        let input = json!({"session_cookie": "abc", "password": "hunter2"});
+        assert_eq!(
+            redact_tool_input_with(&input, &["cookie"]),
+            json!({"session_cookie": "***", "password": "hunter2"})
+        );
+    }
+}